@@ -0,0 +1,498 @@
+//! This module contains multi-fragment overlap assembly design, eg for Gibson assembly or NEBuilder
+//! HiFi: Given fragments in the order they're assembled, we design primers that amplify each, with 5'
+//! tails that create overlaps with its neighbors, and predict the assembled product.
+//!
+//! Each overlap is made of the end of one fragment, and the start of the next. If both are amplified,
+//! the overlap is split between their primers' tails. Fragments used as they are, eg a vector linearized
+//! by restriction digest, don't get primers; their neighbors' primers carry the whole overlap.
+
+use std::fmt::Display;
+
+use crate::{
+    file_io::GenericData,
+    melting_temp_calcs::calc_tm,
+    primer::{Primer, PrimerData, TM_TARGET},
+    secondary_structure::fold,
+    sequence::{seq_complement, FeatureType, Nucleotide, Seq, SeqRange, SeqTopology},
+    IonConcentrations,
+};
+
+/// The shortest annealing region we use, even if a shorter one reaches the target Tm.
+pub(crate) const MIN_ANNEAL_LEN: usize = 18;
+/// Overlaps with single-stranded structure more stable than this, in kcal/mol, may not anneal.
+const OVERLAP_DG_THRESH: f32 = -4.;
+/// Overlaps that share a sequence this long, on either strand, may anneal to each other, assembling
+/// fragments in the wrong order.
+const REPEAT_LEN: usize = 12;
+
+/// A fragment to assemble.
+#[derive(Clone, Default)]
+pub struct AssemblyFragment {
+    pub name: String,
+    /// The fragment's sequence and features, eg from `GenericData::extract`, 5' to 3' in the orientation
+    /// it's assembled in.
+    pub data: GenericData,
+    /// If false, the fragment is used as it is, eg a vector linearized by restriction digest. Its
+    /// neighbors' primers carry the full overlap with it.
+    pub amplify: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct AssemblyParams {
+    /// The shortest overlap between adjacent fragments. NEB recommends 15-20 nt for 2 or 3 fragments,
+    /// and 20-30 nt for more.
+    pub overlap_len: usize,
+    /// Overlaps are extended from their minimum length until they reach this melting temperature, in °C.
+    pub overlap_tm: f32,
+    pub max_overlap_len: usize,
+    /// Annealing regions are extended from their minimum length until they reach this melting temperature.
+    pub anneal_tm: f32,
+    pub max_anneal_len: usize,
+    /// If true, the last fragment joins the first, eg to assemble a plasmid.
+    pub circular: bool,
+}
+
+impl Default for AssemblyParams {
+    fn default() -> Self {
+        Self {
+            overlap_len: 20,
+            overlap_tm: 48.,
+            max_overlap_len: 40,
+            anneal_tm: TM_TARGET,
+            max_anneal_len: 35,
+            circular: true,
+        }
+    }
+}
+
+/// Where two adjacent fragments join.
+#[derive(Clone, Debug)]
+pub struct Junction {
+    /// Indices of the fragments on each side; `right` follows `left` in the product.
+    pub left: usize,
+    pub right: usize,
+    /// The overlap, on the product's top strand.
+    pub overlap: Seq,
+    /// How many nucleotides of the overlap are from the left fragment; the rest are from the right one.
+    pub len_left: usize,
+    /// The overlap's range in the product.
+    pub range: SeqRange,
+    /// °C
+    pub tm: Option<f32>,
+    /// Gibbs free energy of the overlap's most stable single-stranded structure, in kcal/mol.
+    pub dg: f32,
+}
+
+/// Primers that amplify a fragment, with overlap tails.
+#[derive(Clone)]
+pub struct FragmentPrimers {
+    pub fragment: usize,
+    pub fwd: Primer,
+    pub rev: Primer,
+    /// The length of each primer's 5' tail; the rest anneals to the fragment.
+    pub fwd_tail_len: usize,
+    pub rev_tail_len: usize,
+    /// Melting temperature of each primer's annealing region, in °C. Use these for the first PCR cycles.
+    pub fwd_anneal_tm: Option<f32>,
+    pub rev_anneal_tm: Option<f32>,
+    /// The length of the PCR product, including tails.
+    pub product_len: usize,
+}
+
+/// A problem with a design that may reduce assembly efficiency, or produce the wrong product.
+#[derive(Clone, Debug)]
+pub enum AssemblyWarning {
+    /// The overlap didn't reach the target Tm at the maximum length.
+    LowOverlapTm { junction: usize, tm: f32 },
+    /// The overlap's single-stranded structure may prevent it annealing.
+    OverlapStructure { junction: usize, dg: f32 },
+    /// The overlap shares sequence with another junction's, on either strand.
+    RepeatedOverlap { junction: usize, other: usize },
+    /// The overlap occurs more than once in the product, on either strand.
+    OverlapElsewhere { junction: usize },
+}
+
+impl Display for AssemblyWarning {
+    /// Junctions are numbered from 1, for display.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LowOverlapTm { junction, tm } => write!(
+                f,
+                "Junction {}: The overlap's Tm is {tm:.1}°C at the maximum length",
+                junction + 1
+            ),
+            Self::OverlapStructure { junction, dg } => write!(
+                f,
+                "Junction {}: The overlap forms secondary structure, with ΔG {dg:.1} kcal/mol",
+                junction + 1
+            ),
+            Self::RepeatedOverlap { junction, other } => write!(
+                f,
+                "Junction {}: The overlap shares {REPEAT_LEN} nt or more with junction {}'s",
+                junction + 1,
+                other + 1
+            ),
+            Self::OverlapElsewhere { junction } => write!(
+                f,
+                "Junction {}: The overlap occurs elsewhere in the product",
+                junction + 1
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AssemblyError {
+    /// A linear assembly needs at least two fragments; a circular one, at least one.
+    TooFewFragments,
+    EmptyFragment(String),
+    /// Neither fragment at a junction is amplified, so no primer can add an overlap.
+    NoPrimers {
+        left: String,
+        right: String,
+    },
+    /// A fragment is shorter than the part of an overlap it provides.
+    TooShort(String),
+}
+
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooFewFragments => write!(
+                f,
+                "Assembly requires at least two fragments, or one if circular"
+            ),
+            Self::EmptyFragment(name) => write!(f, "Fragment {name} is empty"),
+            Self::NoPrimers { left, right } => write!(
+                f,
+                "Neither {left} nor {right} is amplified, so there's no primer to add an overlap \
+                between them"
+            ),
+            Self::TooShort(name) => write!(f, "Fragment {name} is shorter than its overlap"),
+        }
+    }
+}
+
+/// The result of an assembly design.
+#[derive(Clone)]
+pub struct AssemblyDesign {
+    /// For each amplified fragment.
+    pub primers: Vec<FragmentPrimers>,
+    pub junctions: Vec<Junction>,
+    /// The assembled sequence, with features from each fragment, and the primers.
+    pub product: GenericData,
+    pub warnings: Vec<AssemblyWarning>,
+    /// By fragment index.
+    pub fragment_names: Vec<String>,
+}
+
+/// The shortest region, from `min_len` up, with a melting temperature of at least `tm`. `region` returns
+/// the sequence of a given length. If none reach it, returns the longest.
+pub(crate) fn len_for_tm(
+    min_len: usize,
+    max_len: usize,
+    tm: f32,
+    region: impl Fn(usize) -> Seq,
+    ion: &IonConcentrations,
+) -> usize {
+    (min_len..=max_len)
+        .find(|len| {
+            calc_tm(&region(*len), ion)
+                .map(|t| t >= tm)
+                .unwrap_or(false)
+        })
+        .unwrap_or(max_len)
+}
+
+/// If two sequences share a subsequence of at least `REPEAT_LEN`, on either strand.
+fn shares_repeat(a: &[Nucleotide], b: &[Nucleotide]) -> bool {
+    let b_compl = seq_complement(b);
+    a.windows(REPEAT_LEN).any(|w| {
+        b.windows(REPEAT_LEN).any(|v| v == w) || b_compl.windows(REPEAT_LEN).any(|v| v == w)
+    })
+}
+
+/// Design primers that assemble fragments in order, with overlaps between adjacent ones, and predict
+/// the product.
+pub fn design_assembly(
+    fragments: &[AssemblyFragment],
+    params: &AssemblyParams,
+    ion: &IonConcentrations,
+) -> Result<AssemblyDesign, AssemblyError> {
+    let num_frags = fragments.len();
+    if num_frags == 0 || (num_frags == 1 && !params.circular) {
+        return Err(AssemblyError::TooFewFragments);
+    }
+    if let Some(frag) = fragments.iter().find(|f| f.data.seq.is_empty()) {
+        return Err(AssemblyError::EmptyFragment(frag.name.clone()));
+    }
+
+    let num_junctions = if params.circular {
+        num_frags
+    } else {
+        num_frags - 1
+    };
+
+    // The assembled product: Each fragment in full; overlaps are made of their own sequence.
+    let mut product = GenericData::default();
+    let mut frag_ends = Vec::new(); // 1-based position of each fragment's end in the product.
+    for frag in fragments {
+        let mut data = frag.data.clone();
+        // These describe the source documents, not the product.
+        data.features
+            .retain(|f| f.feature_type != FeatureType::Source);
+        product.paste(product.seq.len() + 1, &data);
+        frag_ends.push(product.seq.len());
+    }
+    product.topology = if params.circular {
+        SeqTopology::Circular
+    } else {
+        SeqTopology::Linear
+    };
+    let product_len = product.seq.len();
+
+    let mut junctions = Vec::new();
+    for i in 0..num_junctions {
+        let (left, right) = (&fragments[i], &fragments[(i + 1) % num_frags]);
+        let (seq_l, seq_r) = (&left.data.seq, &right.data.seq);
+
+        if !left.amplify && !right.amplify {
+            return Err(AssemblyError::NoPrimers {
+                left: left.name.clone(),
+                right: right.name.clone(),
+            });
+        }
+
+        // The overlap, with a total length, and the portion from the left fragment.
+        let split = |len: usize| match (left.amplify, right.amplify) {
+            (true, true) => len / 2,
+            (true, false) => 0,
+            _ => len,
+        };
+        let overlap = |len: usize| -> Seq {
+            let len_left = split(len).min(seq_l.len());
+            let len_right = (len - len_left).min(seq_r.len());
+            let mut result = seq_l[seq_l.len() - len_left..].to_vec();
+            result.extend(&seq_r[..len_right]);
+            result
+        };
+
+        let len = len_for_tm(
+            params.overlap_len,
+            params.max_overlap_len.max(params.overlap_len),
+            params.overlap_tm,
+            overlap,
+            ion,
+        );
+        let len_left = split(len);
+        if len_left > seq_l.len() {
+            return Err(AssemblyError::TooShort(left.name.clone()));
+        }
+        if len - len_left > seq_r.len() {
+            return Err(AssemblyError::TooShort(right.name.clone()));
+        }
+
+        let overlap = overlap(len);
+        let start = (frag_ends[i] + product_len - len_left) % product_len;
+
+        junctions.push(Junction {
+            left: i,
+            right: (i + 1) % num_frags,
+            range: SeqRange::from_start_len(start, len, product_len),
+            tm: calc_tm(&overlap, ion),
+            dg: fold(&overlap, ion).dg,
+            len_left,
+            overlap,
+        });
+    }
+
+    let mut primers = Vec::new();
+    for (i, frag) in fragments.iter().enumerate() {
+        if !frag.amplify {
+            continue;
+        }
+        let seq = &frag.data.seq;
+        let max_anneal = params.max_anneal_len.min(seq.len());
+        let min_anneal = MIN_ANNEAL_LEN.min(max_anneal);
+
+        // The junctions at each end of this fragment, if any.
+        let junction_5p = junctions.iter().find(|j| j.right == i);
+        let junction_3p = junctions.iter().find(|j| j.left == i);
+
+        // The forward primer's tail is the left fragment's part of the overlap.
+        let tail_fwd = junction_5p
+            .map(|j| j.overlap[..j.len_left].to_vec())
+            .unwrap_or_default();
+        let len_fwd = len_for_tm(
+            min_anneal,
+            max_anneal,
+            params.anneal_tm,
+            |len| seq[..len].to_vec(),
+            ion,
+        );
+        let anneal_fwd = &seq[..len_fwd];
+
+        // The reverse primer's tail is the complement of the right fragment's part.
+        let tail_rev = junction_3p
+            .map(|j| seq_complement(&j.overlap[j.len_left..]))
+            .unwrap_or_default();
+        let len_rev = len_for_tm(
+            min_anneal,
+            max_anneal,
+            params.anneal_tm,
+            |len| seq_complement(&seq[seq.len() - len..]),
+            ion,
+        );
+        let anneal_rev = seq_complement(&seq[seq.len() - len_rev..]);
+
+        let make_primer =
+            |tail: &[Nucleotide], anneal: &[Nucleotide], dir: &str, neighbor: Option<usize>| {
+                let mut sequence = tail.to_vec();
+                sequence.extend(anneal);
+
+                let description = match neighbor {
+                    Some(j) => format!(
+                        "Assembly primer. Amplifies {}, with an overlap with {}.",
+                        frag.name, fragments[j].name
+                    ),
+                    None => format!("Assembly primer. Amplifies {}.", frag.name),
+                };
+
+                Primer {
+                    volatile: PrimerData::new(&sequence),
+                    sequence,
+                    name: format!("{} {dir}", frag.name),
+                    description: Some(description),
+                }
+            };
+
+        primers.push(FragmentPrimers {
+            fragment: i,
+            fwd: make_primer(&tail_fwd, anneal_fwd, "fwd", junction_5p.map(|j| j.left)),
+            rev: make_primer(&tail_rev, &anneal_rev, "rev", junction_3p.map(|j| j.right)),
+            fwd_tail_len: tail_fwd.len(),
+            rev_tail_len: tail_rev.len(),
+            fwd_anneal_tm: calc_tm(anneal_fwd, ion),
+            rev_anneal_tm: calc_tm(&anneal_rev, ion),
+            product_len: tail_fwd.len() + seq.len() + tail_rev.len(),
+        });
+    }
+
+    let mut warnings = Vec::new();
+    for (i, junction) in junctions.iter().enumerate() {
+        if let Some(tm) = junction.tm {
+            if tm < params.overlap_tm {
+                warnings.push(AssemblyWarning::LowOverlapTm { junction: i, tm });
+            }
+        }
+
+        if junction.dg < OVERLAP_DG_THRESH {
+            warnings.push(AssemblyWarning::OverlapStructure {
+                junction: i,
+                dg: junction.dg,
+            });
+        }
+
+        for (other_i, other) in junctions.iter().enumerate().skip(i + 1) {
+            if shares_repeat(&junction.overlap, &other.overlap) {
+                warnings.push(AssemblyWarning::RepeatedOverlap {
+                    junction: i,
+                    other: other_i,
+                });
+            }
+        }
+
+        let matcher = Primer {
+            sequence: junction.overlap.clone(),
+            ..Default::default()
+        };
+        if matcher.match_to_seq(&product.seq, product.topology).len() > 1 {
+            warnings.push(AssemblyWarning::OverlapElsewhere { junction: i });
+        }
+    }
+
+    product.primers = primers
+        .iter()
+        .flat_map(|p| [p.fwd.clone(), p.rev.clone()])
+        .collect();
+    let fragment_names: Vec<String> = fragments.iter().map(|f| f.name.clone()).collect();
+    product.metadata.plasmid_name = fragment_names.join(" + ");
+
+    Ok(AssemblyDesign {
+        primers,
+        junctions,
+        product,
+        warnings,
+        fragment_names,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sequence::seq_from_str, test_fixtures::doc};
+
+    fn fragment(name: &str, seq: &str) -> AssemblyFragment {
+        AssemblyFragment {
+            name: name.to_owned(),
+            data: doc(seq, SeqTopology::Linear, Vec::new()),
+            amplify: true,
+        }
+    }
+
+    /// Each fragment starts with the other's start, and ends with the other's end, so the overlap
+    /// between them is also across the origin of a circular product.
+    fn fragments() -> Vec<AssemblyFragment> {
+        vec![
+            fragment(
+                "A",
+                "CGTACGATCGGATTGCAAGCTTCGTAGGCTAACGTTGACCAGTTCAGGTTAGCCATGC",
+            ),
+            fragment(
+                "B",
+                "CGTACGATCGAAGTCCTGATGCGCAATTCGGAGTATCCACTGACTGGATTAGCCATGC",
+            ),
+        ]
+    }
+
+    fn params(circular: bool) -> AssemblyParams {
+        AssemblyParams {
+            overlap_tm: 0.,
+            circular,
+            ..Default::default()
+        }
+    }
+
+    fn overlap_elsewhere(design: &AssemblyDesign) -> bool {
+        design
+            .warnings
+            .iter()
+            .any(|w| matches!(w, AssemblyWarning::OverlapElsewhere { .. }))
+    }
+
+    #[test]
+    fn linear() {
+        let design = design_assembly(&fragments(), &params(false), &Default::default()).unwrap();
+        assert!(design.product.topology == SeqTopology::Linear);
+        assert_eq!(design.product.seq.len(), 116);
+
+        assert_eq!(design.junctions.len(), 1);
+        let junction = &design.junctions[0];
+        assert_eq!(junction.range, SeqRange::new(49, 68));
+        assert_eq!(junction.overlap, seq_from_str("TTAGCCATGCCGTACGATCG"));
+        assert!(!overlap_elsewhere(&design));
+
+        // The ends of the product don't get overlap tails.
+        assert_eq!(design.primers[0].fwd_tail_len, 0);
+        assert_eq!(design.primers[1].rev_tail_len, 0);
+    }
+
+    #[test]
+    fn circular() {
+        let design = design_assembly(&fragments(), &params(true), &Default::default()).unwrap();
+        assert!(design.product.topology == SeqTopology::Circular);
+        assert_eq!(design.junctions.len(), 2);
+        assert_eq!(design.junctions[1].range, SeqRange::new(107, 10));
+        assert!(overlap_elsewhere(&design));
+    }
+}
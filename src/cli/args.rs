@@ -0,0 +1,155 @@
+//! Parsing command-line arguments and options, including ones shared by several commands.
+
+use std::{collections::HashMap, str::FromStr};
+
+use plascad::{
+    melting_temp_calcs::{NnTable, SaltCorrection, TmConfig},
+    IonConcentrations,
+};
+
+/// Options that don't take a value.
+const FLAGS: [&str; 6] = [
+    "alt-starts",
+    "nested",
+    "products",
+    "linear",
+    "fragments",
+    "help",
+];
+
+/// Parsed command-line arguments, after the command.
+pub struct Args {
+    pub positional: Vec<String>,
+    /// Option values, by name without the leading dashes. Options may be repeated.
+    pub options: HashMap<String, Vec<String>>,
+}
+
+impl Args {
+    /// Fails on options other than `known`, eg due to a typo, before they consume a value.
+    pub fn parse(args: &[String], known: &[&str]) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options: HashMap<String, Vec<String>> = HashMap::new();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let name = match arg.as_ref() {
+                "-o" => "out",
+                "-h" => "help",
+                _ => match arg.strip_prefix("--") {
+                    Some(name) => name,
+                    None => {
+                        positional.push(arg.clone());
+                        continue;
+                    }
+                },
+            };
+            if !known.contains(&name) {
+                return Err(format!("Unknown option: {arg}"));
+            }
+
+            let value = if FLAGS.contains(&name) {
+                String::new()
+            } else {
+                iter.next()
+                    .ok_or_else(|| format!("Missing a value for {arg}"))?
+                    .clone()
+            };
+            options.entry(name.to_owned()).or_default().push(value);
+        }
+
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.options
+            .get(name)
+            .and_then(|v| v.last())
+            .map(|v| v.as_ref())
+    }
+
+    pub fn get_all(&self, name: &str) -> &[String] {
+        self.options.get(name).map(|v| v.as_ref()).unwrap_or(&[])
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    /// Parse an option's value, or use a default if it's absent.
+    pub fn parse_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.get(name) {
+            Some(v) => v
+                .parse()
+                .map_err(|_| format!("Invalid value for --{name}: {v}")),
+            None => Ok(default),
+        }
+    }
+}
+
+/// Options for melting temperature conditions, shared by commands that calculate it.
+pub const TM_OPTIONS: [&str; 9] = [
+    "na",
+    "mg",
+    "dntp",
+    "primer-conc",
+    "tris",
+    "dmso",
+    "formamide",
+    "nn-table",
+    "salt-corr",
+];
+
+pub fn ion_concentrations(args: &Args) -> Result<IonConcentrations, String> {
+    let default = IonConcentrations::default();
+
+    // These are numbered as in BioPython's `Tm_NN`.
+    let nn_table = match args.get("nn-table") {
+        Some(v) => *v
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| NnTable::ALL.get(i.wrapping_sub(1)))
+            .ok_or_else(|| format!("Invalid value for --nn-table: {v}; use 1 to 4."))?,
+        None => default.tm_config.nn_table,
+    };
+    let salt_correction = match args.get("salt-corr") {
+        Some(v) => *v
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| SaltCorrection::ALL.get(i))
+            .ok_or_else(|| format!("Invalid value for --salt-corr: {v}; use 0 to 7."))?,
+        None => default.tm_config.salt_correction,
+    };
+
+    Ok(IonConcentrations {
+        monovalent: args.parse_or("na", default.monovalent)?,
+        divalent: args.parse_or("mg", default.divalent)?,
+        dntp: args.parse_or("dntp", default.dntp)?,
+        primer: args.parse_or("primer-conc", default.primer)?,
+        tris: args.parse_or("tris", default.tris)?,
+        tm_config: TmConfig {
+            nn_table,
+            salt_correction,
+            dmso: args.parse_or("dmso", default.tm_config.dmso)?,
+            formamide: args.parse_or("formamide", default.tm_config.formamide)?,
+        },
+    })
+}
+
+/// Parse a pair of values separated by `sep`, eg a `min-max` option.
+pub fn parse_pair<T: FromStr>(
+    args: &Args,
+    name: &str,
+    sep: &str,
+    default: (T, T),
+) -> Result<(T, T), String> {
+    let Some(v) = args.get(name) else {
+        return Ok(default);
+    };
+
+    v.split_once(sep)
+        .and_then(|(a, b)| Some((a.trim().parse().ok()?, b.trim().parse().ok()?)))
+        .ok_or_else(|| format!("Invalid value for --{name}: {v}"))
+}
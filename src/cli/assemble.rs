@@ -0,0 +1,138 @@
+//! The `assemble` command: Gibson and NEBuilder HiFi assembly primers.
+
+use std::path::Path;
+
+use plascad::{
+    assembly::{design_assembly, AssemblyFragment, AssemblyParams},
+    file_io::export_file,
+    sequence::seq_to_str,
+};
+use serde::Serialize;
+
+use crate::{
+    args::{ion_concentrations, Args},
+    load,
+    output::{write_rows, Format, Row},
+};
+
+#[derive(Serialize)]
+struct AssemblyPrimerRow {
+    fragment: usize,
+    name: String,
+    sequence: String,
+    length: usize,
+    tail_len: usize,
+    /// °C, of the annealing region.
+    anneal_tm: Option<f32>,
+    product_len: usize,
+}
+
+impl Row for AssemblyPrimerRow {
+    const HEADER: &'static [&'static str] = &[
+        "fragment",
+        "name",
+        "sequence",
+        "length",
+        "tail_len",
+        "anneal_tm",
+        "product_len",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.fragment.to_string(),
+            self.name.clone(),
+            self.sequence.clone(),
+            self.length.to_string(),
+            self.tail_len.to_string(),
+            self.anneal_tm
+                .map(|v| format!("{v:.1}"))
+                .unwrap_or_default(),
+            self.product_len.to_string(),
+        ]
+    }
+}
+
+pub fn assemble(args: &Args, format: Format) -> Result<bool, String> {
+    let ion = ion_concentrations(args)?;
+
+    let keep = args
+        .get_all("keep")
+        .iter()
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("Invalid value for --keep: {v}"))
+        })
+        .collect::<Result<Vec<usize>, _>>()?;
+
+    let mut fragments = Vec::new();
+    for (i, path) in args.positional.iter().enumerate() {
+        let data = load(Path::new(path))?;
+        let name = if data.metadata.plasmid_name.is_empty() {
+            path.clone()
+        } else {
+            data.metadata.plasmid_name.clone()
+        };
+
+        fragments.push(AssemblyFragment {
+            name,
+            data,
+            amplify: !keep.contains(&(i + 1)),
+        });
+    }
+
+    let default = AssemblyParams::default();
+    let params = AssemblyParams {
+        overlap_len: args.parse_or("overlap", default.overlap_len)?,
+        overlap_tm: args.parse_or("overlap-tm", default.overlap_tm)?,
+        circular: !args.flag("linear"),
+        ..default
+    };
+
+    let design = design_assembly(&fragments, &params, &ion).map_err(|e| e.to_string())?;
+
+    for (i, junction) in design.junctions.iter().enumerate() {
+        eprintln!(
+            "Junction {}: {} -> {}, overlap {} ({} nt, Tm {}°C) at {}",
+            i + 1,
+            design.fragment_names[junction.left],
+            design.fragment_names[junction.right],
+            seq_to_str(&junction.overlap),
+            junction.overlap.len(),
+            junction
+                .tm
+                .map(|v| format!("{v:.1}"))
+                .unwrap_or_else(|| "-".to_owned()),
+            junction.range,
+        );
+    }
+    for warning in &design.warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    if let Some(out) = args.get("out") {
+        export_file(&design.product, Path::new(out)).map_err(|e| format!("{out}: {e}"))?;
+        eprintln!("Product: {} bp -> {out}", design.product.seq.len());
+    }
+
+    let mut rows = Vec::new();
+    for p in &design.primers {
+        for (primer, tail_len, anneal_tm) in [
+            (&p.fwd, p.fwd_tail_len, p.fwd_anneal_tm),
+            (&p.rev, p.rev_tail_len, p.rev_anneal_tm),
+        ] {
+            rows.push(AssemblyPrimerRow {
+                fragment: p.fragment + 1,
+                name: primer.name.clone(),
+                sequence: seq_to_str(&primer.sequence),
+                length: primer.sequence.len(),
+                tail_len,
+                anneal_tm,
+                product_len: p.product_len,
+            });
+        }
+    }
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(true)
+}
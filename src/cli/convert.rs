@@ -0,0 +1,66 @@
+//! The `convert` command: Converting documents between file formats.
+
+use std::path::{Path, PathBuf};
+
+use plascad::file_io::export_file;
+
+use crate::{args::Args, load};
+
+/// Returns false if any file failed to convert; the others are still converted.
+pub fn convert(args: &Args) -> Result<bool, String> {
+    if args.positional.is_empty() {
+        return Err("No input files.".to_owned());
+    }
+
+    let outputs: Vec<(PathBuf, PathBuf)> = match (args.get("out"), args.get("to")) {
+        (Some(out), None) => {
+            if args.positional.len() > 1 {
+                return Err("-o may only be used with one input file; use --to instead.".to_owned());
+            }
+            vec![(PathBuf::from(&args.positional[0]), PathBuf::from(out))]
+        }
+        (None, Some(ext)) => {
+            let ext = ext.trim_start_matches('.');
+            args.positional
+                .iter()
+                .map(|input| {
+                    let input = PathBuf::from(input);
+                    let mut output = match args.get("out-dir") {
+                        Some(dir) => Path::new(dir).join(input.file_name().unwrap_or_default()),
+                        None => input.clone(),
+                    };
+                    output.set_extension(ext);
+                    (input, output)
+                })
+                .collect()
+        }
+        _ => return Err("Specify one of --to or -o.".to_owned()),
+    };
+
+    let mut success = true;
+
+    for (input, output) in outputs {
+        if input == output {
+            eprintln!(
+                "{}: The input and output are the same file; skipping.",
+                input.display()
+            );
+            success = false;
+            continue;
+        }
+
+        let result = load(&input).and_then(|data| {
+            export_file(&data, &output).map_err(|e| format!("{}: {e}", output.display()))
+        });
+
+        match result {
+            Ok(()) => eprintln!("{} -> {}", input.display(), output.display()),
+            Err(e) => {
+                eprintln!("{e}");
+                success = false;
+            }
+        }
+    }
+
+    Ok(success)
+}
@@ -0,0 +1,189 @@
+//! The `digest` command: Finding restriction sites, and the fragments enzymes cut a sequence into.
+
+use plascad::{
+    digest::{digest as digest_seq, FragmentEnd},
+    file_io::GenericData,
+    restriction_enzyme::{find_re_matches, RestrictionEnzyme},
+    sequence::seq_to_str,
+};
+use serde::Serialize;
+
+use crate::{
+    args::Args,
+    enzymes::select_enzymes,
+    load_single,
+    output::{write_rows, Format, Row},
+};
+
+#[derive(Serialize)]
+struct DigestRow {
+    enzyme: String,
+    site: String,
+    start: usize,
+    end: usize,
+    strand: String,
+    /// Each cut is after this nucleotide, on the top strand. Type IIB enzymes have two.
+    cut_after: Vec<usize>,
+    /// The same, for the bottom strand's cuts, in top strand positions.
+    cut_after_bottom: Vec<usize>,
+    ends: String,
+}
+
+impl Row for DigestRow {
+    const HEADER: &'static [&'static str] = &[
+        "enzyme",
+        "site",
+        "start",
+        "end",
+        "strand",
+        "cut_after",
+        "cut_after_bottom",
+        "ends",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let join = |v: &[usize]| {
+            v.iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(";")
+        };
+        vec![
+            self.enzyme.clone(),
+            self.site.clone(),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.strand.clone(),
+            join(&self.cut_after),
+            join(&self.cut_after_bottom),
+            self.ends.clone(),
+        ]
+    }
+}
+
+#[derive(Serialize)]
+struct FragmentRow {
+    start: usize,
+    end: usize,
+    length: usize,
+    /// The enzyme that cut at the fragment's start; none at a linear sequence's end.
+    left_enzyme: Option<String>,
+    left_end: Option<String>,
+    left_overhang: Option<String>,
+    right_enzyme: Option<String>,
+    right_end: Option<String>,
+    right_overhang: Option<String>,
+    features: Vec<String>,
+}
+
+impl Row for FragmentRow {
+    const HEADER: &'static [&'static str] = &[
+        "start",
+        "end",
+        "length",
+        "left_enzyme",
+        "left_end",
+        "left_overhang",
+        "right_enzyme",
+        "right_end",
+        "right_overhang",
+        "features",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+        vec![
+            self.start.to_string(),
+            self.end.to_string(),
+            self.length.to_string(),
+            opt(&self.left_enzyme),
+            opt(&self.left_end),
+            opt(&self.left_overhang),
+            opt(&self.right_enzyme),
+            opt(&self.right_end),
+            opt(&self.right_overhang),
+            self.features.join(";"),
+        ]
+    }
+}
+
+fn digest_fragments(
+    data: &GenericData,
+    lib: &[RestrictionEnzyme],
+    format: Format,
+) -> Result<bool, String> {
+    let fragments = digest_seq(&data.seq, data.topology, lib, &data.features);
+    if fragments.len() == 1 && fragments[0].left.is_none() {
+        eprintln!("None of the enzymes cut this sequence.");
+    }
+
+    // Enzyme name, end type, and overhang.
+    let end_fields = |end: &Option<FragmentEnd>| match end {
+        Some(end) => (
+            Some(lib[end.lib_index].name.clone()),
+            Some(end.end_type.to_string()),
+            Some(seq_to_str(&end.overhang).to_uppercase()),
+        ),
+        None => (None, None, None),
+    };
+
+    let rows: Vec<FragmentRow> = fragments
+        .iter()
+        .map(|fragment| {
+            let (left_enzyme, left_end, left_overhang) = end_fields(&fragment.left);
+            let (right_enzyme, right_end, right_overhang) = end_fields(&fragment.right);
+            FragmentRow {
+                start: fragment.range.start,
+                end: fragment.range.end,
+                length: fragment.len,
+                left_enzyme,
+                left_end,
+                left_overhang,
+                right_enzyme,
+                right_end,
+                right_overhang,
+                features: fragment
+                    .features
+                    .iter()
+                    .map(|i| data.features[*i].label.clone())
+                    .collect(),
+            }
+        })
+        .collect();
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+pub fn digest(args: &Args, format: Format) -> Result<bool, String> {
+    let (_, data) = load_single(args)?;
+    let lib = select_enzymes(args, Some("Common"))?;
+
+    if args.flag("fragments") {
+        return digest_fragments(&data, &lib, format);
+    }
+
+    let seq_len = data.seq.len();
+    // A cut at the origin is after the last nucleotide.
+    let cut_after = |i: usize| if i == 0 { seq_len } else { i };
+
+    let rows: Vec<DigestRow> = find_re_matches(&data.seq, data.topology, &lib)
+        .iter()
+        .map(|re_match| {
+            let re = &lib[re_match.lib_index];
+            DigestRow {
+                enzyme: re.name.clone(),
+                site: re.site_notation(),
+                start: re_match.range.start,
+                end: re_match.range.end,
+                strand: if re_match.reverse { "-" } else { "+" }.to_owned(),
+                cut_after: re_match.cuts.iter().map(|c| cut_after(c.top)).collect(),
+                cut_after_bottom: re_match.cuts.iter().map(|c| cut_after(c.bottom)).collect(),
+                ends: re.end_type().to_string(),
+            }
+        })
+        .collect();
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(true)
+}
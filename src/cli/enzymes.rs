@@ -0,0 +1,180 @@
+//! The `enzymes` command, and choosing enzymes from the library for other commands.
+
+use std::{io::ErrorKind, path::Path};
+
+use plascad::{
+    file_io::{
+        error::{FileError, Imported},
+        rebase::{load_enzyme_sets, DEFAULT_ENZYME_SETS_FILE},
+    },
+    restriction_enzyme::{
+        default_enzyme_sets, isoschizomers, load_re_library, load_re_library_file, EnzymeSet,
+        RestrictionEnzyme,
+    },
+};
+use serde::Serialize;
+
+use crate::{
+    args::Args,
+    output::{write_rows, Format, Row},
+};
+
+/// Options for choosing enzymes.
+pub const ENZYME_OPTIONS: [&str; 4] = ["rebase", "enzymes", "set", "supplier"];
+
+/// The user's saved enzyme sets, or the built-in ones if none have been saved.
+fn enzyme_sets() -> Result<Vec<EnzymeSet>, String> {
+    match load_enzyme_sets(Path::new(DEFAULT_ENZYME_SETS_FILE)) {
+        Ok(Imported { data, warnings }) => {
+            for warning in &warnings {
+                eprintln!("{DEFAULT_ENZYME_SETS_FILE}: warning: {warning}");
+            }
+            Ok(data)
+        }
+        Err(FileError::Io(e)) if e.kind() == ErrorKind::NotFound => Ok(default_enzyme_sets()),
+        Err(e) => Err(format!("{DEFAULT_ENZYME_SETS_FILE}: {e}")),
+    }
+}
+
+/// The enzyme library, from a REBASE file, or the built-in one.
+pub fn enzyme_library(args: &Args) -> Result<Vec<RestrictionEnzyme>, String> {
+    match args.get("rebase") {
+        Some(path) => {
+            let Imported { data, warnings } =
+                load_re_library_file(Path::new(path)).map_err(|e| format!("{path}: {e}"))?;
+            for warning in &warnings {
+                eprintln!("{path}: warning: {warning}");
+            }
+            Ok(data)
+        }
+        None => Ok(load_re_library()),
+    }
+}
+
+/// The enzyme library, from a REBASE file or the built-in one, narrowed by name, set, and supplier.
+/// `default_set` applies if neither enzymes nor a set are given.
+pub fn select_enzymes(
+    args: &Args,
+    default_set: Option<&str>,
+) -> Result<Vec<RestrictionEnzyme>, String> {
+    let mut lib = enzyme_library(args)?;
+
+    let names: Option<Vec<String>> = match (args.get("enzymes"), args.get("set").or(default_set)) {
+        (Some(names), _) => {
+            let names: Vec<String> = names.split(',').map(|n| n.trim().to_owned()).collect();
+            for name in &names {
+                if !lib.iter().any(|re| re.name.eq_ignore_ascii_case(name)) {
+                    return Err(format!("Unknown enzyme: {name}"));
+                }
+            }
+            Some(names)
+        }
+        (None, Some(set)) => {
+            let set = enzyme_sets()?
+                .into_iter()
+                .find(|s| s.name.eq_ignore_ascii_case(set))
+                .ok_or_else(|| format!("Unknown enzyme set: {set}"))?;
+            Some(set.enzymes)
+        }
+        (None, None) => None,
+    };
+
+    if let Some(names) = names {
+        lib.retain(|re| names.iter().any(|n| n.eq_ignore_ascii_case(&re.name)));
+    }
+    if let Some(codes) = args.get("supplier") {
+        lib.retain(|re| codes.chars().any(|c| re.suppliers.contains(&c)));
+    }
+
+    Ok(lib)
+}
+
+#[derive(Serialize)]
+struct EnzymeRow {
+    name: String,
+    site: String,
+    ends: String,
+    /// Neoschizomers are marked with `*`.
+    isoschizomers: Vec<String>,
+    suppliers: String,
+    dam: String,
+    dcm: String,
+    cpg: String,
+    /// Eg `rCutSmart 100%`.
+    buffers: Vec<String>,
+}
+
+impl Row for EnzymeRow {
+    const HEADER: &'static [&'static str] = &[
+        "name",
+        "site",
+        "ends",
+        "isoschizomers",
+        "suppliers",
+        "dam",
+        "dcm",
+        "cpg",
+        "buffers",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.site.clone(),
+            self.ends.clone(),
+            self.isoschizomers.join(","),
+            self.suppliers.clone(),
+            self.dam.clone(),
+            self.dcm.clone(),
+            self.cpg.clone(),
+            self.buffers.join(", "),
+        ]
+    }
+}
+
+pub fn enzymes(args: &Args, format: Format) -> Result<bool, String> {
+    if !args.positional.is_empty() {
+        return Err("This command doesn't take files; use --rebase to load a library.".to_owned());
+    }
+
+    let lib = select_enzymes(args, None)?;
+
+    let rows: Vec<EnzymeRow> = lib
+        .iter()
+        .enumerate()
+        .map(|(i, re)| EnzymeRow {
+            name: re.name.clone(),
+            site: re.site_notation(),
+            ends: re.end_type().to_string(),
+            isoschizomers: isoschizomers(&lib, i)
+                .into_iter()
+                .map(|(j, neo)| {
+                    let name = lib[j].name.clone();
+                    if neo {
+                        format!("{name}*")
+                    } else {
+                        name
+                    }
+                })
+                .collect(),
+            suppliers: re.suppliers.iter().collect(),
+            dam: re.methylation.dam.to_string(),
+            dcm: re.methylation.dcm.to_string(),
+            cpg: re.methylation.cpg.to_string(),
+            buffers: re
+                .buffer_activity
+                .iter()
+                .map(|(buffer, pct)| {
+                    if *pct == 0 {
+                        format!("{buffer} <10%")
+                    } else {
+                        format!("{buffer} {pct}%")
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(true)
+}
@@ -0,0 +1,121 @@
+//! The `golden-gate` command: Golden Gate assembly primers.
+
+use std::path::Path;
+
+use plascad::{
+    file_io::export_file,
+    golden_gate::{design_golden_gate, GoldenGateParams, GoldenGatePart, DEFAULT_ENZYME},
+    sequence::{seq_from_str, seq_to_str},
+};
+use serde::Serialize;
+
+use crate::{
+    args::{ion_concentrations, Args},
+    enzymes::enzyme_library,
+    load,
+    output::{write_rows, Format, Row},
+};
+
+#[derive(Serialize)]
+struct GoldenGatePrimerRow {
+    part: usize,
+    name: String,
+    sequence: String,
+    length: usize,
+    tail_len: usize,
+    /// °C, of the annealing region.
+    anneal_tm: Option<f32>,
+    amplicon_len: usize,
+}
+
+impl Row for GoldenGatePrimerRow {
+    const HEADER: &'static [&'static str] = &[
+        "part",
+        "name",
+        "sequence",
+        "length",
+        "tail_len",
+        "anneal_tm",
+        "amplicon_len",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.part.to_string(),
+            self.name.clone(),
+            self.sequence.clone(),
+            self.length.to_string(),
+            self.tail_len.to_string(),
+            self.anneal_tm
+                .map(|v| format!("{v:.1}"))
+                .unwrap_or_default(),
+            self.amplicon_len.to_string(),
+        ]
+    }
+}
+
+pub fn golden_gate(args: &Args, format: Format) -> Result<bool, String> {
+    let ion = ion_concentrations(args)?;
+
+    let mut parts = Vec::new();
+    for path in &args.positional {
+        let data = load(Path::new(path))?;
+        let name = if data.metadata.plasmid_name.is_empty() {
+            path.clone()
+        } else {
+            data.metadata.plasmid_name.clone()
+        };
+        parts.push(GoldenGatePart { name, data });
+    }
+
+    let mut params = GoldenGateParams {
+        circular: !args.flag("linear"),
+        ..Default::default()
+    };
+
+    let name = args.get("enzyme").unwrap_or(DEFAULT_ENZYME);
+    params.enzyme = enzyme_library(args)?
+        .into_iter()
+        .find(|re| re.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("Unknown enzyme: {name}"))?;
+
+    params.overhangs = match args.get("overhangs") {
+        Some("moclo") => params.moclo_overhangs(parts.len()).ok_or_else(|| {
+            "The MoClo standard defines overhangs for up to 4 parts, or 5 if circular.".to_owned()
+        })?,
+        Some(v) => v.split(',').map(|oh| seq_from_str(oh.trim())).collect(),
+        None => return Err("Specify --overhangs.".to_owned()),
+    };
+
+    let design = design_golden_gate(&parts, &params, &ion).map_err(|e| e.to_string())?;
+
+    for warning in &design.warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    if let (Some(out), Some(product)) = (args.get("out"), &design.product) {
+        export_file(product, Path::new(out)).map_err(|e| format!("{out}: {e}"))?;
+        eprintln!("Product: {} bp -> {out}", product.seq.len());
+    }
+
+    let mut rows = Vec::new();
+    for p in &design.primers {
+        for (primer, tail_len, anneal_tm) in [
+            (&p.fwd, p.fwd_tail_len, p.fwd_anneal_tm),
+            (&p.rev, p.rev_tail_len, p.rev_anneal_tm),
+        ] {
+            rows.push(GoldenGatePrimerRow {
+                part: p.part + 1,
+                name: primer.name.clone(),
+                sequence: seq_to_str(&primer.sequence),
+                length: primer.sequence.len(),
+                tail_len,
+                anneal_tm,
+                amplicon_len: p.amplicon.len(),
+            });
+        }
+    }
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(design.product.is_some())
+}
@@ -0,0 +1,89 @@
+//! The `info` command: Summarizing documents.
+
+use std::path::PathBuf;
+
+use plascad::{file_io::import_file, primer::calc_gc};
+use serde::Serialize;
+
+use crate::{
+    args::Args,
+    output::{write_rows, Format, Row},
+    topology_str,
+};
+
+#[derive(Serialize)]
+struct InfoRow {
+    file: String,
+    name: String,
+    length: usize,
+    topology: &'static str,
+    /// `None` for empty sequences.
+    gc_portion: Option<f32>,
+    features: usize,
+    primers: usize,
+    warnings: usize,
+}
+
+impl Row for InfoRow {
+    const HEADER: &'static [&'static str] = &[
+        "file",
+        "name",
+        "length",
+        "topology",
+        "gc_portion",
+        "features",
+        "primers",
+        "warnings",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.file.clone(),
+            self.name.clone(),
+            self.length.to_string(),
+            self.topology.to_owned(),
+            self.gc_portion
+                .map(|v| format!("{v:.3}"))
+                .unwrap_or_default(),
+            self.features.to_string(),
+            self.primers.to_string(),
+            self.warnings.to_string(),
+        ]
+    }
+}
+
+pub fn info(args: &Args, format: Format) -> Result<bool, String> {
+    let mut rows = Vec::new();
+    let mut success = true;
+
+    for path in &args.positional {
+        let path = PathBuf::from(path);
+        let imported = match import_file(&path) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                success = false;
+                continue;
+            }
+        };
+
+        for warning in &imported.warnings {
+            eprintln!("{}: warning: {warning}", path.display());
+        }
+
+        let data = &imported.data;
+        rows.push(InfoRow {
+            file: path.display().to_string(),
+            name: data.metadata.plasmid_name.clone(),
+            length: data.seq.len(),
+            topology: topology_str(data.topology),
+            gc_portion: (!data.seq.is_empty()).then(|| calc_gc(&data.seq)),
+            features: data.features.len(),
+            primers: data.primers.len(),
+            warnings: imported.warnings.len(),
+        });
+    }
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(success)
+}
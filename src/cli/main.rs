@@ -0,0 +1,288 @@
+//! A command-line interface to PlasCAD, for batch work and scripts. It converts between file formats,
+//! summarizes documents, checks and designs primers, and finds restriction sites, open reading frames,
+//! and PCR products. Results are written to stdout as TSV or JSON; warnings and errors go to stderr.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use plascad::{
+    file_io::{error::Imported, import_file, GenericData},
+    sequence::SeqTopology,
+};
+
+use crate::{
+    args::{Args, TM_OPTIONS},
+    enzymes::ENZYME_OPTIONS,
+    output::Format,
+};
+
+mod args;
+mod assemble;
+mod convert;
+mod digest;
+mod enzymes;
+mod golden_gate;
+mod info;
+mod mutagenesis;
+mod orfs;
+mod output;
+mod pcr;
+mod primers;
+
+const USAGE: &str = "\
+Usage: plascad-cli [--format tsv|json] <COMMAND> [ARGS]
+
+Commands:
+  convert <FILE>... (--to <EXT> [--out-dir <DIR>] | -o <OUTPUT>)
+      Convert files between formats, based on extension: fasta, gb, dna (SnapGene), or pcad.
+  info <FILE>...
+      Summarize documents: name, length, topology, GC content, and feature and primer counts.
+  primers qc [<FILE>...] [--seq <SEQ>]... [TM OPTIONS]
+      Primer quality metrics. Primers are read from documents, FASTA files (one primer per record),
+      or TSV files (name and sequence columns).
+  primers off-target <FILE> [--primers <FILE>]... [--seq <SEQ>]... [--background <FILE>]...
+          [--min-anneal <NT>] [--max-mismatches <N>] [--max-product <BP>] [--products] [TM OPTIONS]
+      Sites primers bind on a construct (both strands) and background sequences, most stable first,
+      and the intended site for each. Primers are the document's, and any given. With --products,
+      list unintended products pairs of sites may amplify instead. Each FASTA background record is
+      a separate sequence.
+  primers design <FILE> (--target <START>..<END> | --feature <LABEL>) [--product <MIN>-<MAX>]
+          [--primer-len <MIN>-<MAX>] [--tm <MIN>-<MAX>] [--gc <MIN>-<MAX>] [--max-tm-diff <C>]
+          [--num <N>] [TM OPTIONS]
+      Design primer pairs that amplify a target region or feature, best first. GC is in percent.
+  assemble <FILE>... [--keep <N>]... [--linear] [--overlap <NT>] [--overlap-tm <C>] [-o <OUTPUT>]
+          [TM OPTIONS]
+      Primers for Gibson or NEBuilder HiFi assembly of documents, in order, with overlaps between
+      adjacent ones. Fragments numbered with --keep, from 1, are used as they are; eg a linearized
+      vector. Overlaps and warnings go to stderr; the assembled product is written to OUTPUT.
+  golden-gate <FILE>... --overhangs <SEQ,SEQ...|moclo> [--enzyme <NAME>] [--rebase <FILE>]
+          [--linear] [-o <OUTPUT>] [TM OPTIONS]
+      Primers for Golden Gate assembly of documents, in order, adding Type IIS sites (BsaI by default,
+      or another from the enzyme library, eg BsmBI, BbsI, SapI) and overhangs. There's an overhang
+      before each part, and for linear assemblies, one after the last. Warnings go to stderr; the
+      simulated product is written to OUTPUT.
+  mutagenesis <FILE> (--sub <START>..<END>=<SEQ> | --ins <POS>=<SEQ> | --del <START>..<END> |
+          --codon <LABEL>:<CHANGE> | --saturate <LABEL>:<POS> [--scheme nnk|nns|22c])
+          [--strategy back-to-back|overlapping] [--anneal-tm <C>] [--code <NCBI ID>] [-o <OUTPUT>]
+          [TM OPTIONS]
+      Primers for site-directed mutagenesis of a circular plasmid: back-to-back (Q5 SDM, default),
+      or overlapping (QuikChange). Insertions start at POS. Codon changes are in protein notation,
+      at an amino acid position in a coding region feature: eg GFP:K45A, GFP:K45del, or GFP:45insG,
+      which inserts before position 45. --saturate randomizes the codon at an amino acid position
+      with degenerate primers: NNK (default), NNS, or 22c, a 12:9:1 mix of NDT, VHG, and TGG
+      primers; mix is each primer's portion. Mutated nucleotides in primers are uppercase. Damaged
+      features go to stderr; the mutated construct is written to OUTPUT.
+  digest <FILE> [--fragments] [ENZYME OPTIONS]
+      Restriction enzyme sites on both strands, the cut positions on each strand, and the ends the
+      cuts leave. Sites may wrap the origin of circular sequences. Uses the Common set by default.
+      With --fragments, list the fragments digesting with all of the enzymes gives instead: their
+      top strand coordinates and length, the enzyme and overhang at each end, and the features each
+      contains entirely.
+  enzymes [ENZYME OPTIONS]
+      The enzyme library: sites, ends, isoschizomers (neoschizomers, which cut differently, are
+      marked), supplier codes, methylation sensitivity, and buffer activity. All enzymes by default.
+  orfs <FILE> [--min-len <AA>] [--alt-starts] [--nested] [--code <NCBI ID>]
+      Open reading frames, and their translations.
+  pcr <TEMPLATE> --fwd <SEQ> --rev <SEQ> [--polymerase normal|high] [--cycles <N>] [TM OPTIONS]
+      PCR products, and thermocycler parameters.
+
+Enzyme options:
+  --rebase <FILE>
+      Load the library from a REBASE file, in withrefm, allenz, or emboss_e format, instead of the
+      built-in one.
+  --enzymes <NAME,NAME...> | --set <NAME>
+      Enzymes by name, or a saved set (enzyme_sets.txt in the working directory), or a built-in
+      one: Common, or Golden Gate.
+  --supplier <CODES>
+      Only enzymes available from one of these REBASE supplier codes, eg N for New England Biolabs.
+
+Tm options:
+  --na <mM> --mg <mM> --dntp <mM> --tris <mM> --primer-conc <nM>
+      Ion and primer concentrations.
+  --dmso <%> --formamide <%>
+      Tm corrections for these additives.
+  --nn-table <1-4>
+      Nearest-neighbor table: Breslauer 1986, Sugimoto 1996, Allawi & SantaLucia 1997, or
+      SantaLucia & Hicks 2004 (default).
+  --salt-corr <0-7>
+      Salt correction, numbered as in BioPython: None, Schildkraut 1965, Wetmur 1991,
+      SantaLucia 1996, SantaLucia 1998 (default), SantaLucia 1998 entropy, Owczarzy 2004,
+      or Owczarzy 2008, which accounts for Mg2+ separately.
+
+Positions are 1-based and inclusive.";
+
+/// Import a document, writing any warnings to stderr.
+fn load(path: &Path) -> Result<GenericData, String> {
+    let Imported { data, warnings } =
+        import_file(path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    for warning in &warnings {
+        eprintln!("{}: warning: {warning}", path.display());
+    }
+    Ok(data)
+}
+
+/// Load a single document, from the only positional argument.
+fn load_single(args: &Args) -> Result<(PathBuf, GenericData), String> {
+    match args.positional.as_slice() {
+        [path] => {
+            let path = PathBuf::from(path);
+            let data = load(&path)?;
+            Ok((path, data))
+        }
+        _ => Err("Expected one input file.".to_owned()),
+    }
+}
+
+fn topology_str(topology: SeqTopology) -> &'static str {
+    match topology {
+        SeqTopology::Linear => "linear",
+        SeqTopology::Circular => "circular",
+    }
+}
+
+/// The options a command accepts, by name without the leading dashes.
+fn command_options(command: &str, subcommand: &str) -> Result<Vec<&'static str>, String> {
+    let (own, shared): (&[&str], &[&str]) = match (command, subcommand) {
+        ("convert", _) => (&["to", "out-dir", "out"], &[]),
+        ("info", _) => (&[], &[]),
+        ("primers", "qc") => (&["seq"], &TM_OPTIONS),
+        ("primers", "off-target") => (
+            &[
+                "primers",
+                "seq",
+                "background",
+                "min-anneal",
+                "max-mismatches",
+                "max-product",
+                "products",
+            ],
+            &TM_OPTIONS,
+        ),
+        ("primers", _) => (
+            &[
+                "target",
+                "feature",
+                "product",
+                "primer-len",
+                "tm",
+                "gc",
+                "max-tm-diff",
+                "num",
+            ],
+            &TM_OPTIONS,
+        ),
+        ("assemble", _) => (
+            &["keep", "linear", "overlap", "overlap-tm", "out"],
+            &TM_OPTIONS,
+        ),
+        ("golden-gate", _) => (
+            &["overhangs", "enzyme", "rebase", "linear", "out"],
+            &TM_OPTIONS,
+        ),
+        ("mutagenesis", _) => (
+            &[
+                "sub",
+                "ins",
+                "del",
+                "codon",
+                "saturate",
+                "scheme",
+                "strategy",
+                "anneal-tm",
+                "code",
+                "out",
+            ],
+            &TM_OPTIONS,
+        ),
+        ("digest", _) => (&["fragments"], &ENZYME_OPTIONS),
+        ("enzymes", _) => (&[], &ENZYME_OPTIONS),
+        ("orfs", _) => (&["min-len", "alt-starts", "nested", "code"], &[]),
+        ("pcr", _) => (&["fwd", "rev", "polymerase", "cycles"], &TM_OPTIONS),
+        _ => return Err(format!("Unknown command: {command}")),
+    };
+
+    Ok([own, shared, &["format", "help"]].concat())
+}
+
+fn run(args: &[String]) -> Result<bool, String> {
+    let mut format = Format::default();
+    let mut args = args;
+
+    while let Some(arg) = args.first() {
+        match arg.as_ref() {
+            "--format" => {
+                let v = args.get(1).ok_or("Missing a value for --format")?;
+                format = v.parse()?;
+                args = &args[2..];
+            }
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(true);
+            }
+            _ => break,
+        }
+    }
+
+    let Some((command, rest)) = args.split_first() else {
+        return Err("No command.".to_owned());
+    };
+
+    let (subcommand, rest) = if command == "primers" {
+        match rest.split_first() {
+            Some((sub, rest)) if ["qc", "off-target", "design"].contains(&sub.as_ref()) => {
+                (sub.as_ref(), rest)
+            }
+            _ => return Err("Expected a primers subcommand: qc, off-target, or design".to_owned()),
+        }
+    } else {
+        ("", rest)
+    };
+
+    let mut args = Args::parse(rest, &command_options(command, subcommand)?)?;
+    // The format may also be specified after the command.
+    if let Some(v) = args
+        .options
+        .remove("format")
+        .and_then(|v| v.last().cloned())
+    {
+        format = v.parse()?;
+    }
+
+    if args.flag("help") {
+        println!("{USAGE}");
+        return Ok(true);
+    }
+
+    match command.as_ref() {
+        "convert" => convert::convert(&args),
+        "info" => info::info(&args, format),
+        "primers" if subcommand == "qc" => primers::qc(&args, format),
+        "primers" if subcommand == "off-target" => primers::off_target(&args, format),
+        "primers" => primers::design(&args, format),
+        "assemble" => assemble::assemble(&args, format),
+        "golden-gate" => golden_gate::golden_gate(&args, format),
+        "mutagenesis" => mutagenesis::mutagenesis(&args, format),
+        "digest" => digest::digest(&args, format),
+        "enzymes" => enzymes::enzymes(&args, format),
+        "orfs" => orfs::orfs(&args, format),
+        "pcr" => pcr::pcr(&args, format),
+        _ => Err(format!("Unknown command: {command}")),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{e}\nRun `plascad-cli --help` for usage.");
+            ExitCode::from(2)
+        }
+    }
+}
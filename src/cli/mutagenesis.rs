@@ -0,0 +1,275 @@
+//! The `mutagenesis` command: Site-directed mutagenesis primers.
+
+use std::path::Path;
+
+use plascad::{
+    file_io::{export_file, GenericData},
+    mutagenesis::{
+        codon_mutation, design_mutagenesis, saturation_mutations, CodonRandomization,
+        MutagenesisParams, MutagenesisStrategy, Mutation, MutationKind,
+    },
+    protein::{translate_feature, AminoAcid, GeneticCode, StopHandling},
+    sequence::{seq_from_str, seq_to_str, SeqRange},
+};
+use serde::Serialize;
+
+use crate::{
+    args::{ion_concentrations, Args},
+    load_single,
+    output::{write_rows, Format, Row},
+};
+
+#[derive(Serialize)]
+struct MutagenesisPrimerRow {
+    name: String,
+    /// Mutated nucleotides are uppercase.
+    sequence: String,
+    length: usize,
+    /// °C, of the annealing region; for overlapping primers, its lower flank.
+    anneal_tm: Option<f32>,
+    /// The portion of the primer mix, for saturation libraries that combine several primers.
+    mix: f32,
+}
+
+impl Row for MutagenesisPrimerRow {
+    const HEADER: &'static [&'static str] = &["name", "sequence", "length", "anneal_tm", "mix"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.sequence.clone(),
+            self.length.to_string(),
+            self.anneal_tm
+                .map(|v| format!("{v:.1}"))
+                .unwrap_or_default(),
+            format!("{:.3}", self.mix),
+        ]
+    }
+}
+
+/// Parse a codon change in protein notation, eg `K45A`, `K45del`, or `45insG`, at an amino acid
+/// position in a feature.
+fn parse_codon_mutation(
+    data: &GenericData,
+    label: &str,
+    change: &str,
+    code: GeneticCode,
+) -> Result<Mutation, String> {
+    let invalid = || format!("Invalid codon change: {change}");
+
+    let feature = data
+        .features
+        .iter()
+        .find(|f| f.label == label)
+        .ok_or_else(|| format!("No feature labeled {label}"))?;
+
+    // The current amino acid is optional; if present, we check it.
+    let (current, rest) = match change.as_bytes().first() {
+        Some(c) if c.is_ascii_alphabetic() || *c == b'*' => (
+            Some(AminoAcid::from_letter(*c).ok_or_else(invalid)?),
+            &change[1..],
+        ),
+        _ => (None, change),
+    };
+
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    let pos: usize = rest[..digits].parse().map_err(|_| invalid())?;
+
+    let (kind, aa) = match &rest[digits..] {
+        "del" => (MutationKind::Deletion, AminoAcid::Unknown),
+        v => {
+            let (kind, aa) = match v.strip_prefix("ins") {
+                Some(aa) => (MutationKind::Insertion, aa),
+                None => (MutationKind::Substitution, v),
+            };
+            let aa = match aa.as_bytes() {
+                [c] => AminoAcid::from_letter(*c).ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            };
+            (kind, aa)
+        }
+    };
+
+    if let Some(current) = current {
+        let protein =
+            translate_feature(feature, &data.seq, code, StopHandling::Include).unwrap_or_default();
+        match protein.get(pos.wrapping_sub(1)) {
+            Some(aa) if *aa == current => (),
+            Some(aa) => {
+                return Err(format!(
+                    "Position {pos} of {label} is {}, not {}.",
+                    aa.to_letter(),
+                    current.to_letter()
+                ))
+            }
+            None => return Err(format!("Position {pos} is outside {label}.")),
+        }
+    }
+
+    codon_mutation(feature, &data.seq, pos, kind, aa, code).ok_or_else(|| {
+        format!("Position {pos} of {label} is outside it, or its codon spans a junction.")
+    })
+}
+
+pub fn mutagenesis(args: &Args, format: Format) -> Result<bool, String> {
+    let (_, data) = load_single(args)?;
+    let ion = ion_concentrations(args)?;
+
+    let code = match args.get("code") {
+        Some(v) => v
+            .parse()
+            .ok()
+            .and_then(GeneticCode::from_ncbi_id)
+            .ok_or_else(|| format!("Unknown genetic code: {v}"))?,
+        None => GeneticCode::Standard,
+    };
+
+    let range = |v: &str| -> Result<SeqRange, String> {
+        let (start, end) = match v.split_once("..") {
+            Some((a, b)) => (a.trim().parse(), b.trim().parse()),
+            None => (v.trim().parse(), v.trim().parse()),
+        };
+        match (start, end) {
+            (Ok(start), Ok(end)) => Ok(SeqRange::new(start, end)),
+            _ => Err(format!("Invalid range: {v}")),
+        }
+    };
+    let with_seq = |v: &str| -> Result<(SeqRange, Vec<_>), String> {
+        let (r, seq) = v
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <RANGE>=<SEQ>: {v}"))?;
+        Ok((range(r)?, seq_from_str(seq.trim())))
+    };
+
+    // Each mutation, with its portion of the primer mix; several for some saturation libraries.
+    let mutations = match (
+        args.get("sub"),
+        args.get("ins"),
+        args.get("del"),
+        args.get("codon"),
+        args.get("saturate"),
+    ) {
+        (Some(v), None, None, None, None) => {
+            let (range, seq) = with_seq(v)?;
+            vec![(
+                Mutation {
+                    kind: MutationKind::Substitution,
+                    range,
+                    seq,
+                },
+                1.,
+            )]
+        }
+        (None, Some(v), None, None, None) => {
+            let (range, seq) = with_seq(v)?;
+            vec![(
+                Mutation {
+                    kind: MutationKind::Insertion,
+                    range,
+                    seq,
+                },
+                1.,
+            )]
+        }
+        (None, None, Some(v), None, None) => vec![(
+            Mutation {
+                kind: MutationKind::Deletion,
+                range: range(v)?,
+                seq: Vec::new(),
+            },
+            1.,
+        )],
+        (None, None, None, Some(v), None) => {
+            let (label, change) = v
+                .rsplit_once(':')
+                .ok_or_else(|| format!("Expected <LABEL>:<CHANGE>: {v}"))?;
+            vec![(parse_codon_mutation(&data, label, change, code)?, 1.)]
+        }
+        (None, None, None, None, Some(v)) => {
+            let (label, pos) = v
+                .rsplit_once(':')
+                .ok_or_else(|| format!("Expected <LABEL>:<POS>: {v}"))?;
+            let pos: usize = pos
+                .parse()
+                .map_err(|_| format!("Invalid amino acid position: {pos}"))?;
+            let feature = data
+                .features
+                .iter()
+                .find(|f| f.label == label)
+                .ok_or_else(|| format!("No feature labeled {label}"))?;
+
+            let scheme = match args.get("scheme") {
+                None | Some("nnk") => CodonRandomization::Nnk,
+                Some("nns") => CodonRandomization::Nns,
+                Some("22c") => CodonRandomization::TwentyTwoC,
+                Some(v) => return Err(format!("Invalid scheme: {v}; use nnk, nns, or 22c.")),
+            };
+
+            saturation_mutations(feature, &data.seq, pos, scheme).ok_or_else(|| {
+                format!("Position {pos} of {label} is outside it, or its codon spans a junction.")
+            })?
+        }
+        _ => return Err("Specify one of --sub, --ins, --del, --codon, or --saturate.".to_owned()),
+    };
+
+    let params = MutagenesisParams {
+        strategy: match args.get("strategy") {
+            None | Some("back-to-back") => MutagenesisStrategy::BackToBack,
+            Some("overlapping") => MutagenesisStrategy::Overlapping,
+            Some(v) => {
+                return Err(format!(
+                    "Invalid strategy: {v}; use back-to-back or overlapping."
+                ))
+            }
+        },
+        anneal_tm: args.parse_or("anneal-tm", MutagenesisParams::default().anneal_tm)?,
+        ..Default::default()
+    };
+
+    let mut rows: Vec<MutagenesisPrimerRow> = Vec::new();
+    for (i, (mutation, mix)) in mutations.iter().enumerate() {
+        let design =
+            design_mutagenesis(&data, mutation, &params, &ion).map_err(|e| e.to_string())?;
+
+        eprintln!("Mutation: {mutation}");
+        for (label, damage) in &design.report.features {
+            eprintln!("note: {label} {damage}");
+        }
+
+        // For libraries of several primers, the construct is the first one's.
+        if i == 0 {
+            if let Some(out) = args.get("out") {
+                export_file(&design.product, Path::new(out)).map_err(|e| format!("{out}: {e}"))?;
+                eprintln!("Product: {} bp -> {out}", design.product.seq.len());
+            }
+        }
+
+        for (primer, change, anneal_tm) in [
+            (&design.fwd, &design.fwd_change, design.fwd_anneal_tm),
+            (&design.rev, &design.rev_change, design.rev_anneal_tm),
+        ] {
+            let seq = seq_to_str(&primer.sequence);
+            let seq = format!(
+                "{}{}{}",
+                &seq[..change.start],
+                seq[change.clone()].to_uppercase(),
+                &seq[change.end..]
+            );
+
+            // Back-to-back libraries share the reverse primer.
+            match rows.iter_mut().find(|r| r.sequence == seq) {
+                Some(row) => row.mix += mix,
+                None => rows.push(MutagenesisPrimerRow {
+                    name: primer.name.clone(),
+                    sequence: seq,
+                    length: primer.sequence.len(),
+                    anneal_tm,
+                    mix: *mix,
+                }),
+            }
+        }
+    }
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(true)
+}
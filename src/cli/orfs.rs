@@ -0,0 +1,81 @@
+//! The `orfs` command: Finding open reading frames.
+
+use plascad::{
+    protein::{protein_to_str, translate_orf, AaIdent, GeneticCode, StopHandling},
+    sequence::{find_orf_matches, NestedOrfPolicy, OrfParams, ReadingFrame},
+};
+use serde::Serialize;
+
+use crate::{
+    args::Args,
+    load_single,
+    output::{write_rows, Format, Row},
+};
+
+#[derive(Serialize)]
+struct OrfRow {
+    frame: String,
+    start: usize,
+    end: usize,
+    length_aa: usize,
+    protein: String,
+}
+
+impl Row for OrfRow {
+    const HEADER: &'static [&'static str] = &["frame", "start", "end", "length_aa", "protein"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.frame.clone(),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.length_aa.to_string(),
+            self.protein.clone(),
+        ]
+    }
+}
+
+pub fn orfs(args: &Args, format: Format) -> Result<bool, String> {
+    let (_, data) = load_single(args)?;
+
+    let code_id = args.parse_or("code", GeneticCode::default() as u8)?;
+    let genetic_code = GeneticCode::from_ncbi_id(code_id)
+        .ok_or_else(|| format!("Unsupported genetic code: {code_id}"))?;
+
+    let params = OrfParams {
+        alt_starts: args.flag("alt-starts"),
+        min_len_aa: args.parse_or("min-len", OrfParams::default().min_len_aa)?,
+        nested: if args.flag("nested") {
+            NestedOrfPolicy::All
+        } else {
+            NestedOrfPolicy::Outermost
+        },
+    };
+
+    let matches = find_orf_matches(
+        &data.seq,
+        data.topology,
+        &ReadingFrame::ALL,
+        genetic_code,
+        &params,
+    );
+
+    let rows: Vec<OrfRow> = matches
+        .iter()
+        .map(|orf| {
+            let protein = translate_orf(orf, &data.seq, genetic_code, StopHandling::Truncate)
+                .unwrap_or_default();
+
+            OrfRow {
+                frame: orf.frame.to_string(),
+                start: orf.range.start,
+                end: orf.range.end,
+                length_aa: protein.len(),
+                protein: protein_to_str(&protein, AaIdent::OneLetter),
+            }
+        })
+        .collect();
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(true)
+}
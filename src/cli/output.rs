@@ -0,0 +1,70 @@
+//! Writing command results to stdout, as TSV or JSON.
+
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    /// Tab-separated values, with a header line.
+    #[default]
+    Tsv,
+    /// A JSON array, with one object per row.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(v: &str) -> Result<Self, Self::Err> {
+        match v.to_lowercase().as_ref() {
+            "tsv" => Ok(Self::Tsv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Invalid format: {v}; use tsv or json.")),
+        }
+    }
+}
+
+/// A row of command output. For JSON, rows are serialized directly; for TSV, columns are in the order
+/// of `HEADER`.
+pub trait Row: Serialize {
+    const HEADER: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+}
+
+/// Tabs and line breaks would break the TSV layout; replace them with spaces.
+fn tsv_field(v: &str) -> String {
+    v.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Write rows to stdout. A closed pipe, eg from `head`, isn't an error.
+pub fn write_rows<T: Row>(rows: &[T], format: Format) -> io::Result<()> {
+    match write_rows_inner(rows, format) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+fn write_rows_inner<T: Row>(rows: &[T], format: Format) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+
+    match format {
+        Format::Tsv => {
+            writeln!(out, "{}", T::HEADER.join("\t"))?;
+            for row in rows {
+                let fields: Vec<String> = row.fields().iter().map(|f| tsv_field(f)).collect();
+                writeln!(out, "{}", fields.join("\t"))?;
+            }
+        }
+        Format::Json => {
+            let json = serde_json::to_string_pretty(rows).map_err(io::Error::other)?;
+            writeln!(out, "{json}")?;
+        }
+    }
+
+    Ok(())
+}
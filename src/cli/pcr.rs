@@ -0,0 +1,108 @@
+//! The `pcr` command: Simulating PCR products.
+
+use plascad::{
+    pcr::{find_products, PcrParams, PolymeraseType},
+    primer::Primer,
+    sequence::{seq_from_str, seq_to_str},
+};
+use serde::Serialize;
+
+use crate::{
+    args::{ion_concentrations, Args},
+    load_single,
+    output::{write_rows, Format, Row},
+};
+
+#[derive(Serialize)]
+struct PcrRow {
+    start: usize,
+    end: usize,
+    length: usize,
+    /// °C
+    annealing_temp: f32,
+    /// Seconds
+    extension_time: u16,
+    num_cycles: u16,
+    sequence: String,
+}
+
+impl Row for PcrRow {
+    const HEADER: &'static [&'static str] = &[
+        "start",
+        "end",
+        "length",
+        "annealing_temp",
+        "extension_time",
+        "num_cycles",
+        "sequence",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.start.to_string(),
+            self.end.to_string(),
+            self.length.to_string(),
+            format!("{:.1}", self.annealing_temp),
+            self.extension_time.to_string(),
+            self.num_cycles.to_string(),
+            self.sequence.clone(),
+        ]
+    }
+}
+
+pub fn pcr(args: &Args, format: Format) -> Result<bool, String> {
+    let (_, data) = load_single(args)?;
+    let ion = ion_concentrations(args)?;
+
+    let primer = |name: &str| -> Result<Primer, String> {
+        let seq = args
+            .get(name)
+            .ok_or_else(|| format!("Missing --{name} primer."))?;
+        Ok(Primer {
+            sequence: seq_from_str(seq),
+            name: name.to_owned(),
+            ..Default::default()
+        })
+    };
+    let primer_fwd = primer("fwd")?;
+    let primer_rev = primer("rev")?;
+
+    let polymerase_type = match args.get("polymerase").unwrap_or("normal") {
+        "normal" => PolymeraseType::NormalFidelity,
+        "high" => PolymeraseType::HighFidelity,
+        v => return Err(format!("Invalid polymerase: {v}; use normal or high.")),
+    };
+    let num_cycles = args.parse_or("cycles", 30)?;
+
+    // Anneal based on the primer with the lower melting temperature.
+    let primer_tm = match (primer_fwd.calc_metrics(&ion), primer_rev.calc_metrics(&ion)) {
+        (Some(fwd), Some(rev)) => fwd.melting_temp.min(rev.melting_temp),
+        _ => return Err("The primers are too short.".to_owned()),
+    };
+
+    let seq_len = data.seq.len();
+    let rows: Vec<PcrRow> = find_products(&data.seq, data.topology, &primer_fwd, &primer_rev)
+        .into_iter()
+        .map(|range| {
+            let length = range.len(seq_len);
+            let params = PcrParams::new(primer_tm, length, polymerase_type, num_cycles);
+
+            PcrRow {
+                start: range.start,
+                end: range.end,
+                length,
+                annealing_temp: params.annealing.temp,
+                extension_time: params.extension.time,
+                num_cycles: params.num_cycles,
+                sequence: seq_to_str(&range.seq(&data.seq).unwrap_or_default()),
+            }
+        })
+        .collect();
+
+    if rows.is_empty() {
+        eprintln!("No products: the primers don't both bind the template, in opposing directions.");
+    }
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(!rows.is_empty())
+}
@@ -0,0 +1,542 @@
+//! The `primers` commands: Checking primer quality, finding off-target binding, and designing
+//! primer pairs.
+
+use std::path::{Path, PathBuf};
+
+use bio::io::fasta;
+use plascad::{
+    file_io::error::Imported,
+    off_target::{find_off_target, load_background, OffTargetParams, TargetSeq},
+    primer::{BindingParams, Primer, PrimerDirection},
+    primer_design::{design_primer_pairs, DesignParams},
+    sequence::{seq_from_str, seq_to_str, SeqRange},
+};
+use serde::Serialize;
+
+use crate::{
+    args::{ion_concentrations, parse_pair, Args},
+    load, load_single,
+    output::{write_rows, Format, Row},
+};
+
+/// Read primers from a FASTA file, with one primer per record.
+fn primers_from_fasta(path: &Path) -> Result<Vec<Primer>, String> {
+    let reader = fasta::Reader::from_file(path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    let mut result = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("{}: {e}", path.display()))?;
+        result.push(Primer {
+            sequence: seq_from_str(&String::from_utf8_lossy(record.seq())),
+            name: record.id().to_owned(),
+            ..Default::default()
+        });
+    }
+
+    Ok(result)
+}
+
+/// Read primers from a TSV file, with name and sequence columns. A line with only one column is a
+/// sequence, without a name. Lines starting with `#` are ignored, as is a header line.
+fn primers_from_tsv(path: &Path) -> Result<Vec<Primer>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    let mut result = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (name, seq) = match line.split_once('\t') {
+            Some((name, rest)) => (name.trim(), rest.split('\t').next().unwrap_or_default()),
+            None => ("", line),
+        };
+
+        if i == 0 && seq.trim().eq_ignore_ascii_case("sequence") {
+            continue;
+        }
+
+        result.push(Primer {
+            sequence: seq_from_str(seq),
+            name: name.to_owned(),
+            ..Default::default()
+        });
+    }
+
+    Ok(result)
+}
+
+#[derive(Serialize)]
+struct PrimerQcRow {
+    name: String,
+    sequence: String,
+    length: usize,
+    /// These are absent for primers too short to evaluate.
+    melting_temp: Option<f32>,
+    /// For degenerate primers, the range of Tm of the sequences they represent, and their number.
+    tm_min: Option<f32>,
+    tm_max: Option<f32>,
+    degeneracy: Option<u64>,
+    gc_portion: Option<f32>,
+    gc_3p_count: Option<u8>,
+    /// Self-dimer ΔG in kcal/mol, with a paired 3' end, and anywhere.
+    self_dimer_3p_dg: Option<f32>,
+    self_dimer_dg: Option<f32>,
+    /// The most stable secondary structure: ΔG in kcal/mol, Tm, and dot-bracket notation.
+    hairpin_dg: Option<f32>,
+    hairpin_tm: Option<f32>,
+    hairpin_structure: Option<String>,
+    repeats: Option<u8>,
+    tm_score: Option<f32>,
+    gc_score: Option<f32>,
+    gc_3p_score: Option<f32>,
+    dimer_score: Option<f32>,
+    hairpin_score: Option<f32>,
+    repeats_score: Option<f32>,
+    quality_score: Option<f32>,
+}
+
+impl Row for PrimerQcRow {
+    const HEADER: &'static [&'static str] = &[
+        "name",
+        "sequence",
+        "length",
+        "melting_temp",
+        "tm_min",
+        "tm_max",
+        "degeneracy",
+        "gc_portion",
+        "gc_3p_count",
+        "self_dimer_3p_dg",
+        "self_dimer_dg",
+        "hairpin_dg",
+        "hairpin_tm",
+        "hairpin_structure",
+        "repeats",
+        "tm_score",
+        "gc_score",
+        "gc_3p_score",
+        "dimer_score",
+        "hairpin_score",
+        "repeats_score",
+        "quality_score",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        fn opt<T: ToString>(v: Option<T>) -> String {
+            v.map(|v| v.to_string()).unwrap_or_default()
+        }
+        fn opt_f(v: Option<f32>, precision: usize) -> String {
+            v.map(|v| format!("{v:.precision$}")).unwrap_or_default()
+        }
+
+        vec![
+            self.name.clone(),
+            self.sequence.clone(),
+            self.length.to_string(),
+            opt_f(self.melting_temp, 1),
+            opt_f(self.tm_min, 1),
+            opt_f(self.tm_max, 1),
+            opt(self.degeneracy),
+            opt_f(self.gc_portion, 3),
+            opt(self.gc_3p_count),
+            opt_f(self.self_dimer_3p_dg, 2),
+            opt_f(self.self_dimer_dg, 2),
+            opt_f(self.hairpin_dg, 2),
+            opt_f(self.hairpin_tm, 1),
+            self.hairpin_structure.clone().unwrap_or_default(),
+            opt(self.repeats),
+            opt_f(self.tm_score, 3),
+            opt_f(self.gc_score, 3),
+            opt_f(self.gc_3p_score, 3),
+            opt_f(self.dimer_score, 3),
+            opt_f(self.hairpin_score, 3),
+            opt_f(self.repeats_score, 3),
+            opt_f(self.quality_score, 3),
+        ]
+    }
+}
+
+/// Read primers from documents, FASTA files (one primer per record), or TSV files, and add ones
+/// specified with `--seq`.
+fn load_primers(paths: &[String], args: &Args) -> Result<Vec<Primer>, String> {
+    let mut primers = Vec::new();
+    for path in paths {
+        let path = PathBuf::from(path);
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match extension.as_ref() {
+            "fasta" | "fa" => primers.extend(primers_from_fasta(&path)?),
+            "tsv" | "txt" => primers.extend(primers_from_tsv(&path)?),
+            _ => primers.extend(load(&path)?.primers),
+        }
+    }
+
+    for (i, seq) in args.get_all("seq").iter().enumerate() {
+        primers.push(Primer {
+            sequence: seq_from_str(seq),
+            name: format!("seq_{}", i + 1),
+            ..Default::default()
+        });
+    }
+
+    Ok(primers)
+}
+
+pub fn qc(args: &Args, format: Format) -> Result<bool, String> {
+    let ion = ion_concentrations(args)?;
+    let primers = load_primers(&args.positional, args)?;
+
+    if primers.is_empty() {
+        return Err("No primers; specify files, or sequences with --seq.".to_owned());
+    }
+
+    let rows: Vec<PrimerQcRow> = primers
+        .iter()
+        .map(|primer| {
+            let metrics = primer.calc_metrics(&ion);
+            let m = metrics.as_ref();
+
+            PrimerQcRow {
+                name: primer.name.clone(),
+                sequence: seq_to_str(&primer.sequence),
+                length: primer.sequence.len(),
+                melting_temp: m.map(|m| m.melting_temp),
+                tm_min: m.map(|m| m.tm_range.0),
+                tm_max: m.map(|m| m.tm_range.1),
+                degeneracy: m.map(|m| m.degeneracy),
+                gc_portion: m.map(|m| m.gc_portion),
+                gc_3p_count: m.map(|m| m.gc_3p_count),
+                self_dimer_3p_dg: m.and_then(|m| m.self_dimer.end_3p.as_ref().map(|d| d.dg)),
+                self_dimer_dg: m.and_then(|m| m.self_dimer.any.as_ref().map(|d| d.dg)),
+                hairpin_dg: m.map(|m| m.hairpin.dg),
+                hairpin_tm: m.and_then(|m| m.hairpin.tm),
+                hairpin_structure: m.map(|m| m.hairpin.dot_bracket(primer.sequence.len())),
+                repeats: m.map(|m| m.repeats),
+                tm_score: m.map(|m| m.tm_score),
+                gc_score: m.map(|m| m.gc_score),
+                gc_3p_score: m.map(|m| m.gc_3p_score),
+                dimer_score: m.map(|m| m.dimer_score),
+                hairpin_score: m.map(|m| m.hairpin_score),
+                repeats_score: m.map(|m| m.repeats_score),
+                quality_score: m.map(|m| m.quality_score),
+            }
+        })
+        .collect();
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[derive(Serialize)]
+struct OffTargetSiteRow {
+    primer: String,
+    target: String,
+    start: usize,
+    end: usize,
+    direction: &'static str,
+    tail_len: usize,
+    /// 1-based positions in the primer, from its 5' end.
+    mismatches: Vec<usize>,
+    melting_temp: Option<f32>,
+    /// kcal/mol, at 37°C.
+    dg: Option<f32>,
+    intended: bool,
+}
+
+impl Row for OffTargetSiteRow {
+    const HEADER: &'static [&'static str] = &[
+        "primer",
+        "target",
+        "start",
+        "end",
+        "direction",
+        "tail_len",
+        "mismatches",
+        "melting_temp",
+        "dg",
+        "intended",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let mismatches: Vec<String> = self.mismatches.iter().map(|i| i.to_string()).collect();
+
+        vec![
+            self.primer.clone(),
+            self.target.clone(),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.direction.to_owned(),
+            self.tail_len.to_string(),
+            mismatches.join(","),
+            self.melting_temp
+                .map(|v| format!("{v:.1}"))
+                .unwrap_or_default(),
+            self.dg.map(|v| format!("{v:.2}")).unwrap_or_default(),
+            self.intended.to_string(),
+        ]
+    }
+}
+
+#[derive(Serialize)]
+struct OffTargetProductRow {
+    primer_fwd: String,
+    primer_rev: String,
+    target: String,
+    start: usize,
+    end: usize,
+    length: usize,
+}
+
+impl Row for OffTargetProductRow {
+    const HEADER: &'static [&'static str] = &[
+        "primer_fwd",
+        "primer_rev",
+        "target",
+        "start",
+        "end",
+        "length",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.primer_fwd.clone(),
+            self.primer_rev.clone(),
+            self.target.clone(),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.length.to_string(),
+        ]
+    }
+}
+
+pub fn off_target(args: &Args, format: Format) -> Result<bool, String> {
+    let (_, data) = load_single(args)?;
+    let ion = ion_concentrations(args)?;
+
+    let mut primers = data.primers.clone();
+    primers.extend(load_primers(args.get_all("primers"), args)?);
+    if primers.is_empty() {
+        return Err("No primers; the document has none, and none were given.".to_owned());
+    }
+
+    let mut background = Vec::new();
+    for path in args.get_all("background") {
+        let path = Path::new(path);
+        let Imported { data, warnings } =
+            load_background(path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+        for warning in &warnings {
+            eprintln!("{}: warning: {warning}", path.display());
+        }
+        background.extend(data);
+    }
+
+    let default = OffTargetParams::default();
+    let params = OffTargetParams {
+        binding: BindingParams {
+            min_anneal_len: args.parse_or("min-anneal", default.binding.min_anneal_len)?,
+            max_mismatches: args.parse_or("max-mismatches", default.binding.max_mismatches)?,
+            ..default.binding
+        },
+        max_product_len: args.parse_or("max-product", default.max_product_len)?,
+    };
+
+    let construct = TargetSeq {
+        name: data.metadata.plasmid_name,
+        seq: data.seq,
+        topology: data.topology,
+    };
+    let report = find_off_target(&primers, &construct, &background, &ion, &params);
+
+    let target_name = |target: Option<usize>| match target {
+        Some(i) => background[i].name.clone(),
+        None => construct.name.clone(),
+    };
+
+    if args.flag("products") {
+        let rows: Vec<OffTargetProductRow> = report
+            .products
+            .iter()
+            .map(|p| OffTargetProductRow {
+                primer_fwd: primers[p.primer_fwd].name.clone(),
+                primer_rev: primers[p.primer_rev].name.clone(),
+                target: target_name(p.target),
+                start: p.range.start,
+                end: p.range.end,
+                length: p.len,
+            })
+            .collect();
+
+        write_rows(&rows, format).map_err(|e| e.to_string())?;
+        return Ok(true);
+    }
+
+    let rows: Vec<OffTargetSiteRow> = report
+        .sites
+        .iter()
+        .map(|s| OffTargetSiteRow {
+            primer: primers[s.primer].name.clone(),
+            target: target_name(s.target),
+            start: s.site.range.start,
+            end: s.site.range.end,
+            direction: match s.site.direction {
+                PrimerDirection::Forward => "forward",
+                PrimerDirection::Reverse => "reverse",
+            },
+            tail_len: s.site.tail_len,
+            mismatches: s.site.mismatches.iter().map(|i| i + 1).collect(),
+            melting_temp: s.site.tm,
+            dg: s.dg,
+            intended: s.intended,
+        })
+        .collect();
+
+    if !report.products.is_empty() {
+        eprintln!(
+            "warning: {} unintended products; run with --products to list them.",
+            report.products.len()
+        );
+    }
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[derive(Serialize)]
+struct PrimerDesignRow {
+    rank: usize,
+    fwd: String,
+    rev: String,
+    fwd_start: usize,
+    fwd_end: usize,
+    rev_start: usize,
+    rev_end: usize,
+    product_start: usize,
+    product_end: usize,
+    product_len: usize,
+    fwd_tm: Option<f32>,
+    rev_tm: Option<f32>,
+    /// Hetero-dimer ΔG in kcal/mol, with a paired 3' end.
+    dimer_3p_dg: Option<f32>,
+    /// ΔG in kcal/mol of the product's most stable secondary structure; for long products, of their
+    /// ends.
+    amplicon_dg: f32,
+    score: f32,
+}
+
+impl Row for PrimerDesignRow {
+    const HEADER: &'static [&'static str] = &[
+        "rank",
+        "fwd",
+        "rev",
+        "fwd_start",
+        "fwd_end",
+        "rev_start",
+        "rev_end",
+        "product_start",
+        "product_end",
+        "product_len",
+        "fwd_tm",
+        "rev_tm",
+        "dimer_3p_dg",
+        "amplicon_dg",
+        "score",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let opt_f = |v: Option<f32>, precision: usize| {
+            v.map(|v| format!("{v:.precision$}")).unwrap_or_default()
+        };
+
+        vec![
+            self.rank.to_string(),
+            self.fwd.clone(),
+            self.rev.clone(),
+            self.fwd_start.to_string(),
+            self.fwd_end.to_string(),
+            self.rev_start.to_string(),
+            self.rev_end.to_string(),
+            self.product_start.to_string(),
+            self.product_end.to_string(),
+            self.product_len.to_string(),
+            opt_f(self.fwd_tm, 1),
+            opt_f(self.rev_tm, 1),
+            opt_f(self.dimer_3p_dg, 2),
+            format!("{:.2}", self.amplicon_dg),
+            format!("{:.3}", self.score),
+        ]
+    }
+}
+
+pub fn design(args: &Args, format: Format) -> Result<bool, String> {
+    let (_, data) = load_single(args)?;
+    let ion = ion_concentrations(args)?;
+
+    let default = DesignParams::default();
+
+    let target = match (args.get("target"), args.get("feature")) {
+        (Some(_), None) => {
+            let (start, end) = parse_pair(args, "target", "..", (0, 0))?;
+            SeqRange::new(start, end)
+        }
+        (None, Some(label)) => {
+            data.features
+                .iter()
+                .find(|f| f.label == label)
+                .ok_or_else(|| format!("No feature labeled {label}"))?
+                .index_range
+        }
+        _ => return Err("Specify one of --target or --feature.".to_owned()),
+    };
+    if !target.is_valid(data.seq.len()) {
+        return Err(format!("The target, {target}, is outside the sequence."));
+    }
+
+    let gc = parse_pair(args, "gc", "-", (default.gc.0 * 100., default.gc.1 * 100.))?;
+    let params = DesignParams {
+        target,
+        product_len: parse_pair(args, "product", "-", default.product_len)?,
+        primer_len: parse_pair(args, "primer-len", "-", default.primer_len)?,
+        tm: parse_pair(args, "tm", "-", default.tm)?,
+        gc: (gc.0 / 100., gc.1 / 100.),
+        max_tm_diff: args.parse_or("max-tm-diff", default.max_tm_diff)?,
+        num_results: args.parse_or("num", default.num_results)?,
+    };
+
+    let pairs = design_primer_pairs(&data.seq, data.topology, &params, &ion);
+
+    let rows: Vec<PrimerDesignRow> = pairs
+        .iter()
+        .enumerate()
+        .map(|(i, pair)| PrimerDesignRow {
+            rank: i + 1,
+            fwd: seq_to_str(&pair.fwd.sequence),
+            rev: seq_to_str(&pair.rev.sequence),
+            fwd_start: pair.fwd_site.start,
+            fwd_end: pair.fwd_site.end,
+            rev_start: pair.rev_site.start,
+            rev_end: pair.rev_site.end,
+            product_start: pair.product.start,
+            product_end: pair.product.end,
+            product_len: pair.product_len,
+            fwd_tm: pair.fwd.volatile.metrics.as_ref().map(|m| m.melting_temp),
+            rev_tm: pair.rev.volatile.metrics.as_ref().map(|m| m.melting_temp),
+            dimer_3p_dg: pair.hetero_dimer.end_3p.as_ref().map(|d| d.dg),
+            amplicon_dg: pair.amplicon_dg,
+            score: pair.score,
+        })
+        .collect();
+
+    if rows.is_empty() {
+        eprintln!("No primer pairs meet these constraints.");
+    }
+
+    write_rows(&rows, format).map_err(|e| e.to_string())?;
+    Ok(!rows.is_empty())
+}
@@ -0,0 +1,403 @@
+//! This module contains virtual restriction digests: Cutting a sequence with one or more enzymes, and
+//! listing the fragments, their ends, and the features they contain. It also simulates the fragments'
+//! bands on an agarose gel, next to a DNA ladder, eg for planning diagnostic digests.
+//!
+//! Fragment coordinates and lengths are of the top strand; fragments with 5' overhangs have a few
+//! more nucleotides on the bottom one.
+
+use std::{cmp::Reverse, fmt::Display};
+
+use crate::{
+    restriction_enzyme::{find_re_matches, CutSite, EndType, RestrictionEnzyme},
+    sequence::{Feature, Nucleotide, Seq, SeqRange, SeqTopology},
+};
+
+/// One end of a fragment, where an enzyme cut.
+#[derive(Clone, Debug)]
+pub struct FragmentEnd {
+    /// The enzyme, as an index in the library digested with.
+    pub lib_index: usize,
+    pub end_type: EndType,
+    /// The overhang's nucleotides, 5' to 3' on the top strand; empty for blunt ends. Ends with the same
+    /// overhang, and end type, can be ligated.
+    pub overhang: Seq,
+}
+
+#[derive(Clone, Debug)]
+pub struct Fragment {
+    /// This may wrap the origin of circular sequences.
+    pub range: SeqRange,
+    pub len: usize,
+    /// The end at the fragment's start. `None` if it's the end of a linear sequence, or the sequence
+    /// wasn't cut.
+    pub left: Option<FragmentEnd>,
+    pub right: Option<FragmentEnd>,
+    /// Indices of features entirely within the fragment.
+    pub features: Vec<usize>,
+}
+
+impl Fragment {
+    /// If this is a circular sequence that wasn't cut.
+    pub fn is_uncut_circle(&self, topology: SeqTopology) -> bool {
+        topology == SeqTopology::Circular && self.left.is_none()
+    }
+}
+
+/// The end a cut leaves, and its overhang.
+fn cut_end(cut: CutSite, lib_index: usize, seq: &[Nucleotide]) -> FragmentEnd {
+    let len = seq.len() as isize;
+    let mut overhang = cut.bottom as isize - cut.top as isize;
+    // On circular sequences, cuts are modulo the length; the overhang is the short way around.
+    if overhang > len / 2 {
+        overhang -= len;
+    } else if overhang < -len / 2 {
+        overhang += len;
+    }
+
+    // The overhang is between the cuts: After the top strand's for 5' overhangs, and before it for 3'.
+    let start = cut.top as isize + overhang.min(0);
+
+    FragmentEnd {
+        lib_index,
+        end_type: match overhang {
+            0 => EndType::Blunt,
+            1.. => EndType::FivePrime(overhang as usize),
+            _ => EndType::ThreePrime(overhang.unsigned_abs()),
+        },
+        overhang: (0..overhang.abs())
+            .map(|i| seq[(start + i).rem_euclid(len) as usize])
+            .collect(),
+    }
+}
+
+/// If all of a feature's ranges are within a fragment.
+fn contains_feature(fragment: SeqRange, feature: &Feature, seq_len: usize) -> bool {
+    let frag_len = fragment.len(seq_len);
+    // 0-based position within the fragment.
+    let offset = |i: usize| (i + seq_len - fragment.start) % seq_len;
+
+    feature.ranges().iter().all(|r| {
+        r.is_valid(seq_len)
+            && r.len(seq_len) <= frag_len
+            && offset(r.start) <= offset(r.end)
+            && offset(r.end) < frag_len
+    })
+}
+
+/// Cut a sequence with each enzyme in a library; eg the few chosen for a digest. Returns fragments
+/// in order along the sequence. If nothing cuts, the result is the whole sequence, with no ends.
+pub fn digest(
+    seq: &[Nucleotide],
+    topology: SeqTopology,
+    lib: &[RestrictionEnzyme],
+    features: &[Feature],
+) -> Vec<Fragment> {
+    let seq_len = seq.len();
+    if seq_len == 0 {
+        return Vec::new();
+    }
+
+    let mut cuts: Vec<(CutSite, usize)> = find_re_matches(seq, topology, lib)
+        .iter()
+        .flat_map(|m| m.cuts.iter().map(move |c| (*c, m.lib_index)))
+        // A cut only on the bottom strand, at a linear sequence's end, doesn't separate a fragment.
+        .filter(|(c, _)| topology == SeqTopology::Circular || (c.top > 0 && c.top < seq_len))
+        .collect();
+
+    // Isoschizomers, and overlapping sites, may cut at the same place.
+    cuts.sort_by_key(|(c, _)| c.top);
+    cuts.dedup_by_key(|(c, _)| c.top);
+
+    let ends: Vec<(usize, FragmentEnd)> = cuts
+        .into_iter()
+        .map(|(c, lib_index)| (c.top, cut_end(c, lib_index, seq)))
+        .collect();
+
+    // Fragment boundaries, as 0-based top strand positions, and the ends there.
+    let mut bounds: Vec<(usize, Option<&FragmentEnd>)> =
+        ends.iter().map(|(i, end)| (*i, Some(end))).collect();
+    match topology {
+        SeqTopology::Linear => {
+            bounds.insert(0, (0, None));
+            bounds.push((seq_len, None));
+        }
+        SeqTopology::Circular => match bounds.first().copied() {
+            // The last fragment wraps the origin, to the first cut.
+            Some((first, end)) => bounds.push((first + seq_len, end)),
+            None => bounds = vec![(0, None), (seq_len, None)],
+        },
+    }
+
+    bounds
+        .windows(2)
+        .map(|w| {
+            let (start, left) = w[0];
+            let (end, right) = w[1];
+            let range = SeqRange::from_start_len(start, end - start, seq_len);
+            // An uncut circle contains every feature, including ones that wrap its origin.
+            let uncut = topology == SeqTopology::Circular && left.is_none();
+
+            Fragment {
+                range,
+                len: end - start,
+                left: left.cloned(),
+                right: right.cloned(),
+                features: features
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, f)| {
+                        if uncut {
+                            f.ranges().iter().all(|r| r.is_valid(seq_len))
+                        } else {
+                            contains_feature(range, f, seq_len)
+                        }
+                    })
+                    .map(|(i, _)| i)
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Commercial DNA size standards. Band sizes and masses are approximately those of NEB's ladders.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Ladder {
+    OneKb,
+    OneKbPlus,
+    HundredBp,
+    FiftyBp,
+}
+
+impl Display for Ladder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::OneKb => "1 kb",
+            Self::OneKbPlus => "1 kb Plus",
+            Self::HundredBp => "100 bp",
+            Self::FiftyBp => "50 bp",
+        };
+        write!(f, "{str}")
+    }
+}
+
+impl Ladder {
+    pub const ALL: [Self; 4] = [Self::OneKb, Self::OneKbPlus, Self::HundredBp, Self::FiftyBp];
+
+    /// Each band's size in bp, and mass in ng, in a typical loading; largest first. Reference bands
+    /// are brighter.
+    pub fn bands(self) -> Vec<(usize, f32)> {
+        match self {
+            Self::OneKb => vec![
+                (10_000, 42.),
+                (8_000, 42.),
+                (6_000, 50.),
+                (5_000, 42.),
+                (4_000, 33.),
+                (3_000, 125.),
+                (2_000, 48.),
+                (1_500, 36.),
+                (1_000, 42.),
+                (500, 42.),
+            ],
+            Self::OneKbPlus => vec![
+                (10_000, 20.),
+                (8_000, 20.),
+                (6_000, 20.),
+                (5_000, 20.),
+                (4_000, 20.),
+                (3_000, 60.),
+                (2_000, 20.),
+                (1_500, 20.),
+                (1_200, 20.),
+                (1_000, 60.),
+                (900, 20.),
+                (800, 20.),
+                (700, 20.),
+                (600, 20.),
+                (500, 60.),
+                (400, 20.),
+                (300, 20.),
+                (200, 20.),
+                (100, 20.),
+            ],
+            Self::HundredBp => vec![
+                (1_517, 45.),
+                (1_200, 35.),
+                (1_000, 95.),
+                (900, 27.),
+                (800, 24.),
+                (700, 21.),
+                (600, 18.),
+                (517, 49.),
+                (500, 48.),
+                (400, 38.),
+                (300, 29.),
+                (200, 25.),
+                (100, 48.),
+            ],
+            Self::FiftyBp => vec![
+                (1_350, 50.),
+                (916, 50.),
+                (766, 50.),
+                (700, 50.),
+                (500, 50.),
+                (350, 100.),
+                (300, 50.),
+                (250, 50.),
+                (200, 50.),
+                (150, 50.),
+                (100, 50.),
+                (50, 50.),
+            ],
+        }
+    }
+}
+
+/// Where a linear double-stranded fragment of this length runs on an agarose gel of this percentage,
+/// from 0 (the well) to 1 (the end of the gel). Migration is roughly linear in the log of length
+/// within the range a gel resolves, eg 0.5 - 10 kb for 1% agarose, and 0.1 - 2 kb for 2%; fragments
+/// outside it run bunched together near the well, or near the end.
+pub fn gel_position(len: usize, agarose_pct: f32) -> f32 {
+    let pct = agarose_pct.clamp(0.3, 4.);
+    let upper = (12_000. / pct.powf(2.3)).log10();
+    let lower = (600. / pct.powf(2.5)).log10();
+
+    let t = (upper - (len.max(1) as f32).log10()) / (upper - lower);
+    0.5 + 0.5 * (1.8 * (t - 0.5)).tanh()
+}
+
+/// Bands from a digest's fragments: Sizes, and relative mass, for equal amounts of each fragment.
+/// Fragments of the same size run as one band. Largest first.
+pub fn fragment_bands(fragments: &[Fragment]) -> Vec<(usize, f32)> {
+    let mut result: Vec<(usize, f32)> = Vec::new();
+    for fragment in fragments {
+        match result.iter_mut().find(|(len, _)| *len == fragment.len) {
+            Some(band) => band.1 += fragment.len as f32,
+            None => result.push((fragment.len, fragment.len as f32)),
+        }
+    }
+
+    result.sort_by_key(|(len, _)| Reverse(*len));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sequence::seq_from_str,
+        test_fixtures::{enzymes, feature},
+    };
+
+    /// EcoRI at 5 - 10, and BamHI at 29 - 34.
+    const SEQ: &str = "AAAAGAATTCAAAAAAAAAAAAAAAAAAGGATCCAAAAAAAAAA";
+
+    const ENZYMES: [&str; 3] = ["EcoRI", "BamHI", "PstI"];
+
+    fn assert_end(end: &Option<FragmentEnd>, lib_index: usize, end_type: EndType, overhang: &str) {
+        let end = end.as_ref().unwrap();
+        assert_eq!(end.lib_index, lib_index);
+        assert_eq!(end.end_type, end_type);
+        assert_eq!(end.overhang, seq_from_str(overhang));
+    }
+
+    #[test]
+    fn circular() {
+        // Within the first fragment, within the second, which wraps the origin, and across a cut.
+        let features = [
+            feature("", &[(10, 20)]),
+            feature("", &[(40, 3)]),
+            feature("", &[(1, 10)]),
+        ];
+        let result = digest(
+            &seq_from_str(SEQ),
+            SeqTopology::Circular,
+            &enzymes(&ENZYMES),
+            &features,
+        );
+        assert_eq!(result.len(), 2);
+
+        assert_eq!(result[0].range, SeqRange::new(6, 29));
+        assert_eq!(result[0].len, 24);
+        assert_end(&result[0].left, 0, EndType::FivePrime(4), "AATT");
+        assert_end(&result[0].right, 1, EndType::FivePrime(4), "GATC");
+        assert_eq!(result[0].features, [0]);
+
+        assert_eq!(result[1].range, SeqRange::new(30, 5));
+        assert_eq!(result[1].len, 20);
+        assert_end(&result[1].left, 1, EndType::FivePrime(4), "GATC");
+        assert_end(&result[1].right, 0, EndType::FivePrime(4), "AATT");
+        assert_eq!(result[1].features, [1]);
+    }
+
+    #[test]
+    fn linear() {
+        let result = digest(
+            &seq_from_str(SEQ),
+            SeqTopology::Linear,
+            &enzymes(&ENZYMES),
+            &[],
+        );
+        let ranges: Vec<_> = result.iter().map(|f| f.range).collect();
+        assert_eq!(
+            ranges,
+            [
+                SeqRange::new(1, 5),
+                SeqRange::new(6, 29),
+                SeqRange::new(30, 44)
+            ]
+        );
+        assert!(result[0].left.is_none() && result[2].right.is_none());
+        assert!(!result[0].is_uncut_circle(SeqTopology::Linear));
+    }
+
+    #[test]
+    fn uncut() {
+        let seq = seq_from_str("AAAAAAAAAACCCCCCCCCC");
+        let result = digest(
+            &seq,
+            SeqTopology::Circular,
+            &enzymes(&ENZYMES),
+            &[feature("", &[(15, 5)])],
+        );
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].range, SeqRange::new(1, 20));
+        assert_eq!(result[0].len, 20);
+        assert!(result[0].is_uncut_circle(SeqTopology::Circular));
+        assert_eq!(result[0].features, [0]);
+
+        assert!(digest(&[], SeqTopology::Circular, &enzymes(&ENZYMES), &[]).is_empty());
+    }
+
+    #[test]
+    fn cuts_near_origin() {
+        // EcoRI across the origin: Its top strand cut is before it, and its bottom strand cut after.
+        let seq = seq_from_str("ATTCAAAAAAAAAAAAAAGA");
+        let result = digest(&seq, SeqTopology::Circular, &enzymes(&ENZYMES), &[]);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].range, SeqRange::new(20, 19));
+        assert_eq!(result[0].len, 20);
+        assert_end(&result[0].left, 0, EndType::FivePrime(4), "AATT");
+        assert_end(&result[0].right, 0, EndType::FivePrime(4), "AATT");
+        assert!(!result[0].is_uncut_circle(SeqTopology::Circular));
+
+        // PstI across the origin, leaving a 3' overhang that starts at it.
+        let seq = seq_from_str("TGCAGAAAAAAAAAAAAAAC");
+        let result = digest(&seq, SeqTopology::Circular, &enzymes(&ENZYMES), &[]);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].range, SeqRange::new(5, 4));
+        assert_end(&result[0].left, 2, EndType::ThreePrime(4), "TGCA");
+    }
+
+    #[test]
+    fn bands() {
+        let seq = seq_from_str("GAATTCAAAAAAAAAAGAATTCAAAAAAAAAAAAAA");
+        let fragments = digest(&seq, SeqTopology::Circular, &enzymes(&ENZYMES), &[]);
+        let lens: Vec<_> = fragments.iter().map(|f| f.len).collect();
+        assert_eq!(lens, [16, 20]);
+
+        let mut fragments = fragments;
+        fragments.push(fragments[0].clone());
+        assert_eq!(fragment_bands(&fragments), [(20, 20.), (16, 32.)]);
+    }
+}
@@ -0,0 +1,308 @@
+//! This module contains primer dimer analysis: Finding the most stable structure formed by two primers
+//! binding each other (a hetero-dimer), or by two copies of the same primer (a self-dimer).
+//!
+//! We use nearest-neighbor thermodynamics, as for melting temperature, extended with single internal
+//! mismatches, terminal mismatches, and dangling ends. We consider each alignment of the two strands,
+//! and the most stable duplex region within it. This doesn't include bulges or loops.
+
+use crate::{
+    melting_temp_calcs::{dH_dS_de, dH_dS_imm, dH_dS_neighbors, dH_dS_tmm, na_equivalent, NnKey},
+    sequence::Nucleotide::{self, A, T},
+    util::map_linear,
+    IonConcentrations,
+};
+
+/// We report ΔG at 37°C, in K, as primer design tools generally do.
+const TEMP: f32 = 310.15;
+
+/// Duplex initiation (dH, dS). SantaLucia & Hicks (2004), Table 1.
+const INIT: (f32, f32) = (0.2, -5.7);
+/// Applied for each end of a duplex that's an A/T pair.
+const TERMINAL_AT: (f32, f32) = (2.2, 6.9);
+
+/// The ΔG range, in kcal/mol, over which a dimer's quality score falls from 1 to 0; for dimers with a
+/// paired 3' end, and for any. [Premier Biosoft](http://www.premierbiosoft.com/tech_notes/PCR_Primer_Design.html):
+/// "Optimally a 3' end self dimer with a ΔG of -5 kcal/mol and an internal self dimer with a ΔG of
+/// -6 kcal/mol is tolerated generally."
+pub const DG_SCORE_3P: (f32, f32) = (-5., -9.);
+pub const DG_SCORE_ANY: (f32, f32) = (-6., -10.);
+
+/// A structure formed by two strands binding each other.
+#[derive(Clone, Debug)]
+pub struct Dimer {
+    /// Gibbs free energy at 37°C, in kcal/mol. More negative values are more stable.
+    pub dg: f32,
+    /// If the 3' end of either strand is paired. A polymerase can extend these, so they're the
+    /// most harmful.
+    pub end_3p: bool,
+    /// For display in a monospace font: The first strand 5' to 3', the base pairs, and the second strand
+    /// 3' to 5'.
+    pub alignment: [String; 3],
+}
+
+/// The most stable dimers between two strands.
+#[derive(Clone, Debug, Default)]
+pub struct DimerAnalysis {
+    /// The most stable dimer, anywhere on the strands.
+    pub any: Option<Dimer>,
+    /// The most stable dimer with a paired 3' end.
+    pub end_3p: Option<Dimer>,
+}
+
+fn pairs(a: Nucleotide, b: Nucleotide) -> bool {
+    !a.is_ambiguous() && a.complement() == b
+}
+
+impl DimerAnalysis {
+    /// A quality score, from 0 to 1: 1 if the dimers are tolerated, and 0 if they're stable enough
+    /// to interfere with PCR.
+    pub fn score(&self) -> f32 {
+        let score = |dimer: &Option<Dimer>, range: (f32, f32)| {
+            let dg = dimer.as_ref().map(|d| d.dg).unwrap_or(0.);
+            map_linear(dg, range, (1., 0.)).clamp(0., 1.)
+        };
+        score(&self.end_3p, DG_SCORE_3P).min(score(&self.any, DG_SCORE_ANY))
+    }
+}
+
+/// Gibbs free energy at our temperature, from enthalpy in kcal/mol, and entropy in cal/K·mol.
+fn calc_dg((dh, ds): (f32, f32)) -> f32 {
+    dh - TEMP * ds / 1_000.
+}
+
+/// A candidate duplex, before building its alignment for display.
+#[derive(Clone, Copy)]
+struct Candidate {
+    dg: f32,
+    offset: isize,
+    /// The first and last paired columns, indexed by position on the first strand.
+    start: isize,
+    end: isize,
+}
+
+/// Find the most stable dimers formed by two strands, both 5' to 3'. For a self-dimer, pass the
+/// same sequence twice.
+pub fn calc_dimers(
+    seq_a: &[Nucleotide],
+    seq_b: &[Nucleotide],
+    ion: &IonConcentrations,
+) -> DimerAnalysis {
+    if seq_a.len() < 2 || seq_b.len() < 2 {
+        return Default::default();
+    }
+
+    // Entropy salt correction per nearest-neighbor stack. SantaLucia (1998).
+    let salt_ds = 0.368 * na_equivalent(ion).ln();
+
+    // The second strand is read 3' to 5', so it's antiparallel to the first.
+    let b_rev: Vec<Nucleotide> = seq_b.iter().rev().copied().collect();
+    let len_a = seq_a.len() as isize;
+    let len_b = b_rev.len() as isize;
+
+    let mut best_any: Option<Candidate> = None;
+    let mut best_3p: Option<Candidate> = None;
+
+    // `offset` is the position on the first strand opposite the second strand's 3' end.
+    for offset in -(len_b - 1)..len_a {
+        let top = |i: isize| (0..len_a).contains(&i).then(|| seq_a[i as usize]);
+        let bottom = |i: isize| {
+            (0..len_b)
+                .contains(&(i - offset))
+                .then(|| b_rev[(i - offset) as usize])
+        };
+        let key = |i: isize| -> NnKey { [top(i), top(i + 1), bottom(i), bottom(i + 1)] };
+
+        // The columns where the strands overlap.
+        let cols = offset.max(0)..len_a.min(offset + len_b);
+
+        let paired: Vec<bool> = cols
+            .clone()
+            .map(|i| pairs(seq_a[i as usize], b_rev[(i - offset) as usize]))
+            .collect();
+
+        // ΔG of the stack between each column and the next. This is `None` if the stack can't be
+        // part of a duplex, eg two mismatches in a row.
+        let stacks: Vec<Option<f32>> = (0..paired.len().saturating_sub(1))
+            .map(|k| {
+                let i = cols.start + k as isize;
+                let v = match (paired[k], paired[k + 1]) {
+                    (true, true) => dH_dS_neighbors((seq_a[i as usize], seq_a[i as usize + 1])),
+                    (false, false) => None,
+                    _ => dH_dS_imm(key(i)),
+                }?;
+                Some(calc_dg((v.0, v.1 + salt_ds)))
+            })
+            .collect();
+
+        // ΔG from the end of a duplex: A terminal mismatch, or dangling end, past `i`, and an A/T
+        // pair at `i`. `key` is the pair, and its neighbor outside the duplex.
+        let end_dg = |i: isize, key: NnKey, neighbor: isize| {
+            let mut result = 0.;
+
+            if matches!(seq_a[i as usize], A | T) {
+                result += calc_dg(TERMINAL_AT);
+            }
+
+            let v = match (top(neighbor), bottom(neighbor)) {
+                (Some(t), Some(b)) if !pairs(t, b) => dH_dS_tmm(key),
+                (Some(_), None) | (None, Some(_)) => dH_dS_de(key),
+                _ => None,
+            };
+            if let Some(v) = v {
+                result += calc_dg(v);
+            }
+
+            result
+        };
+
+        for l in 0..paired.len() {
+            if !paired[l] {
+                continue;
+            }
+
+            let start = cols.start + l as isize;
+            let dg_start = calc_dg(INIT) + end_dg(start, key(start - 1), start - 1);
+
+            let mut dg_stacks = 0.;
+            for r in l + 1..paired.len() {
+                let Some(v) = stacks[r - 1] else {
+                    break;
+                };
+                dg_stacks += v;
+
+                if !paired[r] {
+                    continue;
+                }
+
+                let end = cols.start + r as isize;
+                let candidate = Candidate {
+                    dg: dg_start + dg_stacks + end_dg(end, key(end), end + 1),
+                    offset,
+                    start,
+                    end,
+                };
+
+                if best_any.map(|b| candidate.dg < b.dg).unwrap_or(true) {
+                    best_any = Some(candidate);
+                }
+
+                // The first strand's 3' end is its last nucleotide; the second's is opposite `offset`.
+                let end_3p = end == len_a - 1 || start == offset;
+                if end_3p && best_3p.map(|b| candidate.dg < b.dg).unwrap_or(true) {
+                    best_3p = Some(candidate);
+                }
+            }
+        }
+    }
+
+    let to_dimer = |c: Candidate| Dimer {
+        dg: c.dg,
+        end_3p: c.end == len_a - 1 || c.start == c.offset,
+        alignment: alignment(seq_a, &b_rev, c),
+    };
+
+    DimerAnalysis {
+        any: best_any.map(to_dimer),
+        end_3p: best_3p.map(to_dimer),
+    }
+}
+
+/// Lay out two strands, and the pairs between them, for display.
+fn alignment(seq_a: &[Nucleotide], b_rev: &[Nucleotide], c: Candidate) -> [String; 3] {
+    let len_a = seq_a.len() as isize;
+    let len_b = b_rev.len() as isize;
+
+    let mut top = String::from("5' ");
+    let mut mid = String::from("   ");
+    let mut bottom = String::from("3' ");
+
+    for i in c.offset.min(0)..len_a.max(c.offset + len_b) {
+        let t = (0..len_a).contains(&i).then(|| seq_a[i as usize]);
+        let b = (0..len_b)
+            .contains(&(i - c.offset))
+            .then(|| b_rev[(i - c.offset) as usize]);
+
+        let letter = |nt: Option<Nucleotide>| nt.map(|nt| nt.to_u8_letter() as char).unwrap_or(' ');
+        top.push(letter(t));
+        bottom.push(letter(b));
+
+        let paired = match (t, b) {
+            (Some(t), Some(b)) => (c.start..=c.end).contains(&i) && pairs(t, b),
+            _ => false,
+        };
+        mid.push(if paired { '|' } else { ' ' });
+    }
+
+    [
+        format!("{} 3'", top.trim_end()),
+        mid.trim_end().to_owned(),
+        format!("{} 5'", bottom.trim_end()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sequence::seq_from_str, test_fixtures::na_only};
+
+    #[test]
+    fn complementary() {
+        let a = seq_from_str("CGATGCAG");
+        let b = seq_from_str("CTGCATCG");
+        let result = calc_dimers(&a, &b, &na_only());
+
+        // Initiation, and 7 stacks with 0.368 × ln(0.05) entropy each, at 37°C. No A/T ends.
+        let dimer = result.any.as_ref().unwrap();
+        assert!((dimer.dg - -6.429).abs() < 0.01, "{}", dimer.dg);
+        assert!(dimer.end_3p);
+        assert_eq!(
+            dimer.alignment,
+            [
+                "5' CGATGCAG 3'".to_owned(),
+                "   ||||||||".to_owned(),
+                "3' GCTACGTC 5'".to_owned(),
+            ]
+        );
+        assert_eq!(result.end_3p.as_ref().unwrap().dg, dimer.dg);
+
+        // Past the 3' end dimer threshold, by 1.43 of its 4 kcal/mol range.
+        assert!((result.score() - 0.643).abs() < 0.01, "{}", result.score());
+    }
+
+    #[test]
+    fn dangling_end() {
+        // As above, with a 3' T on the first strand, dangling past the duplex: ".C/TG" from the dangling
+        // end table.
+        let a = seq_from_str("CGATGCAGT");
+        let b = seq_from_str("CTGCATCG");
+        let dimer = calc_dimers(&a, &b, &na_only()).any.unwrap();
+
+        assert!((dimer.dg - -6.766).abs() < 0.01, "{}", dimer.dg);
+        // The second strand's 3' end is paired, but not the first's.
+        assert!(dimer.end_3p);
+        assert_eq!(dimer.alignment[1], "   ||||||||");
+    }
+
+    #[test]
+    fn end_3p() {
+        // Self-complementary at the 3' end only.
+        let seq = seq_from_str("TTTTTTTTTTGCGC");
+        let result = calc_dimers(&seq, &seq, &na_only());
+
+        let dimer = result.end_3p.unwrap();
+        assert!(dimer.end_3p && dimer.dg < 0.);
+        assert!(dimer.alignment[0].ends_with("GCGC 3'"));
+        assert!(dimer.alignment[1].ends_with("||||"));
+    }
+
+    #[test]
+    fn no_dimer() {
+        let seq = seq_from_str("AAAAAAAAAA");
+        let result = calc_dimers(&seq, &seq, &na_only());
+        assert!(result.any.is_none() && result.end_3p.is_none());
+        assert_eq!(result.score(), 1.);
+
+        let result = calc_dimers(&seq_from_str("A"), &seq_from_str("T"), &na_only());
+        assert!(result.any.is_none());
+    }
+}
@@ -0,0 +1,214 @@
+//! Errors and warnings from reading and writing files. Errors are fatal: Nothing was imported or
+//! exported. Warnings are recoverable: The file was imported, but some of its data may be missing or
+//! altered.
+
+use std::{fmt, io};
+
+use crate::sequence::{Nucleotide, Seq};
+
+/// Where in a file a problem occurred.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    /// 0-based byte offset, for binary formats.
+    Byte(usize),
+    /// 1-based record index, eg in a multi-sequence FASTA or GenBank file.
+    Record(usize),
+    /// 1-based index in the file's feature list.
+    Feature(usize),
+    /// 1-based index in the sequence.
+    Nucleotide(usize),
+    /// 1-based line number, for text formats that aren't sequences, eg REBASE enzyme data.
+    Line(usize),
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Byte(v) => write!(f, "byte {v:#x}"),
+            Self::Record(v) => write!(f, "record {v}"),
+            Self::Feature(v) => write!(f, "feature {v}"),
+            Self::Nucleotide(v) => write!(f, "nucleotide {v}"),
+            Self::Line(v) => write!(f, "line {v}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FileError {
+    Io(io::Error),
+    /// The file's contents aren't valid for its format.
+    Format {
+        position: Option<Position>,
+        msg: String,
+    },
+    /// The file's type isn't one we can read or write.
+    Unsupported(String),
+    /// Our data couldn't be converted to the format.
+    Encode(String),
+}
+
+impl FileError {
+    pub fn format(position: Option<Position>, msg: impl Into<String>) -> Self {
+        Self::Format {
+            position,
+            msg: msg.into(),
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Format {
+                position: Some(p),
+                msg,
+            } => write!(f, "{msg} (at {p})"),
+            Self::Format {
+                position: None,
+                msg,
+            } => write!(f, "{msg}"),
+            Self::Unsupported(msg) => write!(f, "Unsupported file: {msg}"),
+            Self::Encode(msg) => write!(f, "Unable to encode: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A problem that didn't prevent importing the file.
+#[derive(Clone, Debug)]
+pub struct ImportWarning {
+    pub position: Option<Position>,
+    pub msg: String,
+}
+
+impl ImportWarning {
+    pub fn new(position: Option<Position>, msg: impl Into<String>) -> Self {
+        Self {
+            position,
+            msg: msg.into(),
+        }
+    }
+}
+
+impl fmt::Display for ImportWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some(p) => write!(f, "{} (at {p})", self.msg),
+            None => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+/// Data read from a file, and problems encountered that didn't prevent reading it.
+pub struct Imported<T> {
+    pub data: T,
+    pub warnings: Vec<ImportWarning>,
+}
+
+impl<T> Imported<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Imported<U> {
+        Imported {
+            data: f(self.data),
+            warnings: self.warnings,
+        }
+    }
+}
+
+/// Parse nucleotides from their letters, skipping characters that aren't nucleotides. Skipped
+/// characters are reported as a single warning, at the position of the first; `position` maps a 0-based
+/// index in `letters` to its position in the file.
+pub fn parse_seq_letters(
+    letters: &[u8],
+    position: impl Fn(usize) -> Position,
+    warnings: &mut Vec<ImportWarning>,
+) -> Seq {
+    let mut result = Vec::with_capacity(letters.len());
+    let mut first_skipped = None;
+    let mut skipped = Vec::new();
+
+    for (i, letter) in letters.iter().enumerate() {
+        match Nucleotide::from_u8_letter(*letter) {
+            Ok(nt) => result.push(nt),
+            Err(_) => {
+                first_skipped.get_or_insert(i);
+                if !skipped.contains(letter) {
+                    skipped.push(*letter);
+                }
+            }
+        }
+    }
+
+    if let Some(i) = first_skipped {
+        let chars: Vec<String> = skipped
+            .iter()
+            .map(|c| format!("{:?}", *c as char))
+            .collect();
+        warnings.push(ImportWarning::new(
+            Some(position(i)),
+            format!(
+                "Skipped {} unexpected characters in the sequence: {}",
+                letters.len() - result.len(),
+                chars.join(", ")
+            ),
+        ));
+    }
+
+    result
+}
+
+/// A summary of the most recent file operation, for display.
+pub struct FileReport {
+    /// A description of the operation, eg "Imported plasmid.gb".
+    pub action: String,
+    pub error: Option<FileError>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+impl FileReport {
+    /// Report the result of a save or export.
+    pub fn from_export(action: &str, result: Result<(), FileError>) -> Self {
+        Self {
+            action: action.to_owned(),
+            error: result.err(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Report the result of a load or import, taking its warnings. Returns the data, if successful.
+    pub fn from_import<T>(
+        action: &str,
+        result: Result<Imported<T>, FileError>,
+    ) -> (Self, Option<T>) {
+        let (data, error, warnings) = match result {
+            Ok(imported) => (Some(imported.data), None, imported.warnings),
+            Err(e) => (None, Some(e), Vec::new()),
+        };
+
+        let report = Self {
+            action: action.to_owned(),
+            error,
+            warnings,
+        };
+        (report, data)
+    }
+
+    /// True if there's nothing worth showing the user; eg a successful save.
+    pub fn is_clean(&self) -> bool {
+        self.error.is_none() && self.warnings.is_empty()
+    }
+}
@@ -4,32 +4,27 @@
 //!
 //! (NIH article on GenBank)[https://www.ncbi.nlm.nih.gov/genbank/]
 
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{self, ErrorKind, Write},
-    ops::Range,
-    path::Path,
-};
+use std::{collections::HashMap, fs::File, path::Path};
 
-use gb_io::{
-    self,
-    reader::SeqReader,
-    seq::{After, Before, Location},
-    writer::SeqWriter,
-};
+use gb_io::{self, reader::SeqReader, seq::Location, writer::SeqWriter};
 
 use crate::{
-    file_io::{get_filename, GenericData},
+    file_io::{
+        error::{parse_seq_letters, FileError, ImportWarning, Imported, Position},
+        get_filename, GenericData,
+    },
     primer::{Primer, PrimerData, PrimerDirection},
-    sequence::{seq_complement, Feature, FeatureDirection, FeatureType, Nucleotide, SeqTopology},
+    sequence::{
+        seq_complement, Feature, FeatureDirection, FeatureType, Nucleotide, SeqRange, SeqTopology,
+    },
     Metadata, Reference,
 };
 
 /// Read a file in the GenBank format.
 /// [Rust docs ref of fields](https://docs.rs/gb-io/latest/gb_io/seq/struct.Seq.html)
-pub fn import_genbank(path: &Path) -> io::Result<GenericData> {
+pub fn import_genbank(path: &Path) -> Result<Imported<GenericData>, FileError> {
     let file = File::open(path)?;
+    let mut warnings = Vec::new();
 
     // todo: This currently only handles a single sequene. It returns the first found.
 
@@ -53,31 +48,32 @@ pub fn import_genbank(path: &Path) -> io::Result<GenericData> {
     //     pub features: Vec<Feature>,
     // }
 
-    for seq in SeqReader::new(file) {
+    let mut reader = SeqReader::new(file);
+
+    if let Some(seq) = reader.next() {
         let seq = seq.map_err(|e| {
-            io::Error::new(
-                ErrorKind::InvalidData,
+            FileError::format(
+                Some(Position::Record(1)),
                 format!("Unable to get GenBank seq {e}"),
             )
         })?;
 
-        let mut seq_ = Vec::new();
-
-        for nt in &seq.seq {
-            match Nucleotide::from_u8_letter(*nt) {
-                Ok(n) => seq_.push(n),
-                Err(_) => {
-                    eprintln!("Unexpected char in GenBank sequence: {:?}", nt);
-                }
-            }
+        if reader.next().is_some() {
+            warnings.push(ImportWarning::new(
+                Some(Position::Record(2)),
+                "Only the first sequence in this file was imported",
+            ));
         }
 
+        let seq_ = parse_seq_letters(&seq.seq, |i| Position::Nucleotide(i + 1), &mut warnings);
+
         let topology = match seq.topology {
             gb_io::seq::Topology::Linear => SeqTopology::Linear,
             gb_io::seq::Topology::Circular => SeqTopology::Circular,
         };
 
-        let (features, primers) = parse_features_primers(&seq.features, &seq_);
+        let (features, primers) =
+            parse_features_primers(&seq.features, &seq_, topology, &mut warnings);
 
         let mut references = Vec::new();
         for ref_ in &seq.references {
@@ -110,31 +106,94 @@ pub fn import_genbank(path: &Path) -> io::Result<GenericData> {
             source,
             organism,
             references,
-            ..Default::default()
         };
 
-        return Ok(GenericData {
-            seq: seq_,
-            topology,
-            features,
-            primers,
-            metadata,
+        return Ok(Imported {
+            data: GenericData {
+                seq: seq_,
+                topology,
+                features,
+                primers,
+                metadata,
+            },
+            warnings,
         });
     }
 
-    Err(io::Error::new(
-        ErrorKind::InvalidData,
-        "No GenBank sequences found",
-    ))
+    Err(FileError::format(None, "No GenBank sequences found"))
 }
 
-/// Parse features and primers, from GenBank's feature list.
-fn parse_features_primers(features: &[gb_io::seq::Feature], seq: &[Nucleotide]) -> (Vec<Feature>, Vec<Primer>) {
+/// Convert a GenBank location to ranges in reading order (5' to 3' on the feature's strand), and whether
+/// it's on the complement strand. We treat `order` like `join`.
+fn parse_location(location: &Location) -> Option<(Vec<SeqRange>, bool)> {
+    match location {
+        // gb_io seems to list the start of the range as 1 too early; compensate.
+        Location::Range(start, end) => Some((
+            vec![SeqRange::new(start.0 as usize + 1, end.0 as usize)],
+            false,
+        )),
+        Location::Complement(inner) => {
+            let (mut ranges, reverse) = parse_location(inner)?;
+            ranges.reverse();
+            Some((ranges, !reverse))
+        }
+        Location::Join(locations) | Location::Order(locations) => {
+            let mut result = Vec::new();
+            let mut reverse_all = None;
+
+            for loc in locations {
+                let (ranges, reverse) = parse_location(loc)?;
+                // We don't support segments on different strands.
+                if *reverse_all.get_or_insert(reverse) != reverse {
+                    return None;
+                }
+                result.extend(ranges);
+            }
+
+            Some((result, reverse_all.unwrap_or_default()))
+        }
+        _ => None,
+    }
+}
+
+/// Convert ranges in order along the top strand to a GenBank location. GenBank represents ranges that
+/// wrap the origin as joins, so we split these.
+fn ranges_to_location(ranges: &[SeqRange], reverse: bool, seq_len: usize) -> Location {
+    let mut locations = Vec::new();
+
+    for range in ranges {
+        // `to_linear` uses 0-based, end-exclusive indexing, as gb_io does.
+        for r in range.to_linear(seq_len) {
+            locations.push(Location::simple_range(r.start as i64, r.end as i64));
+        }
+    }
+
+    let location = if locations.len() == 1 {
+        locations.remove(0)
+    } else {
+        Location::Join(locations)
+    };
+
+    if reverse {
+        Location::Complement(Box::new(location))
+    } else {
+        location
+    }
+}
+
+/// Parse features and primers, from GenBank's feature list. Features with locations we can't
+/// represent are skipped, with a warning.
+fn parse_features_primers(
+    features: &[gb_io::seq::Feature],
+    seq: &[Nucleotide],
+    topology: SeqTopology,
+    warnings: &mut Vec<ImportWarning>,
+) -> (Vec<Feature>, Vec<Primer>) {
     let mut result_ft = Vec::new();
     let mut primers = Vec::new();
 
-    for feature in features {
-        let feature_type = FeatureType::from_external_str(&feature.kind.as_ref());
+    for (i, feature) in features.iter().enumerate() {
+        let feature_type = FeatureType::from_external_str(feature.kind.as_ref());
 
         // We parse label from qualifiers.
         // I'm unsure how direction works in GenBank files. It appears it's some mix of the LEFT/RIGHT
@@ -142,43 +201,54 @@ fn parse_features_primers(features: &[gb_io::seq::Feature], seq: &[Nucleotide])
         let mut direction = FeatureDirection::None;
         let mut label = String::new();
 
-        let index_range = match &feature.location {
-            // gb_io seems to list the start of the range as 1 too early; compensate.
-            Location::Range(start, end) => (start.0 as usize + 1, end.0 as usize),
-            Location::Complement(inner) => match **inner {
-                Location::Range(start, end) => {
+        let mut ranges = match parse_location(&feature.location) {
+            Some((mut ranges, reverse)) if !ranges.is_empty() => {
+                if reverse {
                     direction = FeatureDirection::Reverse;
-                    (start.0 as usize + 1, end.0 as usize)
-                }
-                _ => {
-                    eprintln!("Unexpected gb_io compl range type: {:?}", feature.location);
-                    (0, 0)
+                    // Store ranges in order along the top strand.
+                    ranges.reverse();
                 }
-            },
+                ranges
+            }
             _ => {
-                eprintln!("Unexpected gb_io range type: {:?}", feature.location);
-                (0, 0)
+                warnings.push(ImportWarning::new(
+                    Some(Position::Feature(i + 1)),
+                    format!(
+                        "Skipped a {} feature with an unsupported location: {}",
+                        feature.kind, feature.location
+                    ),
+                ));
+                continue;
             }
         };
 
-        for v in feature.qualifier_values("label".into()) {
+        // GenBank represents features that wrap the origin as joins; merge these.
+        if topology == SeqTopology::Circular {
+            let mut merged: Vec<SeqRange> = Vec::new();
+            for range in ranges {
+                match merged.last_mut() {
+                    Some(prev) if prev.end == seq.len() && range.start == 1 => prev.end = range.end,
+                    _ => merged.push(range),
+                }
+            }
+            ranges = merged;
+        }
+
+        let index_range = SeqRange::new(ranges[0].start, ranges[ranges.len() - 1].end);
+
+        if let Some(v) = feature.qualifier_values("label".into()).next() {
             v.clone_into(&mut label);
-            break;
         }
 
         match feature_type {
-            FeatureType::Primer => {
-                if direction != FeatureDirection::Reverse {
-                    direction = FeatureDirection::Forward;
-                }
+            FeatureType::Primer if direction != FeatureDirection::Reverse => {
+                direction = FeatureDirection::Forward;
+            }
+            // As CDS regions are always directional, if not identified as reverse due to the range
+            // being in complement format, set it to forward.
+            FeatureType::CodingRegion if direction == FeatureDirection::None => {
+                direction = FeatureDirection::Forward;
             }
-            FeatureType::CodingRegion => {
-                // As CDS regions are always directional, if not identified as reverse due to the range
-                // being in complement format, set it to forward.
-                if direction == FeatureDirection::None {
-                    direction = FeatureDirection::Forward
-                }
-            },
             _ => (),
         }
 
@@ -196,14 +266,10 @@ fn parse_features_primers(features: &[gb_io::seq::Feature], seq: &[Nucleotide])
         // GenBank stores primer bind sites (Which we treat as volatile), vice primer sequences.
         // Infer the sequence using the bind indices, and the main sequence.
         if feature_type == FeatureType::Primer {
+            let sequence = index_range.seq(seq).unwrap_or_default();
             let sequence = match direction {
-                FeatureDirection::Reverse => {
-                    let compl = seq_complement(&seq);
-                    compl[seq.len() - (index_range.1 - 1)..seq.len() - (index_range.0)]
-                        .to_vec()
-                }
-                // See other notes on start range index being odd.
-                _ => seq[index_range.0 - 1..index_range.1].to_vec(),
+                FeatureDirection::Reverse => seq_complement(&sequence),
+                _ => sequence,
             };
 
             let volatile = PrimerData::new(&sequence);
@@ -227,14 +293,18 @@ fn parse_features_primers(features: &[gb_io::seq::Feature], seq: &[Nucleotide])
             }
         }
 
-        result_ft.push(Feature {
+        let mut feature = Feature {
             index_range,
+            segments: Vec::new(),
             feature_type,
             direction,
             label,
             color_override: None,
             notes,
-        })
+        };
+        feature.set_ranges(ranges);
+
+        result_ft.push(feature);
     }
 
     (result_ft, primers)
@@ -243,9 +313,9 @@ fn parse_features_primers(features: &[gb_io::seq::Feature], seq: &[Nucleotide])
 /// Export our local state into the GenBank format. This includes sequence, features, and primers.
 pub fn export_genbank(
     data: &GenericData,
-    primer_matches: &[(PrimerDirection, Range<usize>, String)],
+    primer_matches: &[(PrimerDirection, SeqRange, String)],
     path: &Path,
-) -> io::Result<()> {
+) -> Result<(), FileError> {
     let file = File::create(path)?;
 
     let mut gb_data = gb_io::seq::Seq::empty();
@@ -274,19 +344,11 @@ pub fn export_genbank(
             _ => (),
         }
 
-        let start: i64 = feature.index_range.0.try_into().unwrap();
-        let end: i64 = feature.index_range.1.try_into().unwrap();
-
-        let location = match feature.direction {
-            FeatureDirection::Reverse => Location::Complement(Box::new(Location::Range(
-                (end + 1, Before(false)),
-                (start, After(false)), // todo: Offset on end. Whhy?
-            ))),
-            _ => Location::Range(
-                (start - 1, Before(false)),
-                (feature.index_range.1.try_into().unwrap(), After(false)),
-            ),
-        };
+        let location = ranges_to_location(
+            &feature.ranges(),
+            feature.direction == FeatureDirection::Reverse,
+            data.seq.len(),
+        );
 
         gb_data.features.push(gb_io::seq::Feature {
             kind: feature.feature_type.to_external_str().into(),
@@ -295,21 +357,9 @@ pub fn export_genbank(
         });
     }
 
-    for (dir, indexes, name) in primer_matches {
-        // todo: Location code is DRY with features.
-        let start: i64 = indexes.start.try_into().unwrap();
-        let end: i64 = indexes.end.try_into().unwrap();
-
-        let location = match dir {
-            PrimerDirection::Forward => Location::Range(
-                (start, Before(false)),
-                (indexes.end.try_into().unwrap(), After(false)),
-            ),
-            PrimerDirection::Reverse => Location::Complement(Box::new(Location::Range(
-                (data.seq.len() as i64 - (end + 1), Before(false)),
-                (data.seq.len() as i64 - (start - 1), After(false)), // todo: Offset on end. Whhy?
-            ))),
-        };
+    for (dir, range, name) in primer_matches {
+        let location =
+            ranges_to_location(&[*range], *dir == PrimerDirection::Reverse, data.seq.len());
 
         gb_data.features.push(gb_io::seq::Feature {
             kind: "primer_bind".into(),
@@ -350,5 +400,5 @@ pub fn export_genbank(
     }
 
     let mut writer = SeqWriter::new(file);
-    writer.write(&gb_data)
+    Ok(writer.write(&gb_data)?)
 }
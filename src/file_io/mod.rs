@@ -3,12 +3,20 @@
 use std::path::Path;
 
 use crate::{
+    file_io::{
+        error::{FileError, Imported},
+        genbank::{export_genbank, import_genbank},
+        save::{export_fasta, import_fasta, StateToSave},
+        snapgene::{export_snapgene, import_snapgene},
+    },
     primer::Primer,
     sequence::{Feature, Seq, SeqTopology},
-    Metadata, Reference,
+    Metadata,
 };
 
+pub mod error;
 pub mod genbank;
+pub mod rebase;
 pub mod save;
 pub mod snapgene;
 
@@ -35,3 +43,59 @@ fn get_filename(path: &Path) -> String {
         String::new()
     }
 }
+
+fn get_extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Import a document from a file, based on its extension. For our own format, this discards settings
+/// saved with the document, such as its history.
+pub fn import_file(path: &Path) -> Result<Imported<GenericData>, FileError> {
+    match get_extension(path).as_ref() {
+        // Does this work for FASTQ too?
+        "fasta" | "fa" => Ok(import_fasta(path)?.map(|(seq, id, description)| {
+            let mut data = GenericData {
+                seq,
+                ..Default::default()
+            };
+            data.metadata.plasmid_name = id;
+            data.metadata.comments = vec![description];
+            data
+        })),
+        "dna" => import_snapgene(path),
+        "gb" | "gbk" => import_genbank(path),
+        "pcad" => Ok(StateToSave::load(path)?.map(|s| s.generic)),
+        _ => Err(FileError::Unsupported(
+            "The file to import must be in FASTA, GenBank, SnapGene, or PlasCAD format.".to_owned(),
+        )),
+    }
+}
+
+/// Export a document to a file, in the format indicated by its extension.
+pub fn export_file(data: &GenericData, path: &Path) -> Result<(), FileError> {
+    match get_extension(path).as_ref() {
+        "fasta" | "fa" => export_fasta(&data.seq, &data.metadata.plasmid_name, path),
+        "dna" => export_snapgene(data, path),
+        "gb" | "gbk" => {
+            let mut primer_matches = Vec::new();
+            for primer in &data.primers {
+                for (dir, range) in primer.match_to_seq(&data.seq, data.topology) {
+                    primer_matches.push((dir, range, primer.name.clone()));
+                }
+            }
+
+            export_genbank(data, &primer_matches, path)
+        }
+        "pcad" => StateToSave {
+            generic: data.clone(),
+            ..Default::default()
+        }
+        .save(path),
+        _ => Err(FileError::Unsupported(
+            "The file to export must be in FASTA, GenBank, SnapGene, or PlasCAD format.".to_owned(),
+        )),
+    }
+}
@@ -0,0 +1,491 @@
+//! This module contains code for reading restriction enzyme data from REBASE (http://rebase.neb.com),
+//! and for our own enzyme data: buffer activity and methylation sensitivity tables, and enzyme sets.
+//!
+//! We read two REBASE formats:
+//! - withrefm (and allenz): Records of tagged lines, `<1>` through `<8>`: name, isoschizomers,
+//!   recognition site with cut positions, eg `G^AATTC`, `GGTCTC(1/5)`, or `(10/15)ACNNNNGTAYC(12/7)`,
+//!   methylation site, microorganism, source, supplier codes, and references.
+//! - emboss_e: One enzyme per line: name, site, site length, number of cuts, blunt flag, then the top
+//!   and bottom strand cuts, and for Type IIB enzymes, a second pair. These count nucleotides from the
+//!   site's 5' end; positions before the site are negative, with no 0, ie -1 is just before it.
+//!
+//! Enzymes without known cut positions are skipped.
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use crate::{
+    file_io::error::{FileError, ImportWarning, Imported, Position},
+    restriction_enzyme::{EnzymeSet, MethylationSensitivity, RestrictionEnzyme, Sensitivity},
+    sequence::{seq_from_str, Seq},
+};
+
+/// Enzyme sets are saved here, in the working directory, similar to the default document.
+pub const DEFAULT_ENZYME_SETS_FILE: &str = "enzyme_sets.txt";
+
+/// Parse a cut pair in parentheses, eg `1/5`.
+fn parse_cut(cut: &str) -> Option<(isize, isize)> {
+    let (top, bottom) = cut.split_once('/')?;
+    Some((top.trim().parse().ok()?, bottom.trim().parse().ok()?))
+}
+
+/// Parse a recognition site in REBASE notation. Returns the site, and each cut, top strand then bottom,
+/// from the site's 5' end. `None` if the notation is invalid, or doesn't include cut positions.
+fn parse_site(notation: &str) -> Option<(Seq, Vec<(isize, isize)>)> {
+    let notation = notation.trim();
+
+    // A cut before the site, eg `(10/15)ACNNNNGTAYC`.
+    let (before, rest) = match notation.strip_prefix('(') {
+        Some(v) => {
+            let (cut, rest) = v.split_once(')')?;
+            (Some(parse_cut(cut)?), rest)
+        }
+        None => (None, notation),
+    };
+
+    // A cut after it, eg `GGTCTC(1/5)`.
+    let (site_str, after) = match rest.split_once('(') {
+        Some((site, cut)) => (site, Some(parse_cut(cut.strip_suffix(')')?)?)),
+        None => (rest, None),
+    };
+
+    let site = seq_from_str(site_str);
+    let letters = site_str.chars().filter(|c| *c != '^').count();
+    if site.is_empty() || site.len() != letters {
+        return None;
+    }
+    let len = site.len() as isize;
+
+    let mut cuts = Vec::new();
+    if let Some((top, bottom)) = before {
+        cuts.push((-top, -bottom));
+    }
+    // Within the site, for palindromes. The bottom strand's cut is symmetric.
+    if let Some(i) = site_str.find('^') {
+        let i = i as isize;
+        cuts.push((i, len - i));
+    }
+    if let Some((top, bottom)) = after {
+        cuts.push((len + top, len + bottom));
+    }
+
+    (!cuts.is_empty()).then_some((site, cuts))
+}
+
+/// Create an enzyme from its cuts; a second pair is for Type IIB enzymes.
+fn make_enzyme(name: &str, site: Seq, cuts: &[(isize, isize)]) -> RestrictionEnzyme {
+    RestrictionEnzyme {
+        name: name.to_owned(),
+        site,
+        cut_top: cuts[0].0,
+        cut_bottom: cuts[0].1,
+        cut_2: cuts.get(1).copied(),
+        ..Default::default()
+    }
+}
+
+fn skipped_warning(skipped: usize) -> ImportWarning {
+    ImportWarning::new(
+        None,
+        format!("Skipped {skipped} enzymes without known cut positions."),
+    )
+}
+
+/// Parse enzymes from REBASE withrefm (or allenz) format.
+pub fn parse_withrefm(text: &str) -> Imported<Vec<RestrictionEnzyme>> {
+    let mut result = Vec::new();
+    let mut warnings = Vec::new();
+    let mut skipped = 0;
+
+    // The name, its line, and the site and supplier fields, of the record being read.
+    let mut record: Option<(String, usize, String, String)> = None;
+
+    let mut finish = |record: Option<(String, usize, String, String)>,
+                      result: &mut Vec<RestrictionEnzyme>| {
+        let Some((name, line, site, suppliers)) = record else {
+            return;
+        };
+        if name.is_empty() {
+            warnings.push(ImportWarning::new(
+                Some(Position::Line(line)),
+                "Enzyme without a name",
+            ));
+            return;
+        }
+
+        match parse_site(&site) {
+            Some((site, cuts)) => {
+                let mut enzyme = make_enzyme(&name, site, &cuts);
+                enzyme.suppliers = suppliers
+                    .chars()
+                    .filter(|c| c.is_ascii_uppercase())
+                    .collect();
+                result.push(enzyme);
+            }
+            None => skipped += 1,
+        }
+    };
+
+    for (i, line) in text.lines().enumerate() {
+        let Some(rest) = line.strip_prefix('<') else {
+            continue;
+        };
+        let Some((tag, value)) = rest.split_once('>') else {
+            continue;
+        };
+        let value = value.trim().to_owned();
+
+        match tag {
+            "1" => {
+                finish(record.take(), &mut result);
+                record = Some((value, i + 1, String::new(), String::new()));
+            }
+            "3" => {
+                if let Some(r) = &mut record {
+                    r.2 = value;
+                }
+            }
+            "7" => {
+                if let Some(r) = &mut record {
+                    r.3 = value;
+                }
+            }
+            _ => (),
+        }
+    }
+    finish(record, &mut result);
+
+    if skipped > 0 {
+        warnings.push(skipped_warning(skipped));
+    }
+
+    Imported {
+        data: result,
+        warnings,
+    }
+}
+
+/// Parse enzymes from REBASE emboss_e format. This format doesn't include suppliers.
+pub fn parse_emboss_e(text: &str) -> Imported<Vec<RestrictionEnzyme>> {
+    let mut result = Vec::new();
+    let mut warnings = Vec::new();
+    let mut skipped = 0;
+
+    // Positions before the site skip 0.
+    let offset = |v: isize| if v < 0 { v + 1 } else { v };
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let values: Option<Vec<isize>> = fields
+            .get(3..)
+            .map(|v| v.iter().map(|v| v.parse().ok()).collect())
+            .unwrap_or_default();
+
+        let (Some(values), Some(name), Some(site)) = (values, fields.first(), fields.get(1)) else {
+            warnings.push(ImportWarning::new(
+                Some(Position::Line(i + 1)),
+                format!("Invalid enzyme: {line}"),
+            ));
+            continue;
+        };
+
+        // Number of cuts, blunt flag, then the cuts.
+        let num_cuts = values.first().copied().unwrap_or_default();
+        let cuts: Vec<(isize, isize)> = match (num_cuts, values.get(2..)) {
+            (2, Some([c1, c2, ..])) => vec![(offset(*c1), offset(*c2))],
+            (4, Some([c1, c2, c3, c4, ..])) => {
+                vec![(offset(*c1), offset(*c2)), (offset(*c3), offset(*c4))]
+            }
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+
+        let site_seq = seq_from_str(site);
+        if site_seq.len() != site.len() {
+            warnings.push(ImportWarning::new(
+                Some(Position::Line(i + 1)),
+                format!("Invalid site for {name}: {site}"),
+            ));
+            continue;
+        }
+
+        result.push(make_enzyme(name, site_seq, &cuts));
+    }
+
+    if skipped > 0 {
+        warnings.push(skipped_warning(skipped));
+    }
+
+    Imported {
+        data: result,
+        warnings,
+    }
+}
+
+/// Import enzymes from a REBASE file, in withrefm, allenz, or emboss_e format.
+pub fn import_rebase(path: &Path) -> Result<Imported<Vec<RestrictionEnzyme>>, FileError> {
+    let text = fs::read_to_string(path)?;
+
+    let result = if text.lines().any(|l| l.starts_with("<1>")) {
+        parse_withrefm(&text)
+    } else {
+        parse_emboss_e(&text)
+    };
+
+    if result.data.is_empty() {
+        return Err(FileError::format(
+            None,
+            "No enzymes with known cut positions found. The file must be in REBASE withrefm, allenz, \
+            or emboss_e format.",
+        ));
+    }
+
+    Ok(result)
+}
+
+fn parse_sensitivity(v: &str) -> Option<Sensitivity> {
+    match v.trim().to_lowercase().as_ref() {
+        "" | "?" => Some(Sensitivity::Unknown),
+        "-" => Some(Sensitivity::NotSensitive),
+        "impaired" => Some(Sensitivity::Impaired),
+        "blocked" => Some(Sensitivity::Blocked),
+        _ => None,
+    }
+}
+
+/// Set buffer activity and methylation sensitivity of enzymes in the library, from a tab-separated
+/// table. Lines starting with `#` are comments. The first other line is the header: `enzyme`, then
+/// buffer names, and `dam`, `dcm`, and `cpg`. Activity is in percent; `<10` is under 10%. Sensitivity
+/// is `-`, `impaired`, or `blocked`. Enzymes are matched by name; ones not in the library are ignored.
+pub fn apply_enzyme_properties(text: &str, lib: &mut [RestrictionEnzyme]) -> Vec<ImportWarning> {
+    let mut warnings = Vec::new();
+    let mut header: Option<Vec<String>> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+
+        let Some(columns) = &header else {
+            header = Some(fields.iter().map(|f| f.trim().to_owned()).collect());
+            continue;
+        };
+
+        let name = fields[0].trim();
+        let mut activity = Vec::new();
+        let mut methylation = MethylationSensitivity::default();
+        let mut valid = true;
+
+        for (column, v) in columns.iter().zip(&fields).skip(1) {
+            let v = v.trim();
+            let sensitivity = match column.to_lowercase().as_ref() {
+                "dam" => &mut methylation.dam,
+                "dcm" => &mut methylation.dcm,
+                "cpg" => &mut methylation.cpg,
+                _ => {
+                    let pct = if v.starts_with('<') {
+                        Some(0)
+                    } else {
+                        v.parse().ok()
+                    };
+                    match pct {
+                        Some(pct) => activity.push((column.clone(), pct)),
+                        None => valid = false,
+                    }
+                    continue;
+                }
+            };
+
+            match parse_sensitivity(v) {
+                Some(v) => *sensitivity = v,
+                None => valid = false,
+            }
+        }
+
+        if !valid {
+            warnings.push(ImportWarning::new(
+                Some(Position::Line(i + 1)),
+                format!("Invalid properties for {name}"),
+            ));
+            continue;
+        }
+
+        for re in lib.iter_mut() {
+            if re.name.eq_ignore_ascii_case(name) {
+                re.buffer_activity.clone_from(&activity);
+                re.methylation = methylation;
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Load enzyme sets. Each line is a set: Its name, a colon, and enzyme names separated by commas. Lines
+/// starting with `#` are comments.
+pub fn load_enzyme_sets(path: &Path) -> Result<Imported<Vec<EnzymeSet>>, FileError> {
+    let text = fs::read_to_string(path)?;
+
+    let mut result = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((name, enzymes)) = line.split_once(':') else {
+            warnings.push(ImportWarning::new(
+                Some(Position::Line(i + 1)),
+                "Expected a set name, followed by a colon",
+            ));
+            continue;
+        };
+
+        result.push(EnzymeSet {
+            name: name.trim().to_owned(),
+            enzymes: enzymes
+                .split(',')
+                .map(|e| e.trim().to_owned())
+                .filter(|e| !e.is_empty())
+                .collect(),
+        });
+    }
+
+    Ok(Imported {
+        data: result,
+        warnings,
+    })
+}
+
+/// Save enzyme sets, in the format `load_enzyme_sets` reads.
+pub fn save_enzyme_sets(sets: &[EnzymeSet], path: &Path) -> Result<(), FileError> {
+    let mut file = File::create(path)?;
+
+    writeln!(file, "# PlasCAD enzyme sets: <name>: <enzyme>, <enzyme>...")?;
+    for set in sets {
+        // Colons would split the name from its enzymes when loading.
+        writeln!(
+            file,
+            "{}: {}",
+            set.name.replace(':', " "),
+            set.enzymes.join(", ")
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WITHREFM: &str = "REBASE version 410
+<1>BaeI
+<2>
+<3>(10/15)ACNNNNGTAYC(12/7)
+<4>
+<5>Bacillus sphaericus
+<6>
+<7>N
+<8>
+
+<1>BsaI
+<2>Eco31I
+<3>GGTCTC(1/5)
+<4>
+<5>Bacillus stearothermophilus 6-55
+<6>
+<7>N
+<8>
+
+<1>CjeI
+<3>(8/14)CCANNNNNNGT(15/9)
+<7>
+
+<1>EcoRI
+<2>
+<3>G^AATTC
+<4>
+<5>Escherichia coli RY13
+<6>
+<7>BCJKMNOQRSVX
+<8>
+
+<1>Hin4II
+<3>CCTTC
+<7>
+";
+
+    const EMBOSS_E: &str = "# REBASE version 410 emboss_e.410
+#
+BaeI\tACNNNNGTAYC\t11\t4\t0\t-11\t-16\t23\t18
+BsaI\tGGTCTC\t6\t2\t0\t7\t11\t0\t0
+CjeI\tCCANNNNNNGT\t11\t4\t0\t-9\t-15\t26\t20
+EcoRI\tGAATTC\t6\t2\t0\t1\t5\t0\t0
+Hin4II\tCCTTC\t5\t0\t0\t0\t0\t0\t0
+";
+
+    #[test]
+    fn withrefm() {
+        let result = parse_withrefm(WITHREFM);
+        let names: Vec<_> = result.data.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["BaeI", "BsaI", "CjeI", "EcoRI"]);
+        // Hin4II, without known cut positions.
+        assert_eq!(result.warnings.len(), 1);
+
+        // Cuts before the site are negative: 10 nucleotides before it, on the top strand.
+        let bae = &result.data[0];
+        assert_eq!(bae.site, seq_from_str("ACNNNNGTAYC"));
+        assert_eq!((bae.cut_top, bae.cut_bottom), (-10, -15));
+        assert_eq!(bae.cut_2, Some((23, 18)));
+        assert_eq!(bae.suppliers, ['N']);
+
+        let bsa = &result.data[1];
+        assert_eq!((bsa.cut_top, bsa.cut_bottom, bsa.cut_2), (7, 11, None));
+
+        let eco = &result.data[3];
+        assert_eq!((eco.cut_top, eco.cut_bottom, eco.cut_2), (1, 5, None));
+        assert_eq!(eco.suppliers.len(), 12);
+    }
+
+    #[test]
+    fn emboss_e() {
+        let result = parse_emboss_e(EMBOSS_E);
+        assert_eq!(result.warnings.len(), 1);
+
+        // The same enzymes as withrefm, without suppliers. Positions before the site skip 0.
+        let mut expected = parse_withrefm(WITHREFM).data;
+        for enzyme in &mut expected {
+            enzyme.suppliers.clear();
+        }
+        assert_eq!(result.data, expected);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(parse_site("GGTCTC"), None);
+        assert_eq!(parse_site("GGTCTC(1/"), None);
+        assert_eq!(parse_site("GGXCTC(1/5)"), None);
+        assert_eq!(
+            parse_site("GACNNN^NNGTC"),
+            Some((seq_from_str("GACNNNNNGTC"), vec![(6, 5)]))
+        );
+
+        let result = parse_emboss_e("EcoRI\tGAATTC\t6\t2\t0\t1\n");
+        assert!(result.data.is_empty());
+        assert_eq!(result.warnings.len(), 1);
+    }
+}
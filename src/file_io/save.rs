@@ -1,11 +1,20 @@
 //! This module includes code for reading and writing our PlasCAD binary fileformat.
 //!
-//! Todo: Break into packets. This may require further ejecting from bincode, at least at the top level[s].
+//! Files are a versioned container of packets, similar to SnapGene's format:
+//! - 4 bytes: The magic number "PCAD"
+//! - A big endian 16-bit unsigned integer of the format version
+//! - Packets, until the end of the file. Packet structure:
+//!   - A byte indicating the packet's type
+//!   - A big endian 32-bit unsigned integer of packet len
+//!   - The payload. Most are bincode-encoded.
+//!
+//! Packets of types we don't recognize are skipped, so files saved by newer versions open with the data
+//! we understand. Files saved before this container (Version 0) are a single bincode blob; we convert these
+//! in `save_compat`. Projects use the same container, with one nested container per document.
 
 use std::{
-    fs::{File, OpenOptions},
-    io,
-    io::{ErrorKind, Read, Write},
+    fs::File,
+    io::{Read, Write},
     path::Path,
 };
 
@@ -15,53 +24,60 @@ use bincode::{
     Decode, Encode,
 };
 use bio::io::fasta;
+use num_enum::TryFromPrimitive;
 
 use crate::{
-    file_io::GenericData,
-    primer::{Primer, PrimerData},
+    file_io::{
+        error::{parse_seq_letters, FileError, ImportWarning, Imported, Position},
+        GenericData,
+    },
+    history::History,
+    primer::Primer,
+    save_compat::{self, IonConcentrationsV1},
     sequence::{Feature, Nucleotide, ReadingFrame, Seq, SeqTopology},
-    IonConcentrations, Metadata, Reference, State,
+    IonConcentrations, Metadata,
 };
 
 pub const DEFAULT_SAVE_FILE: &str = "plasmid.pcad";
-pub const DEFAULT_FASTA_FILE: &str = "export.fasta";
-pub const DEFAULT_DNA_FILE: &str = "export.dna";
-
-/// This is similar to `State`, but excludes the UI, and other things we don't wish to save.
-pub struct StateToSave {
-    generic: GenericData,
-    insert_loc: usize,
-    ion_concentrations: IonConcentrations,
-    reading_frame: ReadingFrame,
-}
-
-impl Encode for StateToSave {
-    fn encode<E: bincode::enc::Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
-        // todo: We currently use default encoding for all this, but may change later.
-        self.generic.encode(encoder)?;
-        self.insert_loc.encode(encoder)?;
-        self.ion_concentrations.encode(encoder)?;
-        self.reading_frame.encode(encoder)?;
-
-        Ok(())
-    }
+// pub const DEFAULT_FASTA_FILE: &str = "export.fasta";
+// pub const DEFAULT_DNA_FILE: &str = "export.dna";
+
+const MAGIC: &[u8; 4] = b"PCAD";
+/// Increment this when changing the layout of an existing packet type, and add a conversion from the
+/// previous version to `save_compat`. Adding packet types doesn't require a new version.
+pub const FORMAT_VERSION: u16 = 2;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+const PACKET_HEADER_LEN: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Debug, TryFromPrimitive)]
+#[repr(u8)]
+enum PacketType {
+    /// Our compact binary serialization; see `serialize_seq_bin`.
+    Seq = 0,
+    Topology = 1,
+    Features = 2,
+    Primers = 3,
+    Metadata = 4,
+    IonConcentrations = 5,
+    InsertLoc = 6,
+    ReadingFrame = 7,
+    History = 8,
+    /// A nested container, with a document of a project.
+    Document = 0x10,
+    /// The index of the active document in a project.
+    ActiveDocument = 0x11,
 }
 
-impl Decode for StateToSave {
-    fn decode<D: bincode::de::Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-        // todo: We currently use default encoding for all this, but may change later.
-        let generic = GenericData::decode(decoder)?;
-        let insert_loc = usize::decode(decoder)?;
-        let ion_concentrations = IonConcentrations::decode(decoder)?;
-        let reading_frame = ReadingFrame::decode(decoder)?;
-
-        Ok(Self {
-            generic,
-            insert_loc,
-            ion_concentrations,
-            reading_frame,
-        })
-    }
+/// A document, and the settings saved with it. In the application, this is similar to its state, but
+/// excludes the UI, and other things we don't wish to save.
+#[derive(Default)]
+pub struct StateToSave {
+    pub generic: GenericData,
+    pub insert_loc: usize,
+    pub ion_concentrations: IonConcentrations,
+    pub reading_frame: ReadingFrame,
+    /// Undo and redo history, if the user chose to save it.
+    pub history: Option<History>,
 }
 
 impl Encode for GenericData {
@@ -84,8 +100,7 @@ impl Decode for GenericData {
     fn decode<D: bincode::de::Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
         // Deserialize seq using our custom deserializer
         let seq_data = Vec::decode(decoder)?;
-        // let seq = deser_seq_bin(&seq_data).map_err(|_| file_io: :Error::new(ErrorKind::InvalidData, "Error deserializing seq"));
-        let seq = deser_seq_bin(&seq_data).unwrap_or_default(); // todo: Better error handling/propogation.
+        let seq = deser_seq_bin(&seq_data).map_err(|e| DecodeError::OtherString(e.to_string()))?;
 
         // Deserialize other fields using default deserialization
         let topology = SeqTopology::decode(decoder)?;
@@ -103,56 +118,353 @@ impl Decode for GenericData {
     }
 }
 
+bincode::impl_borrow_decode!(GenericData);
+
+fn write_packet(buf: &mut Vec<u8>, packet_type: PacketType, payload: &[u8]) {
+    buf.push(packet_type as u8);
+    buf.extend(&(payload.len() as u32).to_be_bytes());
+    buf.extend(payload);
+}
+
+fn write_packet_bincode<T: Encode>(
+    buf: &mut Vec<u8>,
+    packet_type: PacketType,
+    data: &T,
+) -> Result<(), FileError> {
+    let payload = bincode::encode_to_vec(data, config::standard())
+        .map_err(|e| FileError::Encode(format!("{packet_type:?} packet: {e}")))?;
+
+    write_packet(buf, packet_type, &payload);
+    Ok(())
+}
+
+fn decode_payload<T: Decode>(packet: &Packet) -> Result<T, FileError> {
+    bincode::decode_from_slice(packet.payload, config::standard())
+        .map(|(v, _len)| v)
+        .map_err(|e| {
+            FileError::format(
+                Some(Position::Byte(packet.pos)),
+                format!("Error decoding {:?} packet: {e}", packet.packet_type),
+            )
+        })
+}
+
+fn write_header(buf: &mut Vec<u8>) {
+    buf.extend(MAGIC);
+    buf.extend(&FORMAT_VERSION.to_be_bytes());
+}
+
+/// Returns the format version, or None if the data is from before we used a container.
+fn read_header(buf: &[u8]) -> Option<u16> {
+    if buf.len() < HEADER_LEN || &buf[..MAGIC.len()] != MAGIC {
+        return None;
+    }
+    Some(u16::from_be_bytes([buf[4], buf[5]]))
+}
+
+struct Packet<'a> {
+    packet_type: PacketType,
+    /// The position of the packet's start in the file, for reporting problems.
+    pos: usize,
+    payload: &'a [u8],
+}
+
+/// Split a container's body into packets. Packets of unknown type are skipped, with a warning.
+/// `offset` is the container's position in the file; it's non-zero for nested containers.
+fn read_packets<'a>(
+    buf: &'a [u8],
+    offset: usize,
+    warnings: &mut Vec<ImportWarning>,
+) -> Result<Vec<Packet<'a>>, FileError> {
+    let mut result = Vec::new();
+    let mut i = HEADER_LEN;
+
+    while i < buf.len() {
+        let pos = offset + i;
+
+        if i + PACKET_HEADER_LEN > buf.len() {
+            return Err(FileError::format(
+                Some(Position::Byte(pos)),
+                "Packet header would exceed file length.",
+            ));
+        }
+
+        let type_byte = buf[i];
+        let payload_len = u32::from_be_bytes(buf[i + 1..i + 5].try_into().unwrap()) as usize;
+        i += PACKET_HEADER_LEN;
+
+        if i + payload_len > buf.len() {
+            return Err(FileError::format(
+                Some(Position::Byte(pos)),
+                format!("Packet payload would exceed file length. Len: {payload_len}"),
+            ));
+        }
+
+        let payload = &buf[i..i + payload_len];
+        i += payload_len;
+
+        match PacketType::try_from_primitive(type_byte) {
+            Ok(packet_type) => result.push(Packet {
+                packet_type,
+                pos,
+                payload,
+            }),
+            Err(_) => warnings.push(ImportWarning::new(
+                Some(Position::Byte(pos)),
+                format!("Skipped a packet of unknown type {type_byte:#x}, len {payload_len}"),
+            )),
+        }
+    }
+
+    Ok(result)
+}
+
 impl StateToSave {
-    pub fn from_state(state: &State) -> Self {
-        Self {
-            generic: state.generic.clone(),
-            insert_loc: state.insert_loc,
-            ion_concentrations: state.ion_concentrations.clone(),
-            reading_frame: state.reading_frame.clone(),
+    /// Serialize to our container format, including its header.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FileError> {
+        let mut buf = Vec::new();
+        write_header(&mut buf);
+
+        write_packet(
+            &mut buf,
+            PacketType::Seq,
+            &serialize_seq_bin(&self.generic.seq),
+        );
+        write_packet_bincode(&mut buf, PacketType::Topology, &self.generic.topology)?;
+        write_packet_bincode(&mut buf, PacketType::Features, &self.generic.features)?;
+        write_packet_bincode(&mut buf, PacketType::Primers, &self.generic.primers)?;
+        write_packet_bincode(&mut buf, PacketType::Metadata, &self.generic.metadata)?;
+        write_packet_bincode(
+            &mut buf,
+            PacketType::IonConcentrations,
+            &self.ion_concentrations,
+        )?;
+        write_packet_bincode(&mut buf, PacketType::InsertLoc, &self.insert_loc)?;
+        write_packet_bincode(&mut buf, PacketType::ReadingFrame, &self.reading_frame)?;
+
+        if let Some(history) = &self.history {
+            write_packet_bincode(&mut buf, PacketType::History, history)?;
         }
+
+        Ok(buf)
     }
 
-    /// Used to load to state. The result is data from this struct, augmented with default values.
-    pub fn to_state(self) -> State {
-        State {
-            generic: self.generic,
-            insert_loc: self.insert_loc,
-            ion_concentrations: self.ion_concentrations,
-            reading_frame: self.reading_frame,
-            ..Default::default()
+    /// Deserialize from our container format, or from files saved prior to it. If the data is a
+    /// project, this loads its active document.
+    pub fn from_bytes(buf: &[u8]) -> Result<Imported<Self>, FileError> {
+        let Imported {
+            data: project,
+            mut warnings,
+        } = ProjectToSave::from_bytes(buf)?;
+
+        let active = project.active;
+        if project.documents.len() > 1 {
+            warnings.push(ImportWarning::new(
+                None,
+                format!(
+                    "This is a project with {} documents; only the active one was loaded.",
+                    project.documents.len()
+                ),
+            ));
         }
+
+        let data = project
+            .documents
+            .into_iter()
+            .nth(active)
+            .ok_or_else(|| FileError::format(None, "The project has no documents."))?;
+
+        Ok(Imported { data, warnings })
     }
-}
 
-pub fn save<T: Encode>(path: &Path, data: &T) -> io::Result<()> {
-    let config = config::standard();
+    /// Deserialize a single document's packets; `buf` includes the header, and starts at `offset`
+    /// in the file.
+    fn from_packets(
+        buf: &[u8],
+        offset: usize,
+        warnings: &mut Vec<ImportWarning>,
+    ) -> Result<Self, FileError> {
+        let mut result = Self::default();
+        let version = read_header(buf).unwrap_or(FORMAT_VERSION);
+
+        for packet in read_packets(buf, offset, warnings)? {
+            // A packet we can't decode, eg from a newer version, doesn't prevent loading the others.
+            let decoded = match packet.packet_type {
+                PacketType::Seq => deser_seq_bin(packet.payload).map(|v| result.generic.seq = v),
+                PacketType::Topology => {
+                    decode_payload(&packet).map(|v| result.generic.topology = v)
+                }
+                PacketType::Features => {
+                    decode_payload(&packet).map(|v| result.generic.features = v)
+                }
+                PacketType::Primers => decode_payload(&packet).map(|v| result.generic.primers = v),
+                PacketType::Metadata => {
+                    decode_payload(&packet).map(|v| result.generic.metadata = v)
+                }
+                PacketType::IonConcentrations if version < 2 => {
+                    decode_payload::<IonConcentrationsV1>(&packet)
+                        .map(|v| result.ion_concentrations = v.into())
+                }
+                PacketType::IonConcentrations => {
+                    decode_payload(&packet).map(|v| result.ion_concentrations = v)
+                }
+                PacketType::InsertLoc => decode_payload(&packet).map(|v| result.insert_loc = v),
+                PacketType::ReadingFrame => {
+                    decode_payload(&packet).map(|v| result.reading_frame = v)
+                }
+                PacketType::History => decode_payload(&packet).map(|v| result.history = Some(v)),
+                PacketType::Document | PacketType::ActiveDocument => Ok(()),
+            };
+
+            if let Err(e) = decoded {
+                warnings.push(ImportWarning::new(
+                    Some(Position::Byte(packet.pos)),
+                    format!("Skipped a {:?} packet: {e}", packet.packet_type),
+                ));
+            }
+        }
 
-    let encoded: Vec<u8> = bincode::encode_to_vec(data, config).unwrap();
+        Ok(result)
+    }
 
-    let mut file = File::create(path)?;
-    file.write_all(&encoded)?;
-    Ok(())
+    pub fn save(&self, path: &Path) -> Result<(), FileError> {
+        let mut file = File::create(path)?;
+        Ok(file.write_all(&self.to_bytes()?)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Imported<Self>, FileError> {
+        Self::from_bytes(&read_file(path)?)
+    }
+}
+
+/// All documents in the workspace, saved together. Each is saved with the same settings, eg ion
+/// concentrations; these are loaded from the active one.
+pub struct ProjectToSave {
+    pub documents: Vec<StateToSave>,
+    pub active: usize,
 }
 
-pub fn load<T: Decode>(filename: &str) -> io::Result<T> {
-    let config = config::standard();
+impl ProjectToSave {
+    /// Serialize to our container format, with a nested container for each document.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FileError> {
+        let mut buf = Vec::new();
+        write_header(&mut buf);
+
+        for doc in &self.documents {
+            write_packet(&mut buf, PacketType::Document, &doc.to_bytes()?);
+        }
+        write_packet_bincode(&mut buf, PacketType::ActiveDocument, &self.active)?;
+
+        Ok(buf)
+    }
+
+    /// Deserialize from our container format, or from files saved prior to it. A file with a single
+    /// document loads as a project containing it.
+    pub fn from_bytes(buf: &[u8]) -> Result<Imported<Self>, FileError> {
+        let version = match read_header(buf) {
+            Some(v) => v,
+            None => {
+                return Ok(save_compat::load_v0(buf)?.map(|doc| Self {
+                    documents: vec![doc],
+                    active: 0,
+                }))
+            }
+        };
+
+        let mut warnings = Vec::new();
+
+        if version > FORMAT_VERSION {
+            warnings.push(ImportWarning::new(
+                None,
+                format!(
+                    "This file was saved by a newer version of PlasCAD (Format {version}). Some data may not load."
+                ),
+            ));
+        }
+
+        // A single document's packets are read again below, so only keep these warnings for projects.
+        let mut packet_warnings = Vec::new();
+        let packets = read_packets(buf, 0, &mut packet_warnings)?;
+        if !packets
+            .iter()
+            .any(|p| p.packet_type == PacketType::Document)
+        {
+            let doc = StateToSave::from_packets(buf, 0, &mut warnings)?;
+            return Ok(Imported {
+                data: Self {
+                    documents: vec![doc],
+                    active: 0,
+                },
+                warnings,
+            });
+        }
+
+        warnings.append(&mut packet_warnings);
+
+        let mut documents = Vec::new();
+        let mut active = 0;
+
+        for packet in packets {
+            match packet.packet_type {
+                PacketType::Document => {
+                    let payload_pos = packet.pos + PACKET_HEADER_LEN;
+                    if read_header(packet.payload).is_none() {
+                        warnings.push(ImportWarning::new(
+                            Some(Position::Byte(packet.pos)),
+                            "Skipped a document with an invalid header",
+                        ));
+                        continue;
+                    }
+                    // A damaged document doesn't prevent loading the others.
+                    match StateToSave::from_packets(packet.payload, payload_pos, &mut warnings) {
+                        Ok(doc) => documents.push(doc),
+                        Err(e) => warnings.push(ImportWarning::new(
+                            Some(Position::Byte(packet.pos)),
+                            format!("Skipped a document: {e}"),
+                        )),
+                    }
+                }
+                PacketType::ActiveDocument => match decode_payload(&packet) {
+                    Ok(v) => active = v,
+                    Err(e) => warnings.push(ImportWarning::new(None, e.to_string())),
+                },
+                _ => (),
+            }
+        }
+
+        if documents.is_empty() {
+            return Err(FileError::format(
+                None,
+                "The project has no readable documents.",
+            ));
+        }
+
+        let active = active.min(documents.len() - 1);
+        Ok(Imported {
+            data: Self { documents, active },
+            warnings,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), FileError> {
+        let mut file = File::create(path)?;
+        Ok(file.write_all(&self.to_bytes()?)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Imported<Self>, FileError> {
+        Self::from_bytes(&read_file(path)?)
+    }
+}
 
-    let mut file = File::open(filename)?;
+fn read_file(path: &Path) -> Result<Vec<u8>, FileError> {
+    let mut file = File::open(path)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
-    let (decoded, _len) = match bincode::decode_from_slice(&buffer, config) {
-        Ok(v) => v,
-        Err(_) => {
-            eprintln!("Error loading from file. Did the format change?");
-            return Err(io::Error::new(ErrorKind::Other, "error loading"));
-        }
-    };
-    Ok(decoded)
+    Ok(buffer)
 }
 
 /// Export a sequence in FASTA format.
-pub fn export_fasta(seq: &[Nucleotide], name: &str, path: &Path) -> io::Result<()> {
+pub fn export_fasta(seq: &[Nucleotide], name: &str, path: &Path) -> Result<(), FileError> {
     let file = File::create(path)?;
 
     let seq_u8: Vec<u8> = seq.iter().map(|nt| nt.to_u8_letter()).collect();
@@ -163,49 +475,94 @@ pub fn export_fasta(seq: &[Nucleotide], name: &str, path: &Path) -> io::Result<(
     Ok(())
 }
 
-/// Import from a FASTA file. (Seq, plasmid name (id), description)
-pub fn import_fasta(path: &Path) -> io::Result<(Seq, String, String)> {
+/// Import from a FASTA file. (Seq, plasmid name (id), description). If there are multiple records,
+/// their sequences are joined.
+pub fn import_fasta(path: &Path) -> Result<Imported<(Seq, String, String)>, FileError> {
     let file = File::open(path)?;
 
-    let mut records = fasta::Reader::new(file).records();
-
     let mut result = Vec::new();
+    let mut warnings = Vec::new();
 
     // todo: Do we want id, or description?
     let mut id = String::new();
     let mut description = String::new();
+    let mut record_count = 0;
+
+    for (i, record) in fasta::Reader::new(file).records().enumerate() {
+        let record = record.map_err(|e| {
+            FileError::format(
+                Some(Position::Record(i + 1)),
+                format!("Invalid record: {e}"),
+            )
+        })?;
+        record_count += 1;
+
+        // This reports positions relative to the start of the combined sequence.
+        let start = result.len();
+        result.extend(parse_seq_letters(
+            record.seq(),
+            |j| Position::Nucleotide(start + j + 1),
+            &mut warnings,
+        ));
 
-    while let Some(Ok(record)) = records.next() {
-        for r in record.seq() {
-            result.push(Nucleotide::from_u8_letter(*r)?);
-            record.id().clone_into(&mut id); // Note that this overrides previous records, if applicable.
-            record
-                .desc()
-                .unwrap_or_default()
-                .clone_into(&mut description)
-        }
+        // Note that this overrides previous records, if applicable.
+        record.id().clone_into(&mut id);
+        record
+            .desc()
+            .unwrap_or_default()
+            .clone_into(&mut description);
+    }
+
+    if record_count == 0 {
+        return Err(FileError::format(None, "No FASTA records found"));
+    }
+    if record_count > 1 {
+        warnings.push(ImportWarning::new(
+            None,
+            format!("Joined the sequences of {record_count} records; the name is from the last."),
+        ));
     }
 
-    Ok((result, id, description))
+    Ok(Imported {
+        data: (result, id, description),
+        warnings,
+    })
 }
 
+/// Set in the sequence length header if the sequence includes IUPAC ambiguity codes; these sequences
+/// use the extended 4-bit encoding.
+const SEQ_BIN_EXTENDED_FLAG: u32 = 1 << 31;
+
 /// A compact binary serialization of our sequence. Useful for file storage.
-/// The first byte is sequence length; we need this, since one of our nucleotides necessarily serializes
+/// The first 4 bytes are sequence length; we need this, since one of our nucleotides necessarily serializes
 /// to 0b00.
+///
+/// If the sequence is made of only A, T, G, and C, each nucleotide takes up 2 bits. If it contains
+/// ambiguity codes (eg N), we set the length's MSB, and each nucleotide takes up 4 bits. Sequences
+/// saved prior to this extension always use the 2-bit encoding.
 /// todo: Is this MSB or LSB?
 pub fn serialize_seq_bin(seq: &[Nucleotide]) -> Vec<u8> {
+    let extended = seq.iter().any(|nt| nt.is_ambiguous());
+
+    let (bits_per_nt, len_header) = if extended {
+        (4, seq.len() as u32 | SEQ_BIN_EXTENDED_FLAG)
+    } else {
+        (2, seq.len() as u32)
+    };
+    let nts_per_byte = 8 / bits_per_nt;
+
     let mut result = Vec::new();
-    result.extend(&(seq.len() as u32).to_be_bytes());
+    result.extend(&len_header.to_be_bytes());
 
-    for i in 0..seq.len() / 4 + 1 {
+    for i in 0..seq.len() / nts_per_byte + 1 {
         let mut val = 0;
-        for j in 0..4 {
-            let ind = i * 4 + j;
+        for j in 0..nts_per_byte {
+            let ind = i * nts_per_byte + j;
             if ind + 1 > seq.len() {
                 break;
             }
             let nt = seq[ind];
-            val |= (nt as u8) << (j * 2);
+            val |= (nt as u8) << (j * bits_per_nt);
         }
         result.push(val);
     }
@@ -214,34 +571,112 @@ pub fn serialize_seq_bin(seq: &[Nucleotide]) -> Vec<u8> {
 
 /// A compact binary deserialization of our sequence.
 /// todo: Is this MSB or LSB?
-pub fn deser_seq_bin(data: &[u8]) -> io::Result<Seq> {
+pub fn deser_seq_bin(data: &[u8]) -> Result<Seq, FileError> {
     let mut result = Vec::new();
 
     if data.len() < 4 {
-        return Err(io::Error::new(
-            ErrorKind::InvalidData,
+        return Err(FileError::format(
+            None,
             "Bin nucleotide sequence is too short.",
         ));
     }
 
-    let seq_len = u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize;
+    let len_header = u32::from_be_bytes(data[0..4].try_into().unwrap());
+
+    let extended = len_header & SEQ_BIN_EXTENDED_FLAG != 0;
+    let seq_len = (len_header & !SEQ_BIN_EXTENDED_FLAG) as usize;
+
+    let (bits_per_nt, mask) = if extended { (4, 0b1111) } else { (2, 0b11) };
 
     for byte in &data[4..] {
-        for i in 0..4 {
+        for i in 0..8 / bits_per_nt {
             // This trimming removes extra 00-serialized nucleotides.
             if result.len() >= seq_len {
                 break;
             }
 
-            let bits = (byte >> (2 * i)) & 0b11;
+            let bits = (byte >> (bits_per_nt * i)) & mask;
             result.push(Nucleotide::try_from(bits).map_err(|_| {
-                io::Error::new(
-                    ErrorKind::InvalidData,
+                FileError::format(
+                    None,
                     format!("Invalid NT serialization: {}, {}", byte, bits),
                 )
             })?);
         }
     }
 
+    if result.len() < seq_len {
+        return Err(FileError::format(
+            None,
+            "Bin nucleotide sequence is shorter than its length header.",
+        ));
+    }
+
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::seq_from_str;
+
+    #[test]
+    fn v1_ion_concentrations() {
+        let seq = seq_from_str("ATGGCCAAGTAG");
+
+        let mut buf = Vec::new();
+        buf.extend(MAGIC);
+        buf.extend(&1_u16.to_be_bytes());
+        write_packet(&mut buf, PacketType::Seq, &serialize_seq_bin(&seq));
+        // Monovalent, divalent, dNTP, and primer; this version has no Tris, or Tm settings.
+        write_packet_bincode(
+            &mut buf,
+            PacketType::IonConcentrations,
+            &(100_f32, 2_f32, 0.4_f32, 50_f32),
+        )
+        .unwrap();
+        write_packet_bincode(&mut buf, PacketType::InsertLoc, &5_usize).unwrap();
+
+        let Imported { data, warnings } = StateToSave::from_bytes(&buf).unwrap();
+        assert!(warnings.is_empty());
+
+        assert_eq!(data.generic.seq, seq);
+        assert_eq!(data.insert_loc, 5);
+
+        let ions = &data.ion_concentrations;
+        assert_eq!(ions.monovalent, 100.);
+        assert_eq!(ions.divalent, 2.);
+        assert_eq!(ions.dntp, 0.4);
+        assert_eq!(ions.primer, 50.);
+        assert_eq!(ions.tris, 0.);
+    }
+
+    #[test]
+    fn unknown_packet_skipped() {
+        let state = StateToSave {
+            generic: GenericData {
+                seq: seq_from_str("ATGGCCAAGTAG"),
+                ..Default::default()
+            },
+            insert_loc: 5,
+            ..Default::default()
+        };
+
+        // As if saved by a newer version, with a packet type we don't know, before our own.
+        let mut buf = Vec::new();
+        write_header(&mut buf);
+        buf.push(0x7f);
+        buf.extend(&3_u32.to_be_bytes());
+        buf.extend([1, 2, 3]);
+        buf.extend(&state.to_bytes().unwrap()[HEADER_LEN..]);
+
+        let Imported { data, warnings } = StateToSave::from_bytes(&buf).unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].position, Some(Position::Byte(HEADER_LEN)));
+        assert!(warnings[0].msg.contains("unknown type 0x7f"));
+
+        assert_eq!(data.generic.seq, state.generic.seq);
+        assert_eq!(data.insert_loc, 5);
+    }
+}
@@ -10,7 +10,7 @@
 use std::{
     collections::HashMap,
     fs::File,
-    io::{self, ErrorKind, Read, Write},
+    io::{Read, Write},
     path::Path,
     str,
 };
@@ -20,6 +20,7 @@ use quick_xml::{de::from_str, se::to_string};
 
 use crate::{
     file_io::{
+        error::{parse_seq_letters, FileError, ImportWarning, Imported, Position},
         get_filename,
         snapgene::feature_xml::{
             FeatureSnapGene, Features, Notes, PrimerSnapGene, Primers, Segment,
@@ -28,7 +29,7 @@ use crate::{
     },
     primer::Primer,
     sequence::{
-        seq_from_str, seq_to_str, Feature, FeatureDirection, FeatureType, Nucleotide, Seq,
+        seq_from_str, seq_to_str, Feature, FeatureDirection, FeatureType, Seq, SeqRange,
         SeqTopology,
     },
     util::{color_from_hex, color_to_hex},
@@ -53,7 +54,7 @@ enum PacketType {
 }
 
 /// Import a file in SnapGene's DNA format into local state. This includes sequence, features, and primers.
-pub fn import_snapgene(path: &Path) -> io::Result<GenericData> {
+pub fn import_snapgene(path: &Path) -> Result<Imported<GenericData>, FileError> {
     let mut file = File::open(path)?;
 
     let buf = {
@@ -62,7 +63,15 @@ pub fn import_snapgene(path: &Path) -> io::Result<GenericData> {
         b
     };
 
+    if buf.first() != Some(&(PacketType::Cookie as u8)) {
+        return Err(FileError::format(
+            Some(Position::Byte(0)),
+            "This isn't a SnapGene file: It doesn't start with a cookie packet",
+        ));
+    }
+
     let mut result = GenericData::default();
+    let mut warnings = Vec::new();
 
     result.metadata.plasmid_name = get_filename(path);
 
@@ -72,31 +81,48 @@ pub fn import_snapgene(path: &Path) -> io::Result<GenericData> {
         if i + 6 >= buf.len() {
             break;
         }
+        let packet_start = i;
         let packet_type = PacketType::try_from_primitive(buf[i]).unwrap_or(PacketType::Unknown);
         i += 1;
 
         let payload_len = u32::from_be_bytes(buf[i..i + 4].try_into().unwrap()) as usize;
         i += 4;
 
-        if i + payload_len + 1 > buf.len() {
-            eprintln!(
-                "Error parsing DNA file: Payload would exceed file length. Index: {}, buf len: {}",
-                i + payload_len,
-                buf.len()
-            );
+        if i + payload_len > buf.len() {
+            warnings.push(ImportWarning::new(
+                Some(Position::Byte(packet_start)),
+                format!(
+                    "The file is truncated: A {packet_type:?} packet's payload would exceed file length. \
+                    Data from here on wasn't imported."
+                ),
+            ));
             break;
         }
 
         let payload = &buf[i..i + payload_len];
         i += payload_len;
 
+        // Problems with a packet don't prevent importing the others.
+        let skipped = |e: FileError| {
+            ImportWarning::new(
+                Some(Position::Byte(packet_start)),
+                format!("Skipped a {packet_type:?} packet: {e}"),
+            )
+        };
+
         match packet_type {
             PacketType::Cookie => {
-                if payload_len != COOKIE_PACKET_LEN {
-                    eprintln!("Invalid cookie packet length: {}", payload_len);
+                if payload.get(..8) != Some(b"SnapGene") {
+                    return Err(FileError::format(
+                        Some(Position::Byte(packet_start)),
+                        "This isn't a SnapGene file: Its cookie is invalid",
+                    ));
                 }
-                if &payload[..8] != b"SnapGene" {
-                    eprintln!("Invalid cookie payload: {:?}", &payload[0..8]);
+                if payload_len != COOKIE_PACKET_LEN {
+                    warnings.push(ImportWarning::new(
+                        Some(Position::Byte(packet_start)),
+                        format!("Unexpected cookie packet length: {payload_len}"),
+                    ));
                 }
                 // The next bytes describe the type of seq (1 for DNA, export version, and import version)
                 // at indexes 0xd, 0xf, and 0x11 respectively.
@@ -105,20 +131,21 @@ pub fn import_snapgene(path: &Path) -> io::Result<GenericData> {
                 // todo: Note: This doesn't properly handle if there are multiple DNA packets.
                 // todo: How should we do that?
 
-                match parse_dna(&payload) {
+                match parse_dna(payload, packet_start + 5, &mut warnings) {
                     Ok(v) => {
                         result.seq = v.0;
                         result.topology = v.1;
                     }
-                    Err(e) => eprintln!("Error parsing DNA packet: {:?}", e),
+                    Err(e) => warnings.push(skipped(e)),
                 }
             }
             PacketType::Primers => match parse_primers(payload) {
                 Ok(v) => result.primers = v,
-                Err(e) => eprintln!("Error parsing Primers packet: {:?}", e),
+                Err(e) => warnings.push(skipped(e)),
             },
-            PacketType::Notes => match parse_notes(payload) {
-                Ok(v) => {
+            PacketType::Notes => {
+                // We don't use notes yet, so failing to parse them doesn't lose anything.
+                if let Ok(_v) = parse_notes(payload) {
                     // if !v.inner.is_empty() {
                     //     // todo: Are there ever multiple notes?
                     //     result.metadata.plasmid_name = v.inner[0].title.clone();
@@ -126,54 +153,35 @@ pub fn import_snapgene(path: &Path) -> io::Result<GenericData> {
                     // todo: Other fields, references etc. Compare in SnapGene itself, and how snapgene
                     // todo parses and exports a GenBank file.
                 }
-                Err(e) => eprintln!("Error parsing Notes packet: {:?}", e),
-            },
-            PacketType::Features => match parse_features(payload) {
+            }
+            PacketType::Features => match parse_features(payload, &mut warnings) {
                 Ok(v) => result.features = v,
-                Err(e) => eprintln!("Error parsing Features packet: {:?}", e),
+                Err(e) => warnings.push(skipped(e)),
             },
-            PacketType::Unknown => {
-                println!(
-                    "Unknown packet type: {:x} len: {payload_len}",
-                    buf[i - 5 - payload_len]
-                );
-
-                let payload_str = str::from_utf8(payload)
-                    .map_err(|e| {
-                        io::Error::new(
-                            ErrorKind::InvalidData,
-                            format!("Unable to convert payload to string: {e}",),
-                        )
-                    })
-                    .ok();
-
-                println!("Payload str: \n{:?}", payload_str);
-            }
             _ => (),
         }
     }
 
-    Ok(result)
+    Ok(Imported {
+        data: result,
+        warnings,
+    })
 }
 
-fn parse_dna(payload: &[u8]) -> io::Result<(Seq, SeqTopology)> {
+/// `offset` is the payload's position in the file.
+fn parse_dna(
+    payload: &[u8],
+    offset: usize,
+    warnings: &mut Vec<ImportWarning>,
+) -> Result<(Seq, SeqTopology), FileError> {
     if payload.is_empty() {
-        return Err(io::Error::new(ErrorKind::InvalidData, "Empty DNA packet"));
+        return Err(FileError::format(None, "Empty DNA packet"));
     }
 
     let flags = payload[0];
     let sequence = &payload[1..];
 
-    let mut seq = Vec::new();
-
-    for nt in sequence {
-        match Nucleotide::from_u8_letter(*nt) {
-            Ok(n) => seq.push(n),
-            Err(_) => {
-                eprintln!("Unexpected char in DNA sequence: {:?}", nt);
-            }
-        }
-    }
+    let seq = parse_seq_letters(sequence, |i| Position::Byte(offset + 1 + i), warnings);
 
     let topology = if flags & 0x01 != 0 {
         SeqTopology::Circular
@@ -181,8 +189,6 @@ fn parse_dna(payload: &[u8]) -> io::Result<(Seq, SeqTopology)> {
         SeqTopology::Linear
     };
 
-    println!("Flags: {flags}");
-
     Ok((seq, topology))
 }
 
@@ -309,27 +315,23 @@ mod feature_xml {
     // </Notes>
 }
 
-fn parse_features(payload: &[u8]) -> io::Result<Vec<Feature>> {
+/// Feature segments with ranges we can't parse are skipped, with a warning.
+fn parse_features(
+    payload: &[u8],
+    warnings: &mut Vec<ImportWarning>,
+) -> Result<Vec<Feature>, FileError> {
     let payload_str = str::from_utf8(payload).map_err(|e| {
-        io::Error::new(
-            ErrorKind::InvalidData,
-            format!("Unable to convert payload to string: {e}",),
-        )
+        FileError::format(None, format!("Unable to convert payload to string: {e}",))
     })?;
 
     // println!("\n\n\nPayload str: {:?}\n\n\n", payload_str);
 
-    let features: Features = from_str(payload_str).map_err(|e| {
-        io::Error::new(
-            ErrorKind::InvalidData,
-            format!("Unable to parse features: {e}"),
-        )
-    })?;
+    let features: Features = from_str(payload_str)
+        .map_err(|e| FileError::format(None, format!("Unable to parse features: {e}")))?;
 
     let mut result = Vec::new();
 
-    for feature_sg in &features.inner {
-        // Note: Our model does not include the concept of segments; treat each SnapGene segment as a new feature.
+    for (i, feature_sg) in features.inner.iter().enumerate() {
         let name = feature_sg.name.clone().unwrap_or(String::new());
 
         let direction = match feature_sg.directionality {
@@ -363,48 +365,69 @@ fn parse_features(payload: &[u8]) -> io::Result<Vec<Feature>> {
             }
         }
 
-        // Note: We currently parse multiple segments as separate features, but SnapGene has the concept
-        // of multiple segments per feature. These share all data except the <Segment tag attributes.
-        // (name, range, color, type, translated etc)
+        // SnapGene features may have multiple segments, eg for exons. These share all data except the
+        // <Segment tag attributes. (name, range, color, type, translated etc) We use the first segment's color.
+        let mut ranges = Vec::new();
+        let mut color_override = None;
+
         for segment in &feature_sg.segments {
-            let color_override = match &segment.color {
-                Some(c) => color_from_hex(c).ok(),
-                None => None,
-            };
-
-            let index_range = match &segment.range {
-                Some(r) => range_from_str(r).unwrap_or((1, 1)),
-                None => (1, 1),
-            };
-
-            result.push(Feature {
-                index_range,
-                feature_type,
-                direction,
-                label: name.clone(),
-                color_override,
-                notes: notes.clone(),
-            });
+            // Gap segments, eg introns, are not part of the feature's sequence.
+            if segment.segment_type.as_deref() == Some("gap") {
+                continue;
+            }
+
+            if ranges.is_empty() {
+                color_override = match &segment.color {
+                    Some(c) => color_from_hex(c).ok(),
+                    None => None,
+                };
+            }
+
+            match segment.range.as_deref().map(range_from_str) {
+                Some(Ok(r)) => ranges.push(r),
+                Some(Err(e)) => warnings.push(ImportWarning::new(
+                    Some(Position::Feature(i + 1)),
+                    format!("Skipped a segment of feature {name}: {e}"),
+                )),
+                None => warnings.push(ImportWarning::new(
+                    Some(Position::Feature(i + 1)),
+                    format!("Skipped a segment of feature {name}: It has no range"),
+                )),
+            }
         }
+
+        if ranges.is_empty() {
+            warnings.push(ImportWarning::new(
+                Some(Position::Feature(i + 1)),
+                format!("Skipped feature {name}: It has no segments"),
+            ));
+            continue;
+        }
+
+        let mut feature = Feature {
+            index_range: ranges[0],
+            segments: Vec::new(),
+            feature_type,
+            direction,
+            label: name,
+            color_override,
+            notes,
+        };
+        feature.set_ranges(ranges);
+
+        result.push(feature);
     }
 
     Ok(result)
 }
 
-fn parse_primers(payload: &[u8]) -> io::Result<Vec<Primer>> {
+fn parse_primers(payload: &[u8]) -> Result<Vec<Primer>, FileError> {
     let payload_str = str::from_utf8(payload).map_err(|e| {
-        io::Error::new(
-            ErrorKind::InvalidData,
-            format!("Unable to convert payload to string: {e}",),
-        )
+        FileError::format(None, format!("Unable to convert payload to string: {e}",))
     })?;
 
-    let primers: Primers = from_str(payload_str).map_err(|e| {
-        io::Error::new(
-            ErrorKind::InvalidData,
-            format!("Unable to parse primers: {e}"),
-        )
-    })?;
+    let primers: Primers = from_str(payload_str)
+        .map_err(|e| FileError::format(None, format!("Unable to parse primers: {e}")))?;
 
     let mut result = Vec::new();
     for primer_sg in &primers.inner {
@@ -420,24 +443,15 @@ fn parse_primers(payload: &[u8]) -> io::Result<Vec<Primer>> {
 }
 
 // fn parse_notes(payload: &[u8]) -> io::Result<Vec<String>> {
-fn parse_notes(payload: &[u8]) -> io::Result<Notes> {
+fn parse_notes(payload: &[u8]) -> Result<Notes, FileError> {
     let payload_str = str::from_utf8(payload).map_err(|e| {
-        io::Error::new(
-            ErrorKind::InvalidData,
-            format!("Unable to convert payload to string: {e}",),
-        )
+        FileError::format(None, format!("Unable to convert payload to string: {e}",))
     })?;
 
-    println!("Notes string: \n\n{:?}\n\n", payload_str);
-
     // todo: Is this a strict format, or arbitary notes?
 
-    let notes: feature_xml::Notes = from_str(payload_str).map_err(|e| {
-        io::Error::new(
-            ErrorKind::InvalidData,
-            format!("Unable to parse notes: {e}"),
-        )
-    })?;
+    let notes: feature_xml::Notes = from_str(payload_str)
+        .map_err(|e| FileError::format(None, format!("Unable to parse notes: {e}")))?;
     let result = notes;
 
     // let mut result = Vec::new();
@@ -451,7 +465,7 @@ fn parse_notes(payload: &[u8]) -> io::Result<Notes> {
     Ok(result)
 }
 
-fn range_from_str(range: &str) -> Result<(usize, usize), &'static str> {
+fn range_from_str(range: &str) -> Result<SeqRange, &'static str> {
     let parts: Vec<&str> = range.split('-').collect();
     if parts.len() != 2 {
         return Err("Invalid range format");
@@ -464,11 +478,11 @@ fn range_from_str(range: &str) -> Result<(usize, usize), &'static str> {
         .parse::<usize>()
         .map_err(|_| "Invalid number in range")?;
 
-    Ok((start, end))
+    Ok(SeqRange::new(start, end))
 }
 
 /// Add feature data to the buffer.
-fn export_features(buf: &mut Vec<u8>, features: &[Feature]) -> io::Result<()> {
+fn export_features(buf: &mut Vec<u8>, features: &[Feature]) -> Result<(), FileError> {
     let mut features_sg = Features { inner: Vec::new() };
     for feature in features {
         let directionality = match feature.direction {
@@ -477,15 +491,16 @@ fn export_features(buf: &mut Vec<u8>, features: &[Feature]) -> io::Result<()> {
             FeatureDirection::None => None,
         };
 
-        let segments = vec![Segment {
-            segment_type: None,
-            range: Some(format!(
-                "{}-{}",
-                feature.index_range.0, feature.index_range.1
-            )),
-            name: None,
-            color: feature.color_override.map(color_to_hex),
-        }];
+        let segments = feature
+            .ranges()
+            .iter()
+            .map(|r| Segment {
+                segment_type: None,
+                range: Some(format!("{}-{}", r.start, r.end)),
+                name: None,
+                color: feature.color_override.map(color_to_hex),
+            })
+            .collect();
 
         features_sg.inner.push(FeatureSnapGene {
             feature_type: Some(feature.feature_type.to_string()),
@@ -497,10 +512,7 @@ fn export_features(buf: &mut Vec<u8>, features: &[Feature]) -> io::Result<()> {
     }
 
     let xml_str = to_string(&features_sg).map_err(|e| {
-        io::Error::new(
-            ErrorKind::InvalidData,
-            format!("Unable to convert features to an XML string: {e}"),
-        )
+        FileError::Encode(format!("Unable to convert features to an XML string: {e}"))
     })?;
 
     let xml = xml_str.into_bytes();
@@ -513,7 +525,7 @@ fn export_features(buf: &mut Vec<u8>, features: &[Feature]) -> io::Result<()> {
 }
 
 /// Add primer data to the buffer.
-fn export_primers(buf: &mut Vec<u8>, primers: &[Primer]) -> io::Result<()> {
+fn export_primers(buf: &mut Vec<u8>, primers: &[Primer]) -> Result<(), FileError> {
     let mut primers_sg = Primers { inner: Vec::new() };
     for primer in primers {
         primers_sg.inner.push(PrimerSnapGene {
@@ -524,10 +536,7 @@ fn export_primers(buf: &mut Vec<u8>, primers: &[Primer]) -> io::Result<()> {
     }
 
     let xml_str = to_string(&primers_sg).map_err(|e| {
-        io::Error::new(
-            ErrorKind::InvalidData,
-            format!("Unable to convert primers to an XML string: {e}"),
-        )
+        FileError::Encode(format!("Unable to convert primers to an XML string: {e}"))
     })?;
 
     let xml = xml_str.into_bytes();
@@ -540,7 +549,7 @@ fn export_primers(buf: &mut Vec<u8>, primers: &[Primer]) -> io::Result<()> {
 }
 
 /// Export our local state into the SnapGene dna format. This includes sequence, features, and primers.
-pub fn export_snapgene(data: &GenericData, path: &Path) -> io::Result<()> {
+pub fn export_snapgene(data: &GenericData, path: &Path) -> Result<(), FileError> {
     let mut file = File::create(path)?;
 
     let mut buf = Vec::new();
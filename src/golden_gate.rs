@@ -0,0 +1,609 @@
+//! This module contains Golden Gate (Type IIS) modular cloning design. Type IIS enzymes, such as BsaI,
+//! cut outside their recognition sites, leaving overhangs of any sequence. Parts amplified with primers
+//! that add a site and an overhang at each end assemble in one pot, in the order their overhangs define;
+//! the sites are cut away, so the product can't be cut again.
+//!
+//! Overhangs are inserted between parts; eg with the MoClo standard, they're fusion sites between a
+//! promoter, 5' UTR, coding region, and terminator. We check parts for internal sites, check the overhang
+//! set for ones likely to mis-ligate, and simulate digesting the amplified parts, and ligating the pieces
+//! by matching overhangs.
+
+use std::fmt::Display;
+
+use crate::{
+    assembly::{len_for_tm, MIN_ANNEAL_LEN},
+    digest::digest,
+    file_io::GenericData,
+    melting_temp_calcs::calc_tm,
+    primer::{Primer, PrimerData, TM_TARGET},
+    restriction_enzyme::{find_re_matches, load_re_library, EndType, RestrictionEnzyme},
+    sequence::{
+        seq_complement, seq_from_str, seq_to_str, Feature, FeatureDirection, FeatureType,
+        Nucleotide::{self, A, G, T},
+        Seq, SeqRange, SeqTopology,
+    },
+    IonConcentrations,
+};
+
+/// Added to the 5' end of primers, before the recognition site. Enzymes cut inefficiently at sites
+/// near the end of DNA.
+const PRIMER_PAD: [Nucleotide; 4] = [T, T, G, A];
+
+/// The MoClo standard fusion sites, before each part type, and after the last one. (Weber et al, 2011)
+pub const MOCLO_OVERHANGS: [(&str, &str); 5] = [
+    ("Promoter", "GGAG"),
+    ("5' UTR", "TACT"),
+    ("Coding region", "AATG"),
+    ("Terminator", "GCTT"),
+    ("End", "CGCT"),
+];
+
+/// The enzyme used by default; the most common one for Golden Gate assembly.
+pub const DEFAULT_ENZYME: &str = "BsaI";
+
+/// If an enzyme can be used for Golden Gate assembly: A Type IIS enzyme, which cuts once, after its
+/// unambiguous site, leaving a 5' overhang. Eg BsaI, BsmBI, BbsI, and SapI.
+pub fn is_golden_gate_enzyme(enzyme: &RestrictionEnzyme) -> bool {
+    enzyme.cut_2.is_none()
+        && !enzyme.site.is_empty()
+        && !enzyme.is_palindromic()
+        && !enzyme.site.iter().any(|nt| nt.is_ambiguous())
+        && enzyme.cut_top >= enzyme.site.len() as isize
+        && matches!(enzyme.end_type(), EndType::FivePrime(_))
+}
+
+/// Indices of enzymes in a library that can be used for Golden Gate assembly.
+pub fn golden_gate_enzymes(lib: &[RestrictionEnzyme]) -> Vec<usize> {
+    (0..lib.len())
+        .filter(|i| is_golden_gate_enzyme(&lib[*i]))
+        .collect()
+}
+
+/// The length of the overhang an enzyme leaves; 0 if it doesn't leave a 5' one.
+pub fn overhang_len(enzyme: &RestrictionEnzyme) -> usize {
+    match enzyme.end_type() {
+        EndType::FivePrime(len) => len,
+        _ => 0,
+    }
+}
+
+/// A part to assemble.
+#[derive(Clone, Default)]
+pub struct GoldenGatePart {
+    pub name: String,
+    /// The part's sequence and features, eg from `GenericData::extract`, 5' to 3' in the orientation
+    /// it's assembled in.
+    pub data: GenericData,
+}
+
+#[derive(Clone, Debug)]
+pub struct GoldenGateParams {
+    /// A Type IIS enzyme; see `is_golden_gate_enzyme`.
+    pub enzyme: RestrictionEnzyme,
+    /// The overhang before each part, on the top strand. For linear assemblies, the last is after the
+    /// last part; for circular ones, the first joins the last part to the first.
+    pub overhangs: Vec<Seq>,
+    /// If true, the last part joins the first, eg when one of the parts is a backbone.
+    pub circular: bool,
+    /// Annealing regions are extended from their minimum length until they reach this melting temperature.
+    pub anneal_tm: f32,
+    pub max_anneal_len: usize,
+}
+
+impl Default for GoldenGateParams {
+    fn default() -> Self {
+        Self {
+            enzyme: load_re_library()
+                .into_iter()
+                .find(|re| re.name == DEFAULT_ENZYME)
+                .unwrap_or_default(),
+            overhangs: Vec::new(),
+            circular: true,
+            anneal_tm: TM_TARGET,
+            max_anneal_len: 35,
+        }
+    }
+}
+
+impl GoldenGateParams {
+    /// The number of overhangs required for a number of parts.
+    pub fn num_overhangs(&self, num_parts: usize) -> usize {
+        if self.circular {
+            num_parts
+        } else {
+            num_parts + 1
+        }
+    }
+
+    /// The first MoClo standard overhangs, as many as required for a number of parts. `None` if that's
+    /// more than the standard defines.
+    pub fn moclo_overhangs(&self, num_parts: usize) -> Option<Vec<Seq>> {
+        let num = self.num_overhangs(num_parts);
+        (num <= MOCLO_OVERHANGS.len()).then(|| {
+            MOCLO_OVERHANGS[..num]
+                .iter()
+                .map(|(_, oh)| seq_from_str(oh))
+                .collect()
+        })
+    }
+}
+
+/// Primers that amplify a part, adding a recognition site and overhang at each end.
+#[derive(Clone)]
+pub struct PartPrimers {
+    pub part: usize,
+    pub fwd: Primer,
+    pub rev: Primer,
+    /// The length of each primer's 5' tail, including the site and overhang; the rest anneals to the part.
+    pub fwd_tail_len: usize,
+    pub rev_tail_len: usize,
+    /// Melting temperature of each primer's annealing region, in °C.
+    pub fwd_anneal_tm: Option<f32>,
+    pub rev_anneal_tm: Option<f32>,
+    /// The amplified part, including tails.
+    pub amplicon: Seq,
+}
+
+/// A problem with a design that may reduce assembly efficiency, or produce the wrong product.
+#[derive(Clone, Debug)]
+pub enum GoldenGateWarning {
+    /// A part contains a recognition site, so the enzyme cuts inside it. Domesticate the part by
+    /// mutating the site. The range is in the part.
+    InternalSite { part: usize, range: SeqRange },
+    /// An overhang is its own reverse complement, so parts with it may ligate to themselves, or in
+    /// either orientation.
+    Palindromic { overhang: usize },
+    /// Two overhangs are the same, or one is the reverse complement of the other.
+    Duplicate { overhang: usize, other: usize },
+    /// Two overhangs differ at only one position, on either strand; ligase may join them.
+    Similar { overhang: usize, other: usize },
+    /// More than one piece has a matching overhang, so the order isn't defined.
+    Ambiguous { overhang: String },
+    /// Simulating the assembly didn't join every part in order.
+    Incomplete,
+}
+
+impl Display for GoldenGateWarning {
+    /// Parts and overhangs are numbered from 1, for display.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InternalSite { part, range } => {
+                write!(f, "Part {} has an internal site at {range}", part + 1)
+            }
+            Self::Palindromic { overhang } => {
+                write!(f, "Overhang {} is palindromic", overhang + 1)
+            }
+            Self::Duplicate { overhang, other } => write!(
+                f,
+                "Overhangs {} and {} are the same, on either strand",
+                overhang + 1,
+                other + 1
+            ),
+            Self::Similar { overhang, other } => write!(
+                f,
+                "Overhangs {} and {} differ by one nucleotide, on either strand",
+                overhang + 1,
+                other + 1
+            ),
+            Self::Ambiguous { overhang } => {
+                write!(f, "More than one piece has overhang {overhang}")
+            }
+            Self::Incomplete => {
+                write!(f, "The simulated assembly doesn't join every part in order")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum GoldenGateError {
+    NoParts,
+    /// The enzyme isn't a Type IIS one that leaves a 5' overhang.
+    UnsuitableEnzyme(String),
+    EmptyPart(String),
+    WrongOverhangCount {
+        expected: usize,
+        found: usize,
+    },
+    /// An overhang isn't the length the enzyme leaves, or has ambiguous nucleotides.
+    InvalidOverhang(usize),
+}
+
+impl Display for GoldenGateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoParts => write!(f, "No parts to assemble"),
+            Self::UnsuitableEnzyme(name) => write!(
+                f,
+                "{name} isn't a Type IIS enzyme that cuts after its site, leaving a 5' overhang"
+            ),
+            Self::EmptyPart(name) => write!(f, "Part {name} is empty"),
+            Self::WrongOverhangCount { expected, found } => {
+                write!(f, "Expected {expected} overhangs, but found {found}")
+            }
+            Self::InvalidOverhang(i) => write!(
+                f,
+                "Overhang {} isn't the length the enzyme leaves, or has ambiguous nucleotides",
+                i + 1
+            ),
+        }
+    }
+}
+
+/// The result of a Golden Gate design.
+#[derive(Clone)]
+pub struct GoldenGateDesign {
+    /// For each part.
+    pub primers: Vec<PartPrimers>,
+    /// The assembled sequence, with features from each part, and for each part and overhang. `None`
+    /// if the simulated assembly is incomplete.
+    pub product: Option<GenericData>,
+    pub warnings: Vec<GoldenGateWarning>,
+}
+
+/// A piece of a digested amplicon, with overhangs at both ends.
+struct Piece {
+    part: usize,
+    /// The top strand, starting with the left overhang. The right overhang is on the bottom strand.
+    seq: Seq,
+    left: Seq,
+    right: Seq,
+}
+
+impl Piece {
+    fn left(&self) -> &[Nucleotide] {
+        &self.left
+    }
+
+    fn right(&self) -> &[Nucleotide] {
+        &self.right
+    }
+}
+
+/// Digest an amplicon, and return pieces cut at both ends that don't contain a site; in a one-pot
+/// reaction, pieces with sites are cut again, or are the ends of amplicons.
+fn digest_amplicon(seq: &[Nucleotide], enzyme: &RestrictionEnzyme, part: usize) -> Vec<Piece> {
+    let lib = std::slice::from_ref(enzyme);
+
+    digest(seq, SeqTopology::Linear, lib, &[])
+        .into_iter()
+        .filter_map(|frag| {
+            let (left, right) = (frag.left?, frag.right?);
+            let start = frag.range.start - 1;
+
+            Some(Piece {
+                part,
+                seq: seq[start..start + frag.len].to_vec(),
+                left: left.overhang,
+                right: right.overhang,
+            })
+        })
+        .filter(|p| {
+            let mut full = p.seq.clone();
+            full.extend(&p.right);
+            find_re_matches(&full, SeqTopology::Linear, lib).is_empty()
+        })
+        .collect()
+}
+
+/// Check overhangs for ones likely to ligate to the wrong partner.
+fn check_overhangs(overhangs: &[Seq]) -> Vec<GoldenGateWarning> {
+    let mut result = Vec::new();
+
+    let mismatches =
+        |a: &[Nucleotide], b: &[Nucleotide]| a.iter().zip(b).filter(|(x, y)| x != y).count();
+
+    for (i, oh) in overhangs.iter().enumerate() {
+        if *oh == seq_complement(oh) {
+            result.push(GoldenGateWarning::Palindromic { overhang: i });
+        }
+
+        for (j, other) in overhangs.iter().enumerate().skip(i + 1) {
+            let diff = mismatches(oh, other).min(mismatches(oh, &seq_complement(other)));
+            match diff {
+                0 => result.push(GoldenGateWarning::Duplicate {
+                    overhang: i,
+                    other: j,
+                }),
+                1 => result.push(GoldenGateWarning::Similar {
+                    overhang: i,
+                    other: j,
+                }),
+                _ => (),
+            }
+        }
+    }
+
+    result
+}
+
+/// Ligate pieces by matching overhangs, starting with the first. Returns the pieces in order, and if
+/// the product is circular.
+fn ligate(pieces: &[Piece], warnings: &mut Vec<GoldenGateWarning>) -> (Vec<usize>, bool) {
+    let mut order = vec![0];
+    let mut used = vec![false; pieces.len()];
+    used[0] = true;
+
+    loop {
+        let current = &pieces[order[order.len() - 1]];
+        let next: Vec<usize> = (0..pieces.len())
+            .filter(|i| !used[*i] && pieces[*i].left() == current.right())
+            .collect();
+
+        if next.len() > 1 {
+            warnings.push(GoldenGateWarning::Ambiguous {
+                overhang: seq_to_str(current.right()).to_uppercase(),
+            });
+        }
+
+        match next.first() {
+            Some(&i) => {
+                used[i] = true;
+                order.push(i);
+            }
+            None => {
+                let circular = current.right() == pieces[0].left();
+                return (order, circular);
+            }
+        }
+    }
+}
+
+/// A feature spanning a whole sequence.
+fn whole_feature(len: usize, label: String) -> Feature {
+    Feature {
+        index_range: SeqRange::new(1, len),
+        segments: Vec::new(),
+        feature_type: FeatureType::Generic,
+        direction: FeatureDirection::Forward,
+        label,
+        color_override: None,
+        notes: Default::default(),
+    }
+}
+
+/// Design primers that add recognition sites and overhangs to each part, check the design, and simulate
+/// assembling the parts.
+pub fn design_golden_gate(
+    parts: &[GoldenGatePart],
+    params: &GoldenGateParams,
+    ion: &IonConcentrations,
+) -> Result<GoldenGateDesign, GoldenGateError> {
+    if parts.is_empty() {
+        return Err(GoldenGateError::NoParts);
+    }
+    if let Some(part) = parts.iter().find(|p| p.data.seq.is_empty()) {
+        return Err(GoldenGateError::EmptyPart(part.name.clone()));
+    }
+
+    let expected = params.num_overhangs(parts.len());
+    if params.overhangs.len() != expected {
+        return Err(GoldenGateError::WrongOverhangCount {
+            expected,
+            found: params.overhangs.len(),
+        });
+    }
+
+    let enzyme = &params.enzyme;
+    if !is_golden_gate_enzyme(enzyme) {
+        return Err(GoldenGateError::UnsuitableEnzyme(enzyme.name.clone()));
+    }
+    let overhang_len = overhang_len(enzyme);
+    if let Some(i) = params
+        .overhangs
+        .iter()
+        .position(|oh| oh.len() != overhang_len || oh.iter().any(|nt| nt.is_ambiguous()))
+    {
+        return Err(GoldenGateError::InvalidOverhang(i));
+    }
+
+    let mut warnings = Vec::new();
+
+    for (i, part) in parts.iter().enumerate() {
+        for m in find_re_matches(
+            &part.data.seq,
+            SeqTopology::Linear,
+            std::slice::from_ref(enzyme),
+        ) {
+            warnings.push(GoldenGateWarning::InternalSite {
+                part: i,
+                range: m.range,
+            });
+        }
+    }
+
+    warnings.extend(check_overhangs(&params.overhangs));
+
+    // The pad, site, and nucleotides between the site and where the top strand is cut.
+    let mut tail_base: Seq = PRIMER_PAD.to_vec();
+    tail_base.extend(&enzyme.site);
+    tail_base.extend(vec![A; enzyme.cut_top as usize - enzyme.site.len()]);
+
+    let mut primers = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let seq = &part.data.seq;
+        let oh_before = &params.overhangs[i];
+        let oh_after = &params.overhangs[(i + 1) % params.overhangs.len()];
+
+        let max_anneal = params.max_anneal_len.min(seq.len());
+        let min_anneal = MIN_ANNEAL_LEN.min(max_anneal);
+
+        let len_fwd = len_for_tm(
+            min_anneal,
+            max_anneal,
+            params.anneal_tm,
+            |len| seq[..len].to_vec(),
+            ion,
+        );
+        let len_rev = len_for_tm(
+            min_anneal,
+            max_anneal,
+            params.anneal_tm,
+            |len| seq_complement(&seq[seq.len() - len..]),
+            ion,
+        );
+        let anneal_fwd = &seq[..len_fwd];
+        let anneal_rev = seq_complement(&seq[seq.len() - len_rev..]);
+
+        let mut tail_fwd = tail_base.clone();
+        tail_fwd.extend(oh_before);
+        let mut tail_rev = tail_base.clone();
+        tail_rev.extend(seq_complement(oh_after));
+
+        let make_primer =
+            |tail: &[Nucleotide], anneal: &[Nucleotide], oh: &[Nucleotide], dir: &str| {
+                let mut sequence = tail.to_vec();
+                sequence.extend(anneal);
+                Primer {
+                    volatile: PrimerData::new(&sequence),
+                    sequence,
+                    name: format!("{} GG {dir}", part.name),
+                    description: Some(format!(
+                        "Golden Gate primer. Adds a {} site, and overhang {}, to {}.",
+                        enzyme.name,
+                        seq_to_str(oh).to_uppercase(),
+                        part.name
+                    )),
+                }
+            };
+
+        let mut amplicon = tail_fwd.clone();
+        amplicon.extend(seq);
+        amplicon.extend(seq_complement(&tail_rev));
+
+        primers.push(PartPrimers {
+            part: i,
+            fwd: make_primer(&tail_fwd, anneal_fwd, oh_before, "fwd"),
+            rev: make_primer(&tail_rev, &anneal_rev, oh_after, "rev"),
+            fwd_tail_len: tail_fwd.len(),
+            rev_tail_len: tail_rev.len(),
+            fwd_anneal_tm: calc_tm(anneal_fwd, ion),
+            rev_anneal_tm: calc_tm(&anneal_rev, ion),
+            amplicon,
+        });
+    }
+
+    // Simulate the one-pot reaction: Digest each amplicon, and ligate the pieces without sites.
+    let pieces: Vec<Piece> = primers
+        .iter()
+        .flat_map(|p| digest_amplicon(&p.amplicon, enzyme, p.part))
+        .collect();
+
+    let product = if pieces.is_empty() {
+        None
+    } else {
+        let (order, circular) = ligate(&pieces, &mut warnings);
+
+        // Each part must be a single piece, in order, with its full sequence.
+        let in_order = order.len() == parts.len()
+            && order.iter().enumerate().all(|(i, piece)| {
+                let piece = &pieces[*piece];
+                piece.part == i && piece.seq.len() == parts[i].data.seq.len() + overhang_len
+            });
+
+        (in_order && circular == params.circular).then(|| {
+            let mut product = GenericData::default();
+
+            for (i, piece) in order.iter().map(|i| &pieces[*i]).enumerate() {
+                let part = &parts[piece.part];
+                let overhang = |oh: &[Nucleotide]| GenericData {
+                    seq: oh.to_vec(),
+                    features: vec![whole_feature(
+                        oh.len(),
+                        format!("Overhang {}", seq_to_str(oh).to_uppercase()),
+                    )],
+                    ..Default::default()
+                };
+
+                product.paste(product.seq.len() + 1, &overhang(piece.left()));
+
+                // The part, between its overhangs.
+                let mut body = part.data.clone();
+                body.features
+                    .retain(|f| f.feature_type != FeatureType::Source);
+                body.features
+                    .push(whole_feature(body.seq.len(), part.name.clone()));
+                product.paste(product.seq.len() + 1, &body);
+
+                if i == order.len() - 1 && !circular {
+                    product.paste(product.seq.len() + 1, &overhang(piece.right()));
+                }
+            }
+
+            product.topology = if circular {
+                SeqTopology::Circular
+            } else {
+                SeqTopology::Linear
+            };
+            product.metadata.plasmid_name = parts
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+                .join(" + ");
+            product
+        })
+    };
+
+    if product.is_none() {
+        warnings.push(GoldenGateWarning::Incomplete);
+    }
+
+    Ok(GoldenGateDesign {
+        primers,
+        product,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::doc;
+
+    fn part(name: &str, seq: &str) -> GoldenGatePart {
+        GoldenGatePart {
+            name: name.to_owned(),
+            data: doc(seq, SeqTopology::Linear, Vec::new()),
+        }
+    }
+
+    fn parts() -> Vec<GoldenGatePart> {
+        vec![
+            part("Promoter", "TTGACAGCTAGCTCAGTCCTAGGTATAATGCTAGCACTGAGC"),
+            part("Coding", "ATGCGTAAAGGCGAAGAACTGTTCACCGGCGTTGTGCCGATTCTGTAA"),
+        ]
+    }
+
+    #[test]
+    fn circular() {
+        let mut params = GoldenGateParams::default();
+        params.overhangs = params.moclo_overhangs(2).unwrap();
+
+        let design = design_golden_gate(&parts(), &params, &Default::default()).unwrap();
+        let product = design.product.unwrap();
+        assert!(product.topology == SeqTopology::Circular);
+        // Each part, after its overhang.
+        assert_eq!(product.seq.len(), 42 + 48 + 2 * 4);
+        assert_eq!(design.primers.len(), 2);
+    }
+
+    #[test]
+    fn linear() {
+        let mut params = GoldenGateParams {
+            circular: false,
+            ..Default::default()
+        };
+        params.overhangs = params.moclo_overhangs(2).unwrap();
+
+        let design = design_golden_gate(&parts(), &params, &Default::default()).unwrap();
+        let product = design.product.unwrap();
+        assert!(product.topology == SeqTopology::Linear);
+        // Overhangs before each part, and after the last.
+        assert_eq!(product.seq.len(), 42 + 48 + 3 * 4);
+        assert_eq!(&product.seq[..4], &seq_from_str("GGAG")[..]);
+        assert_eq!(
+            &product.seq[product.seq.len() - 4..],
+            &seq_from_str("AATG")[..]
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! GUI code for multi-fragment overlap assembly, eg Gibson or NEBuilder HiFi: Choosing and ordering
+//! fragments from the workspace, and showing designed primers, overlaps, and the product.
+
+use eframe::egui::{Button, Color32, ComboBox, Grid, RichText, Ui, Vec2};
+use plascad::{
+    assembly::{design_assembly, AssemblyDesign, AssemblyFragment},
+    file_io::GenericData,
+    sequence::{seq_to_str, SeqRange},
+};
+
+use crate::{
+    gui::{float_field, int_field, COL_SPACING, ROW_SPACING},
+    AssemblyFragmentUi, State,
+};
+
+/// A fragment's name, and its sequence and features, in the orientation it's assembled in.
+pub fn fragment_data(state: &State, fragment: &AssemblyFragmentUi) -> (String, GenericData) {
+    let doc = state.document(fragment.document);
+    let mut data = doc.extract(fragment.range);
+    if fragment.reverse {
+        let len = data.seq.len();
+        data.reverse_complement(SeqRange::new(1, len));
+    }
+
+    let mut name = state.document_names()[fragment.document].clone();
+    if fragment.range != SeqRange::new(1, doc.seq.len()) {
+        name = format!("{name} {}", fragment.range);
+    }
+    if fragment.reverse {
+        name = format!("{name} (rc)");
+    }
+
+    (name, data)
+}
+
+fn run_design(state: &mut State) {
+    let fragments: Vec<AssemblyFragment> = state
+        .ui
+        .assembly
+        .fragments
+        .iter()
+        .map(|f| {
+            let (name, data) = fragment_data(state, f);
+            AssemblyFragment {
+                name,
+                data,
+                amplify: f.amplify,
+            }
+        })
+        .collect();
+
+    state.ui.assembly.result = Some(design_assembly(
+        &fragments,
+        &state.ui.assembly.params,
+        &state.ion_concentrations,
+    ));
+}
+
+/// The name and sequence length of each document in the workspace, for choosing fragments.
+pub fn document_choices(state: &State) -> Vec<(String, usize)> {
+    state
+        .document_names()
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (name, state.document(i).seq.len()))
+        .collect()
+}
+
+/// Edit a list of fragments, in the order they're assembled. `documents` is from `document_choices`.
+/// If `amplify` is false, fragments are always amplified, and the option isn't shown.
+pub fn fragments_edit(
+    fragments: &mut Vec<AssemblyFragmentUi>,
+    documents: &[(String, usize)],
+    active: usize,
+    amplify: bool,
+    id: &str,
+    ui: &mut Ui,
+) {
+    let mut remove = None;
+    let mut swap = None;
+    let num_frags = fragments.len();
+
+    Grid::new(id)
+        .spacing(Vec2::new(COL_SPACING / 2., 4.))
+        .show(ui, |ui| {
+            for (i, frag) in fragments.iter_mut().enumerate() {
+                ui.label((i + 1).to_string());
+
+                let prev = frag.document;
+                ComboBox::from_id_source(format!("{id}_{i}"))
+                    .width(140.)
+                    .selected_text(&documents[frag.document].0)
+                    .show_ui(ui, |ui| {
+                        for (j, (name, _)) in documents.iter().enumerate() {
+                            ui.selectable_value(&mut frag.document, j, name);
+                        }
+                    });
+                if frag.document != prev {
+                    frag.range = SeqRange::new(1, documents[frag.document].1);
+                }
+
+                ui.horizontal(|ui| {
+                    int_field(&mut frag.range.start, "Start:", ui);
+                    int_field(&mut frag.range.end, "End:", ui);
+                });
+
+                ui.checkbox(&mut frag.reverse, "Reverse")
+                    .on_hover_text("Assemble the fragment's reverse complement.");
+                if amplify {
+                    ui.checkbox(&mut frag.amplify, "Amplify").on_hover_text(
+                        "Amplify the fragment with primers that add overlaps. Uncheck for fragments \
+                    used as they are, eg a vector linearized by restriction digest.",
+                    );
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(i > 0, Button::new("⏶")).clicked() {
+                        swap = Some((i - 1, i));
+                    }
+                    if ui
+                        .add_enabled(i + 1 < num_frags, Button::new("⏷"))
+                        .clicked()
+                    {
+                        swap = Some((i, i + 1));
+                    }
+                    if ui.button("×").clicked() {
+                        remove = Some(i);
+                    }
+                });
+
+                ui.end_row();
+            }
+        });
+
+    if let Some((a, b)) = swap {
+        fragments.swap(a, b);
+    }
+    if let Some(i) = remove {
+        fragments.remove(i);
+    }
+
+    if ui
+        .button("➕ Add fragment")
+        .on_hover_text(
+            "Add a range of a document. To linearize a circular vector, use a range that wraps the \
+        origin, eg starting just after the insertion point, and ending at it.",
+        )
+        .clicked()
+    {
+        fragments.push(AssemblyFragmentUi {
+            document: active,
+            range: SeqRange::new(1, documents[active].1),
+            reverse: false,
+            amplify: true,
+        });
+    }
+}
+
+fn params_edit(state: &mut State, ui: &mut Ui) {
+    let params = &mut state.ui.assembly.params;
+    ui.horizontal(|ui| {
+        int_field(&mut params.overlap_len, "Overlap len min:", ui);
+        int_field(&mut params.max_overlap_len, "max:", ui);
+        float_field(&mut params.overlap_tm, "Overlap Tm:", ui);
+        ui.add_space(COL_SPACING);
+
+        int_field(&mut params.max_anneal_len, "Anneal len max:", ui);
+        float_field(&mut params.anneal_tm, "Anneal Tm:", ui);
+        ui.add_space(COL_SPACING);
+
+        ui.checkbox(&mut params.circular, "Circular")
+            .on_hover_text("Join the last fragment to the first, eg to assemble a plasmid.");
+    });
+}
+
+pub fn tm_text(tm: Option<f32>) -> String {
+    match tm {
+        Some(tm) => format!("{tm:.1}"),
+        None => "-".to_owned(),
+    }
+}
+
+fn design_disp(design: &AssemblyDesign, ui: &mut Ui) {
+    ui.heading("Primers").on_hover_text(
+        "Tails are lowercase. Tm is of the annealing region, for the first cycles; later cycles \
+    anneal the full primer.",
+    );
+
+    Grid::new("assembly_primers")
+        .spacing(Vec2::new(COL_SPACING, 4.))
+        .striped(true)
+        .show(ui, |ui| {
+            for label in [
+                "Name",
+                "Sequence (5' ⏵ 3')",
+                "Len",
+                "Tm (°C)",
+                "Product (bp)",
+            ] {
+                ui.label(RichText::new(label).strong());
+            }
+            ui.end_row();
+
+            for p in &design.primers {
+                for (primer, tail_len, tm) in [
+                    (&p.fwd, p.fwd_tail_len, p.fwd_anneal_tm),
+                    (&p.rev, p.rev_tail_len, p.rev_anneal_tm),
+                ] {
+                    let seq = seq_to_str(&primer.sequence);
+                    let seq = format!("{}{}", &seq[..tail_len], seq[tail_len..].to_uppercase());
+
+                    ui.label(&primer.name);
+                    ui.label(RichText::new(seq).monospace());
+                    ui.label(primer.sequence.len().to_string());
+                    ui.label(tm_text(tm));
+                    ui.label(p.product_len.to_string());
+                    ui.end_row();
+                }
+            }
+        });
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    ui.heading("Overlaps").on_hover_text(
+        "ΔG is of the overlap's most stable single-stranded structure, in kcal/mol.",
+    );
+    Grid::new("assembly_junctions")
+        .spacing(Vec2::new(COL_SPACING, 4.))
+        .striped(true)
+        .show(ui, |ui| {
+            for label in ["", "Junction", "Overlap", "Range", "Len", "Tm (°C)", "ΔG"] {
+                ui.label(RichText::new(label).strong());
+            }
+            ui.end_row();
+
+            for (i, j) in design.junctions.iter().enumerate() {
+                ui.label((i + 1).to_string());
+                ui.label(format!(
+                    "{} ⏵ {}",
+                    design.fragment_names[j.left], design.fragment_names[j.right]
+                ));
+                ui.label(RichText::new(seq_to_str(&j.overlap)).monospace());
+                ui.label(j.range.to_string());
+                ui.label(j.overlap.len().to_string());
+                ui.label(tm_text(j.tm));
+                ui.label(format!("{:.1}", j.dg));
+                ui.end_row();
+            }
+        });
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    for warning in &design.warnings {
+        ui.label(RichText::new(warning.to_string()).color(Color32::GOLD));
+    }
+
+    ui.label(format!(
+        "Product: {} bp, {} features",
+        design.product.seq.len(),
+        design.product.features.len()
+    ));
+}
+
+/// Design primers for assembling several fragments with overlaps, and predict the product.
+pub fn assembly_page(state: &mut State, ui: &mut Ui) {
+    ui.heading("Gibson and NEBuilder HiFi assembly");
+    ui.label(
+        "Assemble fragments from the workspace, in order. Primers amplify each fragment, with 5' tails \
+    that overlap its neighbors.",
+    );
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    let documents = document_choices(state);
+    fragments_edit(
+        &mut state.ui.assembly.fragments,
+        &documents,
+        state.workspace.active,
+        true,
+        "assembly_fragments",
+        ui,
+    );
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    params_edit(state, ui);
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    if ui.button("Design").clicked() {
+        run_design(state);
+    }
+
+    let mut open = false;
+    match &state.ui.assembly.result {
+        Some(Ok(design)) => {
+            ui.add_space(ROW_SPACING / 2.);
+
+            design_disp(design, ui);
+
+            open = ui
+                .button("Open product as document")
+                .on_hover_text("Open the assembled sequence, with its features and the primers.")
+                .clicked();
+        }
+        Some(Err(e)) => {
+            ui.label(RichText::new(e.to_string()).color(Color32::LIGHT_RED));
+        }
+        None => (),
+    }
+
+    if open {
+        if let Some(Ok(design)) = &state.ui.assembly.result {
+            let product = design.product.clone();
+            state.open_document(product, Default::default());
+        }
+    }
+}
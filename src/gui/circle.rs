@@ -4,11 +4,15 @@ use core::f32::consts::TAU;
 
 use eframe::{
     egui::{
-        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Mesh, Pos2, Rect, RichText, Sense,
-        Shape, Stroke, Ui, Vec2,
+        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Pos2, Rect, RichText, Sense, Shape,
+        Stroke, Ui, Vec2,
     },
     emath::RectTransform,
-    epaint::{CircleShape, ColorMode, PathShape},
+    epaint::{CircleShape, PathShape},
+};
+use plascad::{
+    primer::{Primer, PrimerDirection, PrimerMatch},
+    sequence::{Feature, FeatureDirection, FeatureType},
 };
 
 use crate::{
@@ -16,8 +20,6 @@ use crate::{
         features::feature_table, get_cursor_text, navigation::NAV_BUTTON_COLOR,
         primer_arrow::STROKE_WIDTH, COL_SPACING, ROW_SPACING,
     },
-    primer::{Primer, PrimerData, PrimerDirection},
-    sequence::{Feature, FeatureDirection, FeatureType},
     State,
 };
 
@@ -329,29 +331,71 @@ fn draw_features(
         let feature_color = Color32::from_rgb(r, g, b);
         let stroke = Stroke::new(feature_stroke_width, FEATURE_OUTLINE_COLOR);
 
-        let angle_start = seq_i_to_angle(feature.index_range.0, seq_len);
-        let angle_end = seq_i_to_angle(feature.index_range.1, seq_len);
+        // Angles of each segment. Segments that wrap the origin end past TAU.
+        let angles: Vec<(f32, f32)> = feature
+            .ranges()
+            .iter()
+            .map(|r| {
+                let angle_start = seq_i_to_angle(r.start, seq_len);
+                let mut angle_end = seq_i_to_angle(r.end, seq_len);
+                if r.wraps() {
+                    angle_end += TAU;
+                }
+                (angle_start, angle_end)
+            })
+            .collect();
+
+        let last_i = angles.len() - 1;
+
+        for (i, (angle_start, angle_end)) in angles.iter().enumerate() {
+            // We subtract parts from the start or end angle for the arrow tip, if present. For joined
+            // features, the tip is on the last segment, in the feature's direction.
+            let angle = match feature.direction {
+                FeatureDirection::Forward if i == last_i => (*angle_start, angle_end - TIP_LEN),
+                FeatureDirection::Reverse if i == 0 => (angle_start + TIP_LEN, *angle_end),
+                _ => (*angle_start, *angle_end),
+            };
+
+            result.append(&mut draw_filled_arc(
+                center,
+                radius,
+                angle,
+                feature_width,
+                to_screen,
+                feature_color,
+                stroke,
+            ));
+
+            // Connect joined segments with a thin line, eg across introns.
+            if i < last_i {
+                let mut angle_next = angles[i + 1].0;
+                if angle_next < *angle_end {
+                    angle_next += TAU;
+                }
+
+                result.push(Shape::line(
+                    arc_points(to_screen * center, radius, *angle_end, angle_next),
+                    Stroke::new(feature_stroke_width / 2., feature_color),
+                ));
+            }
+        }
 
-        // We subtract parts from the start or end angle for the arrow tip, if present.
+        let angle_start = angles[0].0;
+        let mut angle_end = angles[last_i].1;
+        if angle_end < angle_start {
+            angle_end += TAU;
+        }
+
+        // The extent of the feature, without the tip.
         let angle = match feature.direction {
             FeatureDirection::None => (angle_start, angle_end),
             FeatureDirection::Forward => (angle_start, angle_end - TIP_LEN),
             FeatureDirection::Reverse => (angle_start + TIP_LEN, angle_end),
         };
 
-        result.append(&mut draw_filled_arc(
-            center,
-            radius,
-            angle,
-            feature_width,
-            to_screen,
-            feature_color,
-            stroke,
-        ));
-
         // Draw the label.
 
-        let angle_mid = (angle.0 + angle.1) / 2.;
+        let angle_mid = ((angle.0 + angle.1) / 2.) % TAU;
 
         let point_mid_outer =
             angle_to_pixel(angle_mid, radius + feature_width / 2.) + center.to_vec2();
@@ -419,16 +463,18 @@ fn draw_primers(
         let primer_matches = &primer.volatile.matches_seq;
 
         // todo: Do not run these calcs each time. Cache.
-        for (direction, seq_range) in primer_matches {
-            // We currently index primers relative to the end they started.
-            let seq_range = match direction {
-                PrimerDirection::Forward => seq_range.clone(),
-                PrimerDirection::Reverse => (seq_len - seq_range.end)..(seq_len - seq_range.start),
-            };
-
+        for PrimerMatch {
+            direction,
+            range: seq_range,
+            ..
+        } in primer_matches
+        {
             let angle_start = seq_i_to_angle(seq_range.start, seq_len);
-            let angle_end = seq_i_to_angle(seq_range.end, seq_len);
-            let angle_mid = (angle_start + angle_end) / 2.;
+            let mut angle_end = seq_i_to_angle(seq_range.end, seq_len);
+            if seq_range.wraps() {
+                angle_end += TAU;
+            }
+            let angle_mid = ((angle_start + angle_end) / 2.) % TAU;
 
             let point_start_inner = angle_to_pixel(angle_start, radius_inner) + center.to_vec2();
             let point_start_outer = angle_to_pixel(angle_start, radius_outer) + center.to_vec2();
@@ -534,11 +580,16 @@ fn find_cursor_i(
 
 /// Draw text in the center of the circle; eg general plasmid information, or information
 /// about a feature.
-fn draw_center_text(center: Pos2, to_screen: &RectTransform, seq_len: usize, state: &mut State, ui: &mut Ui) -> Vec<Shape> {
-let mut result = Vec::new();
+fn draw_center_text(
+    center: Pos2,
+    to_screen: &RectTransform,
+    seq_len: usize,
+    state: &mut State,
+    ui: &mut Ui,
+) -> Vec<Shape> {
+    let mut result = Vec::new();
     // todo: Separate function for center label too if it becomes too complicatged.
 
-
     match &state.ui.feature_hover {
         Some(i) => {
             if state.generic.features.len() + 1 < *i {
@@ -548,14 +599,19 @@ let mut result = Vec::new();
 
             let mut labels = vec![
                 feature.label.clone(),
-                format!("{}..{}", feature.index_range.0, feature.index_range.1),
+                feature
+                    .ranges()
+                    .iter()
+                    .map(|r| r.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
                 feature.feature_type.to_string(),
             ];
             for note in &feature.notes {
                 labels.push(format!("{}: {}", note.0, note.1));
             }
 
-// todo: COlor-code etc?
+            // todo: COlor-code etc?
             for (i, label) in labels.iter().enumerate() {
                 result.push(ui.ctx().fonts(|fonts| {
                     Shape::text(
@@ -563,7 +619,7 @@ let mut result = Vec::new();
                         to_screen * pos2(center.x, center.y + i as f32 * 20.), // slightly below seq name
                         Align2::CENTER_CENTER,
                         label,
-                            FontId::new(16., FontFamily::Proportional),
+                        FontId::new(16., FontFamily::Proportional),
                         TICK_COLOR,
                     )
                 }));
@@ -671,13 +727,14 @@ pub fn circle_page(state: &mut State, ui: &mut Ui) {
             // todo: You may not need the state.ui hover_feature i: You can probably use a local ref here.
             if prev_cursor_i != state.ui.cursor_seq_i {
                 if let Some(seq_i) = state.ui.cursor_seq_i {
-                for (i, feature) in state.generic.features.iter().enumerate() {
-                    if seq_i > feature.index_range.0 && seq_it  < feature.index_range.1 {
-                        state.ui.feature_hover = Some(i);
-                        break; // arbitrarily choosing the first feature.
+                    for (i, feature) in state.generic.features.iter().enumerate() {
+                        // + 1: 1-based indexing.
+                        if feature.contains(seq_i + 1) {
+                            state.ui.feature_hover = Some(i);
+                            break; // arbitrarily choosing the first feature.
+                        }
                     }
                 }
-                }
             }
 
             // Draw the backbone circle
@@ -708,7 +765,9 @@ pub fn circle_page(state: &mut State, ui: &mut Ui) {
                 ui,
             ));
 
-            shapes.append(&mut draw_center_text(center, &to_screen, seq_len, state, ui));
+            shapes.append(&mut draw_center_text(
+                center, &to_screen, seq_len, state, ui,
+            ));
 
             ui.painter().extend(shapes);
         });
@@ -0,0 +1,368 @@
+//! GUI code for virtual restriction digests of the active document: Choosing enzymes, and showing the
+//! fragments, and a simulated agarose gel.
+
+use eframe::egui::{
+    pos2, vec2, Align2, Color32, ComboBox, FontFamily, FontId, Frame, Grid, Rect, RichText, Sense,
+    Shape, TextEdit, Ui, Vec2,
+};
+use plascad::{
+    digest::{gel_position, Fragment, FragmentEnd, Ladder},
+    restriction_enzyme::{EndType, RestrictionEnzyme, Sensitivity},
+    sequence::seq_to_str,
+};
+
+use crate::{
+    gui::{float_field, COL_SPACING, ROW_SPACING},
+    State,
+};
+
+const GEL_HEIGHT: f32 = 400.;
+const LANE_WIDTH: f32 = 44.;
+/// Band sizes are labeled right of each lane.
+const LABEL_WIDTH: f32 = 52.;
+/// Above the wells, for lane names.
+const GEL_HEADER: f32 = 24.;
+const WELL_HEIGHT: f32 = 6.;
+const BAND_HEIGHT: f32 = 3.;
+/// Labels closer than this to the previous one in their lane are skipped.
+const LABEL_SPACING: f32 = 11.;
+
+const GEL_COLOR: Color32 = Color32::from_rgb(24, 26, 36);
+const WELL_COLOR: Color32 = Color32::from_rgb(60, 64, 80);
+const BAND_COLOR: (u8, u8, u8) = (255, 170, 80);
+
+/// Suggestions when searching for enzymes to add.
+const MAX_SUGGESTIONS: usize = 12;
+
+fn size_text(len: usize) -> String {
+    if len >= 1_000 {
+        format!("{:.1} kb", len as f32 / 1_000.)
+    } else {
+        format!("{len} bp")
+    }
+}
+
+fn enzyme_picker(state: &mut State, ui: &mut Ui) {
+    let mut removed = None;
+    let mut added = None;
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Enzymes:");
+        for (i, name) in state.ui.digest.enzymes.iter().enumerate() {
+            if ui
+                .button(format!("{name} ✖"))
+                .on_hover_text("Remove this enzyme.")
+                .clicked()
+            {
+                removed = Some(i);
+            }
+        }
+        ui.add_space(COL_SPACING);
+
+        ui.label("Add:");
+        ui.add(TextEdit::singleline(&mut state.ui.digest.search).desired_width(80.));
+
+        let search = state.ui.digest.search.trim().to_lowercase();
+        if !search.is_empty() {
+            for re in state
+                .restriction_enzyme_lib
+                .iter()
+                .filter(|re| re.name.to_lowercase().contains(&search))
+                .filter(|re| !state.ui.digest.enzymes.contains(&re.name))
+                .take(MAX_SUGGESTIONS)
+            {
+                if ui
+                    .button(&re.name)
+                    .on_hover_text(re.site_notation())
+                    .clicked()
+                {
+                    added = Some(re.name.clone());
+                }
+            }
+        }
+    });
+
+    // Enzymes in the sequence view's set that cut once; often the ones wanted for a diagnostic digest.
+    let mut cut_counts: Vec<(usize, usize)> = Vec::new();
+    for re_match in &state.volatile.restriction_enzyme_sites {
+        match cut_counts
+            .iter_mut()
+            .find(|(i, _)| *i == re_match.lib_index)
+        {
+            Some(count) => count.1 += re_match.cuts.len(),
+            None => cut_counts.push((re_match.lib_index, re_match.cuts.len())),
+        }
+    }
+    let single: Vec<&str> = cut_counts
+        .iter()
+        .filter(|(_, count)| *count == 1)
+        .filter_map(|(i, _)| state.restriction_enzyme_lib.get(*i))
+        .map(|re| re.name.as_str())
+        .filter(|name| !state.ui.digest.enzymes.iter().any(|n| n == name))
+        .collect();
+
+    if !single.is_empty() {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Single cutters:").on_hover_text(
+                "Enzymes shown in the sequence view that cut this sequence once. Click to add.",
+            );
+            for name in single {
+                if ui.small_button(name).clicked() {
+                    added = Some(name.to_owned());
+                }
+            }
+        });
+    }
+
+    if let Some(i) = removed {
+        state.ui.digest.enzymes.remove(i);
+        state.sync_digest();
+    }
+    if let Some(name) = added {
+        state.ui.digest.enzymes.push(name);
+        state.ui.digest.search = String::new();
+        state.sync_digest();
+    }
+}
+
+fn gel_settings(state: &mut State, ui: &mut Ui) {
+    let dg = &mut state.ui.digest;
+
+    ui.horizontal(|ui| {
+        ui.label("Ladder:");
+        ComboBox::from_id_source("digest_ladder")
+            .width(80.)
+            .selected_text(dg.ladder.to_string())
+            .show_ui(ui, |ui| {
+                for ladder in Ladder::ALL {
+                    ui.selectable_value(&mut dg.ladder, ladder, ladder.to_string());
+                }
+            });
+
+        ui.label("Second ladder:");
+        ComboBox::from_id_source("digest_ladder_2")
+            .width(80.)
+            .selected_text(
+                dg.ladder_2
+                    .map(|l| l.to_string())
+                    .unwrap_or("None".to_owned()),
+            )
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut dg.ladder_2, None, "None");
+                for ladder in Ladder::ALL {
+                    ui.selectable_value(&mut dg.ladder_2, Some(ladder), ladder.to_string());
+                }
+            });
+        ui.add_space(COL_SPACING);
+
+        float_field(&mut dg.agarose, "Agarose (%):", ui);
+    });
+}
+
+/// Draw a gel, with a lane for each set of bands: Sizes in bp, and mass. Brightness is relative to
+/// the brightest band in each lane.
+fn draw_gel(lanes: &[(String, Vec<(usize, f32)>)], agarose: f32, ui: &mut Ui) {
+    let width = lanes.len() as f32 * (LANE_WIDTH + LABEL_WIDTH) + COL_SPACING;
+
+    Frame::canvas(ui.style()).fill(GEL_COLOR).show(ui, |ui| {
+        let (response, painter) = ui.allocate_painter(vec2(width, GEL_HEIGHT), Sense::hover());
+        let rect = response.rect;
+
+        let top = rect.top() + GEL_HEADER + WELL_HEIGHT;
+        let run_len = GEL_HEIGHT - GEL_HEADER - WELL_HEIGHT - 12.;
+        let font = FontId::new(11., FontFamily::Proportional);
+
+        let mut shapes = Vec::new();
+        for (i, (label, bands)) in lanes.iter().enumerate() {
+            let x = rect.left() + COL_SPACING / 2. + i as f32 * (LANE_WIDTH + LABEL_WIDTH);
+
+            painter.text(
+                pos2(x + LANE_WIDTH / 2., rect.top() + GEL_HEADER / 2.),
+                Align2::CENTER_CENTER,
+                label,
+                font.clone(),
+                Color32::LIGHT_GRAY,
+            );
+            shapes.push(Shape::rect_filled(
+                Rect::from_min_size(
+                    pos2(x, rect.top() + GEL_HEADER),
+                    Vec2::new(LANE_WIDTH, WELL_HEIGHT),
+                ),
+                1.,
+                WELL_COLOR,
+            ));
+
+            let max_mass = bands.iter().map(|(_, m)| *m).fold(0., f32::max);
+            let mut prev_label_y = f32::MIN;
+
+            for (len, mass) in bands {
+                let y = top + gel_position(*len, agarose) * run_len;
+                let brightness = if max_mass > 0. {
+                    (mass / max_mass).sqrt()
+                } else {
+                    1.
+                };
+                let (r, g, b) = BAND_COLOR;
+
+                shapes.push(Shape::rect_filled(
+                    Rect::from_min_size(
+                        pos2(x + 2., y - BAND_HEIGHT / 2.),
+                        Vec2::new(LANE_WIDTH - 4., BAND_HEIGHT),
+                    ),
+                    1.,
+                    Color32::from_rgba_unmultiplied(r, g, b, (40. + 215. * brightness) as u8),
+                ));
+
+                if y - prev_label_y >= LABEL_SPACING {
+                    painter.text(
+                        pos2(x + LANE_WIDTH + 4., y),
+                        Align2::LEFT_CENTER,
+                        size_text(*len),
+                        font.clone(),
+                        Color32::GRAY,
+                    );
+                    prev_label_y = y;
+                }
+            }
+        }
+
+        painter.extend(shapes);
+    });
+}
+
+/// The enzyme, and overhang, eg `EcoRI 5' AATT`.
+fn end_text(end: &Option<FragmentEnd>, enzymes: &[RestrictionEnzyme], uncut: bool) -> String {
+    let Some(end) = end else {
+        return if uncut { "Uncut" } else { "Sequence end" }.to_owned();
+    };
+
+    let overhang = seq_to_str(&end.overhang).to_uppercase();
+    let ends = match end.end_type {
+        EndType::Blunt => "blunt".to_owned(),
+        EndType::FivePrime(_) => format!("5' {overhang}"),
+        EndType::ThreePrime(_) => format!("3' {overhang}"),
+    };
+    format!("{} {ends}", enzymes[end.lib_index].name)
+}
+
+fn fragment_table(
+    fragments: &[Fragment],
+    enzymes: &[RestrictionEnzyme],
+    state: &State,
+    ui: &mut Ui,
+) {
+    let topology = state.generic.topology;
+
+    Grid::new("digest_fragments")
+        .spacing(Vec2::new(COL_SPACING, 4.))
+        .striped(true)
+        .show(ui, |ui| {
+            for label in [
+                "Start",
+                "End",
+                "Length",
+                "Left end",
+                "Right end",
+                "Features",
+            ] {
+                ui.label(RichText::new(label).strong());
+            }
+            ui.end_row();
+
+            for fragment in fragments {
+                let uncut = fragment.is_uncut_circle(topology);
+
+                ui.label(fragment.range.start.to_string());
+                ui.label(fragment.range.end.to_string());
+                ui.label(format!("{} bp", fragment.len));
+                ui.label(end_text(&fragment.left, enzymes, uncut));
+                ui.label(end_text(&fragment.right, enzymes, uncut));
+
+                let features: Vec<&str> = fragment
+                    .features
+                    .iter()
+                    // Features may have been removed since the digest was synced.
+                    .filter_map(|i| state.generic.features.get(*i))
+                    .map(|f| f.label.as_str())
+                    .collect();
+                ui.label(features.join(", "));
+                ui.end_row();
+            }
+        });
+}
+
+/// Digest the active document with one or more enzymes, listing fragments, and showing them on a
+/// simulated gel next to DNA ladders.
+pub fn digest_page(state: &mut State, ui: &mut Ui) {
+    ui.heading("Restriction digest");
+    ui.label(
+        "Cut this sequence with one or more enzymes, eg to plan a diagnostic digest. Coordinates and \
+    lengths are of the top strand; features listed are entirely within a fragment.",
+    );
+    ui.add_space(ROW_SPACING / 2.);
+
+    enzyme_picker(state, ui);
+    gel_settings(state, ui);
+    ui.add_space(ROW_SPACING / 2.);
+
+    let enzymes = &state.volatile.digest_enzymes;
+    if enzymes.is_empty() {
+        ui.label("Add an enzyme to digest with.");
+        return;
+    }
+
+    for re in enzymes {
+        for (methylation, sensitivity) in [
+            ("Dam", re.methylation.dam),
+            ("Dcm", re.methylation.dcm),
+            ("CpG", re.methylation.cpg),
+        ] {
+            if sensitivity == Sensitivity::Blocked || sensitivity == Sensitivity::Impaired {
+                ui.label(
+                    RichText::new(format!(
+                        "{} is {} by overlapping {methylation} methylation.",
+                        re.name,
+                        sensitivity.to_string().to_lowercase()
+                    ))
+                    .color(Color32::GOLD),
+                );
+            }
+        }
+    }
+
+    let fragments = &state.volatile.digest_fragments;
+
+    let uncut = fragments
+        .first()
+        .is_some_and(|f| f.is_uncut_circle(state.generic.topology));
+    if uncut {
+        ui.label(
+            RichText::new(
+                "None of these enzymes cut. Uncut plasmid is shown at its length; supercoiled DNA runs \
+            faster than linear DNA of the same size.",
+            )
+            .color(Color32::GOLD),
+        );
+    }
+
+    let dg = &state.ui.digest;
+    let mut lanes = vec![
+        (dg.ladder.to_string(), dg.ladder.bands()),
+        ("Digest".to_owned(), state.volatile.digest_bands.clone()),
+    ];
+    if let Some(ladder) = dg.ladder_2 {
+        lanes.push((ladder.to_string(), ladder.bands()));
+    }
+    let agarose = dg.agarose;
+
+    ui.horizontal_top(|ui| {
+        draw_gel(&lanes, agarose, ui);
+        ui.add_space(COL_SPACING);
+
+        ui.vertical(|ui| {
+            ui.label(format!("{} fragments", fragments.len()));
+            ui.add_space(ROW_SPACING / 2.);
+            fragment_table(fragments, enzymes, state, ui);
+        });
+    });
+}
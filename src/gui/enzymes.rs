@@ -0,0 +1,351 @@
+//! GUI code for the restriction enzyme library: Loading it from REBASE files, browsing enzymes, and
+//! editing the enzyme sets displayed in the sequence view.
+
+use std::path::Path;
+
+use eframe::egui::{Button, Color32, ComboBox, Grid, RichText, TextEdit, Ui, Vec2};
+use plascad::{
+    file_io::{
+        error::FileReport,
+        rebase::{save_enzyme_sets, DEFAULT_ENZYME_SETS_FILE},
+    },
+    restriction_enzyme::{
+        default_enzyme_sets, isoschizomers, load_re_library, load_re_library_file, supplier_name,
+        EnzymeSet, RestrictionEnzyme, Sensitivity,
+    },
+};
+
+use crate::{
+    gui::{save::set_report, COL_SPACING, ROW_SPACING},
+    State,
+};
+
+/// Full REBASE files contain thousands of enzymes; we show this many, and ask the user to narrow the
+/// search.
+const MAX_ROWS: usize = 300;
+
+fn save_sets(state: &mut State) {
+    let result = save_enzyme_sets(&state.enzyme_sets, Path::new(DEFAULT_ENZYME_SETS_FILE));
+    set_report(state, FileReport::from_export("Saving enzyme sets", result));
+}
+
+fn set_name(sets: &[EnzymeSet], i: Option<usize>, none: &str) -> String {
+    i.and_then(|i| sets.get(i))
+        .map(|s| s.name.clone())
+        .unwrap_or_else(|| none.to_owned())
+}
+
+fn library_section(state: &mut State, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        let source = match &state.ui.enzymes.library_path {
+            Some(path) => path.display().to_string(),
+            None => "built-in".to_owned(),
+        };
+        ui.label(format!(
+            "Library: {} enzymes ({source})",
+            state.restriction_enzyme_lib.len()
+        ));
+        ui.add_space(COL_SPACING);
+
+        if ui
+            .button("Load REBASE file")
+            .on_hover_text(
+                "Load enzymes from a REBASE file, in withrefm, allenz, or emboss_e format; eg from \
+            rebase.neb.com. This replaces the library until the program is restarted.",
+            )
+            .clicked()
+        {
+            state.ui.file_dialogs.load_rebase.select_file();
+        }
+
+        if state.ui.enzymes.library_path.is_some() && ui.button("Use built-in library").clicked() {
+            state.restriction_enzyme_lib = load_re_library();
+            state.ui.enzymes.library_path = None;
+            state.sync_re_sites();
+        }
+    });
+}
+
+fn sets_section(state: &mut State, ui: &mut Ui) {
+    let mut changed = false;
+    let mut display_changed = false;
+
+    ui.horizontal(|ui| {
+        let ui_state = &mut state.ui.enzymes;
+        let sets = &mut state.enzyme_sets;
+
+        ui.label("Show in sequence:");
+        let prev = ui_state.display_set;
+        ComboBox::from_id_source("enzyme_display_set")
+            .width(120.)
+            .selected_text(set_name(sets, ui_state.display_set, "All enzymes"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut ui_state.display_set, None, "All enzymes");
+                for (i, set) in sets.iter().enumerate() {
+                    ui.selectable_value(&mut ui_state.display_set, Some(i), &set.name);
+                }
+            });
+        display_changed = ui_state.display_set != prev;
+        ui.add_space(COL_SPACING);
+
+        ui.label("Edit set:").on_hover_text(
+            "Check enzymes in the table to add them to this set. Sets are saved automatically.",
+        );
+        ComboBox::from_id_source("enzyme_edit_set")
+            .width(120.)
+            .selected_text(set_name(sets, ui_state.edit_set, "None"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut ui_state.edit_set, None, "None");
+                for (i, set) in sets.iter().enumerate() {
+                    ui.selectable_value(&mut ui_state.edit_set, Some(i), &set.name);
+                }
+            });
+
+        if let Some(i) = ui_state.edit_set {
+            if ui.button("Delete set").clicked() {
+                sets.remove(i);
+                ui_state.edit_set = None;
+                // Indices after the removed set shift.
+                ui_state.display_set = match ui_state.display_set {
+                    Some(d) if d == i => None,
+                    Some(d) if d > i => Some(d - 1),
+                    d => d,
+                };
+                changed = true;
+                display_changed = true;
+            }
+        }
+        ui.add_space(COL_SPACING);
+
+        ui.add(TextEdit::singleline(&mut ui_state.new_set_name).desired_width(100.));
+        let name = ui_state.new_set_name.trim().to_owned();
+        if ui
+            .add_enabled(!name.is_empty(), Button::new("New set"))
+            .clicked()
+        {
+            sets.push(EnzymeSet {
+                name,
+                enzymes: Vec::new(),
+            });
+            ui_state.edit_set = Some(sets.len() - 1);
+            ui_state.new_set_name = String::new();
+            changed = true;
+        }
+
+        if ui
+            .button("Reset sets")
+            .on_hover_text("Replace all sets with the built-in ones.")
+            .clicked()
+        {
+            *sets = default_enzyme_sets();
+            ui_state.edit_set = None;
+            ui_state.display_set = Some(0);
+            changed = true;
+            display_changed = true;
+        }
+    });
+
+    if changed {
+        save_sets(state);
+    }
+    if display_changed {
+        state.sync_re_sites();
+    }
+}
+
+fn sensitivity_text(v: Sensitivity) -> RichText {
+    let color = match v {
+        Sensitivity::Blocked => Color32::LIGHT_RED,
+        Sensitivity::Impaired => Color32::GOLD,
+        _ => Color32::GRAY,
+    };
+    RichText::new(v.to_string()).color(color)
+}
+
+/// The buffer with the highest activity, eg `rCutSmart 100%`, with all buffers as hover text.
+fn buffer_text(re: &RestrictionEnzyme) -> (String, String) {
+    let pct = |v: u8| {
+        if v == 0 {
+            "<10%".to_owned()
+        } else {
+            format!("{v}%")
+        }
+    };
+
+    let best = re
+        .buffer_activity
+        .iter()
+        .max_by_key(|(_, v)| *v)
+        .map(|(buffer, v)| format!("{buffer} {}", pct(*v)))
+        .unwrap_or_default();
+    let all = re
+        .buffer_activity
+        .iter()
+        .map(|(buffer, v)| format!("{buffer}: {}", pct(*v)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    (best, all)
+}
+
+fn library_table(state: &mut State, ui: &mut Ui) {
+    let lib = &state.restriction_enzyme_lib;
+    let ui_state = &state.ui.enzymes;
+
+    let search = ui_state.search.trim().to_lowercase();
+    let suppliers: Vec<char> = ui_state
+        .suppliers
+        .to_uppercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .collect();
+
+    let shown: Vec<usize> = lib
+        .iter()
+        .enumerate()
+        .filter(|(_, re)| {
+            search.is_empty()
+                || re.name.to_lowercase().contains(&search)
+                || re.site_notation().to_lowercase().contains(&search)
+        })
+        .filter(|(_, re)| {
+            suppliers.is_empty() || suppliers.iter().any(|c| re.suppliers.contains(c))
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    if shown.len() > MAX_ROWS {
+        ui.label(format!(
+            "Showing {MAX_ROWS} of {} enzymes. Narrow the search to see others.",
+            shown.len()
+        ));
+    }
+
+    let edit_set = ui_state.edit_set;
+    let mut toggled = None;
+
+    Grid::new("enzyme_library")
+        .spacing(Vec2::new(COL_SPACING, 4.))
+        .striped(true)
+        .show(ui, |ui| {
+            if edit_set.is_some() {
+                ui.label(RichText::new("In set").strong());
+            }
+            for label in [
+                "Name",
+                "Site",
+                "Ends",
+                "Isoschizomers",
+                "Suppliers",
+                "Dam",
+                "Dcm",
+                "CpG",
+                "Buffer",
+            ] {
+                ui.label(RichText::new(label).strong());
+            }
+            ui.end_row();
+
+            for &i in shown.iter().take(MAX_ROWS) {
+                let re = &lib[i];
+
+                if let Some(set) = edit_set.and_then(|s| state.enzyme_sets.get(s)) {
+                    let mut in_set = set.enzymes.iter().any(|n| n.eq_ignore_ascii_case(&re.name));
+                    if ui.checkbox(&mut in_set, "").changed() {
+                        toggled = Some((i, in_set));
+                    }
+                }
+
+                ui.label(&re.name);
+                ui.label(RichText::new(re.site_notation()).monospace());
+                ui.label(re.end_type().to_string());
+
+                let isos: Vec<String> = isoschizomers(lib, i)
+                    .into_iter()
+                    .map(|(j, neo)| {
+                        if neo {
+                            format!("{}*", lib[j].name)
+                        } else {
+                            lib[j].name.clone()
+                        }
+                    })
+                    .collect();
+                ui.label(isos.join(", ")).on_hover_text(
+                    "Enzymes in the library that recognize the same site. Neoschizomers, which cut \
+                it differently, are marked with *.",
+                );
+
+                let names: Vec<&str> = re
+                    .suppliers
+                    .iter()
+                    .filter_map(|c| supplier_name(*c))
+                    .collect();
+                ui.label(re.suppliers.iter().collect::<String>())
+                    .on_hover_text(names.join("\n"));
+
+                ui.label(sensitivity_text(re.methylation.dam));
+                ui.label(sensitivity_text(re.methylation.dcm));
+                ui.label(sensitivity_text(re.methylation.cpg));
+
+                let (best, all) = buffer_text(re);
+                ui.label(best).on_hover_text(all);
+                ui.end_row();
+            }
+        });
+
+    if let (Some((i, in_set)), Some(s)) = (toggled, edit_set) {
+        let name = state.restriction_enzyme_lib[i].name.clone();
+        let set = &mut state.enzyme_sets[s];
+        if in_set {
+            set.enzymes.push(name);
+        } else {
+            set.enzymes.retain(|n| !n.eq_ignore_ascii_case(&name));
+        }
+
+        save_sets(state);
+        if state.ui.enzymes.display_set == Some(s) {
+            state.sync_re_sites();
+        }
+    }
+}
+
+/// Browse the restriction enzyme library, load it from REBASE files, and edit enzyme sets.
+pub fn enzymes_page(state: &mut State, ui: &mut Ui) {
+    ui.heading("Restriction enzymes");
+
+    library_section(state, ui);
+    ui.add_space(ROW_SPACING / 2.);
+
+    sets_section(state, ui);
+    ui.add_space(ROW_SPACING / 2.);
+
+    ui.horizontal(|ui| {
+        ui.label("Search:");
+        ui.add(TextEdit::singleline(&mut state.ui.enzymes.search).desired_width(120.));
+        ui.add_space(COL_SPACING);
+
+        ui.label("Suppliers:").on_hover_text(
+            "REBASE supplier codes; eg N for New England Biolabs, or BNR. Shows enzymes available from \
+        any of them.",
+        );
+        ui.add(TextEdit::singleline(&mut state.ui.enzymes.suppliers).desired_width(60.));
+    });
+    ui.add_space(ROW_SPACING / 2.);
+
+    library_table(state, ui);
+
+    let dialog = &mut state.ui.file_dialogs.load_rebase;
+    dialog.update(ui.ctx());
+
+    if let Some(path) = dialog.take_selected() {
+        let action = format!("Loading {}", path.display());
+        let (report, loaded) = FileReport::from_import(&action, load_re_library_file(&path));
+        set_report(state, report);
+
+        if let Some(lib) = loaded {
+            state.restriction_enzyme_lib = lib;
+            state.ui.enzymes.library_path = Some(path);
+            state.sync_re_sites();
+        }
+    }
+}
@@ -8,12 +8,7 @@ use eframe::{
     egui::{pos2, Align2, Color32, FontFamily, FontId, Pos2, Shape, Stroke, Ui},
     epaint::PathShape,
 };
-
-use crate::{
-    gui::{
-        primer_arrow::{HEIGHT, LABEL_OFFSET, SLANT, STROKE_WIDTH},
-        seq_view::{NT_WIDTH_PX, SEQ_ROW_SPACING_PX},
-    },
+use plascad::{
     sequence::{
         Feature, FeatureDirection,
         FeatureDirection::{Forward, Reverse},
@@ -23,10 +18,16 @@ use crate::{
     Color,
 };
 
+use crate::gui::{
+    primer_arrow::{HEIGHT, LABEL_OFFSET, SLANT, STROKE_WIDTH},
+    seq_view::{NT_WIDTH_PX, SEQ_ROW_SPACING_PX},
+};
+
 const VERTICAL_OFFSET_FEATURE: f32 = 14.; // Number of pixels above the sequence text.
 
 pub fn draw_features(
     features: &[Feature],
+    seq_len: usize,
     row_ranges: &[Range<usize>],
     ui: &mut Ui,
     seq_i_to_px_rel: impl Fn(usize) -> Pos2,
@@ -41,15 +42,13 @@ pub fn draw_features(
             continue;
         }
 
-        if feature.index_range.0 < 1 {
-            continue; // 0 is invalid, in 1-based indexing, and will underflow.
-        }
-
-        // The -1 assymetry is because these ranges are inclusive.
-        let feature_ranges = get_feature_ranges(
-            &(feature.index_range.0 - 1..feature.index_range.1),
-            row_ranges,
-        );
+        // Joined features, and ones that wrap the origin, are drawn in multiple parts. The arrow tip is on
+        // the last part, and the label on the first.
+        let feature_ranges: Vec<Range<usize>> = feature
+            .ranges()
+            .iter()
+            .flat_map(|r| get_feature_ranges(r, row_ranges, seq_len))
+            .collect();
 
         let feature_ranges_px: Vec<(Pos2, Pos2)> = feature_ranges
             .iter()
@@ -120,15 +119,11 @@ pub fn feature_seq_overlay(
 
         // Add a slant, if applicable.
         match direction {
-            Forward => {
-                if i + 1 == feature_ranges_px.len() {
-                    top_right.x -= SLANT;
-                }
+            Forward if i + 1 == feature_ranges_px.len() => {
+                top_right.x -= SLANT;
             }
-            Reverse => {
-                if i == 0 {
-                    top_left.x += SLANT;
-                }
+            Reverse if i == 0 => {
+                top_left.x += SLANT;
             }
             _ => (),
         }
@@ -1,15 +1,19 @@
 //! GUI code for the features editor and related.
 
-use eframe::egui::{Color32, ComboBox, Painter, RichText, Sense, TextEdit, Ui, Vec2};
-
-use crate::{
-    gui::{int_field, ROW_SPACING},
+use eframe::egui::{Color32, ComboBox, Label, Painter, RichText, Sense, TextEdit, Ui, Vec2};
+use plascad::{
+    protein::{protein_to_str, translate_feature, StopHandling},
     sequence::{
         Feature,
         FeatureDirection::{self, Forward, Reverse},
-        FeatureType,
+        FeatureType, SeqRange, SeqTopology,
     },
-    Color, State,
+    Color,
+};
+
+use crate::{
+    gui::{int_field, ROW_SPACING},
+    State,
 };
 
 const LABEL_EDIT_WIDTH: f32 = 140.;
@@ -20,13 +24,14 @@ const COLORS: [Color; 4] = [(255, 255, 255), (255, 0, 0), (0, 255, 0), (0, 0, 25
 fn color_rect(color: Color, ui: &mut Ui) {
     let color_rgb = Color32::from_rgb(color.0, color.1, color.2);
 
-    let (rect, response) = ui.allocate_exact_size(Vec2::new(60.0, 10.0), Sense::click());
+    let (rect, _response) = ui.allocate_exact_size(Vec2::new(60.0, 10.0), Sense::click());
     let painter = Painter::new(ui.ctx().clone(), ui.layer_id(), rect);
     painter.rect_filled(rect, 0.0, color_rgb);
 }
 
-/// A color selector for use with feature addition and editing.
-fn color_picker(val: &mut Option<Color>, id: usize, ui: &mut Ui) {
+/// A color selector for use with feature addition and editing. Returns if it changed.
+fn color_picker(val: &mut Option<Color>, id: usize, ui: &mut Ui) -> bool {
+    let prev = *val;
     let label_none = "Use type color";
     // let text = match val {
     //     Some((r, g, b)) => &format!("{}, {}, {}", r, g, b),
@@ -54,11 +59,13 @@ fn color_picker(val: &mut Option<Color>, id: usize, ui: &mut Ui) {
                 // );
             }
         });
+    *val != prev
 }
 
-/// A selector for use with feature addition and editing.
+/// A selector for use with feature addition and editing. Returns if it changed.
 /// todo: Generic selector creator?
-fn direction_picker(val: &mut FeatureDirection, id: usize, ui: &mut Ui) {
+fn direction_picker(val: &mut FeatureDirection, id: usize, ui: &mut Ui) -> bool {
+    let prev = *val;
     ComboBox::from_id_source(id)
         .width(74.)
         .selected_text(val.to_string())
@@ -67,11 +74,13 @@ fn direction_picker(val: &mut FeatureDirection, id: usize, ui: &mut Ui) {
                 ui.selectable_value(val, dir, dir.to_string());
             }
         });
+    *val != prev
 }
 
-/// A selector for use with feature addition and editing.
+/// A selector for use with feature addition and editing. Returns if it changed.
 /// todo: Generic selector creator?
-fn feature_type_picker(val: &mut FeatureType, id: usize, ui: &mut Ui) {
+fn feature_type_picker(val: &mut FeatureType, id: usize, ui: &mut Ui) -> bool {
+    let prev = *val;
     ComboBox::from_id_source(id)
         .width(140.)
         .selected_text(val.to_string())
@@ -91,30 +100,81 @@ fn feature_type_picker(val: &mut FeatureType, id: usize, ui: &mut Ui) {
                 ui.selectable_value(val, feature_type, feature_type.to_string());
             }
         });
+    *val != prev
 }
 
 pub fn feature_table(state: &mut State, ui: &mut Ui) {
     feature_add_disp(state, ui);
+
+    if !state.clipboard.features.is_empty() {
+        ui.horizontal(|ui| {
+            if ui
+                .button(format!(
+                    "Paste features ({})",
+                    state.clipboard.features.len()
+                ))
+                .on_hover_text("Add copied features where their sequences are found in this one.")
+                .clicked()
+            {
+                state.ui.paste_not_found = state.paste_features();
+            }
+
+            if ui.button("Clear copied").clicked() {
+                state.clipboard.features = Vec::new();
+                state.ui.paste_not_found = Vec::new();
+            }
+        });
+    }
+
+    if !state.ui.paste_not_found.is_empty() {
+        ui.label(
+            RichText::new(format!(
+                "Not pasted, since their sequence isn't present: {}",
+                state.ui.paste_not_found.join(", ")
+            ))
+            .color(Color32::GOLD),
+        );
+    }
+
     ui.add_space(ROW_SPACING);
 
     let mut removed = None;
+    let mut copied = None;
+    let mut edited = false;
     for (i, feature) in state.generic.features.iter_mut().enumerate() {
         ui.horizontal(|ui| {
             // todo: This may be confoudning your 0 vs 1.
-            int_field(&mut feature.index_range.0, "Start:", ui);
-            int_field(&mut feature.index_range.1, "End:", ui);
+            if feature.segments.is_empty() {
+                edited |= int_field(&mut feature.index_range.start, "Start:", ui);
+                edited |= int_field(&mut feature.index_range.end, "End:", ui);
+            } else {
+                // todo: Allow editing segments.
+                let segments: Vec<String> =
+                    feature.segments.iter().map(|r| r.to_string()).collect();
+                ui.label(format!("Joined: {}", segments.join(", ")));
+            }
 
             ui.label("Label:");
-            ui.add(TextEdit::singleline(&mut feature.label).desired_width(LABEL_EDIT_WIDTH));
+            edited |= ui
+                .add(TextEdit::singleline(&mut feature.label).desired_width(LABEL_EDIT_WIDTH))
+                .changed();
 
             ui.label("Type:");
-            feature_type_picker(&mut feature.feature_type, 100 + i, ui);
+            edited |= feature_type_picker(&mut feature.feature_type, 100 + i, ui);
 
             ui.label("Dir:");
-            direction_picker(&mut feature.direction, 300 + i, ui);
+            edited |= direction_picker(&mut feature.direction, 300 + i, ui);
 
             ui.label("Color:");
-            color_picker(&mut feature.color_override, 3 + i, ui);
+            edited |= color_picker(&mut feature.color_override, 3 + i, ui);
+
+            if ui
+                .button("Copy")
+                .on_hover_text("Copy this feature, to paste into another document.")
+                .clicked()
+            {
+                copied = Some(i);
+            }
 
             if ui
                 .button(RichText::new("Delete 🗑").color(Color32::RED))
@@ -123,9 +183,38 @@ pub fn feature_table(state: &mut State, ui: &mut Ui) {
                 removed = Some(i);
             }
         });
+
+        if feature.feature_type == FeatureType::CodingRegion {
+            if let Some(protein) = translate_feature(
+                feature,
+                &state.generic.seq,
+                state.genetic_code,
+                StopHandling::Truncate,
+            ) {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Protein ({} AA):", protein.len()));
+                    ui.add(
+                        Label::new(
+                            RichText::new(protein_to_str(&protein, state.ui.aa_ident)).monospace(),
+                        )
+                        .wrap(),
+                    );
+                });
+            }
+        }
+    }
+    if edited {
+        state.history.mark_edited();
+        // The digest lists the features in each fragment.
+        state.sync_digest();
+    }
+    if let Some(i) = copied {
+        state.copy_feature(i);
     }
     if let Some(rem_i) = removed {
         state.generic.features.remove(rem_i);
+        state.sync_digest();
+        state.commit("Delete feature");
     }
 }
 
@@ -144,6 +233,9 @@ pub fn feature_add_disp(state: &mut State, ui: &mut Ui) {
         ui.label("Type:");
         feature_type_picker(&mut state.ui.feature_add.feature_type, 200, ui);
 
+        ui.label("Dir:");
+        direction_picker(&mut state.ui.feature_add.direction, 201, ui);
+
         ui.label("Color:");
         color_picker(&mut state.ui.feature_add.color, 2, ui);
 
@@ -155,7 +247,10 @@ pub fn feature_add_disp(state: &mut State, ui: &mut Ui) {
                 state.ui.feature_add.end_posit = 1;
             }
 
-            if state.ui.feature_add.start_posit > state.ui.feature_add.end_posit {
+            // On circular sequences, a start after the end creates a feature that wraps the origin.
+            if state.ui.feature_add.start_posit > state.ui.feature_add.end_posit
+                && state.generic.topology == SeqTopology::Linear
+            {
                 std::mem::swap(
                     &mut state.ui.feature_add.start_posit,
                     &mut state.ui.feature_add.end_posit,
@@ -163,20 +258,62 @@ pub fn feature_add_disp(state: &mut State, ui: &mut Ui) {
             }
 
             state.generic.features.push(Feature {
-                index_range: (
+                index_range: SeqRange::new(
                     state.ui.feature_add.start_posit,
                     state.ui.feature_add.end_posit,
                 ),
-                feature_type: FeatureType::Generic,
-                direction: FeatureDirection::None,
+                segments: Vec::new(),
+                feature_type: state.ui.feature_add.feature_type,
+                direction: state.ui.feature_add.direction,
                 label: state.ui.feature_add.label.clone(),
-                color_override: None,
+                color_override: state.ui.feature_add.color,
                 notes: Default::default(),
             });
+            state.commit("Add feature");
         }
     });
 }
 
+/// List open reading frames, with buttons to add them as coding region features.
+pub fn orf_table(state: &mut State, ui: &mut Ui) {
+    ui.heading(format!(
+        "Open reading frames ({})",
+        state.volatile.reading_frame_matches.len()
+    ));
+
+    let seq_len = state.generic.seq.len();
+
+    let mut added = None;
+    for (i, orf) in state.volatile.reading_frame_matches.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{}: {}  {} AA",
+                orf.frame,
+                orf.range,
+                orf.range.len(seq_len) / 3 - 1
+            ));
+
+            let exists =
+                state.generic.features.iter().any(|f| {
+                    f.feature_type == FeatureType::CodingRegion && f.index_range == orf.range
+                });
+
+            if !exists && ui.button("➕ Add as coding region").clicked() {
+                added = Some(i);
+            }
+        });
+    }
+
+    if let Some(i) = added {
+        let feature = state.volatile.reading_frame_matches[i].to_feature();
+        state.generic.features.push(feature);
+        state.commit("Add ORF as coding region");
+    }
+}
+
 pub fn features_page(state: &mut State, ui: &mut Ui) {
     feature_table(state, ui);
+    ui.add_space(ROW_SPACING);
+
+    orf_table(state, ui);
 }
@@ -0,0 +1,233 @@
+//! GUI code for Golden Gate assembly: Choosing parts from the workspace, an enzyme, and overhangs, and
+//! showing designed primers, warnings, and the product.
+
+use eframe::egui::{Color32, ComboBox, Grid, RichText, TextEdit, Ui, Vec2};
+use plascad::{
+    golden_gate::{
+        design_golden_gate, golden_gate_enzymes, overhang_len, GoldenGateDesign, GoldenGatePart,
+    },
+    sequence::{seq_from_str, seq_to_str},
+};
+
+use crate::{
+    gui::{
+        assembly::{document_choices, fragment_data, fragments_edit, tm_text},
+        COL_SPACING, ROW_SPACING,
+    },
+    State,
+};
+
+fn run_design(state: &mut State) {
+    let parts: Vec<GoldenGatePart> = state
+        .ui
+        .golden_gate
+        .parts
+        .iter()
+        .map(|p| {
+            let (name, data) = fragment_data(state, p);
+            GoldenGatePart { name, data }
+        })
+        .collect();
+
+    let gg = &mut state.ui.golden_gate;
+    gg.params.overhangs = gg
+        .overhangs_input
+        .iter()
+        .map(|oh| seq_from_str(oh.trim()))
+        .collect();
+
+    gg.result = Some(design_golden_gate(
+        &parts,
+        &gg.params,
+        &state.ion_concentrations,
+    ));
+}
+
+fn params_edit(state: &mut State, ui: &mut Ui) {
+    let lib = &state.restriction_enzyme_lib;
+    let gg = &mut state.ui.golden_gate;
+
+    ui.horizontal(|ui| {
+        ui.label("Enzyme:").on_hover_text(
+            "Type IIS enzymes in the library that cut after their site, leaving a 5' overhang.",
+        );
+        ComboBox::from_id_source("golden_gate_enzyme")
+            .width(80.)
+            .selected_text(&gg.params.enzyme.name)
+            .show_ui(ui, |ui| {
+                for i in golden_gate_enzymes(lib) {
+                    let enzyme = &lib[i];
+                    if ui
+                        .selectable_label(gg.params.enzyme.name == enzyme.name, &enzyme.name)
+                        .clicked()
+                    {
+                        gg.params.enzyme = enzyme.clone();
+                    }
+                }
+            });
+        ui.label(format!(
+            "{}, {} nt overhangs",
+            gg.params.enzyme.site_notation(),
+            overhang_len(&gg.params.enzyme)
+        ));
+        ui.add_space(COL_SPACING);
+
+        ui.checkbox(&mut gg.params.circular, "Circular")
+            .on_hover_text(
+                "Join the last part to the first, eg when one of the parts is a backbone.",
+            );
+    });
+
+    let num = gg.params.num_overhangs(gg.parts.len());
+    gg.overhangs_input.resize(num, String::new());
+
+    ui.horizontal(|ui| {
+        ui.label("Overhangs:").on_hover_text(
+            "The overhang before each part, on the top strand. For linear assemblies, the last is \
+        after the last part.",
+        );
+
+        for (i, oh) in gg.overhangs_input.iter_mut().enumerate() {
+            let label = if i < gg.parts.len() {
+                format!("{}:", i + 1)
+            } else {
+                "End:".to_owned()
+            };
+            ui.label(label);
+            ui.add(TextEdit::singleline(oh).desired_width(40.));
+        }
+
+        if ui
+            .button("MoClo standard")
+            .on_hover_text(
+                "Use the MoClo fusion sites: promoter, 5' UTR, coding region, terminator.",
+            )
+            .clicked()
+        {
+            if let Some(overhangs) = gg.params.moclo_overhangs(gg.parts.len()) {
+                gg.overhangs_input = overhangs
+                    .iter()
+                    .map(|oh| seq_to_str(oh).to_uppercase())
+                    .collect();
+            }
+        }
+    });
+}
+
+fn design_disp(design: &GoldenGateDesign, ui: &mut Ui) {
+    ui.heading("Primers").on_hover_text(
+        "Tails, including the recognition site and overhang, are lowercase. Tm is of the annealing \
+    region.",
+    );
+
+    Grid::new("golden_gate_primers")
+        .spacing(Vec2::new(COL_SPACING, 4.))
+        .striped(true)
+        .show(ui, |ui| {
+            for label in [
+                "Name",
+                "Sequence (5' ⏵ 3')",
+                "Len",
+                "Tm (°C)",
+                "Amplicon (bp)",
+            ] {
+                ui.label(RichText::new(label).strong());
+            }
+            ui.end_row();
+
+            for p in &design.primers {
+                for (primer, tail_len, tm) in [
+                    (&p.fwd, p.fwd_tail_len, p.fwd_anneal_tm),
+                    (&p.rev, p.rev_tail_len, p.rev_anneal_tm),
+                ] {
+                    let seq = seq_to_str(&primer.sequence);
+                    let seq = format!("{}{}", &seq[..tail_len], seq[tail_len..].to_uppercase());
+
+                    ui.label(&primer.name);
+                    ui.label(RichText::new(seq).monospace());
+                    ui.label(primer.sequence.len().to_string());
+                    ui.label(tm_text(tm));
+                    ui.label(p.amplicon.len().to_string());
+                    ui.end_row();
+                }
+            }
+        });
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    for warning in &design.warnings {
+        ui.label(RichText::new(warning.to_string()).color(Color32::GOLD));
+    }
+
+    if let Some(product) = &design.product {
+        ui.label(format!(
+            "Product: {} bp, {} features",
+            product.seq.len(),
+            product.features.len()
+        ));
+    }
+}
+
+/// Design primers for Golden Gate assembly of parts, check the design, and predict the product.
+pub fn golden_gate_page(state: &mut State, ui: &mut Ui) {
+    ui.heading("Golden Gate assembly");
+    ui.label(
+        "Assemble parts from the workspace, in order. Primers amplify each part, adding a Type IIS \
+    recognition site and overhang at each end.",
+    );
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    let documents = document_choices(state);
+    fragments_edit(
+        &mut state.ui.golden_gate.parts,
+        &documents,
+        state.workspace.active,
+        false,
+        "golden_gate_parts",
+        ui,
+    );
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    params_edit(state, ui);
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    if ui.button("Design").clicked() {
+        run_design(state);
+    }
+
+    let mut open = false;
+    match &state.ui.golden_gate.result {
+        Some(Ok(design)) => {
+            ui.add_space(ROW_SPACING / 2.);
+
+            design_disp(design, ui);
+
+            if design.product.is_some() {
+                open = ui
+                    .button("Open product as document")
+                    .on_hover_text(
+                        "Open the assembled sequence, with features for parts and overhangs.",
+                    )
+                    .clicked();
+            }
+        }
+        Some(Err(e)) => {
+            ui.label(RichText::new(e.to_string()).color(Color32::LIGHT_RED));
+        }
+        None => (),
+    }
+
+    if open {
+        if let Some(Ok(GoldenGateDesign {
+            product: Some(product),
+            ..
+        })) = &state.ui.golden_gate.result
+        {
+            let product = product.clone();
+            state.open_document(product, Default::default());
+        }
+    }
+}
@@ -1,17 +1,19 @@
 //! Contains references, comments, etc about the plasmid.
 
 use eframe::egui::{Color32, RichText, TextEdit, Ui};
+use plascad::Metadata;
 
-use crate::{gui::ROW_SPACING, Metadata};
+use crate::gui::ROW_SPACING;
 
-const LABEL_WIDTH: f32 = 140.; // Helps align the text edits, by forcing a fixed label width.
+// const LABEL_WIDTH: f32 = 140.; // Helps align the text edits, by forcing a fixed label width.
 const WIDTH_RATIO: f32 = 0.6;
 const ROW_HEIGHT: usize = 1;
 
 const HEADING_COLOR: Color32 = Color32::from_rgb(40, 180, 255);
 
-/// A convenience function to create a text edit for Option<String>
-fn option_edit(val: &mut Option<String>, label: &str, multi: bool, ui: &mut Ui) {
+/// A convenience function to create a text edit for Option<String>. Returns if it changed.
+fn option_edit(val: &mut Option<String>, label: &str, multi: bool, ui: &mut Ui) -> bool {
+    let mut changed = false;
     ui.horizontal(|ui| {
         // ui.allocate_exact_size(Vec2::new(LABEL_WIDTH, 0.0), egui::Sense::hover()); // Reserve space
         ui.label(label);
@@ -35,13 +37,18 @@ fn option_edit(val: &mut Option<String>, label: &str, multi: bool, ui: &mut Ui)
             } else {
                 None
             };
+            changed = true;
         }
     });
 
     ui.add_space(ROW_SPACING / 2.);
+    changed
 }
 
-pub fn metadata_page(data: &mut Metadata, ui: &mut Ui) {
+/// Returns if any field changed.
+pub fn metadata_page(data: &mut Metadata, ui: &mut Ui) -> bool {
+    let mut changed = false;
+
     // todo: YOu need neat grid alignment. How can we make the labels take up constant space?
 
     // todo: Examine which fields should be single vs multiline, and the order.
@@ -51,16 +58,16 @@ pub fn metadata_page(data: &mut Metadata, ui: &mut Ui) {
 
     ui.horizontal(|ui| {
         ui.label("Plasmid name:");
-        ui.text_edit_singleline(&mut data.plasmid_name);
+        changed |= ui.text_edit_singleline(&mut data.plasmid_name).changed();
     });
     ui.add_space(ROW_SPACING);
 
-    option_edit(&mut data.definition, "Definition:", true, ui);
-    option_edit(&mut data.accession, "Accession:", true, ui);
-    option_edit(&mut data.version, "Version:", true, ui);
-    option_edit(&mut data.keywords, "Keywords:", true, ui);
-    option_edit(&mut data.source, "Source:", true, ui);
-    option_edit(&mut data.organism, "Organism:", true, ui);
+    changed |= option_edit(&mut data.definition, "Definition:", true, ui);
+    changed |= option_edit(&mut data.accession, "Accession:", true, ui);
+    changed |= option_edit(&mut data.version, "Version:", true, ui);
+    changed |= option_edit(&mut data.keywords, "Keywords:", true, ui);
+    changed |= option_edit(&mut data.source, "Source:", true, ui);
+    changed |= option_edit(&mut data.organism, "Organism:", true, ui);
 
     ui.add_space(ROW_SPACING);
 
@@ -79,30 +86,34 @@ pub fn metadata_page(data: &mut Metadata, ui: &mut Ui) {
     for ref_ in &mut data.references {
         ui.horizontal(|ui| {
             ui.label("Title:");
-            let response = ui.add(
-                TextEdit::multiline(&mut ref_.title)
-                    .desired_width(ui.available_width() * WIDTH_RATIO)
-                    .desired_rows(ROW_HEIGHT),
-            );
+            changed |= ui
+                .add(
+                    TextEdit::multiline(&mut ref_.title)
+                        .desired_width(ui.available_width() * WIDTH_RATIO)
+                        .desired_rows(ROW_HEIGHT),
+                )
+                .changed();
         });
         ui.add_space(ROW_SPACING / 2.);
 
         ui.horizontal(|ui| {
             ui.label("Description:");
-            let response = ui.add(
-                TextEdit::multiline(&mut ref_.description)
-                    .desired_width(ui.available_width() * WIDTH_RATIO)
-                    .desired_rows(ROW_HEIGHT),
-            );
+            changed |= ui
+                .add(
+                    TextEdit::multiline(&mut ref_.description)
+                        .desired_width(ui.available_width() * WIDTH_RATIO)
+                        .desired_rows(ROW_HEIGHT),
+                )
+                .changed();
         });
         ui.add_space(ROW_SPACING / 2.);
 
-        option_edit(&mut ref_.authors, "Authors:", true, ui);
-        option_edit(&mut ref_.consortium, "Consortium:", true, ui);
+        changed |= option_edit(&mut ref_.authors, "Authors:", true, ui);
+        changed |= option_edit(&mut ref_.consortium, "Consortium:", true, ui);
 
-        option_edit(&mut ref_.journal, "Journal:", true, ui);
-        option_edit(&mut ref_.pubmed, "Pubmed:", true, ui);
-        option_edit(&mut ref_.remark, "Remarks:", true, ui);
+        changed |= option_edit(&mut ref_.journal, "Journal:", true, ui);
+        changed |= option_edit(&mut ref_.pubmed, "Pubmed:", true, ui);
+        changed |= option_edit(&mut ref_.remark, "Remarks:", true, ui);
 
         ui.add_space(ROW_SPACING);
     }
@@ -113,17 +124,20 @@ pub fn metadata_page(data: &mut Metadata, ui: &mut Ui) {
     ui.add_space(ROW_SPACING);
     if ui.button("➕ Add").clicked() {
         data.comments.push(String::new());
+        changed = true;
     }
 
     for comment in &mut data.comments {
-        let response = ui.add(
-            TextEdit::multiline(comment)
-                .desired_width(ui.available_width() * WIDTH_RATIO)
-                .desired_rows(ROW_HEIGHT),
-        );
-        // if response.changed() {
-        // }
+        changed |= ui
+            .add(
+                TextEdit::multiline(comment)
+                    .desired_width(ui.available_width() * WIDTH_RATIO)
+                    .desired_rows(ROW_HEIGHT),
+            )
+            .changed();
 
         ui.add_space(ROW_SPACING / 2.);
     }
+
+    changed
 }
@@ -1,27 +1,28 @@
-use std::{path::PathBuf, str::FromStr};
-
 use eframe::{
     egui,
-    egui::{Color32, Context, Key, ScrollArea, TextEdit, Ui},
+    egui::{Button, Color32, Context, Key, ScrollArea, TextEdit, Ui},
 };
 use navigation::Page;
+use plascad::sequence::Nucleotide;
 use url::Url;
 
-use crate::{
-    file_io::save::{save, StateToSave, DEFAULT_SAVE_FILE},
-    gui::primer_qc::primer_details,
-    sequence::Nucleotide,
-    util, State,
-};
+use crate::{gui::primer_qc::primer_details, State};
 
+pub mod assembly;
 mod circle;
+pub mod digest;
+mod enzymes;
 mod feature_overlay;
 mod features;
+pub mod golden_gate;
 mod metadata;
+pub mod mutagenesis;
 pub mod navigation;
+pub mod off_target;
 mod pcr;
 mod portions;
 mod primer_arrow;
+pub mod primer_design;
 pub mod primer_qc;
 mod save;
 pub mod seq_view;
@@ -39,15 +40,30 @@ pub const COL_SPACING: f32 = 30.;
 // todo: Move this BLAST stuff A/R.
 const NCBI_BLAST_URL: &str = "https://blast.ncbi.nlm.nih.gov/Blast.cgi";
 
-pub fn int_field(val: &mut usize, label: &str, ui: &mut Ui) {
+/// Edit an integer value, eg a range bound. Returns if it changed.
+pub fn int_field(val: &mut usize, label: &str, ui: &mut Ui) -> bool {
     ui.label(label);
     let mut entry = val.to_string();
-    if ui
+    let changed = ui
         .add(TextEdit::singleline(&mut entry).desired_width(40.))
-        .changed()
-    {
+        .changed();
+    if changed {
         *val = entry.parse().unwrap_or(0);
     }
+    changed
+}
+
+/// Edit a float value, eg a Tm bound. Returns if it changed.
+pub fn float_field(val: &mut f32, label: &str, ui: &mut Ui) -> bool {
+    ui.label(label);
+    let mut entry = val.to_string();
+    let changed = ui
+        .add(TextEdit::singleline(&mut entry).desired_width(32.))
+        .changed();
+    if changed {
+        *val = entry.parse().unwrap_or(0.);
+    }
+    changed
 }
 
 /// Get a text-representation of the cursor index; a slightly processed version of the raw index.
@@ -83,7 +99,7 @@ pub fn get_cursor_text(cursor_seq_i: Option<usize>, seq_len: usize) -> String {
 /// GCCTGCGTGAGATTCTCGCATGCCAGAGATCCTATTTTTGGCAATCAAATCATTCCGGATACTGCGATTTTAAGTGTTGTTCCATTCCATCACGGTTTTGGAA
 /// TGTTTACTACACTCGGATATTTGATATGTGGATTTCGAGTCGTCTTAATGTATAGAT
 /// todo: Copy to clipboard  for longer seqs?
-fn open_blast(seq: &[Nucleotide]) {
+fn open_blast(_seq: &[Nucleotide]) {
     let params = vec![
         ("PAGE_TYPE", "BlastSearch"),
         ("CMD", "Web"),
@@ -112,6 +128,16 @@ fn open_blast(seq: &[Nucleotide]) {
     }
 }
 
+/// Change the origin. This involves updating the sequence, and all features.
+fn change_origin(state: &mut State) {
+    // Note the 1-based indexing logic we use.
+    state.generic.rotate(state.ui.new_origin);
+
+    // todo: What else to update?
+    state.sync_seq_related(None);
+    state.commit("Set origin");
+}
+
 /// Handle an origin change.
 fn origin_change(state: &mut State, ui: &mut Ui) {
     if ui.button("Set origin").clicked() {
@@ -130,30 +156,162 @@ fn origin_change(state: &mut State, ui: &mut Ui) {
             }
 
             if ui.button("Set").clicked() {
-                util::change_origin(state);
+                change_origin(state);
             }
         });
     }
 }
 
+/// Tabs for switching between documents in the workspace, and opening and closing them.
+fn document_tabs(state: &mut State, ui: &mut Ui) {
+    let mut switch = None;
+    let mut close = None;
+
+    ui.horizontal(|ui| {
+        for (i, name) in state.document_names().into_iter().enumerate() {
+            if ui
+                .selectable_label(i == state.workspace.active, name)
+                .clicked()
+            {
+                switch = Some(i);
+            }
+            if ui
+                .small_button("×")
+                .on_hover_text("Close this document")
+                .clicked()
+            {
+                close = Some(i);
+            }
+
+            ui.add_space(COL_SPACING / 2.);
+        }
+
+        if ui
+            .button("➕ New")
+            .on_hover_text("Open a new, empty document.")
+            .clicked()
+        {
+            state.open_document(Default::default(), Default::default());
+        }
+    });
+
+    if let Some(i) = switch {
+        state.switch_document(i);
+    }
+    if let Some(i) = close {
+        state.close_document(i);
+    }
+}
+
+/// Undo and redo buttons, and a menu listing the history.
+fn history_section(state: &mut State, ui: &mut Ui) {
+    let undo_text = match state.history.undo.last() {
+        Some(entry) => format!("Undo {}. Ctrl + Z", entry.label),
+        None => "Nothing to undo".to_owned(),
+    };
+    if ui
+        .add_enabled(!state.history.undo.is_empty(), Button::new("⟲ Undo"))
+        .on_hover_text(undo_text)
+        .clicked()
+    {
+        state.undo();
+    }
+
+    let redo_text = match state.history.redo.last() {
+        Some(entry) => format!("Redo {}. Ctrl + Shift + Z", entry.label),
+        None => "Nothing to redo".to_owned(),
+    };
+    if ui
+        .add_enabled(!state.history.redo.is_empty(), Button::new("⟳ Redo"))
+        .on_hover_text(redo_text)
+        .clicked()
+    {
+        state.redo();
+    }
+
+    ui.menu_button("History", |ui| {
+        ui.checkbox(&mut state.save_history, "Save history with the file");
+        ui.separator();
+
+        // Clicking an action returns the document to its state after that action.
+        let num_undo = state.history.undo.len();
+        let num_redo = state.history.redo.len();
+        let mut undo_count = 0;
+        let mut redo_count = 0;
+
+        ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+            if ui.selectable_label(num_undo == 0, "Start").clicked() {
+                undo_count = num_undo;
+            }
+
+            for (i, entry) in state.history.undo.iter().enumerate() {
+                if ui
+                    .selectable_label(i + 1 == num_undo, &entry.label)
+                    .clicked()
+                {
+                    undo_count = num_undo - i - 1;
+                }
+            }
+
+            // The redo stack's last entry is the next action to redo.
+            for (i, entry) in state.history.redo.iter().enumerate().rev() {
+                let label = egui::RichText::new(&entry.label).color(Color32::GRAY);
+                if ui.selectable_label(false, label).clicked() {
+                    redo_count = num_redo - i;
+                }
+            }
+        });
+
+        for _ in 0..undo_count {
+            state.undo();
+        }
+        for _ in 0..redo_count {
+            state.redo();
+        }
+    });
+}
+
 pub fn draw(state: &mut State, ctx: &Context) {
+    // Text fields handle their own undo shortcuts while focused.
+    let text_focused = ctx.memory(|m| m.focused().is_some());
+
+    let (mut undo, mut redo, mut save) = (false, false, false);
+
     ctx.input(|ip| {
         if ip.key_pressed(Key::A) && ip.modifiers.ctrl {
             state.generic.primers.push(Default::default());
+            state.commit("Add primer");
         }
 
-        if ip.key_pressed(Key::S) && ip.modifiers.ctrl {
-            if let Err(e) = save(
-                &PathBuf::from(DEFAULT_SAVE_FILE),
-                &StateToSave::from_state(state),
-            ) {
-                println!("Error saving: {e}");
+        if !text_focused && ip.modifiers.command {
+            if ip.key_pressed(Key::Z) {
+                if ip.modifiers.shift {
+                    redo = true;
+                } else {
+                    undo = true;
+                }
+            } else if ip.key_pressed(Key::Y) {
+                redo = true;
             }
         }
 
+        if ip.key_pressed(Key::S) && ip.modifiers.ctrl {
+            save = true;
+        }
+
         state.ui.cursor_pos = ip.pointer.hover_pos().map(|pos| (pos.x, pos.y));
     });
 
+    if undo {
+        state.undo();
+    }
+    if redo {
+        state.redo();
+    }
+    if save {
+        save::save_default(state);
+    }
+
     egui::CentralPanel::default().show(ctx, |ui| {
         // todo: This section DRY with seq viewx.
 
@@ -162,15 +320,25 @@ pub fn draw(state: &mut State, ctx: &Context) {
         visuals.override_text_color = Some(Color32::LIGHT_GRAY);
         ctx.set_visuals(visuals);
 
+        document_tabs(state, ui);
+
+        ui.add_space(ROW_SPACING / 2.);
+
         ui.horizontal(|ui| {
             navigation::page_selector(state, ui);
 
             ui.add_space(COL_SPACING);
 
             ui.label("Name: ");
-            ui.add(
-                TextEdit::singleline(&mut state.generic.metadata.plasmid_name).desired_width(160.),
-            );
+            if ui
+                .add(
+                    TextEdit::singleline(&mut state.generic.metadata.plasmid_name)
+                        .desired_width(160.),
+                )
+                .changed()
+            {
+                state.history.mark_edited();
+            }
         });
 
         ui.add_space(ROW_SPACING / 2.);
@@ -186,8 +354,14 @@ pub fn draw(state: &mut State, ctx: &Context) {
             }
 
             origin_change(state, ui);
+
+            ui.add_space(COL_SPACING);
+
+            history_section(state, ui);
         });
 
+        save::file_report(state, ui);
+
         ui.add_space(ROW_SPACING);
 
         ScrollArea::vertical().show(ui, |ui| match state.ui.page {
@@ -196,9 +370,19 @@ pub fn draw(state: &mut State, ctx: &Context) {
             Page::Features => features::features_page(state, ui),
             Page::Primers => primer_details(state, ui),
             Page::Pcr => pcr::pcr_page(state, ui),
-            Page::Metadata => metadata::metadata_page(&mut state.generic.metadata, ui),
-            _ => (),
-            // Page::Portions => portions::portions_page(state, ui),
+            Page::Metadata => {
+                if metadata::metadata_page(&mut state.generic.metadata, ui) {
+                    state.history.mark_edited();
+                }
+            }
+            Page::Portions => portions::portions_page(state, ui),
+            Page::Enzymes => enzymes::enzymes_page(state, ui),
         });
     });
+
+    // Record edits made this frame that weren't recorded explicitly, eg typing in a feature's label.
+    // Consecutive edits on the same page are merged. This is a no-op unless a field was marked as
+    // edited.
+    let label = format!("Edit {}", state.ui.page.to_string().to_lowercase());
+    state.history.sync(&label, &state.generic);
 }
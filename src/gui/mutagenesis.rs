@@ -0,0 +1,356 @@
+//! GUI code for site-directed mutagenesis: Choosing a change to the active document, by range or by
+//! codon, and showing designed primers and the mutated construct.
+
+use std::ops::Range;
+
+use eframe::egui::{Button, Color32, ComboBox, Grid, RichText, TextEdit, Ui, Vec2};
+use plascad::{
+    mutagenesis::{
+        codon_mutation, design_mutagenesis, saturation_mutations, CodonRandomization,
+        MutagenesisDesign, MutagenesisError, MutagenesisStrategy, MutationKind,
+    },
+    primer::Primer,
+    protein::{translate_feature, AminoAcid, StopHandling},
+    sequence::{seq_from_str, seq_to_str, FeatureType},
+};
+
+use crate::{
+    gui::{assembly::tm_text, float_field, int_field, COL_SPACING, ROW_SPACING},
+    State,
+};
+
+fn mutation_edit(state: &mut State, ui: &mut Ui) {
+    let mg = &mut state.ui.mutagenesis;
+
+    ui.horizontal(|ui| {
+        ComboBox::from_id_source("mutation_kind")
+            .width(100.)
+            .selected_text(mg.mutation.kind.to_string())
+            .show_ui(ui, |ui| {
+                for kind in [
+                    MutationKind::Substitution,
+                    MutationKind::Insertion,
+                    MutationKind::Deletion,
+                ] {
+                    ui.selectable_value(&mut mg.mutation.kind, kind, kind.to_string());
+                }
+            });
+
+        let range = &mut mg.mutation.range;
+        if mg.mutation.kind == MutationKind::Insertion {
+            int_field(&mut range.start, "Position:", ui);
+            range.end = range.start;
+        } else {
+            int_field(&mut range.start, "Start:", ui);
+            int_field(&mut range.end, "End:", ui);
+        }
+
+        if mg.mutation.kind != MutationKind::Deletion {
+            ui.label("New nucleotides:");
+            ui.add(TextEdit::singleline(&mut mg.seq_input).desired_width(200.));
+        }
+    });
+}
+
+/// Set the mutation from a codon in a coding region feature. Returns true if a saturation library
+/// at the codon is requested.
+fn codon_edit(state: &mut State, ui: &mut Ui) -> bool {
+    let mut library = false;
+
+    ui.horizontal(|ui| {
+        ui.label("By codon:").on_hover_text(
+            "Set the change from an amino acid position in a coding region. Substitutions use the \
+        codon for the new amino acid most similar to the current one; insertions go before the position.",
+        );
+
+        let mg = &mut state.ui.mutagenesis;
+        let feature = mg.feature.and_then(|i| state.generic.features.get(i));
+
+        ComboBox::from_id_source("mutagenesis_feature")
+            .width(120.)
+            .selected_text(feature.map(|f| f.label.clone()).unwrap_or_default())
+            .show_ui(ui, |ui| {
+                for (i, f) in state.generic.features.iter().enumerate() {
+                    if f.feature_type == FeatureType::CodingRegion {
+                        ui.selectable_value(&mut mg.feature, Some(i), &f.label);
+                    }
+                }
+            });
+
+        int_field(&mut mg.aa_pos, "Amino acid:", ui);
+
+        let current = feature
+            .and_then(|f| {
+                translate_feature(
+                    f,
+                    &state.generic.seq,
+                    state.genetic_code,
+                    StopHandling::Include,
+                )
+            })
+            .and_then(|protein| protein.get(mg.aa_pos.wrapping_sub(1)).copied());
+        if let Some(aa) = current {
+            ui.label(format!("({})", aa.to_str_3_letter()));
+        }
+
+        ui.label("New:");
+        ui.add(TextEdit::singleline(&mut mg.aa_input).desired_width(20.));
+
+        let aa = mg
+            .aa_input
+            .trim()
+            .bytes()
+            .next()
+            .and_then(AminoAcid::from_letter);
+        let ready =
+            feature.is_some() && (aa.is_some() || mg.mutation.kind == MutationKind::Deletion);
+
+        if ui.add_enabled(ready, Button::new("Set")).clicked() {
+            let mutation = feature.and_then(|f| {
+                codon_mutation(
+                    f,
+                    &state.generic.seq,
+                    mg.aa_pos,
+                    mg.mutation.kind,
+                    aa.unwrap_or(AminoAcid::Unknown),
+                    state.genetic_code,
+                )
+            });
+
+            if let Some(mutation) = mutation {
+                mg.seq_input = seq_to_str(&mutation.seq).to_uppercase();
+                mg.mutation = mutation;
+            }
+        }
+        ui.add_space(COL_SPACING);
+
+        ComboBox::from_id_source("mutagenesis_randomization")
+            .width(140.)
+            .selected_text(mg.randomization.to_string())
+            .show_ui(ui, |ui| {
+                for scheme in [
+                    CodonRandomization::Nnk,
+                    CodonRandomization::Nns,
+                    CodonRandomization::TwentyTwoC,
+                ] {
+                    ui.selectable_value(&mut mg.randomization, scheme, scheme.to_string());
+                }
+            });
+
+        library = ui
+            .add_enabled(feature.is_some(), Button::new("Design library"))
+            .on_hover_text(
+                "Design degenerate primers that randomize this codon, for a saturation mutagenesis \
+            library.",
+            )
+            .clicked();
+    });
+
+    library
+}
+
+fn params_edit(state: &mut State, ui: &mut Ui) {
+    let params = &mut state.ui.mutagenesis.params;
+    ui.horizontal(|ui| {
+        ui.label("Primers:");
+        ComboBox::from_id_source("mutagenesis_strategy")
+            .width(180.)
+            .selected_text(params.strategy.to_string())
+            .show_ui(ui, |ui| {
+                for strategy in [
+                    MutagenesisStrategy::BackToBack,
+                    MutagenesisStrategy::Overlapping,
+                ] {
+                    ui.selectable_value(&mut params.strategy, strategy, strategy.to_string());
+                }
+            });
+        ui.add_space(COL_SPACING);
+
+        int_field(&mut params.max_anneal_len, "Anneal len max:", ui);
+        float_field(&mut params.anneal_tm, "Anneal Tm:", ui);
+    });
+}
+
+/// The primers of each design, with their portion of the primer mix. Back-to-back saturation libraries
+/// share the reverse primer, so identical primers are combined.
+fn library_primers(
+    designs: &[(MutagenesisDesign, f32)],
+) -> Vec<(&Primer, &Range<usize>, Option<f32>, f32)> {
+    let mut result: Vec<(&Primer, &Range<usize>, Option<f32>, f32)> = Vec::new();
+
+    for (design, mix) in designs {
+        for (primer, change, tm) in [
+            (&design.fwd, &design.fwd_change, design.fwd_anneal_tm),
+            (&design.rev, &design.rev_change, design.rev_anneal_tm),
+        ] {
+            match result
+                .iter_mut()
+                .find(|(p, ..)| p.sequence == primer.sequence)
+            {
+                Some(existing) => existing.3 += mix,
+                None => result.push((primer, change, tm, *mix)),
+            }
+        }
+    }
+
+    result
+}
+
+fn design_disp(designs: &[(MutagenesisDesign, f32)], ui: &mut Ui) {
+    let Some((design, _)) = designs.first() else {
+        return;
+    };
+    let library = designs.len() > 1;
+
+    ui.heading("Primers").on_hover_text(
+        "Mutated nucleotides are uppercase. Tm is of the annealing region; for overlapping primers, \
+    of the lower flank. Mix is each primer's portion, for libraries that combine several.",
+    );
+
+    Grid::new("mutagenesis_primers")
+        .spacing(Vec2::new(COL_SPACING, 4.))
+        .striped(true)
+        .show(ui, |ui| {
+            for label in ["Name", "Sequence (5' ⏵ 3')", "Len", "Tm (°C)"] {
+                ui.label(RichText::new(label).strong());
+            }
+            if library {
+                ui.label(RichText::new("Mix").strong());
+            }
+            ui.end_row();
+
+            for (primer, change, tm, mix) in library_primers(designs) {
+                let seq = seq_to_str(&primer.sequence);
+                let seq = format!(
+                    "{}{}{}",
+                    &seq[..change.start],
+                    seq[change.clone()].to_uppercase(),
+                    &seq[change.end..]
+                );
+
+                ui.label(&primer.name);
+                ui.label(RichText::new(seq).monospace());
+                ui.label(primer.sequence.len().to_string());
+                ui.label(tm_text(tm));
+                if library {
+                    ui.label(format!("{:.0}%", mix * 100.));
+                }
+                ui.end_row();
+            }
+        });
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    for (label, damage) in &design.report.features {
+        ui.label(RichText::new(format!("{label} is {damage}")).color(Color32::GOLD));
+    }
+
+    ui.label(format!(
+        "Mutated construct: {} bp",
+        design.product.seq.len()
+    ));
+}
+
+/// Design primers that introduce a substitution, insertion, or deletion in the active document, and
+/// preview the mutated construct.
+pub fn mutagenesis_page(state: &mut State, ui: &mut Ui) {
+    ui.heading("Site-directed mutagenesis");
+    ui.label(
+        "Change a range of the sequence, or a codon. Primers amplify the whole plasmid: Back-to-back \
+    primers carry the change in their 5' tails; overlapping ones carry it in their center.",
+    );
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    mutation_edit(state, ui);
+    let library = codon_edit(state, ui);
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    params_edit(state, ui);
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    if ui.button("Design").clicked() {
+        let mg = &mut state.ui.mutagenesis;
+        mg.mutation.seq = seq_from_str(mg.seq_input.trim());
+        mg.result = Some(
+            design_mutagenesis(
+                &state.generic,
+                &mg.mutation,
+                &mg.params,
+                &state.ion_concentrations,
+            )
+            .map(|design| vec![(design, 1.)]),
+        );
+    }
+
+    if library {
+        let mg = &mut state.ui.mutagenesis;
+        let mutations = mg.feature.and_then(|i| {
+            saturation_mutations(
+                &state.generic.features[i],
+                &state.generic.seq,
+                mg.aa_pos,
+                mg.randomization,
+            )
+        });
+
+        mg.result = Some(match mutations {
+            Some(mutations) => mutations
+                .into_iter()
+                .map(|(mutation, mix)| {
+                    design_mutagenesis(
+                        &state.generic,
+                        &mutation,
+                        &mg.params,
+                        &state.ion_concentrations,
+                    )
+                    .map(|design| (design, mix))
+                })
+                .collect(),
+            None => Err(MutagenesisError::InvalidRange),
+        });
+    }
+
+    let mut add_primers = false;
+    let mut open = false;
+    match &state.ui.mutagenesis.result {
+        Some(Ok(designs)) => {
+            ui.add_space(ROW_SPACING / 2.);
+
+            design_disp(designs, ui);
+
+            ui.horizontal(|ui| {
+                add_primers = ui
+                    .button("Add primers")
+                    .on_hover_text("Add these primers to this document's primer list.")
+                    .clicked();
+                open = ui
+                    .button("Open mutated construct as document")
+                    .on_hover_text("Open the mutated sequence, with its features and the primers.")
+                    .clicked();
+            });
+        }
+        Some(Err(e)) => {
+            ui.label(RichText::new(e.to_string()).color(Color32::LIGHT_RED));
+        }
+        None => (),
+    }
+
+    if let Some(Ok(designs)) = &state.ui.mutagenesis.result {
+        if add_primers {
+            let primers: Vec<Primer> = library_primers(designs)
+                .into_iter()
+                .map(|(p, ..)| p.clone())
+                .collect();
+            state.generic.primers.extend(primers);
+            state.sync_primer_matches(None);
+            state.commit("Add mutagenesis primers");
+        } else if let (true, Some((design, _))) = (open, designs.first()) {
+            // For libraries, the degenerate construct is the first design's.
+            let product = design.product.clone();
+            state.open_document(product, Default::default());
+        }
+    }
+}
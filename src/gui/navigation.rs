@@ -8,10 +8,11 @@ use crate::{gui::COL_SPACING, State};
 
 pub const NAV_BUTTON_COLOR: Color32 = Color32::from_rgb(0, 00, 110);
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Default)]
 pub enum Page {
     /// Primer design and QC, including for cloning
     /// (Replacement name: Sequence?
+    #[default]
     Sequence,
     /// A circular "graphical map" of the plasmid
     Map,
@@ -19,15 +20,10 @@ pub enum Page {
     Primers,
     /// Determine optimal PCR parameters
     Pcr,
+    #[allow(dead_code)] // todo: Re-enable once this page has content.
     Portions,
     Metadata,
-    // Enzymes,
-}
-
-impl Default for Page {
-    fn default() -> Self {
-        Self::Sequence
-    }
+    Enzymes,
 }
 
 impl Display for Page {
@@ -40,6 +36,7 @@ impl Display for Page {
             Self::Primers => "Primers",
             Self::Portions => "Mixing portions",
             Self::Metadata => "Data",
+            Self::Enzymes => "Enzymes",
         }
         .to_owned();
         write!(f, "{}", str)
@@ -54,6 +51,7 @@ pub fn page_selector(state: &mut State, ui: &mut Ui) {
         page_button(&mut state.ui.page, Page::Primers, ui, true);
         page_button(&mut state.ui.page, Page::Pcr, ui, true);
         page_button(&mut state.ui.page, Page::Metadata, ui, true);
+        page_button(&mut state.ui.page, Page::Enzymes, ui, true);
         // page_button(&mut state.ui.page, Page::Portions, ui);
     });
 }
@@ -82,24 +80,27 @@ pub fn page_button<T: PartialEq + ToString>(page_state: &mut T, page: T, ui: &mu
 }
 
 /// This is used for selecting what is displayed in the sequence view, ie view or edit.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Default)]
 pub enum PageSeq {
     EditSeq,
     EditSlic,
+    Assembly,
+    GoldenGate,
+    Mutagenesis,
+    Digest,
+    #[default]
     View,
 }
 
-impl Default for PageSeq {
-    fn default() -> Self {
-        Self::View
-    }
-}
-
 impl Display for PageSeq {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
             Self::EditSeq => "Edit sequence",
             Self::EditSlic => "SLIC/FC cloning",
+            Self::Assembly => "Gibson assembly",
+            Self::GoldenGate => "Golden Gate",
+            Self::Mutagenesis => "Mutagenesis",
+            Self::Digest => "Digest",
             Self::View => "View sequence",
         }
         .to_owned();
@@ -112,23 +113,22 @@ pub fn page_seq_selector(state: &mut State, ui: &mut Ui) {
         page_button(&mut state.ui.page_seq, PageSeq::EditSeq, ui, true);
         page_button(&mut state.ui.page_seq, PageSeq::View, ui, true);
         page_button(&mut state.ui.page_seq, PageSeq::EditSlic, ui, true);
+        page_button(&mut state.ui.page_seq, PageSeq::Assembly, ui, true);
+        page_button(&mut state.ui.page_seq, PageSeq::GoldenGate, ui, true);
+        page_button(&mut state.ui.page_seq, PageSeq::Mutagenesis, ui, true);
+        page_button(&mut state.ui.page_seq, PageSeq::Digest, ui, true);
     });
 }
 
 /// This is used for selecting what is displayed above the sequence view, ie various tabular editors.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Default)]
 pub enum PageSeqTop {
     Primers,
     Features,
+    #[default]
     None,
 }
 
-impl Default for PageSeqTop {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl Display for PageSeqTop {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
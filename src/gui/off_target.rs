@@ -0,0 +1,309 @@
+//! GUI code for off-target priming analysis: Choosing background sequences, and showing sites and
+//! products found.
+
+use eframe::egui::{Color32, Grid, RichText, ScrollArea, Ui, Vec2};
+use plascad::{
+    file_io::error::FileReport,
+    off_target::{find_off_target, load_background, OffTargetReport, PrimerSite, TargetSeq},
+    primer::PrimerDirection,
+};
+
+use crate::{
+    gui::{int_field, save::set_report, COL_SPACING, ROW_SPACING},
+    State,
+};
+
+/// Don't list more sites than this; a primer may bind many weak sites in a genome.
+const MAX_SITES_DISP: usize = 200;
+
+/// Off-target sites more stable than this, in kcal/mol, are likely to prime.
+const DG_THRESH_BAD: f32 = -12.;
+
+const COLOR_INTENDED: Color32 = Color32::GREEN;
+const COLOR_WEAK: Color32 = Color32::GOLD;
+const COLOR_STRONG: Color32 = Color32::LIGHT_RED;
+
+/// The result of the most recent analysis, with names for display; primers and documents may have
+/// changed since.
+pub struct OffTargetResult {
+    pub report: OffTargetReport,
+    pub primer_names: Vec<String>,
+    /// The construct's name, followed by each background sequence's.
+    pub target_names: Vec<String>,
+}
+
+impl OffTargetResult {
+    fn target_name(&self, target: Option<usize>) -> &str {
+        let i = target.map(|i| i + 1).unwrap_or(0);
+        &self.target_names[i]
+    }
+}
+
+fn run_analysis(state: &mut State) {
+    let construct = TargetSeq {
+        name: state.generic.metadata.plasmid_name.clone(),
+        seq: state.generic.seq.clone(),
+        topology: state.generic.topology,
+    };
+
+    // The active document's slot in the workspace is empty; it's the construct.
+    let mut background: Vec<TargetSeq> = state
+        .workspace
+        .documents
+        .iter()
+        .zip(&state.ui.off_target.documents)
+        .enumerate()
+        .filter(|(i, (_, selected))| **selected && *i != state.workspace.active)
+        .map(|(_, (doc, _))| TargetSeq {
+            name: doc.generic.metadata.plasmid_name.clone(),
+            seq: doc.generic.seq.clone(),
+            topology: doc.generic.topology,
+        })
+        .collect();
+    background.extend(state.ui.off_target.files.iter().cloned());
+
+    let report = find_off_target(
+        &state.generic.primers,
+        &construct,
+        &background,
+        &state.ion_concentrations,
+        &state.ui.off_target.params,
+    );
+
+    state.ui.off_target.result = Some(OffTargetResult {
+        report,
+        primer_names: state
+            .generic
+            .primers
+            .iter()
+            .map(|p| p.name.clone())
+            .collect(),
+        target_names: std::iter::once(construct.name)
+            .chain(background.into_iter().map(|t| t.name))
+            .collect(),
+    });
+}
+
+/// Select background sequences: Other documents in the workspace, and files.
+fn background_edit(state: &mut State, ui: &mut Ui) {
+    let names = state.document_names();
+    state.ui.off_target.documents.resize(names.len(), false);
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Background documents:");
+        for (i, name) in names.iter().enumerate() {
+            if i == state.workspace.active {
+                continue;
+            }
+            ui.checkbox(&mut state.ui.off_target.documents[i], name);
+        }
+    });
+
+    ui.horizontal_wrapped(|ui| {
+        if ui
+            .button("Add background file")
+            .on_hover_text(
+                "Load sequences to check primers against, eg a host genome. Each FASTA record \
+            is a separate sequence.",
+            )
+            .clicked()
+        {
+            state.ui.file_dialogs.load_background.select_file();
+        }
+
+        let mut remove = None;
+        for (i, target) in state.ui.off_target.files.iter().enumerate() {
+            ui.label(format!("{} ({} bp)", target.name, target.seq.len()));
+            if ui.small_button("×").clicked() {
+                remove = Some(i);
+            }
+        }
+        if let Some(i) = remove {
+            state.ui.off_target.files.remove(i);
+        }
+    });
+}
+
+fn site_row(site: &PrimerSite, result: &OffTargetResult, ui: &mut Ui) {
+    let m = &site.site;
+
+    let color = if site.intended {
+        COLOR_INTENDED
+    } else if site.dg.map(|dg| dg < DG_THRESH_BAD).unwrap_or(false) {
+        COLOR_STRONG
+    } else {
+        COLOR_WEAK
+    };
+
+    let dir = match m.direction {
+        PrimerDirection::Forward => "Fwd",
+        PrimerDirection::Reverse => "Rev",
+    };
+    let mismatches: Vec<String> = m.mismatches.iter().map(|i| (i + 1).to_string()).collect();
+
+    ui.label(RichText::new(&result.primer_names[site.primer]).color(color));
+    ui.label(result.target_name(site.target));
+    ui.label(m.range.to_string());
+    ui.label(dir);
+    ui.label(m.tail_len.to_string());
+    ui.label(mismatches.join(", "));
+    ui.label(match m.tm {
+        Some(tm) => format!("{tm:.1}"),
+        None => "-".to_owned(),
+    });
+    ui.label(
+        RichText::new(match site.dg {
+            Some(dg) => format!("{dg:.1}"),
+            None => "-".to_owned(),
+        })
+        .color(color),
+    );
+    ui.label(if site.intended { "Intended" } else { "" });
+
+    ui.end_row();
+}
+
+fn result_disp(result: &OffTargetResult, ui: &mut Ui) {
+    let report = &result.report;
+    let num_off_target = report.off_target_sites().count();
+
+    let color = if num_off_target == 0 && report.products.is_empty() {
+        COLOR_INTENDED
+    } else if report.products.is_empty() {
+        COLOR_WEAK
+    } else {
+        COLOR_STRONG
+    };
+    ui.label(
+        RichText::new(format!(
+            "{num_off_target} off-target sites, {} unintended products",
+            report.products.len()
+        ))
+        .color(color),
+    );
+
+    ui.add_space(ROW_SPACING / 2.);
+
+    if !report.products.is_empty() {
+        ui.heading("Unintended products");
+        Grid::new("off_target_products")
+            .spacing(Vec2::new(COL_SPACING, 4.))
+            .striped(true)
+            .show(ui, |ui| {
+                for label in [
+                    "Fwd primer",
+                    "Rev primer",
+                    "Sequence",
+                    "Range",
+                    "Length (bp)",
+                ] {
+                    ui.label(RichText::new(label).strong());
+                }
+                ui.end_row();
+
+                for product in &report.products {
+                    ui.label(&result.primer_names[product.primer_fwd]);
+                    ui.label(&result.primer_names[product.primer_rev]);
+                    ui.label(result.target_name(product.target));
+                    ui.label(product.range.to_string());
+                    ui.label(product.len.to_string());
+                    ui.end_row();
+                }
+            });
+
+        ui.add_space(ROW_SPACING / 2.);
+    }
+
+    ui.heading("Sites").on_hover_text(
+        "Sites primers may bind, most stable first. Mismatches are positions in the primer, from its \
+    5' end. ΔG is at 37°C, in kcal/mol.",
+    );
+    ScrollArea::vertical()
+        .id_source("off_target_sites")
+        .max_height(400.)
+        .show(ui, |ui| {
+            Grid::new("off_target_sites")
+                .spacing(Vec2::new(COL_SPACING, 4.))
+                .striped(true)
+                .show(ui, |ui| {
+                    for label in [
+                        "Primer",
+                        "Sequence",
+                        "Range",
+                        "Dir",
+                        "Tail",
+                        "Mismatches",
+                        "Tm (°C)",
+                        "ΔG",
+                        "",
+                    ] {
+                        ui.label(RichText::new(label).strong());
+                    }
+                    ui.end_row();
+
+                    for site in report.sites.iter().take(MAX_SITES_DISP) {
+                        site_row(site, result, ui);
+                    }
+                });
+
+            if report.sites.len() > MAX_SITES_DISP {
+                ui.label(format!(
+                    "{} more, less stable sites not shown.",
+                    report.sites.len() - MAX_SITES_DISP
+                ));
+            }
+        });
+}
+
+/// Find sites primers bind other than the ones they're designed for, on this sequence and background
+/// sequences, and products they may amplify.
+pub fn off_target_section(state: &mut State, ui: &mut Ui) {
+    ui.collapsing("Off-target priming", |ui| {
+        ui.label(
+            "Find sites the primers may bind other than their intended ones, on this sequence and on \
+        background sequences, and unintended products pairs of these sites may amplify.",
+        );
+
+        background_edit(state, ui);
+
+        ui.horizontal(|ui| {
+            let binding = &mut state.ui.off_target.params.binding;
+            int_field(&mut binding.min_anneal_len, "Min anneal len:", ui);
+            int_field(&mut binding.exact_3p_len, "Exact 3' len:", ui);
+            int_field(&mut binding.max_mismatches, "Max mismatches:", ui);
+            int_field(
+                &mut state.ui.off_target.params.max_product_len,
+                "Max product len:",
+                ui,
+            );
+
+            ui.add_space(COL_SPACING);
+
+            if ui
+                .button("Analyze")
+                .on_hover_text("Search both strands of each sequence. This may take a while for genomes.")
+                .clicked()
+            {
+                run_analysis(state);
+            }
+        });
+
+        if let Some(result) = &state.ui.off_target.result {
+            ui.add_space(ROW_SPACING / 2.);
+            result_disp(result, ui);
+        }
+    });
+
+    let dialog = &mut state.ui.file_dialogs.load_background;
+    dialog.update(ui.ctx());
+
+    if let Some(path) = dialog.take_selected() {
+        let action = format!("Loading background {}", path.display());
+        let (report, loaded) = FileReport::from_import(&action, load_background(&path));
+        set_report(state, report);
+
+        if let Some(targets) = loaded {
+            state.ui.off_target.files.extend(targets);
+        }
+    }
+}
@@ -1,16 +1,18 @@
 use std::str::FromStr;
 
 use eframe::egui::{Color32, ComboBox, Grid, RichText, TextEdit, Ui, Vec2};
+use plascad::{
+    pcr::{PolymeraseType, TempTime},
+    primer::TM_TARGET,
+};
 
 use crate::{
     gui::{COL_SPACING, ROW_SPACING},
-    pcr::{PolymeraseType, TempTime},
-    primer::TM_TARGET,
     PcrUi, State,
 };
 
 fn temp_time_disp(tt: &TempTime, label: &str, ui: &mut Ui) {
-    ui.label(&format!("{label}:"));
+    ui.label(format!("{label}:"));
     ui.label(RichText::new(format!("{}°C", tt.temp)).color(Color32::LIGHT_BLUE));
     ui.label(RichText::new(format!("{}s", tt.time)).color(Color32::LIGHT_BLUE));
 
@@ -24,7 +26,7 @@ where
 {
     let mut changed = false;
 
-    ui.label(&format!("{}: ", label));
+    ui.label(format!("{}: ", label));
     let mut entry = val.to_string();
     let response = ui.add(TextEdit::singleline(&mut entry).desired_width(30.));
     if response.changed() {
@@ -4,7 +4,7 @@ use eframe::egui::Ui;
 
 use crate::{gui::ROW_SPACING, State};
 
-pub fn portions_page(state: &mut State, ui: &mut Ui) {
+pub fn portions_page(_state: &mut State, ui: &mut Ui) {
     ui.heading("Mixing portions");
 
     ui.add_space(ROW_SPACING);
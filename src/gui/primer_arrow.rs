@@ -2,15 +2,17 @@
 
 use std::ops::Range;
 
-use eframe::egui::{Pos2, Shape, Ui};
-
-use crate::{
-    gui::feature_overlay,
-    primer::{Primer, PrimerDirection},
+use eframe::egui::{
+    pos2, vec2, Align2, Color32, FontFamily, FontId, Pos2, Rect, Shape, Stroke, Ui,
+};
+use plascad::{
+    primer::{Primer, PrimerDirection, PrimerMatch},
     sequence::FeatureType,
     util,
 };
 
+use crate::gui::{feature_overlay, seq_view::NT_WIDTH_PX};
+
 pub const STROKE_WIDTH: f32 = 2.;
 
 pub const VERTICAL_OFFSET_PRIMER: f32 = 14.; // Number of pixels above the sequence text.
@@ -18,7 +20,13 @@ pub const LABEL_OFFSET: f32 = 7.;
 pub const HEIGHT: f32 = 16.;
 pub const SLANT: f32 = 12.; // slant different, in pixels, for the arrow.
 
-/// Add primer arrows to the display.
+/// Tails longer than this are drawn at this length.
+const TAIL_LEN_MAX_DRAWN: usize = 12;
+const COLOR_TAIL: Color32 = Color32::GRAY;
+const COLOR_MISMATCH: Color32 = Color32::from_rgba_premultiplied(160, 0, 0, 160);
+
+/// Add primer arrows to the display. The arrow covers the annealing region; 5' tails that don't anneal
+/// are drawn as a line leading away from it, and mismatches are highlighted.
 pub fn draw_primers(
     primers: &[Primer],
     row_ranges: &[Range<usize>],
@@ -32,14 +40,10 @@ pub fn draw_primers(
         let primer_matches = &primer.volatile.matches_seq;
 
         // todo: Do not run these calcs each time. Cache.
-        for (direction, seq_range) in primer_matches {
-            // We currently index primers relative to the end they started.
-            let seq_range = match direction {
-                PrimerDirection::Forward => seq_range.clone(),
-                PrimerDirection::Reverse => (seq_len - seq_range.end)..(seq_len - seq_range.start),
-            };
-
-            let feature_ranges = util::get_feature_ranges(&seq_range, row_ranges);
+        for primer_match in primer_matches {
+            let direction = primer_match.direction;
+            let seq_range = &primer_match.range;
+            let feature_ranges = util::get_feature_ranges(seq_range, row_ranges, seq_len);
 
             let feature_ranges_px: Vec<(Pos2, Pos2)> = feature_ranges
                 .iter()
@@ -51,13 +55,35 @@ pub fn draw_primers(
                 PrimerDirection::Reverse => (0, 255, 0),
             };
 
+            // Mismatches are drawn under the arrow.
+            for i in &primer_match.mismatches {
+                // 0-based index in the sequence, of the mismatched nucleotide.
+                let offset = i - primer_match.tail_len;
+                let seq_i = match direction {
+                    PrimerDirection::Forward => (seq_range.start - 1 + offset) % seq_len,
+                    PrimerDirection::Reverse => (seq_range.end - 1 + seq_len - offset) % seq_len,
+                };
+
+                let pos = seq_i_to_px_rel(seq_i);
+                let top_left = pos2(pos.x, pos.y - VERTICAL_OFFSET_PRIMER);
+                shapes.push(Shape::rect_filled(
+                    Rect::from_min_size(top_left, vec2(NT_WIDTH_PX, HEIGHT)),
+                    0.,
+                    COLOR_MISMATCH,
+                ));
+            }
+
+            if primer_match.tail_len > 0 {
+                shapes.append(&mut draw_tail(primer_match, seq_len, ui, &seq_i_to_px_rel));
+            }
+
             // todo: PUt back; temp check on compiling.
             shapes.append(&mut feature_overlay::feature_seq_overlay(
                 &feature_ranges_px,
                 FeatureType::Primer,
                 Some(color),
                 VERTICAL_OFFSET_PRIMER,
-                (*direction).into(),
+                direction.into(),
                 &primer.name,
                 ui,
             ));
@@ -65,3 +91,52 @@ pub fn draw_primers(
     }
     shapes
 }
+
+/// Draw a primer's 5' tail as a line leading away from its annealing region, since it isn't
+/// opposite the sequence. Labeled with its length.
+fn draw_tail(
+    primer_match: &PrimerMatch,
+    seq_len: usize,
+    ui: &mut Ui,
+    seq_i_to_px_rel: impl Fn(usize) -> Pos2,
+) -> Vec<Shape> {
+    let tail_px = primer_match.tail_len.min(TAIL_LEN_MAX_DRAWN) as f32 * NT_WIDTH_PX;
+
+    // The tail leaves from the 5' end: The left for forward primers, and the right for reverse ones.
+    let (start, end, align) = match primer_match.direction {
+        PrimerDirection::Forward => {
+            let pos = seq_i_to_px_rel((primer_match.range.start - 1) % seq_len);
+            let start = pos2(pos.x, pos.y - VERTICAL_OFFSET_PRIMER);
+            (
+                start,
+                pos2(start.x - tail_px, start.y - HEIGHT / 2.),
+                Align2::RIGHT_CENTER,
+            )
+        }
+        PrimerDirection::Reverse => {
+            let pos = seq_i_to_px_rel((primer_match.range.end - 1) % seq_len);
+            let start = pos2(pos.x + NT_WIDTH_PX, pos.y - VERTICAL_OFFSET_PRIMER + HEIGHT);
+            (
+                start,
+                pos2(start.x + tail_px, start.y + HEIGHT / 2.),
+                Align2::LEFT_CENTER,
+            )
+        }
+    };
+
+    let label = ui.ctx().fonts(|fonts| {
+        Shape::text(
+            fonts,
+            end,
+            align,
+            format!("+{}", primer_match.tail_len),
+            FontId::new(12., FontFamily::Proportional),
+            COLOR_TAIL,
+        )
+    });
+
+    vec![
+        Shape::line_segment([start, end], Stroke::new(STROKE_WIDTH, COLOR_TAIL)),
+        label,
+    ]
+}
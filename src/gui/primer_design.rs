@@ -0,0 +1,203 @@
+//! GUI code for designing primer pairs that amplify a target region.
+
+use eframe::egui::{Color32, ComboBox, Grid, RichText, Ui, Vec2};
+use plascad::{
+    primer::Primer,
+    primer_design::{design_primer_pairs, PrimerPair},
+    sequence::seq_to_str,
+};
+
+use crate::{
+    gui::{float_field, int_field, COL_SPACING, ROW_SPACING},
+    State,
+};
+
+fn params_edit(state: &mut State, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.label("Target:");
+
+        let selected = match state.ui.primer_design.feature {
+            Some(i) => state
+                .generic
+                .features
+                .get(i)
+                .map(|f| f.label.clone())
+                .unwrap_or_default(),
+            None => "Range".to_owned(),
+        };
+
+        let prev = state.ui.primer_design.feature;
+        ComboBox::from_id_source("primer_design_target")
+            .width(120.)
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.ui.primer_design.feature, None, "Range");
+                for (i, feature) in state.generic.features.iter().enumerate() {
+                    ui.selectable_value(
+                        &mut state.ui.primer_design.feature,
+                        Some(i),
+                        &feature.label,
+                    );
+                }
+            });
+
+        if state.ui.primer_design.feature != prev {
+            if let Some(feature) = state
+                .ui
+                .primer_design
+                .feature
+                .and_then(|i| state.generic.features.get(i))
+            {
+                state.ui.primer_design.params.target = feature.index_range;
+            }
+        }
+
+        let target = &mut state.ui.primer_design.params.target;
+        int_field(&mut target.start, "Start:", ui);
+        int_field(&mut target.end, "End:", ui);
+    });
+
+    let params = &mut state.ui.primer_design.params;
+    ui.horizontal(|ui| {
+        int_field(&mut params.product_len.0, "Product len min:", ui);
+        int_field(&mut params.product_len.1, "max:", ui);
+        ui.add_space(COL_SPACING);
+
+        int_field(&mut params.primer_len.0, "Primer len min:", ui);
+        int_field(&mut params.primer_len.1, "max:", ui);
+        ui.add_space(COL_SPACING);
+
+        float_field(&mut params.tm.0, "Tm min:", ui);
+        float_field(&mut params.tm.1, "max:", ui);
+        ui.add_space(COL_SPACING);
+
+        // Displayed as percentages.
+        let mut gc = (params.gc.0 * 100., params.gc.1 * 100.);
+        if float_field(&mut gc.0, "GC % min:", ui) | float_field(&mut gc.1, "max:", ui) {
+            params.gc = (gc.0 / 100., gc.1 / 100.);
+        }
+        ui.add_space(COL_SPACING);
+
+        float_field(&mut params.max_tm_diff, "Max Tm diff:", ui);
+    });
+}
+
+fn color_from_score(score: f32) -> Color32 {
+    if score > 0.8 {
+        Color32::GREEN
+    } else if score > 0.5 {
+        Color32::GOLD
+    } else {
+        Color32::LIGHT_RED
+    }
+}
+
+/// Show designed pairs. Returns the index of one to add to the primer list, if the user selected one.
+fn results_disp(pairs: &[PrimerPair], ui: &mut Ui) -> Option<usize> {
+    let mut result = None;
+
+    Grid::new("primer_design_results")
+        .spacing(Vec2::new(COL_SPACING / 2., 4.))
+        .striped(true)
+        .show(ui, |ui| {
+            for label in [
+                "",
+                "Forward (5' ⏵ 3')",
+                "Reverse (5' ⏵ 3')",
+                "Product",
+                "Len",
+                "Tm",
+                "Dimer ΔG",
+                "Amplicon ΔG",
+                "Score",
+                "",
+            ] {
+                ui.label(RichText::new(label).strong());
+            }
+            ui.end_row();
+
+            for (i, pair) in pairs.iter().enumerate() {
+                let tm = |p: &Primer| {
+                    p.volatile
+                        .metrics
+                        .as_ref()
+                        .map(|m| format!("{:.1}", m.melting_temp))
+                        .unwrap_or_default()
+                };
+                let dimer_dg = pair
+                    .hetero_dimer
+                    .end_3p
+                    .as_ref()
+                    .or(pair.hetero_dimer.any.as_ref())
+                    .map(|d| format!("{:.1}", d.dg))
+                    .unwrap_or_else(|| "-".to_owned());
+
+                ui.label((i + 1).to_string());
+                ui.label(RichText::new(seq_to_str(&pair.fwd.sequence)).monospace());
+                ui.label(RichText::new(seq_to_str(&pair.rev.sequence)).monospace());
+                ui.label(pair.product.to_string());
+                ui.label(pair.product_len.to_string());
+                ui.label(format!("{} / {}", tm(&pair.fwd), tm(&pair.rev)));
+                ui.label(RichText::new(dimer_dg).color(color_from_score(pair.dimer_score)));
+                ui.label(
+                    RichText::new(format!("{:.1}", pair.amplicon_dg))
+                        .color(color_from_score(pair.structure_score)),
+                );
+                ui.label(
+                    RichText::new(format!("{:.2}", pair.score)).color(color_from_score(pair.score)),
+                );
+                if ui
+                    .button("Add")
+                    .on_hover_text("Add this pair to the primer list.")
+                    .clicked()
+                {
+                    result = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+
+    result
+}
+
+/// Design primer pairs that amplify a target range or feature, and add a selected one.
+pub fn primer_design_section(state: &mut State, ui: &mut Ui) {
+    ui.collapsing("Design primer pair", |ui| {
+        ui.label(
+            "Design primers that amplify a target region, within the constraints below. Primers bind \
+        outside the target. Pairs are ranked by primer quality, Tm difference, hetero-dimers, and \
+        secondary structure of the amplicon.",
+        );
+
+        params_edit(state, ui);
+
+        ui.add_space(ROW_SPACING / 2.);
+
+        if ui.button("Design").clicked() {
+            state.ui.primer_design.results = design_primer_pairs(
+                &state.generic.seq,
+                state.generic.topology,
+                &state.ui.primer_design.params,
+                &state.ion_concentrations,
+            );
+            state.ui.primer_design.searched = true;
+        }
+
+        if state.ui.primer_design.searched && state.ui.primer_design.results.is_empty() {
+            ui.label(
+                RichText::new("No pairs meet these constraints. Try widening them.")
+                    .color(Color32::GOLD),
+            );
+        }
+
+        if let Some(i) = results_disp(&state.ui.primer_design.results, ui) {
+            let pair = &state.ui.primer_design.results[i];
+            state
+                .generic
+                .primers
+                .extend([pair.fwd.clone(), pair.rev.clone()]);
+            state.sync_primer_matches(None);
+            state.commit("Add designed primers");
+        }
+    });
+}
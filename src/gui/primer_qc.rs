@@ -1,13 +1,22 @@
 //! This module contains code to the primer editor, QC etc.
 
-use eframe::egui::{Align, Color32, Layout, RichText, TextEdit, Ui};
+use eframe::egui::{Align, Color32, ComboBox, Layout, RichText, TextEdit, Ui};
 use egui_extras::{Column, TableBuilder};
+use plascad::{
+    dimer::{Dimer, DimerAnalysis, DG_SCORE_3P, DG_SCORE_ANY},
+    melting_temp_calcs::{NnTable, SaltCorrection},
+    primer::{make_amplification_primers, Primer, PrimerDirection, PrimerMatch, TuneSetting},
+    secondary_structure::Fold,
+    sequence::{seq_from_str, seq_to_str, Nucleotide},
+    IonConcentrations,
+};
 
 use crate::{
-    gui::{COL_SPACING, ROW_SPACING},
-    primer::{make_amplification_primers, Primer, PrimerData, TuneSetting},
-    sequence::{seq_from_str, seq_to_str},
-    IonConcentrations, State,
+    gui::{
+        off_target::off_target_section, primer_design::primer_design_section, COL_SPACING,
+        ROW_SPACING,
+    },
+    State,
 };
 
 const TABLE_ROW_HEIGHT: f32 = 60.;
@@ -16,8 +25,6 @@ const COLOR_GOOD: Color32 = Color32::GREEN;
 const COLOR_MARGINAL: Color32 = Color32::GOLD;
 const COLOR_BAD: Color32 = Color32::LIGHT_RED;
 
-pub const DEFAULT_TRIM_AMT: usize = 32 - 20;
-
 // const TM_IDEAL: f32 = 59.; // todo: Fill thi sin
 //
 // const THRESHOLDS_TM: (f32, f32) = (59., 60.);
@@ -37,6 +44,103 @@ fn color_from_score(score: f32) -> Color32 {
     }
 }
 
+/// Color a dimer's ΔG, using the thresholds from our quality score.
+fn color_from_dimer(dimer: &Dimer) -> Color32 {
+    let thresh = if dimer.end_3p {
+        DG_SCORE_3P.0
+    } else {
+        DG_SCORE_ANY.0
+    };
+
+    if dimer.dg > thresh {
+        COLOR_GOOD
+    } else if dimer.dg > thresh - 2. {
+        COLOR_MARGINAL
+    } else {
+        COLOR_BAD
+    }
+}
+
+/// Show a dimer's ΔG, and its structure.
+fn dimer_disp(label: &str, dimer: &Option<Dimer>, ui: &mut Ui) {
+    match dimer {
+        Some(d) => {
+            ui.label(
+                RichText::new(format!("{label}: ΔG {:.1} kcal/mol", d.dg))
+                    .color(color_from_dimer(d)),
+            );
+            for line in &d.alignment {
+                ui.label(RichText::new(line).monospace());
+            }
+        }
+        None => {
+            ui.label(format!("{label}: None"));
+        }
+    }
+}
+
+/// Show the most stable dimers: The one with a paired 3' end, and the one anywhere.
+fn dimer_analysis_disp(analysis: &DimerAnalysis, ui: &mut Ui) {
+    dimer_disp("3' end", &analysis.end_3p, ui);
+    ui.add_space(ROW_SPACING / 2.);
+    dimer_disp("Any", &analysis.any, ui);
+}
+
+/// Show a secondary structure's ΔG and Tm, and the sequence with its dot-bracket notation below,
+/// wrapped to fit.
+pub fn fold_disp(seq: &[Nucleotide], fold: &Fold, ui: &mut Ui) {
+    const LINE_LEN: usize = 60;
+
+    if fold.pairs.is_empty() {
+        ui.label("No stable structure");
+        return;
+    }
+
+    let tm = match fold.tm {
+        Some(tm) => format!("{tm:.1}°C"),
+        None => "-".to_owned(),
+    };
+    ui.label(format!("ΔG {:.1} kcal/mol, Tm {tm}", fold.dg));
+
+    let seq_str = seq_to_str(seq).to_uppercase();
+    let dot_bracket = fold.dot_bracket(seq.len());
+    for start in (0..seq.len()).step_by(LINE_LEN) {
+        let end = (start + LINE_LEN).min(seq.len());
+        ui.label(RichText::new(&seq_str[start..end]).monospace());
+        ui.label(RichText::new(&dot_bracket[start..end]).monospace());
+    }
+}
+
+/// List the sites a primer binds: Position, direction, tail length, mismatches, and binding Tm.
+fn binding_sites_disp(matches: &[PrimerMatch], ui: &mut Ui) {
+    for m in matches {
+        let dir = match m.direction {
+            PrimerDirection::Forward => "Fwd",
+            PrimerDirection::Reverse => "Rev",
+        };
+        let tm = match m.tm {
+            Some(tm) => format!("Tm {tm:.1}°C"),
+            None => "Tm -".to_owned(),
+        };
+
+        let mut text = format!("{} {dir}, {tm}", m.range);
+        if m.tail_len > 0 {
+            text += &format!(", 5' tail {} nt", m.tail_len);
+        }
+        if !m.mismatches.is_empty() {
+            // 1-based positions in the primer, for display.
+            let positions: Vec<String> = m.mismatches.iter().map(|i| (i + 1).to_string()).collect();
+            text += &format!(", mismatches at {}", positions.join(", "));
+        }
+
+        ui.label(RichText::new(text).color(if m.is_exact() {
+            Color32::WHITE
+        } else {
+            COLOR_MARGINAL
+        }));
+    }
+}
+
 /// Allows editing ion concentration, including float manip. Return if the response changed,
 /// so we can redo TM calcs downstream.
 fn ion_edit(val: &mut f32, label: &str, ui: &mut Ui) -> bool {
@@ -53,13 +157,76 @@ fn ion_edit(val: &mut f32, label: &str, ui: &mut Ui) -> bool {
     }
 }
 
+/// Settings for how we calculate melting temperature. These are saved with the document.
+fn tm_config_edit(state: &mut State, ui: &mut Ui) {
+    let config = &mut state.ion_concentrations.tm_config;
+    let config_prev = *config;
+
+    ui.horizontal(|ui| {
+        ui.heading("Tm:");
+
+        ui.label("Parameters");
+        ComboBox::from_id_source("nn_table")
+            .width(180.)
+            .selected_text(config.nn_table.to_string())
+            .show_ui(ui, |ui| {
+                for table in NnTable::ALL {
+                    ui.selectable_value(&mut config.nn_table, table, table.to_string());
+                }
+            })
+            .response
+            .on_hover_text("The nearest-neighbor enthalpy and entropy values used to calculate melting temperature.");
+
+        ui.label("Salt correction");
+        ComboBox::from_id_source("salt_correction")
+            .width(180.)
+            .selected_text(config.salt_correction.to_string())
+            .show_ui(ui, |ui| {
+                for method in SaltCorrection::ALL {
+                    ui.selectable_value(&mut config.salt_correction, method, method.to_string());
+                }
+            })
+            .response
+            .on_hover_text("How ion concentrations affect melting temperature. Owczarzy 2008 accounts for Mg2+ \
+            separately from monovalent ions, and is used by many vendors' calculators.");
+
+        ui.add_space(COL_SPACING);
+
+        ion_edit(&mut config.dmso, "DMSO (%)", ui);
+        ion_edit(&mut config.formamide, "Formamide (%)", ui);
+    });
+
+    if *config != config_prev {
+        for primer in &mut state.generic.primers {
+            primer.run_calcs(&state.ion_concentrations);
+        }
+        // Binding site Tms depend on these settings.
+        state.sync_primer_matches(None);
+    }
+}
+
 pub fn primer_details(state: &mut State, ui: &mut Ui) {
     ui.horizontal(|ui| {
         let add_btn = ui
             .button("➕ Add primer")
             .on_hover_text("Adds a primer to the list below. Ctrl + A");
         if add_btn.clicked() {
-            state.generic.primers.push(Default::default())
+            state.generic.primers.push(Default::default());
+            state.commit("Add primer");
+        }
+
+        if !state.clipboard.primers.is_empty() {
+            if ui
+                .button(format!("Paste primers ({})", state.clipboard.primers.len()))
+                .on_hover_text("Add primers copied from another document.")
+                .clicked()
+            {
+                state.paste_primers();
+            }
+
+            if ui.button("Clear copied").clicked() {
+                state.clipboard.primers = Vec::new();
+            }
         }
 
         if ui
@@ -67,7 +234,13 @@ pub fn primer_details(state: &mut State, ui: &mut Ui) {
             .on_hover_text("Adds a primer pair that amplify the entire loaded sequence.")
             .clicked()
         {
-            make_amplification_primers(state);
+            if let Some(primers) =
+                make_amplification_primers(&state.generic.seq, &state.ion_concentrations)
+            {
+                state.generic.primers.extend(primers);
+                state.sync_primer_matches(None); // note: Not requried to run on all primers.
+            }
+            state.commit("Make whole seq primers");
         }
 
         let mut sync_primer_matches = false; // Prevents a double-borrow error.
@@ -80,6 +253,7 @@ pub fn primer_details(state: &mut State, ui: &mut Ui) {
 
         if sync_primer_matches {
             state.sync_primer_matches(None);
+            state.commit("Tune all primers");
         }
 
         ui.add_space(COL_SPACING * 2.);
@@ -89,13 +263,15 @@ pub fn primer_details(state: &mut State, ui: &mut Ui) {
         ui.heading("Ions: (mMol)");
 
         if ion_edit(&mut state.ion_concentrations.monovalent, "Na+ and K+", ui)
-            || ion_edit(&mut state.ion_concentrations.divalent, "mg2+", ui)
-            || ion_edit(&mut state.ion_concentrations.dntp, "dNTP", ui)
-            || ion_edit(&mut state.ion_concentrations.primer, "primer (nM)", ui)
+            | ion_edit(&mut state.ion_concentrations.divalent, "mg2+", ui)
+            | ion_edit(&mut state.ion_concentrations.dntp, "dNTP", ui)
+            | ion_edit(&mut state.ion_concentrations.tris, "Tris", ui)
+            | ion_edit(&mut state.ion_concentrations.primer, "primer (nM)", ui)
         {
             for primer in &mut state.generic.primers {
                 primer.run_calcs(&state.ion_concentrations); // Note: We only need to run the TM calc.
             }
+            state.sync_primer_matches(None);
         }
 
         // if ui.button("Load").clicked() {}
@@ -110,6 +286,12 @@ pub fn primer_details(state: &mut State, ui: &mut Ui) {
         // }
     });
 
+    tm_config_edit(state, ui);
+
+    primer_design_section(state, ui);
+
+    off_target_section(state, ui);
+
     ui.label("Tuning instructions: Include more of the target sequence than required on the end[s] that can be tuned. These are the \
      ends that do not define your insert, gene of interest, insertion point etc. Mark that end as tunable using the \"T\" button. \
 To learn about a table column, mouse over it.");
@@ -129,7 +311,7 @@ To learn about a table column, mouse over it.");
                 return;
             }
 
-            ui.heading(&format!("Selected: {}", &state.generic.primers[sel_i].name));
+            ui.heading(format!("Selected: {}", &state.generic.primers[sel_i].name));
 
             ui.add_space(COL_SPACING);
 
@@ -138,12 +320,22 @@ To learn about a table column, mouse over it.");
                 if sel_i != 0 {
                     state.generic.primers.swap(sel_i, sel_i - 1);
                     state.ui.primer_selected = Some(sel_i - 1);
+                    state.commit("Move primer");
                 }
             }
             if ui.button(RichText::new("Dn")).clicked() && sel_i != state.generic.primers.len() - 1
             {
                 state.generic.primers.swap(sel_i, sel_i + 1);
                 state.ui.primer_selected = Some(sel_i + 1);
+                state.commit("Move primer");
+            }
+
+            if ui
+                .button("Copy")
+                .on_hover_text("Copy this primer, to paste into another document.")
+                .clicked()
+            {
+                state.copy_primer(sel_i);
             }
 
             if ui
@@ -151,6 +343,7 @@ To learn about a table column, mouse over it.");
                 .clicked()
             {
                 state.generic.primers.remove(sel_i);
+                state.commit("Delete primer");
             }
 
             if ui
@@ -161,10 +354,44 @@ To learn about a table column, mouse over it.");
             }
         });
 
+        state.sync_primer_dimers();
+
+        if let Some(dimers) = state.volatile.primer_dimers.get(sel_i) {
+            ui.collapsing("Dimers", |ui| {
+                ui.label("The most stable structures this primer forms with itself, and with each other primer. ΔG is at 37°C.");
+
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.heading("Self");
+                        dimer_analysis_disp(dimers, ui);
+                    });
+
+                    for (i, (other, dimers)) in state
+                        .generic
+                        .primers
+                        .iter()
+                        .zip(&state.volatile.primer_dimers)
+                        .enumerate()
+                    {
+                        if i == sel_i {
+                            continue;
+                        }
+                        ui.add_space(COL_SPACING);
+
+                        ui.vertical(|ui| {
+                            ui.heading(format!("With {}", other.name));
+                            dimer_analysis_disp(dimers, ui);
+                        });
+                    }
+                });
+            });
+        }
+
         ui.add_space(ROW_SPACING);
     }
 
     let mut run_match_sync = None; // Avoids a double-mutation error.
+    let mut name_edited = false;
 
     TableBuilder::new(ui)
         .column(Column::initial(600.).resizable(true)) // Sequence
@@ -177,6 +404,7 @@ To learn about a table column, mouse over it.");
         .column(Column::auto().resizable(true))// 3' GC content
         .column(Column::auto().resizable(true))// Complexity
         .column(Column::auto().resizable(true))// Dimer formation
+        .column(Column::auto().resizable(true))// Hairpin
         .column(Column::auto().resizable(true))  // Repeats
         .column(Column::remainder())
         .header(20.0, |mut header| {
@@ -190,14 +418,16 @@ To learn about a table column, mouse over it.");
                 ui.heading("Len").on_hover_text("Number of nucleotides in the (tuned, if applicable) primer");
             });
             header.col(|ui| {
-                ui.heading("Mt").on_hover_text("Number of matches with the target sequence");
+                ui.heading("Mt").on_hover_text("Number of sites the primer binds in the sequence, including ones with 5' tails, \
+                and mismatches. Mouse over a value to see the sites.");
             });
             header.col(|ui| {
                 ui.heading("Qual").on_hover_text("Overall primer quality. This is an abstract estimate, taking all other listed factors into account.");
             });
             header.col(|ui| {
                 ui.heading("TM").on_hover_text("Primer melting temperature, in °C. Calculated using base a base stacking method, where\
-                 enthalpy and entropy of neighboring base pairs are added. See the readme for calculations and assumptions.");
+                 enthalpy and entropy of neighboring base pairs are added. See the readme for calculations and assumptions. \
+                 For degenerate primers, this is the range over the sequences they represent.");
             });
             header.col(|ui| {
                 ui.heading("GC").on_hover_text("The percentage of nucleotides that are C or G.");
@@ -209,7 +439,12 @@ To learn about a table column, mouse over it.");
             //     ui.heading("Cplx").on_hover_text("Sequence complexity. See the readme for calculations and assumptions.");
             // });
             header.col(|ui| {
-                ui.heading("Dmr").on_hover_text("Potential of forming a self-end dimer. See the readme for calculations and assumptions.");
+                ui.heading("Dmr").on_hover_text("Self-dimers: ΔG at 37°C, in kcal/mol, of the most stable structure formed by two copies of this primer with a paired 3' end, \
+                and of the most stable one anywhere. 3' end dimers weaker than -5, and others weaker than -6 are generally tolerated. Select a primer to see hetero-dimers.");
+            });
+            header.col(|ui| {
+                ui.heading("Hp").on_hover_text("Hairpins: ΔG at 37°C, in kcal/mol, of the most stable secondary structure this primer forms \
+                on its own. Structures weaker than -3 are generally tolerated. Mouse over a value to see the structure, and its melting temperature.");
             });
             header.col(|ui| {
                 ui.heading("Rep").on_hover_text("Count of repeats of a single or double nt sequence >4 in a row, and count of triplet \
@@ -268,14 +503,13 @@ To learn about a table column, mouse over it.");
 
                             ui.add_space(COL_SPACING);
 
-                            if primer.volatile.tunable_3p != TuneSetting::Disabled || primer.volatile.tunable_5p != TuneSetting::Disabled {
-                                if ui
+                            if (primer.volatile.tunable_3p != TuneSetting::Disabled || primer.volatile.tunable_5p != TuneSetting::Disabled)
+                                && ui
                                     .button(RichText::new("Tune")).on_hover_text("Tune selected ends for this primer").clicked()
                                 {
                                     primer.tune(&state.ion_concentrations);
                                     run_match_sync = Some(i);
                                 }
-                            }
                         });
 
                         let updated_seq = primer_tune_display(primer, &state.ion_concentrations, ui);
@@ -285,7 +519,9 @@ To learn about a table column, mouse over it.");
                     });
 
                     row.col(|ui| {
-                        ui.add(TextEdit::singleline(&mut primer.name));
+                        if ui.add(TextEdit::singleline(&mut primer.name)).changed() {
+                            name_edited = true;
+                        }
                     });
 
                     row.col(|ui| {
@@ -293,10 +529,11 @@ To learn about a table column, mouse over it.");
                     });
 
                     row.col(|ui| {
-                        // todo: Cache this?
-                        // let num_matches = data.matches_seq.len() + data.matches_vector_with_insert.len();
-                        let num_matches = primer.volatile.matches_seq.len() +  primer.volatile.matches_seq.len();
-                        ui.label(num_matches.to_string());
+                        let matches = &primer.volatile.matches_seq;
+                        let response = ui.label(matches.len().to_string());
+                        if !matches.is_empty() {
+                            response.on_hover_ui(|ui| binding_sites_disp(matches, ui));
+                        }
                     });
 
                     row.col(|ui| {
@@ -311,13 +548,25 @@ To learn about a table column, mouse over it.");
                     });
 
                     row.col(|ui| {
-                        let text = match & primer.volatile.metrics {
-                            Some(m) => RichText::new(format!("{:.1}°C", m.melting_temp))
-                                .color(color_from_score(m.tm_score)),
-
-                            None => RichText::new("-"),
+                        match & primer.volatile.metrics {
+                            // For degenerate primers, show the range over the sequences they represent.
+                            Some(m) if m.degeneracy > 1 => {
+                                let text = RichText::new(format!("{:.0}-{:.0}°C", m.tm_range.0, m.tm_range.1))
+                                    .color(color_from_score(m.tm_score));
+                                ui.label(text).on_hover_text(format!(
+                                    "Degenerate: {}-fold. Mean Tm {:.1}°C.",
+                                    m.degeneracy, m.melting_temp
+                                ));
+                            }
+                            Some(m) => {
+                                let text = RichText::new(format!("{:.1}°C", m.melting_temp))
+                                    .color(color_from_score(m.tm_score));
+                                ui.label(text);
+                            }
+                            None => {
+                                ui.label("-");
+                            }
                         };
-                        ui.label(text);
                     });
 
                     row.col(|ui| {
@@ -340,12 +589,35 @@ To learn about a table column, mouse over it.");
                     });
 
                     row.col(|ui| {
-                        let text = match & primer.volatile.metrics {
-                            Some(m) => RichText::new(format!("{}", m.self_end_dimer))
-                                .color(color_from_score(m.dimer_score)),
-                            None => RichText::new("-"),
+                        match & primer.volatile.metrics {
+                            Some(m) => {
+                                let dg = |d: &Option<Dimer>| match d {
+                                    Some(d) => format!("{:.1}", d.dg),
+                                    None => "-".to_owned(),
+                                };
+                                let text = RichText::new(format!("{} / {}", dg(&m.self_dimer.end_3p), dg(&m.self_dimer.any)))
+                                    .color(color_from_score(m.dimer_score));
+
+                                ui.label(text).on_hover_ui(|ui| dimer_analysis_disp(&m.self_dimer, ui));
+                            }
+                            None => {
+                                ui.label("-");
+                            }
+                        };
+                    });
+
+                    row.col(|ui| {
+                        match & primer.volatile.metrics {
+                            Some(m) => {
+                                let text = RichText::new(format!("{:.1}", m.hairpin.dg))
+                                    .color(color_from_score(m.hairpin_score));
+
+                                ui.label(text).on_hover_ui(|ui| fold_disp(&primer.sequence, &m.hairpin, ui));
+                            }
+                            None => {
+                                ui.label("-");
+                            }
                         };
-                        ui.label(text);
                     });
 
                     row.col(|ui| {
@@ -378,6 +650,9 @@ To learn about a table column, mouse over it.");
             }
         });
 
+    if run_match_sync.is_some() || name_edited {
+        state.history.mark_edited();
+    }
     if run_match_sync.is_some() {
         state.sync_seq_related(run_match_sync);
     }
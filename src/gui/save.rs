@@ -5,19 +5,18 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use eframe::egui::Ui;
+use eframe::egui::{Color32, RichText, Ui};
 use egui_file_dialog::FileDialog;
-
-use crate::{
-    file_io::{
-        genbank::{export_genbank, import_genbank},
-        save::{export_fasta, import_fasta, save, StateToSave, DEFAULT_SAVE_FILE},
-        snapgene::{export_snapgene, import_snapgene},
-    },
-    sequence::seq_to_str,
-    State,
+use plascad::file_io::{
+    error::FileReport,
+    genbank::export_genbank,
+    import_file,
+    save::{export_fasta, StateToSave, DEFAULT_SAVE_FILE},
+    snapgene::export_snapgene,
 };
 
+use crate::{workspace::Document, State};
+
 fn save_button(
     dialog: &mut FileDialog,
     plasmid_name: &str,
@@ -43,6 +42,64 @@ fn save_button(
     }
 }
 
+/// Show the result of a file operation, if there's anything to show. A clean result clears the
+/// previous one.
+pub fn set_report(state: &mut State, report: FileReport) {
+    state.ui.file_report = (!report.is_clean()).then_some(report);
+}
+
+/// Save the active document to the default file.
+pub fn save_default(state: &mut State) {
+    let result = state.to_save().save(&PathBuf::from(DEFAULT_SAVE_FILE));
+    set_report(state, FileReport::from_export("Saving", result));
+}
+
+/// Show errors and warnings from the most recent file operation, with a button to dismiss them.
+pub fn file_report(state: &mut State, ui: &mut Ui) {
+    let Some(report) = &state.ui.file_report else {
+        return;
+    };
+
+    let mut dismiss = false;
+
+    ui.horizontal(|ui| {
+        match &report.error {
+            Some(e) => {
+                ui.label(
+                    RichText::new(format!("{} failed: {e}", report.action))
+                        .color(Color32::LIGHT_RED),
+                );
+            }
+            None => {
+                ui.label(
+                    RichText::new(format!(
+                        "{}: {} warnings. Some data may not have been imported.",
+                        report.action,
+                        report.warnings.len()
+                    ))
+                    .color(Color32::GOLD),
+                );
+            }
+        }
+
+        if ui.button("Dismiss").clicked() {
+            dismiss = true;
+        }
+    });
+
+    if !report.warnings.is_empty() {
+        ui.collapsing("Warnings", |ui| {
+            for warning in &report.warnings {
+                ui.label(RichText::new(warning.to_string()).color(Color32::GOLD));
+            }
+        });
+    }
+
+    if dismiss {
+        state.ui.file_report = None;
+    }
+}
+
 fn load_button(dialog: &mut FileDialog, text: &str, hover_text: &str, ui: &mut Ui) {
     if ui.button(text).on_hover_text(hover_text).clicked() {
         dialog.select_file();
@@ -57,12 +114,7 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
         .on_hover_text("Save data. (Ctrl + s)")
         .clicked()
     {
-        if let Err(e) = save(
-            &PathBuf::from(DEFAULT_SAVE_FILE),
-            &StateToSave::from_state(state),
-        ) {
-            println!("Error saving: {e}");
-        }
+        save_default(state);
     }
 
     save_button(
@@ -77,14 +129,30 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
     load_button(
         &mut state.ui.file_dialogs.load,
         "Load",
-        "Load data in the PlasCAD format.",
+        "Load data in the PlasCAD format, as a new document.",
+        ui,
+    );
+
+    save_button(
+        &mut state.ui.file_dialogs.save_project,
+        "project",
+        "pcadproj",
+        "Save project",
+        "Save all open documents together, as a PlasCAD project.",
+        ui,
+    );
+
+    load_button(
+        &mut state.ui.file_dialogs.load_project,
+        "Load project",
+        "Load a PlasCAD project, replacing the open documents.",
         ui,
     );
 
     load_button(
         &mut state.ui.file_dialogs.import,
         "Import",
-        "Import data in the FASTA, GenBank, or .dna (SnapGene) formats",
+        "Import data in the FASTA, GenBank, or .dna (SnapGene) formats, as a new document.",
         ui,
     );
 
@@ -124,84 +192,81 @@ pub fn save_section(state: &mut State, ui: &mut Ui) {
     state.ui.file_dialogs.export_genbank.update(ctx);
     state.ui.file_dialogs.export_dna.update(ctx);
     state.ui.file_dialogs.import.update(ctx);
+    state.ui.file_dialogs.save_project.update(ctx);
+    state.ui.file_dialogs.load_project.update(ctx);
 
-    let mut sync = false;
-
+    // Imported and loaded files open as new documents in the workspace.
     if let Some(path) = state.ui.file_dialogs.import.take_selected() {
         state.ui.file_dialogs.selected = Some(path.to_owned());
 
-        if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
-            match extension.to_lowercase().as_ref() {
-                // Does this work for FASTQ too?
-                "fasta" => {
-                    if let Ok((seq, id, description)) = import_fasta(&path) {
-                        state.generic.seq = seq;
-                        state.generic.metadata.plasmid_name = id;
-                        state.generic.metadata.comments = vec![description];
-                        sync = true;
-                    }
-                }
-                "dna" => {
-                    if let Ok(data) = import_snapgene(&path) {
-                        state.generic = data;
-                        sync = true;
-                    }
-                }
-                "gb" | "gbk" => {
-                    if let Ok(data) = import_genbank(&path) {
-                        state.generic = data;
-                        sync = true;
-                    }
-                }
-                _ => {
-                    eprintln!("The file to import must be in FASTA, GenBank, or SnapGene format.")
-                }
-            }
+        let action = format!("Importing {}", path.display());
+        let (report, data) = FileReport::from_import(&action, import_file(&path));
+        set_report(state, report);
+        if let Some(generic) = data {
+            state.open_document(generic, Default::default());
         }
     } else if let Some(path) = state.ui.file_dialogs.save.take_selected() {
         state.ui.file_dialogs.selected = Some(path.to_owned());
-        if let Err(e) = save(&path, &StateToSave::from_state(state)) {
-            eprintln!("Error saving in PlasCAD format: {:?}", e);
-        };
+
+        let result = state.to_save().save(&path);
+        set_report(state, FileReport::from_export("Saving", result));
     } else if let Some(path) = state.ui.file_dialogs.load.take_selected() {
         state.ui.file_dialogs.selected = Some(path.to_owned());
-        *state = State::load(path.to_str().unwrap());
-        sync = true;
+
+        let action = format!("Loading {}", path.display());
+        let (report, loaded) = FileReport::from_import(&action, StateToSave::load(&path));
+        set_report(state, report);
+
+        if let Some(s) = loaded {
+            state.open_saved(Document::from_save(s));
+        }
+    } else if let Some(path) = state.ui.file_dialogs.save_project.take_selected() {
+        state.ui.file_dialogs.selected = Some(path.to_owned());
+
+        let result = state.to_project().save(&path);
+        set_report(state, FileReport::from_export("Saving project", result));
+    } else if let Some(path) = state.ui.file_dialogs.load_project.take_selected() {
+        state.ui.file_dialogs.selected = Some(path.to_owned());
+
+        let action = format!("Loading project {}", path.display());
+        let (report, loaded) =
+            FileReport::from_import(&action, State::load_project(path.to_str().unwrap()));
+
+        if let Some(s) = loaded {
+            *state = s;
+        }
+        set_report(state, report);
     } else if let Some(path) = state.ui.file_dialogs.export_fasta.take_selected() {
         state.ui.file_dialogs.selected = Some(path.to_owned());
 
-        if let Err(e) = export_fasta(
+        let result = export_fasta(
             &state.generic.seq,
             &state.generic.metadata.plasmid_name,
             &path,
-        ) {
-            eprintln!("Error exporting to FASTA: {:?}", e);
-        };
+        );
+        set_report(state, FileReport::from_export("Exporting to FASTA", result));
     } else if let Some(path) = state.ui.file_dialogs.export_genbank.take_selected() {
         state.ui.file_dialogs.selected = Some(path.to_owned());
 
         let mut primer_matches = Vec::new();
         for primer in &state.generic.primers {
-            for (dir, range) in &primer.volatile.matches_seq {
-                primer_matches.push((*dir, range.clone(), primer.name.clone()));
+            for m in &primer.volatile.matches_seq {
+                primer_matches.push((m.direction, m.range, primer.name.clone()));
             }
         }
 
-        if let Err(e) = export_genbank(&state.generic, &primer_matches, &path) {
-            eprintln!("Error exporting to GenBank: {:?}", e);
-        };
+        let result = export_genbank(&state.generic, &primer_matches, &path);
+        set_report(
+            state,
+            FileReport::from_export("Exporting to GenBank", result),
+        );
     } else if let Some(path) = state.ui.file_dialogs.export_dna.take_selected() {
         state.ui.file_dialogs.selected = Some(path.to_owned());
 
-        if let Err(e) = export_snapgene(&state.generic, &path) {
-            eprintln!("Error exporting to SnapGene: {:?}", e);
-        };
-    }
-
-    if sync {
-        state.sync_pcr();
-        state.sync_primer_metrics();
-        state.sync_seq_related(None);
-        state.ui.seq_input = seq_to_str(&state.generic.seq);
+        let result = export_snapgene(&state.generic, &path);
+        set_report(
+            state,
+            FileReport::from_export("Exporting to SnapGene", result),
+        );
     }
 }
@@ -4,28 +4,35 @@ use std::ops::Range;
 
 use eframe::{
     egui::{
-        pos2, vec2, Align2, Color32, FontFamily, FontId, Frame, Pos2, Rect, ScrollArea, Sense,
-        Shape, Ui,
+        pos2, vec2, Align2, Color32, ComboBox, FontFamily, FontId, Frame, Pos2, Rect, RichText,
+        ScrollArea, Sense, Shape, Ui,
     },
     emath::RectTransform,
     epaint::PathStroke,
 };
+use plascad::{
+    protein::{translate_feature, translate_orf, AaIdent, AminoAcid, GeneticCode, StopHandling},
+    sequence::{FeatureDirection, FeatureType, NestedOrfPolicy, ReadingFrame},
+    util::get_row_ranges,
+};
 
 use crate::{
     gui::{
-        feature_overlay::draw_features, get_cursor_text, navigation::page_button, primer_arrow,
-        COL_SPACING, ROW_SPACING,
+        feature_overlay::draw_features,
+        get_cursor_text, int_field,
+        navigation::{page_button, NAV_BUTTON_COLOR},
+        primer_arrow, COL_SPACING, ROW_SPACING,
     },
-    sequence::ReadingFrame,
-    util::{get_row_ranges, pixel_to_seq_i, seq_i_to_pixel},
     State, StateUi,
 };
 
-// Pub for use in `util` functions.
 pub const FONT_SIZE_SEQ: f32 = 14.;
 pub const COLOR_SEQ: Color32 = Color32::LIGHT_BLUE;
 pub const COLOR_CODING_REGION: Color32 = Color32::from_rgb(255, 0, 170);
 pub const COLOR_RE: Color32 = Color32::LIGHT_RED;
+pub const COLOR_AMBIGUOUS: Color32 = Color32::GRAY;
+pub const COLOR_AA: Color32 = Color32::from_rgb(255, 210, 120);
+pub const COLOR_STOP: Color32 = Color32::from_rgb(255, 80, 80);
 
 const BACKGROUND_COLOR: Color32 = Color32::from_rgb(10, 20, 10);
 
@@ -34,9 +41,56 @@ pub const VIEW_AREA_PAD_LEFT: f32 = 60.; // Bigger to accomodate the index displ
 pub const VIEW_AREA_PAD_RIGHT: f32 = 20.;
 pub const SEQ_ROW_SPACING_PX: f32 = 34.;
 
+const FONT_SIZE_AA: f32 = 11.;
+const AA_OFFSET_Y: f32 = 16.; // Number of pixels below the top of the sequence text.
+
+// todo: We currently don't use this as a standalone fn; wrap back into `seq_i_to_pixel` a/r.
+/// Maps sequence index, as displayed on a manually-wrapped UI display, to row and column indices.
+fn seq_i_to_col_row(seq_i: usize, row_ranges: &[Range<usize>]) -> (usize, usize) {
+    let mut row = 0;
+    let mut row_range = 0..10;
+
+    for (row_, range) in row_ranges.iter().enumerate() {
+        if range.contains(&seq_i) {
+            row = row_;
+            row_range = range.clone();
+            break;
+        }
+    }
+
+    let col = seq_i - row_range.start;
+
+    (col, row)
+}
+
+/// Maps sequence index, as displayed on a manually-wrapped UI display, to the relative pixel.
+pub fn seq_i_to_pixel(seq_i: usize, row_ranges: &[Range<usize>]) -> Pos2 {
+    let (col, row) = seq_i_to_col_row(seq_i, row_ranges);
+
+    pos2(
+        TEXT_X_START + col as f32 * NT_WIDTH_PX,
+        TEXT_Y_START + row as f32 * SEQ_ROW_SPACING_PX,
+    )
+}
+
+pub fn pixel_to_seq_i(pixel: Pos2, row_ranges: &[Range<usize>]) -> Option<usize> {
+    // todo: ROunding?
+    let row = ((pixel.y - TEXT_Y_START) / SEQ_ROW_SPACING_PX) as usize;
+    let col = ((pixel.x - TEXT_X_START) / NT_WIDTH_PX) as usize;
+
+    // todo: Index vice loop?
+    for (row_, range) in row_ranges.iter().enumerate() {
+        if row_ == row {
+            return Some(range.start + col);
+        }
+    }
+
+    None
+}
+
 pub const TEXT_X_START: f32 = VIEW_AREA_PAD_LEFT;
 pub const TEXT_Y_START: f32 = TEXT_X_START;
-const MAX_SEQ_AREA_HEIGHT: u16 = 300;
+// const MAX_SEQ_AREA_HEIGHT: u16 = 300;
 
 fn re_sites(state: &State, ui: &mut Ui, seq_i_to_px_rel: impl Fn(usize) -> Pos2) -> Vec<Shape> {
     let mut result = Vec::new();
@@ -47,24 +101,45 @@ fn re_sites(state: &State, ui: &mut Ui, seq_i_to_px_rel: impl Fn(usize) -> Pos2)
         }
         let re = &state.restriction_enzyme_lib[re_match.lib_index];
 
-        let cut_i = re_match.seq_index + 1; // to display in the right place.
+        let Some(first_cut) = re_match.cuts.first() else {
+            continue;
+        };
+
+        // Each cut is a line down from the top strand's, across to the bottom strand's, and down from
+        // it; this shows the overhang. The modulo is for cuts at the end of linear sequences.
+        let seq_len = state.generic.seq.len();
+        for cut in &re_match.cuts {
+            let top = seq_i_to_px_rel(cut.top % seq_len);
+            let bottom = seq_i_to_px_rel(cut.bottom % seq_len);
+            let stroke = PathStroke::new(2., COLOR_RE);
+
+            let mid = pos2(top.x, top.y + 10.);
+            result.push(Shape::LineSegment {
+                points: [top, mid],
+                stroke: stroke.clone(),
+            });
 
-        // let cut_i = match re_match.direction {
-        //     Forward => re_match.seq_index + 1,
-        //     // todo: Rev may be deprecated.
-        //     Reverse => seq_len - re_match.seq_index,
-        // };
-        let cut_pos = seq_i_to_px_rel(cut_i + re.cut_after as usize);
+            // The cuts may be on different rows.
+            let bottom_mid = if bottom.y == top.y {
+                result.push(Shape::LineSegment {
+                    points: [mid, pos2(bottom.x, mid.y)],
+                    stroke: stroke.clone(),
+                });
+                pos2(bottom.x, mid.y)
+            } else {
+                pos2(bottom.x, bottom.y + 10.)
+            };
 
-        let bottom = pos2(cut_pos.x, cut_pos.y + 20.);
+            result.push(Shape::LineSegment {
+                points: [bottom_mid, pos2(bottom.x, bottom.y + 20.)],
+                stroke,
+            });
+        }
 
-        result.push(Shape::LineSegment {
-            points: [cut_pos, bottom],
-            stroke: PathStroke::new(2., COLOR_RE),
-        });
+        let cut_pos = seq_i_to_px_rel(first_cut.top % seq_len);
 
         // let label_text = format!("{} - {}", re.name, re_match.seq_index);
-        let label_text = format!("{}", re.name);
+        let label_text = re.name.to_string();
         let mut label_pos = pos2(cut_pos.x + 2., cut_pos.y - 4.);
 
         // Move the label position left if there is a nearby RE site on the right.
@@ -73,8 +148,8 @@ fn re_sites(state: &State, ui: &mut Ui, seq_i_to_px_rel: impl Fn(usize) -> Pos2)
         for (i_other, re_match_other) in state.volatile.restriction_enzyme_sites.iter().enumerate()
         {
             if i_other != i_match
-                && re_match_other.seq_index > re_match.seq_index
-                && re_match_other.seq_index - re_match.seq_index < 10
+                && re_match_other.range.start > re_match.range.start
+                && re_match_other.range.start - re_match.range.start < 10
             {
                 neighbor_on_right = true;
                 break;
@@ -124,6 +199,10 @@ pub fn display_filters(state_ui: &mut StateUi, ui: &mut Ui) {
         ui.label("Reading frame:");
         ui.checkbox(&mut state_ui.seq_visibility.show_reading_frame, "");
         ui.add_space(COL_SPACING / 2.);
+
+        ui.label("Translation:");
+        ui.checkbox(&mut state_ui.seq_visibility.show_translation, "");
+        ui.add_space(COL_SPACING / 2.);
     });
 }
 
@@ -157,25 +236,185 @@ fn draw_seq_indexes(
     result
 }
 
+/// A button to select a reading frame, or all of them. Similar to `page_button`.
+fn orf_button(text: &str, selected: bool, ui: &mut Ui) -> bool {
+    let color = if selected {
+        Color32::GREEN
+    } else {
+        Color32::WHITE
+    };
+
+    ui.button(
+        RichText::new(text)
+            .color(color)
+            .background_color(NAV_BUTTON_COLOR),
+    )
+    .clicked()
+}
+
 fn orf_selector(state: &mut State, ui: &mut Ui) {
     ui.label("Reading frame:");
 
-    let orf = &mut state.reading_frame;
+    let orig = (state.reading_frame, state.ui.orf_all_frames);
 
-    let orig = *orf;
+    if orf_button("All", state.ui.orf_all_frames, ui) {
+        state.ui.orf_all_frames = true;
+    }
+
+    for frame in ReadingFrame::ALL {
+        let selected = !state.ui.orf_all_frames && state.reading_frame == frame;
+        if orf_button(&frame.to_string(), selected, ui) {
+            state.reading_frame = frame;
+            state.ui.orf_all_frames = false;
+        }
+    }
 
-    page_button(orf, ReadingFrame::Fwd0, ui, false);
-    page_button(orf, ReadingFrame::Fwd1, ui, false);
-    page_button(orf, ReadingFrame::Fwd2, ui, false);
-    page_button(orf, ReadingFrame::Rev0, ui, false);
-    page_button(orf, ReadingFrame::Rev1, ui, false);
-    page_button(orf, ReadingFrame::Rev2, ui, false);
+    if (state.reading_frame, state.ui.orf_all_frames) != orig {
+        state.sync_reading_frame()
+    }
+}
 
-    if *orf != orig {
+/// Settings for finding open reading frames.
+fn orf_params_selector(state: &mut State, ui: &mut Ui) {
+    let orig = state.orf_params.clone();
+
+    int_field(&mut state.orf_params.min_len_aa, "Min ORF len (AA):", ui);
+
+    ui.label("Alt starts:");
+    ui.checkbox(&mut state.orf_params.alt_starts, "");
+
+    ui.label("Nested ORFs:");
+    page_button(
+        &mut state.orf_params.nested,
+        NestedOrfPolicy::Outermost,
+        ui,
+        false,
+    );
+    page_button(
+        &mut state.orf_params.nested,
+        NestedOrfPolicy::All,
+        ui,
+        false,
+    );
+
+    if state.orf_params != orig {
         state.sync_reading_frame()
     }
 }
 
+/// Select the genetic code used for translation, and how to display amino acids.
+fn translation_selector(state: &mut State, ui: &mut Ui) {
+    let code_prev = state.genetic_code;
+
+    ui.label("Genetic code:");
+    ComboBox::from_id_source(400)
+        .width(160.)
+        .selected_text(state.genetic_code.to_string())
+        .show_ui(ui, |ui| {
+            for code in GeneticCode::ALL {
+                ui.selectable_value(&mut state.genetic_code, code, code.to_string());
+            }
+        });
+    ui.add_space(COL_SPACING / 2.);
+
+    // Start and stop codons depend on the genetic code.
+    if state.genetic_code != code_prev {
+        state.sync_reading_frame();
+    }
+
+    ui.label("Amino acids:");
+    page_button(&mut state.ui.aa_ident, AaIdent::OneLetter, ui, false);
+    page_button(&mut state.ui.aa_ident, AaIdent::ThreeLetters, ui, false);
+}
+
+/// Draw amino acids below the codons that encode them, for coding regions, and open reading frames.
+fn draw_translations(
+    state: &State,
+    ui: &mut Ui,
+    seq_i_to_px_rel: impl Fn(usize) -> Pos2,
+) -> Vec<Shape> {
+    let mut result = Vec::new();
+    let seq = &state.generic.seq;
+    let seq_len = seq.len();
+
+    // Each item is a translated protein, and the 0-based indices of the nucleotides it's translated from,
+    // in reading order. This handles joined features, and ranges that wrap the origin.
+    let mut translations = Vec::new();
+
+    if state.ui.seq_visibility.show_features {
+        for feature in &state.generic.features {
+            if feature.feature_type != FeatureType::CodingRegion {
+                continue;
+            }
+            if let Some(protein) =
+                translate_feature(feature, seq, state.genetic_code, StopHandling::Include)
+            {
+                let mut indices: Vec<usize> = feature
+                    .ranges()
+                    .into_iter()
+                    .flat_map(|r| r.indices(seq_len))
+                    .collect();
+
+                if feature.direction == FeatureDirection::Reverse {
+                    indices.reverse();
+                }
+                translations.push((protein, indices));
+            }
+        }
+    }
+
+    if state.ui.seq_visibility.show_reading_frame {
+        for orf in &state.volatile.reading_frame_matches {
+            if let Some(protein) =
+                translate_orf(orf, seq, state.genetic_code, StopHandling::Include)
+            {
+                let mut indices = orf.range.indices(seq_len);
+                if orf.frame.is_reverse() {
+                    indices.reverse();
+                }
+                translations.push((protein, indices));
+            }
+        }
+    }
+
+    for (protein, indices) in translations {
+        for (i, aa) in protein.iter().enumerate() {
+            // Center the amino acid on its codon's middle nucleotide.
+            let Some(middle_i) = indices.get(3 * i + 1) else {
+                break;
+            };
+
+            let text = match state.ui.aa_ident {
+                AaIdent::OneLetter => aa.to_letter().to_string(),
+                AaIdent::ThreeLetters => aa.to_str_3_letter().to_owned(),
+            };
+
+            let color = if *aa == AminoAcid::Stop {
+                COLOR_STOP
+            } else {
+                COLOR_AA
+            };
+
+            let mut pos = seq_i_to_px_rel(*middle_i);
+            pos.x += NT_WIDTH_PX / 2.;
+            pos.y += AA_OFFSET_Y;
+
+            result.push(ui.ctx().fonts(|fonts| {
+                Shape::text(
+                    fonts,
+                    pos,
+                    Align2::CENTER_TOP,
+                    text,
+                    FontId::new(FONT_SIZE_AA, FontFamily::Monospace),
+                    color,
+                )
+            }));
+        }
+    }
+
+    result
+}
+
 /// Find the sequence index under the cursor, if it is over the sequence.
 fn find_cursor_i(
     cursor_pos: Option<(f32, f32)>,
@@ -218,6 +457,17 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
         ui.label("Cursor:");
         ui.heading(cursor_posit_text);
     });
+
+    ui.horizontal(|ui| {
+        if state.ui.seq_visibility.show_reading_frame {
+            orf_params_selector(state, ui);
+            ui.add_space(COL_SPACING);
+        }
+
+        if state.ui.seq_visibility.show_translation {
+            translation_selector(state, ui);
+        }
+    });
     ScrollArea::vertical().id_source(0).show(ui, |ui| {
         Frame::canvas(ui.style())
             .fill(BACKGROUND_COLOR)
@@ -258,14 +508,18 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
 
                         if state.ui.seq_visibility.show_reading_frame {
                             for rf in &state.volatile.reading_frame_matches {
-                                let (start, end) = rf.range;
-                                let i_ = i + 1; // 1-based indexing
-                                if start <= i_ && i_ <= end {
+                                // + 1: 1-based indexing
+                                if rf.range.contains(i + 1) {
                                     r = COLOR_CODING_REGION;
                                 }
                             }
                         }
 
+                        // Make ambiguous nucleotides (eg N) stand out.
+                        if nt.is_ambiguous() {
+                            r = COLOR_AMBIGUOUS;
+                        }
+
                         r
                     };
 
@@ -282,6 +536,10 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
                     }));
                 }
 
+                if state.ui.seq_visibility.show_translation {
+                    shapes.append(&mut draw_translations(state, ui, seq_i_to_px_rel));
+                }
+
                 if state.ui.seq_visibility.show_primers {
                     shapes.append(&mut primer_arrow::draw_primers(
                         &state.generic.primers,
@@ -299,6 +557,7 @@ pub fn sequence_vis(state: &mut State, ui: &mut Ui) {
                 if state.ui.seq_visibility.show_features {
                     shapes.append(&mut draw_features(
                         &state.generic.features,
+                        seq_len,
                         &row_ranges,
                         ui,
                         seq_i_to_px_rel,
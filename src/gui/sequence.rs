@@ -1,24 +1,137 @@
 //! This module contains GUI code related to the sequence view.
 
-use eframe::egui::{TextEdit, Ui};
+use eframe::egui::{Button, Color32, RichText, TextEdit, Ui};
+use plascad::{
+    primer::make_cloning_primers,
+    secondary_structure::fold,
+    sequence::{seq_from_str, seq_to_str},
+};
 
 // todo: monospace font for all seqs.
-use crate::sequence::{seq_from_str, seq_to_str};
 use crate::{
     gui::{
+        assembly::assembly_page,
+        digest::digest_page,
         features::feature_table,
+        golden_gate::golden_gate_page,
+        mutagenesis::mutagenesis_page,
         navigation::{page_seq_selector, page_seq_top_selector, PageSeq, PageSeqTop},
-        primer_qc::primer_details,
+        primer_qc::{fold_disp, primer_details},
         seq_view::sequence_vis,
     },
-    primer::make_cloning_primers,
-};
-// todo: monospace font for all seqs.
-use crate::{
-    gui::{COL_SPACING, ROW_SPACING},
+    gui::{int_field, COL_SPACING, ROW_SPACING},
     State,
 };
 
+/// Folding takes time proportional to the cube of length, so we limit the range size.
+const MAX_FOLD_LEN: usize = 500;
+
+/// Controls for editing a region of the sequence, keeping features in sync.
+fn edit_controls(state: &mut State, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        int_field(&mut state.ui.edit_range.start, "Start:", ui);
+        int_field(&mut state.ui.edit_range.end, "End:", ui);
+
+        ui.add_space(COL_SPACING);
+
+        ui.label("Nucleotides:");
+        let response = ui.add(TextEdit::singleline(&mut state.ui.edit_input).desired_width(200.));
+        if response.changed() {
+            state.ui.edit_input = seq_to_str(&seq_from_str(&state.ui.edit_input));
+        }
+
+        ui.add_space(COL_SPACING);
+
+        let range = state.ui.edit_range;
+        let insert = seq_from_str(&state.ui.edit_input);
+
+        if ui
+            .button("Insert")
+            .on_hover_text("Insert the nucleotides, starting at the start position.")
+            .clicked()
+        {
+            state.edit_seq("Insert", |g| g.insert(range.start, &insert));
+        }
+        if ui.button("Delete").clicked() {
+            state.edit_seq("Delete", |g| g.delete(range));
+        }
+        if ui
+            .button("Replace")
+            .on_hover_text("Replace the range with the nucleotides.")
+            .clicked()
+        {
+            state.edit_seq("Replace", |g| g.replace(range, &insert));
+        }
+        if ui.button("Reverse complement").clicked() {
+            state.edit_seq("Reverse complement", |g| g.reverse_complement(range));
+        }
+
+        ui.add_space(COL_SPACING);
+
+        if ui
+            .button("Copy")
+            .on_hover_text("Copy the range, and features entirely inside it.")
+            .clicked()
+        {
+            state.ui.edit_clipboard = Some(state.generic.extract(range));
+        }
+        if let Some(fragment) = state.ui.edit_clipboard.clone() {
+            if ui
+                .button("Paste")
+                .on_hover_text("Paste the copied range, starting at the start position.")
+                .clicked()
+            {
+                state.edit_seq("Paste", |g| g.paste(range.start, &fragment));
+            }
+        }
+
+        if ui
+            .button("Open as document")
+            .on_hover_text("Open the range, and features entirely inside it, as a new document.")
+            .clicked()
+        {
+            let mut fragment = state.generic.extract(range);
+            fragment.metadata.plasmid_name =
+                format!("{} {}", state.generic.metadata.plasmid_name, range);
+            state.open_document(fragment, Default::default());
+        }
+
+        ui.add_space(COL_SPACING);
+
+        let seq_len = state.generic.seq.len();
+        let foldable = range.is_valid(seq_len) && range.len(seq_len) <= MAX_FOLD_LEN;
+        if ui
+            .add_enabled(foldable, Button::new("Fold"))
+            .on_hover_text("Find the most stable secondary structure of the range, as single-stranded DNA.")
+            .on_disabled_hover_text(format!(
+                "Set a valid range of up to {MAX_FOLD_LEN} nucleotides to find its secondary structure."
+            ))
+            .clicked()
+        {
+            let seq = state.generic.extract(range).seq;
+            let fold = fold(&seq, &state.ion_concentrations);
+            state.ui.fold = Some((range, seq, fold));
+        }
+    });
+
+    if let Some((range, seq, fold)) = &state.ui.fold {
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.label(format!("Secondary structure of {range}:"));
+            close = ui.button("Close").clicked();
+        });
+        fold_disp(seq, fold, ui);
+
+        if close {
+            state.ui.fold = None;
+        }
+    }
+
+    if let Some(report) = &state.ui.edit_report {
+        ui.label(RichText::new(format!("Damaged by the last edit: {report}")).color(Color32::GOLD));
+    }
+}
+
 fn seq_editor(state: &mut State, ui: &mut Ui) {
     // ui.heading("Amplification");
 
@@ -26,14 +139,19 @@ fn seq_editor(state: &mut State, ui: &mut Ui) {
 
     ui.horizontal(|ui| {
         ui.heading("Sequence:");
-        ui.label(&format!("len: {}", state.ui.seq_input.len()));
+        ui.label(format!("len: {}", state.ui.seq_input.len()));
     });
 
+    edit_controls(state, ui);
+
+    ui.add_space(ROW_SPACING / 2.);
+
     let response = ui.add(TextEdit::multiline(&mut state.ui.seq_input).desired_width(800.));
     if response.changed() {
-        state.generic.seq = seq_from_str(&state.ui.seq_input);
-        state.ui.seq_input = seq_to_str(&state.generic.seq);
-        state.sync_seq_related(None);
+        let seq_new = seq_from_str(&state.ui.seq_input);
+        state.apply_seq_edit(|g| g.edit_from_seq(&seq_new));
+        // Typing is recorded as one action in the undo history, until another action.
+        state.history.commit_merged("Edit sequence", &state.generic);
     }
 }
 
@@ -56,18 +174,28 @@ fn seq_editor_slic(state: &mut State, ui: &mut Ui) {
         ui.add_space(COL_SPACING);
 
         if ui.button("➕ Make cloning primers").clicked() {
-            make_cloning_primers(state);
+            if let Some(primers) = make_cloning_primers(
+                &seq_from_str(&state.ui.seq_vector_input),
+                &seq_from_str(&state.ui.seq_insert_input),
+                state.insert_loc,
+                &state.ion_concentrations,
+            ) {
+                state.generic.primers.extend(primers);
+                state.sync_primer_matches(None); // note: Not requried to run on all primers.
+            }
+            state.commit("Make cloning primers");
         }
 
         if ui.button("Update seq with insert and vec").clicked() {
             state.sync_cloning_product();
             state.sync_seq_related(None);
+            state.commit("Insert into vector");
         }
     });
 
     ui.horizontal(|ui| {
         ui.heading("Insert:");
-        ui.label(&format!("len: {}", state.ui.seq_insert_input.len()));
+        ui.label(format!("len: {}", state.ui.seq_insert_input.len()));
     });
 
     let response = ui.add(
@@ -82,7 +210,7 @@ fn seq_editor_slic(state: &mut State, ui: &mut Ui) {
 
     ui.horizontal(|ui| {
         ui.heading("Vector:");
-        ui.label(&format!("len: {}", state.ui.seq_vector_input.len()));
+        ui.label(format!("len: {}", state.ui.seq_vector_input.len()));
     });
 
     let response = ui.add(
@@ -119,8 +247,20 @@ pub fn seq_page(state: &mut State, ui: &mut Ui) {
         PageSeq::EditSlic => {
             seq_editor_slic(state, ui);
         }
+        PageSeq::Assembly => {
+            assembly_page(state, ui);
+        }
+        PageSeq::GoldenGate => {
+            golden_gate_page(state, ui);
+        }
+        PageSeq::Mutagenesis => {
+            mutagenesis_page(state, ui);
+        }
+        PageSeq::Digest => {
+            digest_page(state, ui);
+        }
         PageSeq::View => {
-            ui.horizontal(|ui| {
+            ui.horizontal(|_ui| {
                 // todo: DRY with above
 
                 // todo: Impl insert loc changing A/R. Likely in a specialty cloning mode.
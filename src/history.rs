@@ -0,0 +1,166 @@
+//! This module contains undo and redo history for the document: the sequence, features, primers,
+//! and metadata. We store snapshots of the document; each action records the document as it
+//! was before the action.
+
+use bincode::{config, Decode, Encode};
+
+use crate::file_io::GenericData;
+
+/// Older entries are discarded past this.
+const MAX_HISTORY: usize = 100;
+
+#[derive(Clone, Encode, Decode)]
+pub struct HistoryEntry {
+    /// A description of the action, for display.
+    pub label: String,
+    /// The document on the other side of the action: Before it for undo, and after it for redo.
+    pub data: GenericData,
+}
+
+#[derive(Default, Clone)]
+pub struct History {
+    pub undo: Vec<HistoryEntry>,
+    pub redo: Vec<HistoryEntry>,
+    /// The document as of the last recorded action.
+    current: GenericData,
+    /// `current` in its encoded form, used to detect changes that weren't recorded explicitly.
+    current_bytes: Vec<u8>,
+    /// Set if the last action may be extended by more of the same, eg typing in a text field.
+    merge_label: Option<String>,
+    /// Set when a field editing the document changed this frame; we only compare the document to
+    /// `current` when this is set.
+    edited: bool,
+}
+
+impl Encode for History {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        // The current document is saved separately.
+        self.undo.encode(encoder)?;
+        self.redo.encode(encoder)?;
+
+        Ok(())
+    }
+}
+
+impl Decode for History {
+    fn decode<D: bincode::de::Decoder>(
+        decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        let undo = Vec::<HistoryEntry>::decode(decoder)?;
+        let redo = Vec::<HistoryEntry>::decode(decoder)?;
+
+        // Run `reset_current` after loading the document.
+        Ok(Self {
+            undo,
+            redo,
+            ..Default::default()
+        })
+    }
+}
+
+fn encode_doc(data: &GenericData) -> Vec<u8> {
+    bincode::encode_to_vec(data, config::standard()).unwrap_or_default()
+}
+
+impl History {
+    /// Clear history, eg when opening a new document.
+    pub fn reset(&mut self, data: &GenericData) {
+        *self = Default::default();
+        self.reset_current(data);
+    }
+
+    /// Set the current document without recording an action, eg after loading history from a file.
+    pub fn reset_current(&mut self, data: &GenericData) {
+        self.current = data.clone();
+        self.current_bytes = encode_doc(data);
+        self.merge_label = None;
+    }
+
+    /// Record an action, after it's been applied to the document.
+    pub fn commit(&mut self, label: &str, data: &GenericData) {
+        self.push(label, data);
+        self.merge_label = None;
+    }
+
+    /// Record an action that further actions with the same label are merged into, until a different
+    /// one is recorded. Use this for continuous edits, like typing.
+    pub fn commit_merged(&mut self, label: &str, data: &GenericData) {
+        if self.merge_label.as_deref() != Some(label) {
+            self.push(label, data);
+            self.merge_label = Some(label.to_owned());
+        } else {
+            self.current = data.clone();
+            self.current_bytes = encode_doc(data);
+        }
+    }
+
+    fn push(&mut self, label: &str, data: &GenericData) {
+        let before = std::mem::replace(&mut self.current, data.clone());
+        self.current_bytes = encode_doc(data);
+
+        self.undo.push(HistoryEntry {
+            label: label.to_owned(),
+            data: before,
+        });
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+
+        self.redo = Vec::new();
+    }
+
+    /// Flag that a field editing the document changed, eg a feature's label. The next `sync` records
+    /// the change.
+    pub fn mark_edited(&mut self) {
+        self.edited = true;
+    }
+
+    /// Record changes that weren't recorded explicitly, eg from editing fields in a table. Run this once
+    /// per frame; it only checks the document if `mark_edited` was called since the last run. Returns
+    /// true if there were changes.
+    pub fn sync(&mut self, label: &str, data: &GenericData) -> bool {
+        if !std::mem::take(&mut self.edited) || encode_doc(data) == self.current_bytes {
+            return false;
+        }
+
+        self.commit_merged(label, data);
+        true
+    }
+
+    /// Undo the last action, updating the document. Returns false if there's nothing to undo.
+    pub fn undo(&mut self, data: &mut GenericData) -> bool {
+        let Some(entry) = self.undo.pop() else {
+            return false;
+        };
+
+        self.redo.push(HistoryEntry {
+            label: entry.label,
+            data: std::mem::replace(&mut self.current, entry.data),
+        });
+        self.restore(data);
+        true
+    }
+
+    /// Redo the last undone action, updating the document. Returns false if there's nothing to redo.
+    pub fn redo(&mut self, data: &mut GenericData) -> bool {
+        let Some(entry) = self.redo.pop() else {
+            return false;
+        };
+
+        self.undo.push(HistoryEntry {
+            label: entry.label,
+            data: std::mem::replace(&mut self.current, entry.data),
+        });
+        self.restore(data);
+        true
+    }
+
+    fn restore(&mut self, data: &mut GenericData) {
+        *data = self.current.clone();
+        self.current_bytes = encode_doc(data);
+        self.merge_label = None;
+    }
+}
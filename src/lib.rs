@@ -0,0 +1,95 @@
+//! PlasCAD: Tools for plasmid and primer design, PCR, and related. This library contains sequence
+//! and primer data structures, melting temperature and primer QC calculations, and reading and writing
+//! FASTA, GenBank, SnapGene, and PlasCAD files. It doesn't depend on the GUI; the `plascad` application
+//! is built on it, with the `gui` feature.
+
+use bincode::{Decode, Encode};
+
+use crate::melting_temp_calcs::TmConfig;
+
+pub mod assembly;
+pub mod digest;
+pub mod dimer;
+mod features_known;
+pub mod file_io;
+pub mod golden_gate;
+pub mod history;
+pub mod melting_temp_calcs;
+pub mod mutagenesis;
+pub mod off_target;
+pub mod pcr;
+pub mod primer;
+pub mod primer_design;
+pub mod primer_metrics;
+pub mod protein;
+pub mod restriction_enzyme;
+mod save_compat;
+pub mod secondary_structure;
+pub mod seq_edit;
+pub mod sequence;
+mod solution_helper;
+#[cfg(test)]
+mod test_fixtures;
+pub mod toxic_proteins;
+pub mod util;
+
+pub type Color = (u8, u8, u8); // RGB
+
+#[derive(Clone, PartialEq, Encode, Decode)]
+/// Concentrations of common ions in the oglio solution. Affects melting temperature (TM).
+/// All values are in milliMolar.
+pub struct IonConcentrations {
+    /// Na+ or K+
+    pub monovalent: f32,
+    /// Mg2+
+    pub divalent: f32,
+    pub dntp: f32,
+    /// Primer concentration, in nM.
+    pub primer: f32,
+    /// Tris buffer. This counts as half its concentration of monovalent ions.
+    pub tris: f32,
+    /// How we calculate Tm, including DMSO and formamide corrections.
+    pub tm_config: TmConfig,
+}
+
+impl Default for IonConcentrations {
+    fn default() -> Self {
+        // todo: Adjust A/R
+        Self {
+            monovalent: 50.,
+            divalent: 1.5,
+            dntp: 0.2,
+            primer: 25.,
+            tris: 0.,
+            tm_config: Default::default(),
+        }
+    }
+}
+
+/// Based on GenBank's reference format
+#[derive(Default, Clone, Encode, Decode)]
+pub struct Reference {
+    pub description: String,
+    pub authors: Option<String>,
+    pub consortium: Option<String>,
+    pub title: String,
+    pub journal: Option<String>,
+    pub pubmed: Option<String>,
+    pub remark: Option<String>,
+}
+
+/// Contains sequence-level metadata.
+#[derive(Clone, Default, Encode, Decode)]
+pub struct Metadata {
+    pub plasmid_name: String,
+    pub comments: Vec<String>,
+    pub references: Vec<Reference>,
+    pub locus: String,
+    pub definition: Option<String>,
+    pub accession: Option<String>,
+    pub version: Option<String>,
+    // pub keywords: Vec<String>,
+    pub keywords: Option<String>, // todo vec?
+    pub source: Option<String>,
+    pub organism: Option<String>,
+}
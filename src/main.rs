@@ -1,74 +1,59 @@
 // Disables the terminal window. Use this for releases, but disable when debugging.
 // #![windows_subsystem = "windows"]
 
-use std::{io, path::PathBuf, sync::Arc};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use bincode::{Decode, Encode};
 use eframe::{self, egui, egui::Context};
 use egui_file_dialog::FileDialog;
-use file_io::save::{load, StateToSave, DEFAULT_SAVE_FILE};
 use gui::navigation::{Page, PageSeq};
-use sequence::{seq_from_str, Seq};
-
-use crate::{
-    file_io::GenericData,
-    gui::{navigation::PageSeqTop, WINDOW_HEIGHT, WINDOW_TITLE, WINDOW_WIDTH},
+use plascad::{
+    assembly::{AssemblyDesign, AssemblyError, AssemblyParams},
+    digest::{digest, fragment_bands, Fragment, Ladder},
+    dimer::{calc_dimers, DimerAnalysis},
+    file_io::{
+        error::{FileError, FileReport, Imported},
+        rebase::{load_enzyme_sets, DEFAULT_ENZYME_SETS_FILE},
+        save::{ProjectToSave, StateToSave, DEFAULT_SAVE_FILE},
+        GenericData,
+    },
+    golden_gate::{GoldenGateDesign, GoldenGateError, GoldenGateParams},
+    history::History,
+    mutagenesis::{
+        CodonRandomization, MutagenesisDesign, MutagenesisError, MutagenesisParams, Mutation,
+        MutationKind,
+    },
+    off_target::{OffTargetParams, TargetSeq},
     pcr::{PcrParams, PolymeraseType},
     primer::TM_TARGET,
-    restriction_enzyme::{load_re_library, ReMatch, RestrictionEnzyme},
+    primer_design::{DesignParams, PrimerPair},
+    protein::{AaIdent, GeneticCode},
+    restriction_enzyme::{
+        default_enzyme_sets, find_re_matches, load_re_library, EnzymeSet, ReMatch,
+        RestrictionEnzyme,
+    },
+    secondary_structure::Fold,
+    seq_edit::EditReport,
     sequence::{
-        find_orf_matches, seq_to_str, FeatureDirection, FeatureType, ReadingFrame,
-        ReadingFrameMatch,
+        find_orf_matches, seq_from_str, seq_to_str, FeatureDirection, FeatureType, OrfParams,
+        ReadingFrame, ReadingFrameMatch, Seq, SeqRange,
     },
+    Color, IonConcentrations,
 };
-use crate::sequence::Feature;
-
-mod features_known;
-mod file_io;
-mod gui;
-mod melting_temp_calcs;
-mod pcr;
-mod primer;
-mod primer_metrics;
-mod restriction_enzyme;
-mod save_compat;
-mod sequence;
-mod solution_helper;
-mod toxic_proteins;
-mod util;
-
-type Color = (u8, u8, u8); // RGB
-
-#[derive(Clone, Copy)]
-enum AminoAcid {
-    Met,
-    Ser,
-    Ile,
-    Gln,
-    His,
-    Phe,
-    Arg,
-    Val,
-    Leu,
-    Cys,
-    Asp,
-}
 
-struct PlasmidData {
-    // todo : Which seq type? There are many.
-    seq_expected: Seq,
-    seq_read_assembled: Seq,
-    // seq_reads: Vec<SequenceRead>,
-}
-
-fn check_seq_integrity(data: &PlasmidData) {}
-
-fn check_toxic_proteins(data: &PlasmidData) {}
+use crate::{
+    gui::{
+        navigation::PageSeqTop, off_target::OffTargetResult, WINDOW_HEIGHT, WINDOW_TITLE,
+        WINDOW_WIDTH,
+    },
+    workspace::{Clipboard, Workspace},
+};
 
-fn check_all(data: &PlasmidData) {
-    check_seq_integrity(data);
-    check_toxic_proteins(data);
-}
+mod gui;
+mod workspace;
 
 impl eframe::App for State {
     /// This is the GUI's event loop.
@@ -99,31 +84,150 @@ impl Default for PcrUi {
     }
 }
 
-#[derive(Clone, Encode, Decode)]
-/// Concentrations of common ions in the oglio solution. Affects melting temperature (TM).
-/// All values are in milliMolar.
-struct IonConcentrations {
-    /// Na+ or K+
-    pub monovalent: f32,
-    /// Mg2+
-    pub divalent: f32,
-    pub dntp: f32,
-    /// Primer concentration, in nM.
-    pub primer: f32,
+/// Constraints and results for primer pair design.
+#[derive(Default)]
+struct PrimerDesignUi {
+    pub params: DesignParams,
+    /// The feature selected as the target, if any. Otherwise, the target is a range.
+    pub feature: Option<usize>,
+    pub results: Vec<PrimerPair>,
+    /// If a design has been run, to report when it found nothing.
+    pub searched: bool,
+}
+
+/// A fragment for overlap assembly: A range of a document in the workspace.
+#[derive(Clone)]
+struct AssemblyFragmentUi {
+    /// Index in the workspace.
+    pub document: usize,
+    /// This may wrap the origin, eg to linearize a circular vector.
+    pub range: SeqRange,
+    /// Assemble the fragment's reverse complement.
+    pub reverse: bool,
+    /// Amplify the fragment with primers that add overlaps, vice using it as it is.
+    pub amplify: bool,
+}
+
+/// Fragments, in order, and results for overlap assembly design.
+#[derive(Default)]
+struct AssemblyUi {
+    pub fragments: Vec<AssemblyFragmentUi>,
+    pub params: AssemblyParams,
+    pub result: Option<Result<AssemblyDesign, AssemblyError>>,
+}
+
+/// Parts, in order, overhangs, and results for Golden Gate assembly design.
+#[derive(Default)]
+struct GoldenGateUi {
+    /// Parts are always amplified; their `amplify` field is unused.
+    pub parts: Vec<AssemblyFragmentUi>,
+    pub params: GoldenGateParams,
+    /// The overhang before each part, as entered.
+    pub overhangs_input: Vec<String>,
+    pub result: Option<Result<GoldenGateDesign, GoldenGateError>>,
+}
+
+/// The change, and results, for site-directed mutagenesis of the active document.
+struct MutagenesisUi {
+    /// The mutation's nucleotides are set from `seq_input` when designing.
+    pub mutation: Mutation,
+    pub seq_input: String,
+    /// For setting the mutation from a codon: A coding region feature, by index, a 1-based amino acid
+    /// position in it, and the new amino acid's one-letter code.
+    pub feature: Option<usize>,
+    pub aa_pos: usize,
+    pub aa_input: String,
+    /// For saturation libraries, at the same codon.
+    pub randomization: CodonRandomization,
+    pub params: MutagenesisParams,
+    /// Each design, with its portion of the primer mix. Saturation libraries may have several.
+    pub result: Option<Result<Vec<(MutagenesisDesign, f32)>, MutagenesisError>>,
+}
+
+impl Default for MutagenesisUi {
+    fn default() -> Self {
+        Self {
+            mutation: Mutation {
+                kind: MutationKind::Substitution,
+                range: SeqRange::new(1, 1),
+                seq: Vec::new(),
+            },
+            seq_input: String::new(),
+            feature: None,
+            aa_pos: 1,
+            aa_input: String::new(),
+            randomization: Default::default(),
+            params: Default::default(),
+            result: None,
+        }
+    }
+}
+
+/// Browsing the restriction enzyme library, and editing enzyme sets.
+struct EnzymesUi {
+    /// The set whose sites are shown in the sequence view, by index. All enzymes if `None`.
+    pub display_set: Option<usize>,
+    /// The set enzymes are added to and removed from in the library table, by index.
+    pub edit_set: Option<usize>,
+    pub new_set_name: String,
+    /// Show enzymes whose name or site contains this.
+    pub search: String,
+    /// Show enzymes available from any of these REBASE supplier codes; all if empty.
+    pub suppliers: String,
+    /// Where the library was loaded from, if not the built-in one.
+    pub library_path: Option<PathBuf>,
+}
+
+impl Default for EnzymesUi {
+    fn default() -> Self {
+        Self {
+            // The first default set, of common enzymes.
+            display_set: Some(0),
+            edit_set: None,
+            new_set_name: String::new(),
+            search: String::new(),
+            suppliers: String::new(),
+            library_path: None,
+        }
+    }
+}
+
+/// Enzymes, and gel settings, for a virtual digest of the active document.
+struct DigestUi {
+    /// Enzymes by name, so the selection survives loading another library.
+    pub enzymes: Vec<String>,
+    /// Enzymes whose name contains this are offered for adding.
+    pub search: String,
+    pub ladder: Ladder,
+    /// A second ladder, on the gel's other side.
+    pub ladder_2: Option<Ladder>,
+    /// Agarose concentration, in percent.
+    pub agarose: f32,
 }
 
-impl Default for IonConcentrations {
+impl Default for DigestUi {
     fn default() -> Self {
-        // todo: Adjust A/R
         Self {
-            monovalent: 50.,
-            divalent: 1.5,
-            dntp: 0.2,
-            primer: 25.,
+            enzymes: Vec::new(),
+            search: String::new(),
+            ladder: Ladder::OneKb,
+            ladder_2: None,
+            agarose: 1.,
         }
     }
 }
 
+/// Background sequences and results for off-target priming analysis.
+#[derive(Default)]
+struct OffTargetUi {
+    /// If each document in the workspace is included in the background, by index.
+    pub documents: Vec<bool>,
+    /// Sequences loaded from files, eg a host genome.
+    pub files: Vec<TargetSeq>,
+    pub params: OffTargetParams,
+    pub result: Option<OffTargetResult>,
+}
+
 #[derive(Default)]
 struct StateFeatureAdd {
     // This is in 1-based indexing.
@@ -144,6 +248,9 @@ struct SeqVisibility {
     /// todo: Show and hide individual features?
     show_features: bool,
     show_reading_frame: bool,
+    /// Show amino acids below coding regions, and open reading frames.
+    show_translation: bool,
+    #[allow(dead_code)] // todo: Implement.
     show_start_stop_codons: bool,
 }
 
@@ -154,6 +261,7 @@ impl Default for SeqVisibility {
             show_primers: true,
             show_features: true,
             show_reading_frame: true,
+            show_translation: true,
             show_start_stop_codons: false,
         }
     }
@@ -166,6 +274,10 @@ struct FileDialogs {
     export_fasta: FileDialog,
     export_genbank: FileDialog,
     export_dna: FileDialog,
+    save_project: FileDialog,
+    load_project: FileDialog,
+    load_background: FileDialog,
+    load_rebase: FileDialog,
     selected: Option<PathBuf>,
 }
 
@@ -177,7 +289,11 @@ impl Default for FileDialogs {
             // })
             .add_file_filter(
                 "PlasCAD files",
-                Arc::new(|p| p.extension().unwrap_or_default().to_ascii_lowercase() == "pcad"),
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("pcad")
+                }),
             )
             .default_file_filter("PlasCAD files")
             .id("0");
@@ -186,14 +302,22 @@ impl Default for FileDialogs {
         let load_ = FileDialog::new()
             .add_file_filter(
                 "PlasCAD files",
-                Arc::new(|p| p.extension().unwrap_or_default().to_ascii_lowercase() == "pcad"),
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("pcad")
+                }),
             )
             .id("1");
 
         let import = FileDialog::new()
             .add_file_filter(
                 "FASTA files",
-                Arc::new(|p| p.extension().unwrap_or_default().to_ascii_lowercase() == "fasta"),
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("fasta")
+                }),
             )
             .add_file_filter(
                 "GenBank files",
@@ -204,7 +328,11 @@ impl Default for FileDialogs {
             )
             .add_file_filter(
                 "SnapGene DNA files",
-                Arc::new(|p| p.extension().unwrap_or_default().to_ascii_lowercase() == "dna"),
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("dna")
+                }),
             )
             .add_file_filter(
                 // Note: We experience glitches if this name is too long. (Window extends horizontally)
@@ -220,7 +348,11 @@ impl Default for FileDialogs {
         let export_fasta = FileDialog::new()
             .add_file_filter(
                 "FASTA files",
-                Arc::new(|p| p.extension().unwrap_or_default().to_ascii_lowercase() == "fasta"),
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("fasta")
+                }),
             )
             .default_file_filter("FASTA files")
             .id("3");
@@ -239,11 +371,52 @@ impl Default for FileDialogs {
         let export_dna = FileDialog::new()
             .add_file_filter(
                 "SnapGene DNA files",
-                Arc::new(|p| p.extension().unwrap_or_default().to_ascii_lowercase() == "dna"),
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("dna")
+                }),
             )
             .default_file_filter("SnapGene DNA files")
             .id("5");
 
+        let save_project = FileDialog::new()
+            .add_file_filter(
+                "PlasCAD projects",
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("pcadproj")
+                }),
+            )
+            .default_file_filter("PlasCAD projects")
+            .id("6");
+
+        let load_project = FileDialog::new()
+            .add_file_filter(
+                "PlasCAD projects",
+                Arc::new(|p| {
+                    p.extension()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case("pcadproj")
+                }),
+            )
+            .id("7");
+
+        let load_background = FileDialog::new()
+            .add_file_filter(
+                "FASTA/GB/SnapGene",
+                Arc::new(|p| {
+                    let ext = p.extension().unwrap_or_default().to_ascii_lowercase();
+                    ext == "fasta" || ext == "fa" || ext == "gb" || ext == "gbk" || ext == "dna"
+                }),
+            )
+            .default_file_filter("FASTA/GB/SnapGene")
+            .id("8");
+
+        // REBASE files are named by format and version, eg withrefm.404, so we don't filter them.
+        let load_rebase = FileDialog::new().id("9");
+
         Self {
             save,
             load: load_,
@@ -251,6 +424,10 @@ impl Default for FileDialogs {
             export_fasta,
             export_genbank,
             export_dna,
+            save_project,
+            load_project,
+            load_background,
+            load_rebase,
             selected: None,
         }
     }
@@ -267,9 +444,15 @@ struct StateUi {
     seq_vector_input: String,
     seq_input: String,
     pcr: PcrUi,
+    primer_design: PrimerDesignUi,
+    off_target: OffTargetUi,
+    assembly: AssemblyUi,
+    golden_gate: GoldenGateUi,
+    mutagenesis: MutagenesisUi,
+    enzymes: EnzymesUi,
+    digest: DigestUi,
     feature_add: StateFeatureAdd,
     primer_selected: Option<usize>,
-    feature_selected: Option<usize>,
     feature_hover: Option<usize>,
     seq_visibility: SeqVisibility,
     hide_map_feature_editor: bool,
@@ -279,6 +462,23 @@ struct StateUi {
     /// Show or hide the field to change origin
     show_origin_change: bool,
     new_origin: usize,
+    /// Display amino acids with one or three letters.
+    aa_ident: AaIdent,
+    /// Find ORFs in all six reading frames, vice only the selected one.
+    orf_all_frames: bool,
+    /// Range and nucleotides used by the sequence edit controls.
+    edit_range: SeqRange,
+    edit_input: String,
+    /// Sequence and features copied with the edit controls, for pasting.
+    edit_clipboard: Option<GenericData>,
+    /// Annotations damaged by the most recent sequence edit.
+    edit_report: Option<EditReport>,
+    /// Labels of copied features that weren't pasted, since their sequence isn't in the document.
+    paste_not_found: Vec<String>,
+    /// Errors and warnings from the most recent file operation.
+    file_report: Option<FileReport>,
+    /// The secondary structure of a range, and its nucleotides, from the edit controls.
+    fold: Option<(SeqRange, Seq, Fold)>,
 }
 
 impl Default for StateUi {
@@ -291,9 +491,15 @@ impl Default for StateUi {
             seq_vector_input: Default::default(),
             seq_input: Default::default(),
             pcr: Default::default(),
+            primer_design: Default::default(),
+            off_target: Default::default(),
+            assembly: Default::default(),
+            golden_gate: Default::default(),
+            mutagenesis: Default::default(),
+            enzymes: Default::default(),
+            digest: Default::default(),
             feature_add: Default::default(),
             primer_selected: None,
-            feature_selected: Default::default(),
             feature_hover: Default::default(),
             seq_visibility: Default::default(),
             hide_map_feature_editor: true,
@@ -302,56 +508,42 @@ impl Default for StateUi {
             file_dialogs: Default::default(),
             show_origin_change: false,
             new_origin: 0,
+            aa_ident: Default::default(),
+            orf_all_frames: true,
+            edit_range: Default::default(),
+            edit_input: Default::default(),
+            edit_clipboard: None,
+            edit_report: None,
+            paste_not_found: Vec::new(),
+            file_report: None,
+            fold: None,
         }
     }
 }
 
+#[derive(Default)]
 pub enum Selection {
     Feature(usize), // index
     Primer(usize),
+    #[default]
     None,
 }
 
-impl Default for Selection {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
-/// Based on GenBank's reference format
-#[derive(Default, Clone, Encode, Decode)]
-pub struct Reference {
-    pub description: String,
-    pub authors: Option<String>,
-    pub consortium: Option<String>,
-    pub title: String,
-    pub journal: Option<String>,
-    pub pubmed: Option<String>,
-    pub remark: Option<String>,
-}
-
 /// This struct contains state that does not need to persist between sessesions or saves, but is not
 /// a good fit for `StateUi`. This is, generally, calculated data from persistent staet.
 #[derive(Default)]
 struct StateVolatile {
     restriction_enzyme_sites: Vec<ReMatch>,
     reading_frame_matches: Vec<ReadingFrameMatch>,
-}
-
-/// Contains sequence-level metadata.
-#[derive(Clone, Default, Encode, Decode)]
-pub struct Metadata {
-    pub plasmid_name: String,
-    pub comments: Vec<String>,
-    pub references: Vec<Reference>,
-    pub locus: String,
-    pub definition: Option<String>,
-    pub accession: Option<String>,
-    pub version: Option<String>,
-    // pub keywords: Vec<String>,
-    pub keywords: Option<String>, // todo vec?
-    pub source: Option<String>,
-    pub organism: Option<String>,
+    /// The enzymes selected on the digest page; fragment ends refer to them by index.
+    digest_enzymes: Vec<RestrictionEnzyme>,
+    digest_fragments: Vec<Fragment>,
+    /// The digest's lane on the gel.
+    digest_bands: Vec<(usize, f32)>,
+    /// Dimers the selected primer forms with each primer, including itself.
+    primer_dimers: Vec<DimerAnalysis>,
+    /// The selected primer, primer sequences, and ion concentrations `primer_dimers` is from.
+    primer_dimers_input: Option<(usize, Vec<Seq>, IonConcentrations)>,
 }
 
 /// Note: use of serde traits here and on various sub-structs are for saving and loading.
@@ -370,8 +562,22 @@ struct State {
     ion_concentrations: IonConcentrations,
     pcr: PcrParams,
     restriction_enzyme_lib: Vec<RestrictionEnzyme>, // Does not need to be saved
+    /// Named selections from the library. These are saved to their own file, vice with documents.
+    enzyme_sets: Vec<EnzymeSet>,
+    #[allow(dead_code)] // todo: Implement.
     selected_item: Selection,
     reading_frame: ReadingFrame,
+    /// Used to translate coding regions and reading frames.
+    genetic_code: GeneticCode,
+    orf_params: OrfParams,
+    /// Undo and redo history for the document.
+    history: History,
+    /// Save undo and redo history along with the document.
+    save_history: bool,
+    /// All open documents. The active one's data is in `generic` and `history`.
+    workspace: Workspace,
+    /// Features and primers copied for pasting into other documents. Does not need to be saved.
+    clipboard: Clipboard,
     volatile: StateVolatile,
 }
 
@@ -384,71 +590,134 @@ impl State {
         };
 
         for primer in primers {
-            primer.volatile.matches_seq = primer.match_to_seq(&self.generic.seq);
+            primer.volatile.matches_seq = primer.find_binding_sites(
+                &self.generic.seq,
+                self.generic.topology,
+                &self.ion_concentrations,
+                &Default::default(),
+            );
         }
     }
 
     pub fn sync_pcr(&mut self) {
-        self.pcr = PcrParams::new(&self.ui.pcr);
+        let data = &self.ui.pcr;
+        self.pcr = PcrParams::new(
+            data.primer_tm,
+            data.product_len,
+            data.polymerase_type,
+            data.num_cycles,
+        );
     }
 
-    /// Identify restriction enzyme sites in the sequence
+    /// Identify restriction enzyme sites in the sequence, for the enzymes in the displayed set.
     pub fn sync_re_sites(&mut self) {
-        self.volatile.restriction_enzyme_sites = Vec::new();
-
-        // let seq_comp = seq_complement(seq);
-
-        for (lib_index, re) in self.restriction_enzyme_lib.iter().enumerate() {
-            // todo: Use bio lib?
-            let seq_len = self.generic.seq.len();
-            for i in 0..seq_len {
-                if i + re.seq.len() + 1 >= seq_len {
-                    continue;
-                }
+        let lib = &self.restriction_enzyme_lib;
+        let indices = match self
+            .ui
+            .enzymes
+            .display_set
+            .and_then(|i| self.enzyme_sets.get(i))
+        {
+            Some(set) => set.indices(lib),
+            None => (0..lib.len()).collect(),
+        };
+        let enzymes: Vec<RestrictionEnzyme> = indices.iter().map(|i| lib[*i].clone()).collect();
 
-                if re.seq == self.generic.seq[i..i + re.seq.len()] {
-                    self.volatile.restriction_enzyme_sites.push(ReMatch {
-                        lib_index,
-                        seq_index: i,
-                        // direction: PrimerDirection::Forward,
-                    });
-                }
-                // todo: Simpler way?
-                // todo: Evaluate if this is what you want.
-                // if re.seq == seq_comp[i..i + re.seq.len()] {
-                //     self.restriction_enzyme_sites.push(ReMatch {
-                //         lib_index: i_re,
-                //         seq_index: i,
-                //         direction: PrimerDirection::Reverse,
-                //     });
-                // }
-            }
+        let mut matches = find_re_matches(&self.generic.seq, self.generic.topology, &enzymes);
+        // Matches index the full library.
+        for m in &mut matches {
+            m.lib_index = indices[m.lib_index];
         }
+        self.volatile.restriction_enzyme_sites = matches;
+    }
 
-        // This sorting aids in our up/down label alternation in the display.
-        self.volatile
-            .restriction_enzyme_sites
-            .sort_by(|a, b| a.seq_index.cmp(&b.seq_index));
+    /// Digest the sequence with the enzymes selected on the digest page. Run this when the sequence, or
+    /// the selection, changes.
+    pub fn sync_digest(&mut self) {
+        let lib = &self.restriction_enzyme_lib;
+        self.volatile.digest_enzymes = self
+            .ui
+            .digest
+            .enzymes
+            .iter()
+            .filter_map(|name| {
+                lib.iter()
+                    .find(|re| re.name.eq_ignore_ascii_case(name))
+                    .cloned()
+            })
+            .collect();
+
+        self.volatile.digest_fragments = digest(
+            &self.generic.seq,
+            self.generic.topology,
+            &self.volatile.digest_enzymes,
+            &self.generic.features,
+        );
+        self.volatile.digest_bands = fragment_bands(&self.volatile.digest_fragments);
     }
 
     pub fn sync_reading_frame(&mut self) {
-        self.volatile.reading_frame_matches =
-            find_orf_matches(&self.generic.seq, self.reading_frame);
+        let frames = if self.ui.orf_all_frames {
+            &ReadingFrame::ALL[..]
+        } else {
+            &[self.reading_frame]
+        };
+
+        self.volatile.reading_frame_matches = find_orf_matches(
+            &self.generic.seq,
+            self.generic.topology,
+            frames,
+            self.genetic_code,
+            &self.orf_params,
+        );
     }
 
     pub fn sync_primer_metrics(&mut self) {
         for primer in &mut self.generic.primers {
             primer.volatile.sequence_input = seq_to_str(&primer.sequence);
 
-            if let Some(metrics) = &mut primer.volatile.metrics {
-                metrics.update_scores();
-            }
-            if primer.volatile.metrics.is_none() {
+            if primer.volatile.metrics.is_some() {
+                // Recalculate, since some metrics, eg dimers, aren't saved.
+                primer.volatile.metrics = primer.calc_metrics(&self.ion_concentrations);
+            } else {
                 primer.run_calcs(&self.ion_concentrations);
             }
         }
     }
 
+    /// Find dimers between the selected primer and each primer, if the selection, primers, or ion
+    /// concentrations changed since we last did.
+    pub fn sync_primer_dimers(&mut self) {
+        let Some((sel_i, selected)) = self
+            .ui
+            .primer_selected
+            .and_then(|i| Some((i, self.generic.primers.get(i)?)))
+        else {
+            self.volatile.primer_dimers = Vec::new();
+            self.volatile.primer_dimers_input = None;
+            return;
+        };
+
+        let seqs = self
+            .generic
+            .primers
+            .iter()
+            .map(|p| p.sequence.clone())
+            .collect();
+        let input = Some((sel_i, seqs, self.ion_concentrations.clone()));
+        if input == self.volatile.primer_dimers_input {
+            return;
+        }
+
+        self.volatile.primer_dimers = self
+            .generic
+            .primers
+            .iter()
+            .map(|p| calc_dimers(&selected.sequence, &p.sequence, &self.ion_concentrations))
+            .collect();
+        self.volatile.primer_dimers_input = input;
+    }
+
     /// Update the combined SLIC vector + insert sequence.
     pub fn sync_cloning_product(&mut self) {
         let seq_vector = seq_from_str(&self.ui.seq_vector_input);
@@ -476,26 +745,136 @@ impl State {
         self.sync_primer_matches(primer_i);
         self.sync_re_sites();
         self.sync_reading_frame();
+        self.sync_digest();
 
         self.ui.seq_input = seq_to_str(&self.generic.seq);
     }
 
-    /// Load state from a (our format) file.
+    /// Edit the sequence, and record the edit in the undo history, with a label describing it.
+    pub fn edit_seq(&mut self, label: &str, edit: impl FnOnce(&mut GenericData) -> EditReport) {
+        self.apply_seq_edit(edit);
+        self.commit(label);
+    }
+
+    /// Edit the sequence, keeping features and primers in sync, and record damaged annotations.
+    pub fn apply_seq_edit(&mut self, edit: impl FnOnce(&mut GenericData) -> EditReport) {
+        let report = edit(&mut self.generic);
+        self.ui.edit_report = if report.is_empty() {
+            None
+        } else {
+            Some(report)
+        };
+
+        self.sync_seq_related(None);
+    }
+
+    /// Record an action in the undo history. Run this after the action, and after syncing data
+    /// derived from the document.
+    pub fn commit(&mut self, label: &str) {
+        self.history.commit(label, &self.generic);
+    }
+
+    /// Undo the last action in the history.
+    pub fn undo(&mut self) {
+        if self.history.undo(&mut self.generic) {
+            self.sync_after_history();
+        }
+    }
+
+    /// Redo the last undone action in the history.
+    pub fn redo(&mut self) {
+        if self.history.redo(&mut self.generic) {
+            self.sync_after_history();
+        }
+    }
+
+    fn sync_after_history(&mut self) {
+        self.ui.edit_report = None;
+        self.sync_primer_metrics();
+        self.sync_seq_related(None);
+        // Syncing updates derived data that's stored with the document; this isn't a new action.
+        self.history.reset_current(&self.generic);
+    }
+
+    /// The active document, and settings to save with it.
+    pub fn to_save(&self) -> StateToSave {
+        StateToSave {
+            generic: self.generic.clone(),
+            insert_loc: self.insert_loc,
+            ion_concentrations: self.ion_concentrations.clone(),
+            reading_frame: self.reading_frame,
+            history: self.save_history.then(|| self.history.clone()),
+        }
+    }
+
+    /// Used to load to state. The result is data from the saved document, augmented with default
+    /// values.
+    pub fn from_save(s: StateToSave) -> Self {
+        Self {
+            generic: s.generic,
+            insert_loc: s.insert_loc,
+            ion_concentrations: s.ion_concentrations,
+            reading_frame: s.reading_frame,
+            save_history: s.history.is_some(),
+            // Run `History::reset_current` once the state is synced.
+            history: s.history.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Load state from a (our format) file. If the file can't be read, this uses a default state, and
+    /// reports the error.
     pub fn load(path: &str) -> Self {
-        let state_loaded: io::Result<StateToSave> = load(path);
-        let mut result = match state_loaded {
-            Ok(s) => s.to_state(),
-            Err(_) => Default::default(),
+        let (report, loaded) = FileReport::from_import(
+            &format!("Loading {path}"),
+            StateToSave::load(Path::new(path)),
+        );
+
+        let mut result = match loaded {
+            Some(s) => Self::from_save(s),
+            None => Default::default(),
         };
 
-        result.restriction_enzyme_lib = load_re_library();
+        // There's no file to load the first time the program is run.
+        let not_found =
+            matches!(&report.error, Some(FileError::Io(e)) if e.kind() == ErrorKind::NotFound);
+        if !report.is_clean() && !not_found {
+            result.ui.file_report = Some(report);
+        }
 
-        result.sync_pcr();
-        result.sync_primer_metrics();
-        result.sync_seq_related(None);
-        result.ui.seq_input = seq_to_str(&result.generic.seq);
+        result.init_loaded()
+    }
+
+    /// Load state from a project file, containing several documents.
+    pub fn load_project(path: &str) -> Result<Imported<Self>, FileError> {
+        let project = ProjectToSave::load(Path::new(path))?;
+        Ok(project.map(|p| Self::from_project(p).init_loaded()))
+    }
+
+    /// Set up state loaded from a file; this includes data that isn't saved.
+    fn init_loaded(mut self) -> Self {
+        self.restriction_enzyme_lib = load_re_library();
+
+        // There's no file until the user edits a set.
+        self.enzyme_sets = match load_enzyme_sets(Path::new(DEFAULT_ENZYME_SETS_FILE)) {
+            Err(FileError::Io(e)) if e.kind() == ErrorKind::NotFound => default_enzyme_sets(),
+            result => {
+                let action = format!("Loading {DEFAULT_ENZYME_SETS_FILE}");
+                let (report, sets) = FileReport::from_import(&action, result);
+                if !report.is_clean() && self.ui.file_report.is_none() {
+                    self.ui.file_report = Some(report);
+                }
+                sets.unwrap_or_else(default_enzyme_sets)
+            }
+        };
+
+        self.sync_pcr();
+        self.sync_primer_metrics();
+        self.sync_seq_related(None);
+        self.ui.seq_input = seq_to_str(&self.generic.seq);
+        self.history.reset_current(&self.generic);
 
-        result
+        self
     }
 }
 
@@ -6,305 +6,568 @@
 //!
 //! The calculations are based primarily on [SantaLucia & Hicks (2004)](https://pubmed.ncbi.nlm.nih.gov/15139820/)
 
+use std::fmt::Display;
+
+use bincode::{Decode, Encode};
+
 use crate::{
     primer::{calc_gc, MIN_PRIMER_LEN},
     sequence::Nucleotide::{self, A, C, G, T},
     IonConcentrations,
 };
 
-/// Enthalpy (dH) and entropy (dS) tables based on terminal missmatch
-fn _dH_dS_tmm(nts: (Nucleotide, Nucleotide)) -> Option<(f32, f32)> {
-    match nts {
-        (A, A) => Some((-7.6, -21.3)),
-        (A, T) => Some((-7.2, -20.4)),
-        (T, A) => Some((-7.2, -21.3)),
-        (C, A) => Some((-8.5, -22.7)),
-        (C, G) => Some((-10.6, -27.2)),
-        (G, A) => Some((-8.2, -22.2)),
-        (G, C) => Some((-9.8, -24.4)),
-        (G, T) => Some((-8.4, -22.4)),
-        (G, G) => Some((-8.0, -19.9)),
-        _ => None,
-    }
+/// A nearest-neighbor pair of columns in a duplex, keyed as in BioPython's tables: The top strand 5' to 3',
+/// then the bottom strand 3' to 5'. `None` is a missing nucleotide, for dangling ends. Eg "AG/TT" is
+/// `[Some(A), Some(G), Some(T), Some(T)]`.
+pub(crate) type NnKey = [Option<Nucleotide>; 4];
+
+/// Find a key in one of our tables. Tables contain one of each pair of equivalent keys; the other is
+/// the duplex rotated 180°, ie the key's string reversed. Returns (dH, dS), in kcal/mol and cal/K·mol.
+fn lookup_nn(table: &[(&str, f32, f32)], key: NnKey) -> Option<(f32, f32)> {
+    let letter = |nt: Option<Nucleotide>| match nt {
+        Some(nt) => nt.to_u8_letter(),
+        None => b'.',
+    };
+
+    let fwd = [
+        letter(key[0]),
+        letter(key[1]),
+        b'/',
+        letter(key[2]),
+        letter(key[3]),
+    ];
+    let mut rev = fwd;
+    rev.reverse();
+
+    table
+        .iter()
+        .find(|(k, _, _)| k.as_bytes() == fwd || k.as_bytes() == rev)
+        .map(|(_, dH, dS)| (*dH, *dS))
+}
 
-    // # Terminal mismatch table (DNA)
-    // # SantaLucia & Peyret (2001) Patent Application WO 01/94611
-    // DNA_TMM1 = {
-    //     "AA/TA": (-3.1, -7.8),
-    //     "TA/AA": (-2.5, -6.3),
-    //     "CA/GA": (-4.3, -10.7),
-    //     "GA/CA": (-8.0, -22.5),
-    //     "AC/TC": (-0.1, 0.5),
-    //     "TC/AC": (-0.7, -1.3),
-    //     "CC/GC": (-2.1, -5.1),
-    //     "GC/CC": (-3.9, -10.6),
-    //     "AG/TG": (-1.1, -2.1),
-    //     "TG/AG": (-1.1, -2.7),
-    //     "CG/GG": (-3.8, -9.5),
-    //     "GG/CG": (-0.7, -19.2),
-    //     "AT/TT": (-2.4, -6.5),
-    //     "TT/AT": (-3.2, -8.9),
-    //     "CT/GT": (-6.1, -16.9),
-    //     "GT/CT": (-7.4, -21.2),
-    //     "AA/TC": (-1.6, -4.0),
-    //     "AC/TA": (-1.8, -3.8),
-    //     "CA/GC": (-2.6, -5.9),
-    //     "CC/GA": (-2.7, -6.0),
-    //     "GA/CC": (-5.0, -13.8),
-    //     "GC/CA": (-3.2, -7.1),
-    //     "TA/AC": (-2.3, -5.9), "TC/AA": (-2.7, -7.0),
-    //     "AC/TT": (-0.9, -1.7), "AT/TC": (-2.3, -6.3), "CC/GT": (-3.2, -8.0),
-    //     "CT/GC": (-3.9, -10.6), "GC/CT": (-4.9, -13.5), "GT/CC": (-3.0, -7.8),
-    //     "TC/AT": (-2.5, -6.3), "TT/AC": (-0.7, -1.2),
-    //     "AA/TG": (-1.9, -4.4), "AG/TA": (-2.5, -5.9), "CA/GG": (-3.9, -9.6),
-    //     "CG/GA": (-6.0, -15.5), "GA/CG": (-4.3, -11.1), "GG/CA": (-4.6, -11.4),
-    //     "TA/AG": (-2.0, -4.7), "TG/AA": (-2.4, -5.8),
-    //     "AG/TT": (-3.2, -8.7), "AT/TG": (-3.5, -9.4), "CG/GT": (-3.8, -9.0),
-    //     "CT/GG": (-6.6, -18.7), "GG/CT": (-5.7, -15.9), "GT/CG": (-5.9, -16.1),
-    //     "TG/AT": (-3.9, -10.5), "TT/AG": (-3.6, -9.8)}
+/// Terminal mismatch table (DNA). SantaLucia & Peyret (2001) Patent Application WO 01/94611
+#[rustfmt::skip]
+const DNA_TMM1: &[(&str, f32, f32)] = &[
+    ("AA/TA", -3.1, -7.8), ("TA/AA", -2.5, -6.3), ("CA/GA", -4.3, -10.7),
+    ("GA/CA", -8.0, -22.5),
+    ("AC/TC", -0.1, 0.5), ("TC/AC", -0.7, -1.3), ("CC/GC", -2.1, -5.1),
+    ("GC/CC", -3.9, -10.6),
+    ("AG/TG", -1.1, -2.1), ("TG/AG", -1.1, -2.7), ("CG/GG", -3.8, -9.5),
+    ("GG/CG", -0.7, -19.2),
+    ("AT/TT", -2.4, -6.5), ("TT/AT", -3.2, -8.9), ("CT/GT", -6.1, -16.9),
+    ("GT/CT", -7.4, -21.2),
+    ("AA/TC", -1.6, -4.0), ("AC/TA", -1.8, -3.8), ("CA/GC", -2.6, -5.9),
+    ("CC/GA", -2.7, -6.0), ("GA/CC", -5.0, -13.8), ("GC/CA", -3.2, -7.1),
+    ("TA/AC", -2.3, -5.9), ("TC/AA", -2.7, -7.0),
+    ("AC/TT", -0.9, -1.7), ("AT/TC", -2.3, -6.3), ("CC/GT", -3.2, -8.0),
+    ("CT/GC", -3.9, -10.6), ("GC/CT", -4.9, -13.5), ("GT/CC", -3.0, -7.8),
+    ("TC/AT", -2.5, -6.3), ("TT/AC", -0.7, -1.2),
+    ("AA/TG", -1.9, -4.4), ("AG/TA", -2.5, -5.9), ("CA/GG", -3.9, -9.6),
+    ("CG/GA", -6.0, -15.5), ("GA/CG", -4.3, -11.1), ("GG/CA", -4.6, -11.4),
+    ("TA/AG", -2.0, -4.7), ("TG/AA", -2.4, -5.8),
+    ("AG/TT", -3.2, -8.7), ("AT/TG", -3.5, -9.4), ("CG/GT", -3.8, -9.0),
+    ("CT/GG", -6.6, -18.7), ("GG/CT", -5.7, -15.9), ("GT/CG", -5.9, -16.1),
+    ("TG/AT", -3.9, -10.5), ("TT/AG", -3.6, -9.8),
+];
+
+/// Internal mismatch table (DNA). We don't use the inosine values from the source.
+/// Allawi & SantaLucia (1997), Biochemistry 36: 10581-10594
+/// Allawi & SantaLucia (1998), Biochemistry 37: 9435-9444
+/// Allawi & SantaLucia (1998), Biochemistry 37: 2170-2179
+/// Allawi & SantaLucia (1998), Nucl Acids Res 26: 2694-2701
+/// Peyret et al. (1999), Biochemistry 38: 3468-3477
+/// Watkins & SantaLucia (2005), Nucl Acids Res 33: 6258-6267
+#[rustfmt::skip]
+const DNA_IMM1: &[(&str, f32, f32)] = &[
+    ("AG/TT", 1.0, 0.9), ("AT/TG", -2.5, -8.3), ("CG/GT", -4.1, -11.7),
+    ("CT/GG", -2.8, -8.0), ("GG/CT", 3.3, 10.4), ("GG/TT", 5.8, 16.3),
+    ("GT/CG", -4.4, -12.3), ("GT/TG", 4.1, 9.5), ("TG/AT", -0.1, -1.7),
+    ("TG/GT", -1.4, -6.2), ("TT/AG", -1.3, -5.3), ("AA/TG", -0.6, -2.3),
+    ("AG/TA", -0.7, -2.3), ("CA/GG", -0.7, -2.3), ("CG/GA", -4.0, -13.2),
+    ("GA/CG", -0.6, -1.0), ("GG/CA", 0.5, 3.2), ("TA/AG", 0.7, 0.7),
+    ("TG/AA", 3.0, 7.4),
+    ("AC/TT", 0.7, 0.2), ("AT/TC", -1.2, -6.2), ("CC/GT", -0.8, -4.5),
+    ("CT/GC", -1.5, -6.1), ("GC/CT", 2.3, 5.4), ("GT/CC", 5.2, 13.5),
+    ("TC/AT", 1.2, 0.7), ("TT/AC", 1.0, 0.7),
+    ("AA/TC", 2.3, 4.6), ("AC/TA", 5.3, 14.6), ("CA/GC", 1.9, 3.7),
+    ("CC/GA", 0.6, -0.6), ("GA/CC", 5.2, 14.2), ("GC/CA", -0.7, -3.8),
+    ("TA/AC", 3.4, 8.0), ("TC/AA", 7.6, 20.2),
+    ("AA/TA", 1.2, 1.7), ("CA/GA", -0.9, -4.2), ("GA/CA", -2.9, -9.8),
+    ("TA/AA", 4.7, 12.9), ("AC/TC", 0.0, -4.4), ("CC/GC", -1.5, -7.2),
+    ("GC/CC", 3.6, 8.9), ("TC/AC", 6.1, 16.4), ("AG/TG", -3.1, -9.5),
+    ("CG/GG", -4.9, -15.3), ("GG/CG", -6.0, -15.8), ("TG/AG", 1.6, 3.6),
+    ("AT/TT", -2.7, -10.8), ("CT/GT", -5.0, -15.8), ("GT/CT", -2.2, -8.4),
+    ("TT/AT", 0.2, -1.5),
+];
+
+/// Dangling ends table (DNA). Bommarito et al. (2000), Nucl Acids Res 28: 1929-1934
+#[rustfmt::skip]
+const DNA_DE1: &[(&str, f32, f32)] = &[
+    ("AA/.T", 0.2, 2.3), ("AC/.G", -6.3, -17.1), ("AG/.C", -3.7, -10.0),
+    ("AT/.A", -2.9, -7.6), ("CA/.T", 0.6, 3.3), ("CC/.G", -4.4, -12.6),
+    ("CG/.C", -4.0, -11.9), ("CT/.A", -4.1, -13.0), ("GA/.T", -1.1, -1.6),
+    ("GC/.G", -5.1, -14.0), ("GG/.C", -3.9, -10.9), ("GT/.A", -4.2, -15.0),
+    ("TA/.T", -6.9, -20.0), ("TC/.G", -4.0, -10.9), ("TG/.C", -4.9, -13.8),
+    ("TT/.A", -0.2, -0.5),
+    (".A/AT", -0.7, -0.8), (".C/AG", -2.1, -3.9), (".G/AC", -5.9, -16.5),
+    (".T/AA", -0.5, -1.1), (".A/CT", 4.4, 14.9), (".C/CG", -0.2, -0.1),
+    (".G/CC", -2.6, -7.4), (".T/CA", 4.7, 14.2), (".A/GT", -1.6, -3.6),
+    (".C/GG", -3.9, -11.2), (".G/GC", -3.2, -10.4), (".T/GA", -4.1, -13.1),
+    (".A/TT", 2.9, 10.4), (".C/TG", -4.4, -13.1), (".G/TC", -5.2, -15.0),
+    (".T/TA", -3.8, -12.6),
+];
+
+/// Enthalpy (dH) and entropy (dS) of a mismatch at the end of a duplex, and the pair next to it.
+pub(crate) fn dH_dS_tmm(key: NnKey) -> Option<(f32, f32)> {
+    lookup_nn(DNA_TMM1, key)
 }
 
-/// Enthalpy (dH) and entropy (dS) tables based on internal missmatch
-fn _dH_dS_imm(nts: (Nucleotide, Nucleotide)) -> Option<(f32, f32)> {
-    match nts {
-        (A, A) => Some((-7.6, -21.3)),
-        (A, T) => Some((-7.2, -20.4)),
-        (T, A) => Some((-7.2, -21.3)),
-        (C, A) => Some((-8.5, -22.7)),
-        (C, G) => Some((-10.6, -27.2)),
-        (G, A) => Some((-8.2, -22.2)),
-        (G, C) => Some((-9.8, -24.4)),
-        (G, T) => Some((-8.4, -22.4)),
-        (G, G) => Some((-8.0, -19.9)),
-        _ => None,
-    }
+/// Enthalpy (dH) and entropy (dS) of a single internal mismatch, and a pair next to it. An internal
+/// mismatch has two of these: One for each neighboring pair.
+pub(crate) fn dH_dS_imm(key: NnKey) -> Option<(f32, f32)> {
+    lookup_nn(DNA_IMM1, key)
+}
+
+/// Enthalpy (dH) and entropy (dS) of an unpaired nucleotide at the end of a duplex, and the pair
+/// next to it.
+pub(crate) fn dH_dS_de(key: NnKey) -> Option<(f32, f32)> {
+    lookup_nn(DNA_DE1, key)
+}
+
+/// Nearest-neighbor parameters for perfectly-matched duplexes: Enthalpy (dH) in kcal/mol, and entropy
+/// (dS) in cal/K·mol.
+struct NnParams {
+    /// Initiation, applied once per duplex.
+    init: (f32, f32),
+    /// Applied per terminal A/T pair, and per terminal G/C pair.
+    init_at: (f32, f32),
+    init_gc: (f32, f32),
+    /// Applied if the duplex has at least one G/C pair, or if it has none.
+    init_one_gc: (f32, f32),
+    init_all_at: (f32, f32),
+    /// Applied if the sequence starts with T, and if it ends with A.
+    init_5t_a: (f32, f32),
+    /// Stacks, in this order: AA/TT, AT/TA, TA/AT, CA/GT, GT/CA, CT/GA, GA/CT, CG/GC, GC/CG, GG/CC
+    stacks: [(f32, f32); 10],
+}
+
+/// Breslauer et al. (1986), Proc Natl Acad Sci USA 83: 3746-3750
+const DNA_NN1: NnParams = NnParams {
+    init: (0., 0.),
+    init_at: (0., 0.),
+    init_gc: (0., 0.),
+    init_one_gc: (0., -16.8),
+    init_all_at: (0., -20.1),
+    init_5t_a: (0., 0.),
+    stacks: [
+        (-9.1, -24.0),
+        (-8.6, -23.9),
+        (-6.0, -16.9),
+        (-5.8, -12.9),
+        (-6.5, -17.3),
+        (-7.8, -20.8),
+        (-5.6, -13.5),
+        (-11.9, -27.8),
+        (-11.1, -26.7),
+        (-11.0, -26.6),
+    ],
+};
 
-    // # Internal mismatch and inosine table (DNA)
-    // # Allawi & SantaLucia (1997), Biochemistry 36: 10581-10594
-    // # Allawi & SantaLucia (1998), Biochemistry 37: 9435-9444
-    // # Allawi & SantaLucia (1998), Biochemistry 37: 2170-2179
-    // # Allawi & SantaLucia (1998), Nucl Acids Res 26: 2694-2701
-    // # Peyret et al. (1999), Biochemistry 38: 3468-3477
-    // # Watkins & SantaLucia (2005), Nucl Acids Res 33: 6258-6267
-    // DNA_IMM1 = {
-    //     "AG/TT": (1.0, 0.9), "AT/TG": (-2.5, -8.3), "CG/GT": (-4.1, -11.7),
-    //     "CT/GG": (-2.8, -8.0), "GG/CT": (3.3, 10.4), "GG/TT": (5.8, 16.3),
-    //     "GT/CG": (-4.4, -12.3), "GT/TG": (4.1, 9.5), "TG/AT": (-0.1, -1.7),
-    //     "TG/GT": (-1.4, -6.2), "TT/AG": (-1.3, -5.3), "AA/TG": (-0.6, -2.3),
-    //     "AG/TA": (-0.7, -2.3), "CA/GG": (-0.7, -2.3), "CG/GA": (-4.0, -13.2),
-    //     "GA/CG": (-0.6, -1.0), "GG/CA": (0.5, 3.2), "TA/AG": (0.7, 0.7),
-    //     "TG/AA": (3.0, 7.4),
-    //     "AC/TT": (0.7, 0.2), "AT/TC": (-1.2, -6.2), "CC/GT": (-0.8, -4.5),
-    //     "CT/GC": (-1.5, -6.1), "GC/CT": (2.3, 5.4), "GT/CC": (5.2, 13.5),
-    //     "TC/AT": (1.2, 0.7), "TT/AC": (1.0, 0.7),
-    //     "AA/TC": (2.3, 4.6), "AC/TA": (5.3, 14.6), "CA/GC": (1.9, 3.7),
-    //     "CC/GA": (0.6, -0.6), "GA/CC": (5.2, 14.2), "GC/CA": (-0.7, -3.8),
-    //     "TA/AC": (3.4, 8.0), "TC/AA": (7.6, 20.2),
-    //     "AA/TA": (1.2, 1.7), "CA/GA": (-0.9, -4.2), "GA/CA": (-2.9, -9.8),
-    //     "TA/AA": (4.7, 12.9), "AC/TC": (0.0, -4.4), "CC/GC": (-1.5, -7.2),
-    //     "GC/CC": (3.6, 8.9), "TC/AC": (6.1, 16.4), "AG/TG": (-3.1, -9.5),
-    //     "CG/GG": (-4.9, -15.3), "GG/CG": (-6.0, -15.8), "TG/AG": (1.6, 3.6),
-    //     "AT/TT": (-2.7, -10.8), "CT/GT": (-5.0, -15.8), "GT/CT": (-2.2, -8.4),
-    //     "TT/AT": (0.2, -1.5),
-    //     "AI/TC": (-8.9, -25.5), "TI/AC": (-5.9, -17.4), "AC/TI": (-8.8, -25.4),
-    //     "TC/AI": (-4.9, -13.9), "CI/GC": (-5.4, -13.7), "GI/CC": (-6.8, -19.1),
-    //     "CC/GI": (-8.3, -23.8), "GC/CI": (-5.0, -12.6),
-    //     "AI/TA": (-8.3, -25.0), "TI/AA": (-3.4, -11.2), "AA/TI": (-0.7, -2.6),
-    //     "TA/AI": (-1.3, -4.6), "CI/GA": (2.6, 8.9), "GI/CA": (-7.8, -21.1),
-    //     "CA/GI": (-7.0, -20.0), "GA/CI": (-7.6, -20.2),
-    //     "AI/TT": (0.49, -0.7), "TI/AT": (-6.5, -22.0), "AT/TI": (-5.6, -18.7),
-    //     "TT/AI": (-0.8, -4.3), "CI/GT": (-1.0, -2.4), "GI/CT": (-3.5, -10.6),
-    //     "CT/GI": (0.1, -1.0), "GT/CI": (-4.3, -12.1),
-    //     "AI/TG": (-4.9, -15.8), "TI/AG": (-1.9, -8.5), "AG/TI": (0.1, -1.8),
-    //     "TG/AI": (1.0, 1.0), "CI/GG": (7.1, 21.3), "GI/CG": (-1.1, -3.2),
-    //     "CG/GI": (5.8, 16.9), "GG/CI": (-7.6, -22.0),
-    //     "AI/TI": (-3.3, -11.9), "TI/AI": (0.1, -2.3), "CI/GI": (1.3, 3.0),
-    //     "GI/CI": (-0.5, -1.3)}
+/// Sugimoto et al. (1996), Nuc Acids Res 24: 4501-4505
+const DNA_NN2: NnParams = NnParams {
+    init: (0.6, -9.0),
+    init_at: (0., 0.),
+    init_gc: (0., 0.),
+    init_one_gc: (0., 0.),
+    init_all_at: (0., 0.),
+    init_5t_a: (0., 0.),
+    stacks: [
+        (-8.0, -21.9),
+        (-5.6, -15.2),
+        (-6.6, -18.4),
+        (-8.2, -21.0),
+        (-9.4, -25.5),
+        (-6.6, -16.4),
+        (-8.8, -23.5),
+        (-11.8, -29.0),
+        (-10.5, -26.4),
+        (-10.9, -28.4),
+    ],
+};
+
+/// Allawi & SantaLucia (1997), Biochemistry 36: 10581-10594
+const DNA_NN3: NnParams = NnParams {
+    init: (0., 0.),
+    init_at: (2.3, 4.1),
+    init_gc: (0.1, -2.8),
+    init_one_gc: (0., 0.),
+    init_all_at: (0., 0.),
+    init_5t_a: (0., 0.),
+    stacks: [
+        (-7.9, -22.2),
+        (-7.2, -20.4),
+        (-7.2, -21.3),
+        (-8.5, -22.7),
+        (-8.4, -22.4),
+        (-7.8, -21.0),
+        (-8.2, -22.2),
+        (-10.6, -27.2),
+        (-9.8, -24.4),
+        (-8.0, -19.9),
+    ],
+};
+
+/// SantaLucia & Hicks (2004), Annu. Rev. Biophys. Biomol. Struct 33: 415-440, Table 1.
+const DNA_NN4: NnParams = NnParams {
+    init: (0.2, -5.7),
+    init_at: (2.2, 6.9),
+    init_gc: (0., 0.),
+    init_one_gc: (0., 0.),
+    init_all_at: (0., 0.),
+    init_5t_a: (0., 0.),
+    stacks: [
+        (-7.6, -21.3),
+        (-7.2, -20.4),
+        (-7.2, -21.3),
+        (-8.5, -22.7),
+        (-8.4, -22.4),
+        (-7.8, -21.0),
+        (-8.2, -22.2),
+        (-10.6, -27.2),
+        (-9.8, -24.4),
+        (-8.0, -19.9),
+    ],
+};
+
+/// The nearest-neighbor parameter set used to calculate melting temperature.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Encode, Decode)]
+pub enum NnTable {
+    Breslauer1986,
+    Sugimoto1996,
+    Allawi1997,
+    #[default]
+    SantaLucia2004,
 }
 
-/// Enthalpy (dH) and entropy (dS) tables based on dangling ends.
-fn _dH_dS_de(nts: (Nucleotide, Nucleotide)) -> Option<(f32, f32)> {
-    match nts {
-        (A, A) => Some((0.2, 2.3)),
-        (A, T) => Some((-7.2, -20.4)),
-        (T, A) => Some((-7.2, -21.3)),
-        (C, A) => Some((-8.5, -22.7)),
-        (C, G) => Some((-10.6, -27.2)),
-        (G, A) => Some((-8.2, -22.2)),
-        (G, C) => Some((-9.8, -24.4)),
-        (G, T) => Some((-8.4, -22.4)),
-        (G, G) => Some((-8.0, -19.9)),
-        _ => None,
+impl NnTable {
+    /// In the order of BioPython's `DNA_NN1` through `DNA_NN4`.
+    pub const ALL: [Self; 4] = [
+        Self::Breslauer1986,
+        Self::Sugimoto1996,
+        Self::Allawi1997,
+        Self::SantaLucia2004,
+    ];
+
+    fn params(self) -> &'static NnParams {
+        match self {
+            Self::Breslauer1986 => &DNA_NN1,
+            Self::Sugimoto1996 => &DNA_NN2,
+            Self::Allawi1997 => &DNA_NN3,
+            Self::SantaLucia2004 => &DNA_NN4,
+        }
+    }
+}
+
+impl Display for NnTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Breslauer1986 => "Breslauer 1986",
+            Self::Sugimoto1996 => "Sugimoto 1996",
+            Self::Allawi1997 => "Allawi & SantaLucia 1997",
+            Self::SantaLucia2004 => "SantaLucia & Hicks 2004",
+        }
+        .to_owned();
+        write!(f, "{}", str)
     }
+}
 
-    // # Dangling ends table (DNA)
-    // # Bommarito et al. (2000), Nucl Acids Res 28: 1929-1934
-    // DNA_DE1 = {
-    //     "AA/.T": (0.2, 2.3), "AC/.G": (-6.3, -17.1), "AG/.C": (-3.7, -10.0),
-    //     "AT/.A": (-2.9, -7.6), "CA/.T": (0.6, 3.3), "CC/.G": (-4.4, -12.6),
-    //     "CG/.C": (-4.0, -11.9), "CT/.A": (-4.1, -13.0), "GA/.T": (-1.1, -1.6),
-    //     "GC/.G": (-5.1, -14.0), "GG/.C": (-3.9, -10.9), "GT/.A": (-4.2, -15.0),
-    //     "TA/.T": (-6.9, -20.0), "TC/.G": (-4.0, -10.9), "TG/.C": (-4.9, -13.8),
-    //     "TT/.A": (-0.2, -0.5),
-    //     ".A/AT": (-0.7, -0.8), ".C/AG": (-2.1, -3.9), ".G/AC": (-5.9, -16.5),
-    //     ".T/AA": (-0.5, -1.1), ".A/CT": (4.4, 14.9), ".C/CG": (-0.2, -0.1),
-    //     ".G/CC": (-2.6, -7.4), ".T/CA": (4.7, 14.2), ".A/GT": (-1.6, -3.6),
-    //     ".C/GG": (-3.9, -11.2), ".G/GC": (-3.2, -10.4), ".T/GA": (-4.1, -13.1),
-    //     ".A/TT": (2.9, 10.4), ".C/TG": (-4.4, -13.1), ".G/TC": (-5.2, -15.0),
-    //     ".T/TA": (-3.8, -12.6)}
+/// Enthalpy (dH) and entropy (dS) of a nearest-neighbor stack, from one of our tables.
+fn stack_params(table: &NnParams, neighbors: (Nucleotide, Nucleotide)) -> Option<(f32, f32)> {
+    let i = match neighbors {
+        (A, A) | (T, T) => 0,
+        (A, T) => 1,
+        (T, A) => 2,
+        (C, A) | (T, G) => 3,
+        (G, T) | (A, C) => 4,
+        (C, T) | (A, G) => 5,
+        (G, A) | (T, C) => 6,
+        (C, G) => 7,
+        (G, C) => 8,
+        (G, G) | (C, C) => 9,
+        _ => return None,
+    };
+
+    Some(table.stacks[i])
 }
 
 /// Enthalpy (dH) and entropy (dS) based on nearest neighbors.
-/// SantaLucia & Hicks, 2004, Table 1. Value are in kcal/Mol.
+/// SantaLucia & Hicks, 2004, Table 1. Value are in kcal/Mol. Our mismatch and dangling end tables
+/// are used with these.
 ///
-/// `neighbors` refers to the values between adjacent pairs of NTs.
-fn dH_dS_neighbors(neighbors: (Nucleotide, Nucleotide)) -> (f32, f32) {
-    match neighbors {
-        (A, A) | (T, T) => (-7.6, -21.3),
-        (A, T) => (-7.2, -20.4),
-        (T, A) => (-7.2, -21.3),
-        (T, G) | (C, A) => (-8.5, -22.7),
-        (A, C) | (G, T) => (-8.4, -22.4),
-        (A, G) | (C, T) => (-7.8, -21.0),
-        (T, C) | (G, A) => (-8.2, -22.2),
-        (C, G) => (-10.6, -27.2),
-        (G, C) => (-9.8, -24.4),
-        (C, C) | (G, G) => (-8.0, -19.9),
-    }
+/// `neighbors` refers to the values between adjacent pairs of NTs. Returns `None` if either nucleotide
+/// is an ambiguity code.
+pub(crate) fn dH_dS_neighbors(neighbors: (Nucleotide, Nucleotide)) -> Option<(f32, f32)> {
+    stack_params(&DNA_NN4, neighbors)
 }
 
-/// Calculate a Tm correction term due to salt ions.
-/// https://github.com/biopython/biopython/blob/master/Bio/SeqUtils/MeltingTemp.py#L475
-fn salt_correction(seq: &[Nucleotide], ion: &IonConcentrations) -> Option<f32> {
-    // todo: Using Some casual defaults for now.
-    // These are millimolar concentration of respective ions.
-    let method = 4; // todo?
+/// Methods of correcting melting temperature for the concentration of salt ions. These correspond
+/// to BioPython's `saltcorr` options 0 through 7.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Encode, Decode)]
+pub enum SaltCorrection {
+    None,
+    /// 16.6 × log10[Na+]. Schildkraut & Lifson (1965), Biopolymers 3: 195-208
+    Schildkraut1965,
+    /// 16.6 × log10([Na+] / (1 + 0.7 × [Na+])). Wetmur (1991), Crit Rev Biochem Mol Biol 126: 227-259
+    Wetmur1991,
+    /// 12.5 × log10[Na+]. SantaLucia et al. (1996), Biochemistry 35: 3555-3562
+    SantaLucia1996,
+    /// 11.7 × log10[Na+]. SantaLucia (1998), Proc Natl Acad Sci USA 95: 1460-1465
+    #[default]
+    SantaLucia1998,
+    /// An entropy correction of 0.368 × (N - 1) × ln[Na+]. SantaLucia (1998)
+    SantaLucia1998Entropy,
+    /// A correction to 1/Tm, depending on GC content. Owczarzy et al. (2004), Biochemistry 43: 3537-3554
+    Owczarzy2004,
+    /// A correction to 1/Tm, accounting for Mg2+ separately from monovalent ions, and for Mg2+ bound by
+    /// dNTPs. Owczarzy et al. (2008), Biochemistry 47: 5336-5353
+    Owczarzy2008,
+}
 
-    let tris = 0.; // todo: Do we want this?
+impl SaltCorrection {
+    /// Indexed by BioPython's `saltcorr` method number.
+    pub const ALL: [Self; 8] = [
+        Self::None,
+        Self::Schildkraut1965,
+        Self::Wetmur1991,
+        Self::SantaLucia1996,
+        Self::SantaLucia1998,
+        Self::SantaLucia1998Entropy,
+        Self::Owczarzy2004,
+        Self::Owczarzy2008,
+    ];
+}
 
-    if (5..=7).contains(&method) && seq.is_empty() {
-        // return Err("sequence is missing (is needed to calculate GC content or sequence length).".into());
-        return None;
+impl Display for SaltCorrection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::None => "None",
+            Self::Schildkraut1965 => "Schildkraut & Lifson 1965",
+            Self::Wetmur1991 => "Wetmur 1991",
+            Self::SantaLucia1996 => "SantaLucia 1996",
+            Self::SantaLucia1998 => "SantaLucia 1998",
+            Self::SantaLucia1998Entropy => "SantaLucia 1998 (entropy)",
+            Self::Owczarzy2004 => "Owczarzy 2004",
+            Self::Owczarzy2008 => "Owczarzy 2008 (Mg2+)",
+        }
+        .to_owned();
+        write!(f, "{}", str)
     }
+}
 
-    let mut corr = 0.0;
-    if method == 0 {
-        return Some(corr);
-    }
+/// Settings for melting temperature calculations. These are saved with each document's ion
+/// concentrations.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Encode, Decode)]
+pub struct TmConfig {
+    pub nn_table: NnTable,
+    pub salt_correction: SaltCorrection,
+    /// DMSO, in % (v/v).
+    pub dmso: f32,
+    /// Formamide, in % (v/v).
+    pub formamide: f32,
+}
 
-    // It appears that this section modifies the monovalent concentration with divalent values.
-    let mut mon = ion.monovalent + tris / 2.0;
-    let mon_molar = mon * 1e-3;
-    let mg_molar = ion.divalent * 1e-3;
+/// The sodium concentration equivalent to our monovalent and divalent ions, in M. Mg2+ that isn't
+/// bound by dNTPs is counted as in von Ahsen et al. (2001).
+pub(crate) fn na_equivalent(ion: &IonConcentrations) -> f32 {
+    let mg_free = (ion.divalent - ion.dntp).max(0.);
+    (ion.monovalent + ion.tris / 2. + 120. * mg_free.sqrt()) * 1e-3
+}
 
-    if (ion.monovalent > 0.0 || ion.divalent > 0.0 || tris > 0.0 || ion.dntp > 0.0)
-        && method != 7
-        && ion.dntp < ion.divalent
-    {
-        mon += 120.0 * (ion.divalent - ion.dntp).sqrt();
+/// Calculate a salt correction term. Depending on the method, this is added to Tm (1-4) or entropy (5),
+/// or to 1/Tm (6, 7). Returns `None` if the method requires ions we don't have.
+/// https://github.com/biopython/biopython/blob/master/Bio/SeqUtils/MeltingTemp.py#L475
+fn salt_correction(seq: &[Nucleotide], ion: &IonConcentrations) -> Option<f32> {
+    let method = ion.tm_config.salt_correction;
+    if method == SaltCorrection::None {
+        return Some(0.);
     }
 
-    if (1..=6).contains(&method) && mon_molar == 0.0 {
-        // return Err("Total ion concentration of zero is not allowed in this method.".into());
+    // Methods other than Owczarzy 2008 count Mg2+ as an equivalent amount of Na+.
+    let mon_molar = if method == SaltCorrection::Owczarzy2008 {
+        (ion.monovalent + ion.tris / 2.) * 1e-3
+    } else {
+        na_equivalent(ion)
+    };
+
+    if method != SaltCorrection::Owczarzy2008 && mon_molar <= 0. {
         return None;
     }
 
-    match method {
-        1 => corr = 16.6 * mon_molar.log10(),
-        2 => corr = 16.6 * (mon_molar / (1.0 + 0.7 * mon_molar)).log10(),
-        3 => corr = 12.5 * mon_molar.log10(),
-        4 => corr = 11.7 * mon_molar.log10(),
-        5 => {
-            corr = 0.368 * (seq.len() as f32 - 1.0) * mon_molar.ln();
-        }
-        6 => {
+    let corr = match method {
+        SaltCorrection::Schildkraut1965 => 16.6 * mon_molar.log10(),
+        SaltCorrection::Wetmur1991 => 16.6 * (mon_molar / (1.0 + 0.7 * mon_molar)).log10(),
+        SaltCorrection::SantaLucia1996 => 12.5 * mon_molar.log10(),
+        SaltCorrection::SantaLucia1998 => 11.7 * mon_molar.log10(),
+        SaltCorrection::SantaLucia1998Entropy => 0.368 * (seq.len() as f32 - 1.0) * mon_molar.ln(),
+        SaltCorrection::Owczarzy2004 => {
             let gc_fraction = calc_gc(seq);
-            corr = ((4.29 * gc_fraction - 3.95) * 1e-5 * mon_molar.ln())
-                + 9.40e-6 * mon_molar.ln().powi(2);
+            (4.29 * gc_fraction - 3.95) * 1e-5 * mon_molar.ln() + 9.40e-6 * mon_molar.ln().powi(2)
         }
-        7 => {
+        SaltCorrection::Owczarzy2008 => {
             let (mut a, b, c, mut d, e, f, mut g) = (3.92, -0.911, 6.26, 1.42, -48.2, 52.5, 8.31);
-            let mut mg_free = mg_molar;
-            if ion.dntp > 0.0 {
-                let dntps_molar = ion.dntp * 1e-3;
-                let ka = 3e4;
-                //  Free Mg2+ calculation
-                mg_free = (-(ka * dntps_molar - ka * mg_free + 1.0)
-                    + ((ka * dntps_molar - ka * mg_free + 1.0).powi(2) + 4.0 * ka * mg_free)
-                        .sqrt())
-                    / (2.0 * ka);
+
+            // Free Mg2+: dNTPs bind it, with association constant `KA`.
+            const KA: f32 = 3e4;
+            let mg_molar = ion.divalent * 1e-3;
+            let dntp_molar = ion.dntp * 1e-3;
+            let mg_free = if dntp_molar > 0. {
+                let v = KA * dntp_molar - KA * mg_molar + 1.0;
+                (-v + (v.powi(2) + 4.0 * KA * mg_molar).sqrt()) / (2.0 * KA)
+            } else {
+                mg_molar
+            };
+
+            if mg_free <= 0. {
+                if mon_molar <= 0. {
+                    return None;
+                }
+                // Without Mg2+, this is the same as Owczarzy 2004.
+                let gc_fraction = calc_gc(seq);
+                return Some(
+                    (4.29 * gc_fraction - 3.95) * 1e-5 * mon_molar.ln()
+                        + 9.40e-6 * mon_molar.ln().powi(2),
+                );
             }
 
-            if mon > 0.0 {
-                let r = (mg_free).sqrt() / mon_molar;
+            if mon_molar > 0. {
+                let r = mg_free.sqrt() / mon_molar;
                 if r < 0.22 {
+                    // Monovalent ions dominate.
                     let gc_fraction = calc_gc(seq);
-                    corr = (4.29 * gc_fraction - 3.95) * 1e-5 * mon_molar.ln()
-                        + 9.40e-6 * mon_molar.ln().powi(2);
-                    return Some(corr);
+                    return Some(
+                        (4.29 * gc_fraction - 3.95) * 1e-5 * mon_molar.ln()
+                            + 9.40e-6 * mon_molar.ln().powi(2),
+                    );
                 } else if r < 6.0 {
                     a = 3.92 * (0.843 - 0.352 * mon_molar.sqrt() * mon_molar.ln());
                     d = 1.42
                         * (1.279 - 4.03e-3 * mon_molar.ln() - 8.03e-3 * mon_molar.ln().powi(2));
                     g = 8.31 * (0.486 - 0.258 * mon_molar.ln() + 5.25e-3 * mon_molar.ln().powi(3));
                 }
-
-                let gc_fraction = calc_gc(seq);
-                corr = (a
-                    + b * mg_free.ln()
-                    + gc_fraction * (c + d * mg_free.ln())
-                    + (1.0 / (2.0 * (seq.len() as f32 - 1.0)))
-                        * (e + f * mg_free.ln() + g * mg_free.ln().powi(2)))
-                    * 1e-5;
             }
+
+            let gc_fraction = calc_gc(seq);
+            (a + b * mg_free.ln()
+                + gc_fraction * (c + d * mg_free.ln())
+                + (1.0 / (2.0 * (seq.len() as f32 - 1.0)))
+                    * (e + f * mg_free.ln() + g * mg_free.ln().powi(2)))
+                * 1e-5
         }
-        _ => return None,
-    }
+        SaltCorrection::None => unreachable!(),
+    };
 
     Some(corr)
 }
 
+/// Correct melting temperature for DMSO and formamide. von Ahsen et al. (2001), Clin Chem 47: 1956-1961,
+/// and McConaughy et al. (1969), Biochemistry 8: 3289-3295
+fn chem_correction(tm: f32, config: &TmConfig) -> f32 {
+    const DMSO_FACTOR: f32 = 0.75;
+    const FORMAMIDE_FACTOR: f32 = 0.65;
+
+    tm - DMSO_FACTOR * config.dmso - FORMAMIDE_FACTOR * config.formamide
+}
+
+/// Calculate melting temperature, in °C, using the nearest-neighbor table, and salt and chemical
+/// corrections set in `ion_concentrations`. Returns `None` if the sequence is too short, contains
+/// ambiguity codes, as our nearest-neighbor tables only cover A, T, G, and C, or if the salt correction
+/// method can't be used with these ion concentrations.
 pub fn calc_tm(seq: &[Nucleotide], ion_concentrations: &IonConcentrations) -> Option<f32> {
-    if seq.len() < MIN_PRIMER_LEN {
+    calc_tm_duplex(seq, None, ion_concentrations)
+}
+
+/// Calculate melting temperature, in °C, of a sequence bound to a strand that may not be its exact
+/// complement; eg a primer at a site with mismatches. `template` is the bound strand, 3' to 5', with each
+/// nucleotide opposite the one at the same index in `seq`. Single mismatches use internal mismatch
+/// parameters. Returns `None` under the same conditions as `calc_tm`, and if the ends don't pair, or if
+/// there are adjacent mismatches.
+pub fn calc_tm_mismatched(
+    seq: &[Nucleotide],
+    template: &[Nucleotide],
+    ion_concentrations: &IonConcentrations,
+) -> Option<f32> {
+    if template.len() != seq.len() {
         return None;
     }
+    calc_tm_duplex(seq, Some(template), ion_concentrations)
+}
 
-    // Inititial values. (Table 1)
-    let mut dH = 0.2;
-    let mut dS = -5.7;
-
-    // If no GC content, apply additional values. (Table 1)
-    if calc_gc(seq) < 0.001 {
-        dH += 2.2;
-        dS += 6.9;
+fn calc_tm_duplex(
+    seq: &[Nucleotide],
+    template: Option<&[Nucleotide]>,
+    ion_concentrations: &IonConcentrations,
+) -> Option<f32> {
+    if seq.len() < MIN_PRIMER_LEN {
+        return None;
     }
 
-    // Add to dH and dS based on the terminal pair.
-    {
-        let term_pair = vec![seq[0], seq[seq.len() - 1]];
-        let mut at_term_count = 0;
-        //C onstants for the CG term are 0, so we don't need it.
-        for nt in term_pair {
-            if nt == A || nt == T {
-                at_term_count += 1;
-            }
-        }
-        dH += 2.2 * at_term_count as f32;
-        dS += 6.9 * at_term_count as f32;
+    let pairs = |i: usize| match template {
+        Some(t) => !seq[i].is_ambiguous() && seq[i].complement() == t[i],
+        None => true,
+    };
+    if !pairs(0) || !pairs(seq.len() - 1) {
+        return None;
     }
 
-    for (i, nt) in seq.iter().enumerate() {
-        if i + 1 >= seq.len() {
-            break;
-        }
+    let config = &ion_concentrations.tm_config;
+    let table = config.nn_table.params();
+
+    let mut dH = table.init.0;
+    let mut dS = table.init.1;
 
-        let neighbors = (*nt, seq[i + 1]);
+    // Initiation values depending on if there are any G or C nucleotides.
+    let (h, s) = if calc_gc(seq) < 0.001 {
+        table.init_all_at
+    } else {
+        table.init_one_gc
+    };
+    dH += h;
+    dS += s;
+
+    // Add to dH and dS based on the terminal pairs.
+    for nt in [seq[0], seq[seq.len() - 1]] {
+        let (h, s) = match nt {
+            A | T => table.init_at,
+            _ => table.init_gc,
+        };
+        dH += h;
+        dS += s;
+    }
+    if seq[0] == T {
+        dH += table.init_5t_a.0;
+        dS += table.init_5t_a.1;
+    }
+    if seq[seq.len() - 1] == A {
+        dH += table.init_5t_a.0;
+        dS += table.init_5t_a.1;
+    }
 
-        let (dH_nn, dS_nn) = dH_dS_neighbors(neighbors);
+    for i in 0..seq.len() - 1 {
+        let (dH_nn, dS_nn) = match template {
+            Some(t) if !pairs(i) || !pairs(i + 1) => {
+                dH_dS_imm([seq[i], seq[i + 1], t[i], t[i + 1]].map(Some))?
+            }
+            _ => stack_params(table, (seq[i], seq[i + 1]))?,
+        };
         dH += dH_nn;
         dS += dS_nn;
     }
 
+    let salt_corr = salt_correction(seq, ion_concentrations)?;
+    if config.salt_correction == SaltCorrection::SantaLucia1998Entropy {
+        dS += salt_corr;
+    }
+
     const R: f32 = 1.987; // Universal gas constant (Cal/C * Mol)
 
     // We are multiplying by two, as it's one per strand.
@@ -313,24 +576,116 @@ pub fn calc_tm(seq: &[Nucleotide], ion_concentrations: &IonConcentrations) -> Op
     // SantaLucia and Hicks, Equation 3.
     let mut result = (1_000. * dH) / (dS + R * ((C_T / 4.).ln())) - 273.15;
 
-    // if saltcorr == 5:
-    //         delta_s += corr
-    //     melting_temp = (1000 * delta_h) / (delta_s + (R * (math.log(k)))) - 273.15
-    //     if saltcorr in (1, 2, 3, 4):
-    //         melting_temp += corr
-    //     if saltcorr in (6, 7):
-    //         # Tm = 1/(1/Tm + corr)
-    //         melting_temp = 1 / (1 / (melting_temp + 273.15) + corr) - 273.15
-
-    //  We will assume saltcorr method 1-4 for now.
-    if let Some(sc) = salt_correction(seq, ion_concentrations) {
-        result += sc;
-
-        // for saltcorr 6/7:
-        // result = 1. / (1. / (result + 273.15) + sc) - 273.15
-    } else {
-        println!("Error on SC")
+    match config.salt_correction {
+        SaltCorrection::Schildkraut1965
+        | SaltCorrection::Wetmur1991
+        | SaltCorrection::SantaLucia1996
+        | SaltCorrection::SantaLucia1998 => result += salt_corr,
+        SaltCorrection::Owczarzy2004 | SaltCorrection::Owczarzy2008 => {
+            result = 1. / (1. / (result + 273.15) + salt_corr) - 273.15;
+        }
+        _ => (),
+    }
+
+    Some(chem_correction(result, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sequence::seq_from_str, test_fixtures::na_only};
+
+    /// The example from BioPython's `Tm_NN` documentation. Expected values are from BioPython, with
+    /// `dnac1=25, dnac2=25`.
+    const SEQ: &str = "CGTTCCAAAGATGTGGGCATGAGCTTAC";
+
+    /// BioPython's default ion concentrations, with our default 25 nM primer.
+    fn ions(nn_table: NnTable, salt_correction: SaltCorrection) -> IonConcentrations {
+        let mut result = na_only();
+        result.tm_config.nn_table = nn_table;
+        result.tm_config.salt_correction = salt_correction;
+        result
+    }
+
+    fn assert_tm(ion: &IonConcentrations, expected: f32) {
+        let tm = calc_tm(&seq_from_str(SEQ), ion).unwrap();
+        assert!(
+            (tm - expected).abs() < 0.02,
+            "{} / {}: {tm}, expected {expected}",
+            ion.tm_config.nn_table,
+            ion.tm_config.salt_correction
+        );
+    }
+
+    #[test]
+    fn tm_nn_tables() {
+        // `Tm_NN(seq, nn_table=DNA_NN1)` through `DNA_NN4`, with `saltcorr=5`.
+        let expected = [72.195, 65.469, 60.321, 60.272];
+        for (table, expected) in NnTable::ALL.into_iter().zip(expected) {
+            assert_tm(
+                &ions(table, SaltCorrection::SantaLucia1998Entropy),
+                expected,
+            );
+        }
     }
 
-    Some(result)
+    #[test]
+    fn tm_salt_corrections() {
+        // `Tm_NN(seq, nn_table=DNA_NN4, Na=50, saltcorr=0)` through `saltcorr=7`.
+        let expected = [
+            75.897, 54.300, 54.052, 59.634, 60.675, 60.272, 59.808, 59.808,
+        ];
+        for (method, expected) in SaltCorrection::ALL.into_iter().zip(expected) {
+            assert_tm(&ions(NnTable::SantaLucia2004, method), expected);
+        }
+    }
+
+    #[test]
+    fn tm_mg_dntp() {
+        // `Tm_NN(seq, nn_table=DNA_NN4, Na=50, Mg=1.5, dNTPs=0.2, saltcorr=...)`
+        for (method, expected) in [
+            (SaltCorrection::SantaLucia1998, 67.372),
+            (SaltCorrection::Owczarzy2008, 66.532),
+        ] {
+            let mut ion = ions(NnTable::SantaLucia2004, method);
+            ion.divalent = 1.5;
+            ion.dntp = 0.2;
+            assert_tm(&ion, expected);
+        }
+    }
+
+    #[test]
+    fn tm_chem_and_invalid() {
+        let mut ion = ions(NnTable::Allawi1997, SaltCorrection::SantaLucia1998Entropy);
+        ion.tm_config.dmso = 4.;
+        ion.tm_config.formamide = 2.;
+        assert_tm(&ion, 60.321 - 0.75 * 4. - 0.65 * 2.);
+
+        let ion = ions(NnTable::SantaLucia2004, SaltCorrection::SantaLucia1998);
+        assert_eq!(calc_tm(&seq_from_str("ACGTACGT"), &ion), None);
+        assert_eq!(calc_tm(&seq_from_str("ACGTACGTNACGT"), &ion), None);
+
+        let mut no_salt = ion;
+        no_salt.monovalent = 0.;
+        assert_eq!(calc_tm(&seq_from_str(SEQ), &no_salt), None);
+    }
+
+    #[test]
+    fn tm_mismatched() {
+        let ion = ions(NnTable::SantaLucia2004, SaltCorrection::SantaLucia1998);
+        let seq = seq_from_str(SEQ);
+        let exact: Vec<_> = seq.iter().map(|nt| nt.complement()).collect();
+        let tm = calc_tm(&seq, &ion).unwrap();
+        assert_eq!(calc_tm_mismatched(&seq, &exact, &ion), Some(tm));
+
+        // A single internal mismatch lowers Tm.
+        let mut template = exact.clone();
+        template[12] = template[12].complement();
+        assert!(calc_tm_mismatched(&seq, &template, &ion).unwrap() < tm);
+
+        // Mismatched ends don't bind.
+        let mut template = exact;
+        template[SEQ.len() - 1] = template[SEQ.len() - 1].complement();
+        assert_eq!(calc_tm_mismatched(&seq, &template, &ion), None);
+    }
 }
@@ -0,0 +1,495 @@
+//! This module contains site-directed mutagenesis primer design: substitutions, insertions, and
+//! deletions, made by amplifying a whole circular plasmid with primers that carry the change.
+//!
+//! Back-to-back primers (eg NEB Q5 site-directed mutagenesis, with KLD treatment) anneal on either side
+//! of the change, pointing away from each other; the change is in their 5' tails, and the linear product
+//! is phosphorylated and ligated. Overlapping primers (eg QuikChange) are complementary, with the change
+//! in their center, and template-matching flanks on both sides; the product is nicked, and the template
+//! removed by DpnI digestion.
+
+use std::{fmt::Display, ops::Range};
+
+use crate::{
+    assembly::{len_for_tm, MIN_ANNEAL_LEN},
+    file_io::GenericData,
+    melting_temp_calcs::calc_tm,
+    primer::{Primer, PrimerData, TuneSetting, TM_TARGET},
+    protein::{feature_genetic_code, AminoAcid, GeneticCode},
+    seq_edit::EditReport,
+    sequence::{
+        seq_complement, seq_to_str, Feature, FeatureDirection, Nucleotide, Seq, SeqRange,
+        SeqTopology,
+    },
+    IonConcentrations,
+};
+
+/// For back-to-back primers, changes longer than this are split between both primers' tails, so
+/// neither tail is too long to anneal efficiently.
+const MAX_SINGLE_TAIL: usize = 6;
+/// The shortest flank on each side of the change, for overlapping primers.
+const MIN_FLANK_LEN: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum MutationKind {
+    #[default]
+    Substitution,
+    Insertion,
+    Deletion,
+}
+
+impl Display for MutationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Substitution => "Substitution",
+            Self::Insertion => "Insertion",
+            Self::Deletion => "Deletion",
+        }
+        .to_owned();
+        write!(f, "{}", str)
+    }
+}
+
+/// A change to a sequence, on the top strand.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mutation {
+    pub kind: MutationKind,
+    /// The nucleotides substituted or deleted. For insertions, only the start is used: The first
+    /// inserted nucleotide is at this position. The range may wrap the origin.
+    pub range: SeqRange,
+    /// The new nucleotides, for substitutions and insertions. A substitution may change the length.
+    pub seq: Seq,
+}
+
+/// Displayed in HGVS notation, eg `123_125delinsGCA`, `122_123insTAG`, or `123del`.
+impl Display for Mutation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let range = if self.range.start == self.range.end {
+            self.range.start.to_string()
+        } else {
+            format!("{}_{}", self.range.start, self.range.end)
+        };
+        let seq = seq_to_str(&self.seq).to_uppercase();
+
+        match self.kind {
+            MutationKind::Substitution => write!(f, "{range}delins{seq}"),
+            MutationKind::Insertion => write!(
+                f,
+                "{}_{}ins{seq}",
+                self.range.start.saturating_sub(1),
+                self.range.start
+            ),
+            MutationKind::Deletion => write!(f, "{range}del"),
+        }
+    }
+}
+
+/// The range, on the top strand, of the codon at an amino acid position in a coding region feature.
+/// Positions are 1-based. `None` if the position is outside the feature, or the codon spans a junction
+/// between segments.
+pub fn codon_range(feature: &Feature, seq_len: usize, aa_pos: usize) -> Option<SeqRange> {
+    let mut indices: Vec<usize> = feature
+        .ranges()
+        .into_iter()
+        .flat_map(|r| r.indices(seq_len))
+        .collect();
+    if feature.direction == FeatureDirection::Reverse {
+        indices.reverse();
+    }
+
+    let first = aa_pos.checked_sub(1)? * 3;
+    let codon = indices.get(first..first + 3)?;
+
+    // The codon's nucleotides must be adjacent on the top strand, in either direction. This may wrap
+    // the origin.
+    let start = codon
+        .iter()
+        .copied()
+        .find(|s| (0..3).all(|i| codon.contains(&((s + i) % seq_len))))?;
+
+    Some(SeqRange::from_start_len(start, 3, seq_len))
+}
+
+/// A mutation of the codon at an amino acid position in a coding region feature: Substituting it with
+/// the codon for `aa` that's most similar to it, inserting a codon for `aa` before it, or deleting it.
+/// Positions are 1-based, and the genetic code is the feature's own if it has one.
+pub fn codon_mutation(
+    feature: &Feature,
+    seq: &[Nucleotide],
+    aa_pos: usize,
+    kind: MutationKind,
+    aa: AminoAcid,
+    code: GeneticCode,
+) -> Option<Mutation> {
+    let range = codon_range(feature, seq.len(), aa_pos)?;
+    let reverse = feature.direction == FeatureDirection::Reverse;
+
+    let mut current = range.seq(seq)?;
+    if reverse {
+        current = seq_complement(&current);
+    }
+
+    let mismatches =
+        |codon: &[Nucleotide; 3]| codon.iter().zip(&current).filter(|(a, b)| a != b).count();
+    let codon = feature_genetic_code(feature, code)
+        .codons(aa)
+        .into_iter()
+        .min_by_key(mismatches)
+        .map(|c| c.to_vec());
+
+    // Convert from the feature's strand to the top one.
+    let top = |codon: Seq| {
+        if reverse {
+            seq_complement(&codon)
+        } else {
+            codon
+        }
+    };
+
+    let (range, new) = match kind {
+        MutationKind::Substitution => (range, top(codon?)),
+        // Before the codon, in the feature's direction.
+        MutationKind::Insertion if reverse => {
+            (SeqRange::new(range.end + 1, range.end + 1), top(codon?))
+        }
+        MutationKind::Insertion => (range, top(codon?)),
+        MutationKind::Deletion => (range, Vec::new()),
+    };
+
+    Some(Mutation {
+        kind,
+        range,
+        seq: new,
+    })
+}
+
+/// Degenerate codons for saturation mutagenesis, randomizing one amino acid position.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum CodonRandomization {
+    /// 32 codons, encoding all 20 amino acids and one stop codon (TAG).
+    #[default]
+    Nnk,
+    /// 32 codons, as NNK; the same amino acids and stop codon.
+    Nns,
+    /// The 22c trick (Kille et al, 2013): A mix of NDT, VHG, and TGG primers, at 12:9:1. 22 codons,
+    /// encoding all 20 amino acids with no stop codons, and little redundancy.
+    TwentyTwoC,
+}
+
+impl Display for CodonRandomization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Nnk => "NNK",
+            Self::Nns => "NNS",
+            Self::TwentyTwoC => "22c (NDT, VHG, TGG)",
+        }
+        .to_owned();
+        write!(f, "{}", str)
+    }
+}
+
+impl CodonRandomization {
+    /// Each degenerate codon, in the coding direction, with the portion of the primer mix it makes up.
+    pub fn codons(self) -> Vec<(Seq, f32)> {
+        use Nucleotide::*;
+        match self {
+            Self::Nnk => vec![(vec![N, N, K], 1.)],
+            Self::Nns => vec![(vec![N, N, S], 1.)],
+            Self::TwentyTwoC => vec![
+                (vec![N, D, T], 12. / 22.),
+                (vec![V, H, G], 9. / 22.),
+                (vec![T, G, G], 1. / 22.),
+            ],
+        }
+    }
+}
+
+/// Substitutions of the codon at an amino acid position in a coding region feature with degenerate
+/// codons, for a saturation mutagenesis library; each with the portion of the primer mix it makes up.
+/// Positions are 1-based.
+pub fn saturation_mutations(
+    feature: &Feature,
+    seq: &[Nucleotide],
+    aa_pos: usize,
+    scheme: CodonRandomization,
+) -> Option<Vec<(Mutation, f32)>> {
+    let range = codon_range(feature, seq.len(), aa_pos)?;
+
+    Some(
+        scheme
+            .codons()
+            .into_iter()
+            .map(|(codon, portion)| {
+                let codon = if feature.direction == FeatureDirection::Reverse {
+                    seq_complement(&codon)
+                } else {
+                    codon
+                };
+                let mutation = Mutation {
+                    kind: MutationKind::Substitution,
+                    range,
+                    seq: codon,
+                };
+                (mutation, portion)
+            })
+            .collect(),
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum MutagenesisStrategy {
+    /// Non-overlapping primers that anneal on either side of the change; eg NEB Q5 site-directed
+    /// mutagenesis.
+    #[default]
+    BackToBack,
+    /// Complementary primers with the change in their center; eg QuikChange.
+    Overlapping,
+}
+
+impl Display for MutagenesisStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::BackToBack => "Back-to-back (Q5 SDM)",
+            Self::Overlapping => "Overlapping (QuikChange)",
+        }
+        .to_owned();
+        write!(f, "{}", str)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MutagenesisParams {
+    pub strategy: MutagenesisStrategy,
+    /// Annealing regions, or for overlapping primers, each flank, are extended from their minimum length
+    /// until they reach this melting temperature.
+    pub anneal_tm: f32,
+    pub max_anneal_len: usize,
+}
+
+impl Default for MutagenesisParams {
+    fn default() -> Self {
+        Self {
+            strategy: Default::default(),
+            anneal_tm: TM_TARGET,
+            max_anneal_len: 35,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum MutagenesisError {
+    /// Both strategies amplify the whole plasmid.
+    NotCircular,
+    InvalidRange,
+    /// A substitution or insertion without nucleotides.
+    NoNucleotides,
+    /// A substitution with the nucleotides already there.
+    NoChange,
+}
+
+impl Display for MutagenesisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotCircular => write!(f, "Mutagenesis requires a circular sequence"),
+            Self::InvalidRange => write!(f, "The range is outside the sequence"),
+            Self::NoNucleotides => write!(f, "Enter the nucleotides to substitute or insert"),
+            Self::NoChange => write!(f, "The sequence already has these nucleotides"),
+        }
+    }
+}
+
+/// Designed mutagenesis primers, and the mutated construct.
+#[derive(Clone)]
+pub struct MutagenesisDesign {
+    pub fwd: Primer,
+    pub rev: Primer,
+    /// Indices in each primer, from its 5' end, of the nucleotides that differ from the template.
+    /// Empty for deletions.
+    pub fwd_change: Range<usize>,
+    pub rev_change: Range<usize>,
+    /// Melting temperature of each primer's annealing region, in °C. For overlapping primers, this is
+    /// the lower of its flanks.
+    pub fwd_anneal_tm: Option<f32>,
+    pub rev_anneal_tm: Option<f32>,
+    /// The template with the mutation, features updated, and the primers added.
+    pub product: GenericData,
+    /// Features the mutation damaged in the product; eg a coding region that was modified.
+    pub report: EditReport,
+}
+
+/// Design primers that introduce a mutation in a circular plasmid, and predict the mutated construct.
+pub fn design_mutagenesis(
+    data: &GenericData,
+    mutation: &Mutation,
+    params: &MutagenesisParams,
+    ion: &IonConcentrations,
+) -> Result<MutagenesisDesign, MutagenesisError> {
+    let seq = &data.seq;
+    let seq_len = seq.len();
+    if data.topology != SeqTopology::Circular {
+        return Err(MutagenesisError::NotCircular);
+    }
+
+    // 0-based indices of the template nucleotides immediately before and after the change.
+    let (before, after) = match mutation.kind {
+        MutationKind::Insertion => {
+            if mutation.range.start < 1 || mutation.range.start > seq_len + 1 {
+                return Err(MutagenesisError::InvalidRange);
+            }
+            (
+                mutation.range.start as isize - 2,
+                mutation.range.start as isize - 1,
+            )
+        }
+        _ => {
+            if !mutation.range.is_valid(seq_len) {
+                return Err(MutagenesisError::InvalidRange);
+            }
+            (
+                mutation.range.start as isize - 2,
+                mutation.range.end as isize,
+            )
+        }
+    };
+
+    let new = match mutation.kind {
+        MutationKind::Deletion => Vec::new(),
+        _ => mutation.seq.clone(),
+    };
+    if mutation.kind != MutationKind::Deletion && new.is_empty() {
+        return Err(MutagenesisError::NoNucleotides);
+    }
+    if mutation.kind == MutationKind::Substitution && mutation.range.seq(seq) == Some(new.clone()) {
+        return Err(MutagenesisError::NoChange);
+    }
+
+    let nts = |start: isize, len: usize| -> Seq {
+        (0..len as isize)
+            .map(|i| seq[(start + i).rem_euclid(seq_len as isize) as usize])
+            .collect()
+    };
+    // Template on the top strand, starting after the change, and ending before it.
+    let downstream = |len: usize| nts(after, len);
+    let upstream = |len: usize| nts(before - len as isize + 1, len);
+
+    let max_anneal = params.max_anneal_len.min(seq_len / 2);
+    let name = mutation.to_string();
+
+    let (fwd, rev, fwd_change, rev_change, fwd_anneal_tm, rev_anneal_tm) = match params.strategy {
+        MutagenesisStrategy::BackToBack => {
+            // Long changes are split between both tails; the reverse primer's is its complement.
+            let split = if new.len() > MAX_SINGLE_TAIL {
+                new.len() / 2
+            } else {
+                0
+            };
+            let tail_fwd = new[split..].to_vec();
+            let tail_rev = seq_complement(&new[..split]);
+
+            let min_anneal = MIN_ANNEAL_LEN.min(max_anneal);
+            let len_fwd = len_for_tm(min_anneal, max_anneal, params.anneal_tm, downstream, ion);
+            let len_rev = len_for_tm(
+                min_anneal,
+                max_anneal,
+                params.anneal_tm,
+                |len| seq_complement(&upstream(len)),
+                ion,
+            );
+
+            // The annealing regions start at their longest, with the 3' ends tunable to the length
+            // we chose; the tails are fixed.
+            let make_primer = |tail: &[Nucleotide], anneal: Seq, len: usize, dir: &str| {
+                let mut sequence_input = tail.to_vec();
+                sequence_input.extend(&anneal);
+
+                let mut primer = Primer {
+                    name: format!("{name} {dir}"),
+                    description: Some(format!(
+                        "Back-to-back mutagenesis primer. Introduces {name}."
+                    )),
+                    volatile: PrimerData {
+                        tunable_3p: TuneSetting::Enabled(anneal.len() - len),
+                        ..PrimerData::new(&sequence_input)
+                    },
+                    ..Default::default()
+                };
+                primer.run_calcs(ion);
+                primer
+            };
+
+            (
+                make_primer(&tail_fwd, downstream(max_anneal), len_fwd, "fwd"),
+                make_primer(
+                    &tail_rev,
+                    seq_complement(&upstream(max_anneal)),
+                    len_rev,
+                    "rev",
+                ),
+                0..tail_fwd.len(),
+                0..tail_rev.len(),
+                calc_tm(&downstream(len_fwd), ion),
+                calc_tm(&seq_complement(&upstream(len_rev)), ion),
+            )
+        }
+        MutagenesisStrategy::Overlapping => {
+            let min_flank = MIN_FLANK_LEN.min(max_anneal);
+            let len_up = len_for_tm(min_flank, max_anneal, params.anneal_tm, upstream, ion);
+            let len_down = len_for_tm(min_flank, max_anneal, params.anneal_tm, downstream, ion);
+
+            let mut sequence = upstream(len_up);
+            sequence.extend(&new);
+            sequence.extend(downstream(len_down));
+
+            let make_primer = |sequence: Seq, dir: &str| {
+                let mut primer = Primer {
+                    volatile: PrimerData::new(&sequence),
+                    sequence,
+                    name: format!("{name} {dir}"),
+                    description: Some(format!(
+                        "Overlapping mutagenesis primer. Introduces {name}."
+                    )),
+                };
+                primer.volatile.metrics = primer.calc_metrics(ion);
+                primer
+            };
+
+            let tm_up = calc_tm(&upstream(len_up), ion);
+            let tm_down = calc_tm(&downstream(len_down), ion);
+            let tm = match (tm_up, tm_down) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                _ => None,
+            };
+
+            (
+                make_primer(sequence.clone(), "fwd"),
+                make_primer(seq_complement(&sequence), "rev"),
+                len_up..len_up + new.len(),
+                len_down..len_down + new.len(),
+                tm,
+                tm,
+            )
+        }
+    };
+
+    let mut product = data.clone();
+    let report = match mutation.kind {
+        MutationKind::Substitution => product.replace(mutation.range, &new),
+        // Features spanning the insertion include it; eg a coding region with an inserted codon.
+        MutationKind::Insertion => product.insert_extending(mutation.range.start, &new),
+        MutationKind::Deletion => product.delete(mutation.range),
+    };
+    product.primers.extend([fwd.clone(), rev.clone()]);
+    product.metadata.plasmid_name = format!("{} {name}", data.metadata.plasmid_name)
+        .trim()
+        .to_owned();
+
+    Ok(MutagenesisDesign {
+        fwd,
+        rev,
+        fwd_change,
+        rev_change,
+        fwd_anneal_tm,
+        rev_anneal_tm,
+        product,
+        report,
+    })
+}
@@ -0,0 +1,270 @@
+//! This module contains off-target priming analysis: Finding sites primers may bind other than the one
+//! they're designed for, on the construct and on background sequences such as a host genome or other
+//! plasmids, and products that pairs of these sites may amplify.
+//!
+//! Sites are found with the same 3'-anchored binding search as on the sequence view, with looser
+//! criteria, and ranked by the stability (ΔG) of the primer bound to each.
+
+use std::{fs::File, path::Path};
+
+use bio::io::fasta;
+
+use crate::{
+    dimer::calc_dimers,
+    file_io::{
+        error::{parse_seq_letters, FileError, Imported, Position},
+        import_file,
+    },
+    primer::{BindingParams, Primer, PrimerDirection, PrimerMatch},
+    sequence::{seq_complement, Nucleotide, Seq, SeqRange, SeqTopology},
+    IonConcentrations,
+};
+
+/// A sequence to search for primer binding sites.
+#[derive(Clone, Default)]
+pub struct TargetSeq {
+    pub name: String,
+    pub seq: Seq,
+    pub topology: SeqTopology,
+}
+
+#[derive(Clone, Debug)]
+pub struct OffTargetParams {
+    /// Criteria for a primer binding a site. These are looser than the ones used to display primers on
+    /// the sequence, since weak sites may still prime at low annealing temperatures.
+    pub binding: BindingParams,
+    /// Products longer than this, in base pairs, aren't reported; they're unlikely to amplify in
+    /// typical extension times.
+    pub max_product_len: usize,
+}
+
+impl Default for OffTargetParams {
+    fn default() -> Self {
+        Self {
+            binding: BindingParams {
+                min_anneal_len: 12,
+                exact_3p_len: 5,
+                max_mismatches: 3,
+                min_tm: 30.,
+            },
+            max_product_len: 5_000,
+        }
+    }
+}
+
+/// A site a primer binds, on the construct or a background sequence.
+#[derive(Clone, Debug)]
+pub struct PrimerSite {
+    /// Index of the primer.
+    pub primer: usize,
+    /// Index of the background sequence; `None` for the construct.
+    pub target: Option<usize>,
+    pub site: PrimerMatch,
+    /// Gibbs free energy at 37°C of the primer bound to the site, in kcal/mol. More negative
+    /// values are more stable.
+    pub dg: Option<f32>,
+    /// If this is the site the primer is designed for: Its most stable one on the construct.
+    pub intended: bool,
+}
+
+/// A product that may be amplified by a primer binding in the forward direction, and one binding
+/// downstream in the reverse direction. This may be the same primer.
+#[derive(Clone, Debug)]
+pub struct OffTargetProduct {
+    pub primer_fwd: usize,
+    pub primer_rev: usize,
+    /// Index of the background sequence; `None` for the construct.
+    pub target: Option<usize>,
+    /// The range on the target's top strand, from the start of the forward site to the end of the
+    /// reverse one.
+    pub range: SeqRange,
+    /// Product length in base pairs, including primer tails.
+    pub len: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OffTargetReport {
+    /// All sites found, including intended ones, with the most stable first.
+    pub sites: Vec<PrimerSite>,
+    /// Products other than the one amplified from both primers' intended sites, shortest first.
+    pub products: Vec<OffTargetProduct>,
+}
+
+impl OffTargetReport {
+    /// Sites other than the intended ones.
+    pub fn off_target_sites(&self) -> impl Iterator<Item = &PrimerSite> {
+        self.sites.iter().filter(|s| !s.intended)
+    }
+}
+
+/// Stability of a primer bound to a site. We calculate this as a dimer between the primer and the strand
+/// it binds, both 5' to 3'; the duplex with a paired 3' end is the site.
+fn bound_dg(
+    primer: &Primer,
+    site: &PrimerMatch,
+    seq: &[Nucleotide],
+    ion: &IonConcentrations,
+) -> Option<f32> {
+    let top = site.range.seq(seq)?;
+    // Forward primers bind the bottom strand, and reverse primers the top one.
+    let strand = match site.direction {
+        PrimerDirection::Forward => seq_complement(&top),
+        PrimerDirection::Reverse => top,
+    };
+
+    calc_dimers(&primer.sequence, &strand, ion)
+        .end_3p
+        .map(|d| d.dg)
+}
+
+/// Find sites a set of primers bind on the construct they're designed for, and on background
+/// sequences, and products pairs of these sites may amplify. Both strands are searched; for circular
+/// sequences, this includes sites and products that wrap the origin.
+pub fn find_off_target(
+    primers: &[Primer],
+    construct: &TargetSeq,
+    background: &[TargetSeq],
+    ion: &IonConcentrations,
+    params: &OffTargetParams,
+) -> OffTargetReport {
+    let mut sites = Vec::new();
+
+    let targets = std::iter::once((None, construct))
+        .chain(background.iter().enumerate().map(|(i, t)| (Some(i), t)));
+
+    for (target_i, target) in targets {
+        for (primer_i, primer) in primers.iter().enumerate() {
+            for site in
+                primer.find_binding_sites(&target.seq, target.topology, ion, &params.binding)
+            {
+                let dg = bound_dg(primer, &site, &target.seq, ion);
+                sites.push(PrimerSite {
+                    primer: primer_i,
+                    target: target_i,
+                    site,
+                    dg,
+                    intended: false,
+                });
+            }
+        }
+    }
+
+    // Each primer's most stable site on the construct is the one it's designed for.
+    for primer_i in 0..primers.len() {
+        let intended = sites
+            .iter_mut()
+            .filter(|s| s.primer == primer_i && s.target.is_none())
+            .min_by(|a, b| cmp_dg(a.dg, b.dg));
+        if let Some(site) = intended {
+            site.intended = true;
+        }
+    }
+
+    sites.sort_by(|a, b| cmp_dg(a.dg, b.dg));
+
+    let mut products = Vec::new();
+    for fwd in &sites {
+        if fwd.site.direction != PrimerDirection::Forward {
+            continue;
+        }
+
+        for rev in &sites {
+            if rev.site.direction != PrimerDirection::Reverse
+                || rev.target != fwd.target
+                || (fwd.intended && rev.intended)
+            {
+                continue;
+            }
+
+            let target = match fwd.target {
+                Some(i) => &background[i],
+                None => construct,
+            };
+
+            let range = SeqRange::new(fwd.site.range.start, rev.site.range.end);
+            // On linear sequences, the reverse primer must bind after the forward one.
+            if target.topology == SeqTopology::Linear
+                && (range.wraps() || rev.site.range.start < fwd.site.range.start)
+            {
+                continue;
+            }
+
+            let len = range.len(target.seq.len()) + fwd.site.tail_len + rev.site.tail_len;
+            if len > params.max_product_len {
+                continue;
+            }
+
+            products.push(OffTargetProduct {
+                primer_fwd: fwd.primer,
+                primer_rev: rev.primer,
+                target: fwd.target,
+                range,
+                len,
+            });
+        }
+    }
+
+    products.sort_by_key(|p| p.len);
+
+    OffTargetReport { sites, products }
+}
+
+/// Sort by ΔG, most stable first, with unknown values last.
+fn cmp_dg(a: Option<f32>, b: Option<f32>) -> std::cmp::Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+}
+
+/// Load background sequences from a file. Each record of a FASTA file is a separate, linear sequence,
+/// eg the chromosomes of a genome. Other formats are loaded as a single document.
+pub fn load_background(path: &Path) -> Result<Imported<Vec<TargetSeq>>, FileError> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    if extension != "fasta" && extension != "fa" {
+        return Ok(import_file(path)?.map(|data| {
+            vec![TargetSeq {
+                name: data.metadata.plasmid_name,
+                seq: data.seq,
+                topology: data.topology,
+            }]
+        }));
+    }
+
+    let file = File::open(path)?;
+
+    let mut result = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (i, record) in fasta::Reader::new(file).records().enumerate() {
+        let record = record.map_err(|e| {
+            FileError::format(
+                Some(Position::Record(i + 1)),
+                format!("Invalid record: {e}"),
+            )
+        })?;
+
+        let seq = parse_seq_letters(record.seq(), |_| Position::Record(i + 1), &mut warnings);
+        result.push(TargetSeq {
+            name: record.id().to_owned(),
+            seq,
+            topology: SeqTopology::Linear,
+        });
+    }
+
+    if result.is_empty() {
+        return Err(FileError::format(None, "No FASTA records found"));
+    }
+
+    Ok(Imported {
+        data: result,
+        warnings,
+    })
+}
@@ -2,7 +2,10 @@
 
 use bincode::{Decode, Encode};
 
-use crate::PcrUi;
+use crate::{
+    primer::{Primer, PrimerDirection},
+    sequence::{Nucleotide, SeqRange, SeqTopology},
+};
 
 /// This is a common pattern for PCR parameters
 #[derive(Default, Encode, Decode)]
@@ -19,19 +22,14 @@ impl TempTime {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Default)]
 pub enum PolymeraseType {
+    #[default]
     NormalFidelity,
     /// Eg Phusion; results in a shorter extension time.
     HighFidelity,
 }
 
-impl Default for PolymeraseType {
-    fn default() -> Self {
-        Self::NormalFidelity
-    }
-}
-
 impl PolymeraseType {
     pub fn extension_time(&self, product_len: usize) -> u16 {
         match self {
@@ -61,19 +59,64 @@ pub struct PcrParams {
 }
 
 impl PcrParams {
-    pub fn new(data: &PcrUi) -> Self {
+    /// `primer_tm` is in °C, and `product_len` is in base pairs.
+    pub fn new(
+        primer_tm: f32,
+        product_len: usize,
+        polymerase_type: PolymeraseType,
+        num_cycles: u16,
+    ) -> Self {
         Self {
             // 94-98? 30-120s?
             initial_denaturation: TempTime::new(94., 120),
             // 94-98? 10-30s?
             denaturation: TempTime::new(94., 30),
             // Alternative: Ta = 0.3 x  Tm(primer) + 0.7 Tm(product) – 14.9.
-            annealing: TempTime::new(data.primer_tm - 5., 30), // 15-60s. How do we choose.
+            annealing: TempTime::new(primer_tm - 5., 30), // 15-60s. How do we choose.
             // 72 is good if Taq, and Phusion.
-            extension: TempTime::new(72., data.polymerase_type.extension_time(data.product_len)),
+            extension: TempTime::new(72., polymerase_type.extension_time(product_len)),
             // Alternatively: 5-10 mins? Perhaps 30s per 1kb?)
             final_extension: TempTime::new(72., 60),
-            num_cycles: data.num_cycles,
+            num_cycles,
+        }
+    }
+}
+
+/// Find the products amplified from a template by a pair of primers: Each site where the forward primer
+/// binds, paired with each downstream site where the reverse primer binds. Ranges are on the template's
+/// top strand, and include both primers. For circular templates, products may wrap the origin.
+pub fn find_products(
+    template: &[Nucleotide],
+    topology: SeqTopology,
+    primer_fwd: &Primer,
+    primer_rev: &Primer,
+) -> Vec<SeqRange> {
+    let mut result = Vec::new();
+
+    let sites_fwd = primer_fwd.match_to_seq(template, topology);
+    let sites_rev = primer_rev.match_to_seq(template, topology);
+
+    for (dir_fwd, range_fwd) in &sites_fwd {
+        if *dir_fwd != PrimerDirection::Forward {
+            continue;
+        }
+
+        for (dir_rev, range_rev) in &sites_rev {
+            if *dir_rev != PrimerDirection::Reverse {
+                continue;
+            }
+
+            let product = SeqRange::new(range_fwd.start, range_rev.end);
+            // On linear templates, the reverse primer must bind after the forward one.
+            if topology == SeqTopology::Linear
+                && (product.wraps() || range_rev.start < range_fwd.start)
+            {
+                continue;
+            }
+
+            result.push(product);
         }
     }
+
+    result
 }
@@ -1,23 +1,28 @@
 //! This module contains code related to primer (oglionucleotide) design and QC.
 
-use std::ops::Range;
-
-use bincode::{Decode, Encode};
+use bincode::{
+    de::Decoder,
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+    Decode, Encode,
+};
 
 use crate::{
-    gui::primer_qc::DEFAULT_TRIM_AMT,
+    melting_temp_calcs::calc_tm_mismatched,
     primer_metrics::PrimerMetrics,
     sequence::{
         seq_complement, seq_from_str, seq_to_str, Nucleotide,
         Nucleotide::{C, G},
-        Seq,
+        Seq, SeqRange, SeqTopology,
     },
-    IonConcentrations, State,
+    IonConcentrations,
 };
 
 // If a primer length is below this, many calculations will be disabled for it.
 pub const MIN_PRIMER_LEN: usize = 10;
 pub const TM_TARGET: f32 = 59.; // Also used as a default for PCR GUI.
+/// The number of extra nucleotides tunable primer ends start with, for tuning to remove.
+pub const DEFAULT_TRIM_AMT: usize = 32 - 20;
 
 // todo: Sort out your types.
 
@@ -41,6 +46,83 @@ pub enum PrimerDirection {
     Reverse,
 }
 
+/// A site a primer binds to. The primer's 3' end anneals; its 5' end may be a tail that doesn't, eg
+/// with a restriction site or an overlap for assembly. The annealing region may contain mismatches, eg
+/// for mutagenesis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrimerMatch {
+    pub direction: PrimerDirection,
+    /// The part of the sequence the annealing region binds, on the top strand for both directions.
+    pub range: SeqRange,
+    /// The number of nucleotides at the primer's 5' end that don't anneal.
+    pub tail_len: usize,
+    /// Indices in the primer, from its 5' end, of nucleotides in the annealing region that don't match.
+    pub mismatches: Vec<usize>,
+    /// Melting temperature of the annealing region, in °C, accounting for mismatches. `None` if it can't
+    /// be calculated, eg due to ambiguity codes in the sequence.
+    pub tm: Option<f32>,
+}
+
+impl PrimerMatch {
+    /// A site where the whole primer matches the sequence.
+    pub fn exact(direction: PrimerDirection, range: SeqRange) -> Self {
+        Self {
+            direction,
+            range,
+            tail_len: 0,
+            mismatches: Vec::new(),
+            tm: None,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.tail_len == 0 && self.mismatches.is_empty()
+    }
+}
+
+// Matches are recalculated after loading, so we save them in the same format as exact matches were
+// previously: Direction and range.
+impl Encode for PrimerMatch {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.direction.encode(encoder)?;
+        self.range.encode(encoder)
+    }
+}
+
+impl Decode for PrimerMatch {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let direction = PrimerDirection::decode(decoder)?;
+        let range = SeqRange::decode(decoder)?;
+        Ok(Self::exact(direction, range))
+    }
+}
+
+bincode::impl_borrow_decode!(PrimerMatch);
+
+/// Criteria for a primer binding a site, when it doesn't match it exactly.
+#[derive(Clone, Debug)]
+pub struct BindingParams {
+    /// The minimum length of the annealing region, at the primer's 3' end.
+    pub min_anneal_len: usize,
+    /// This many nucleotides at the 3' end must match, for a polymerase to extend the primer.
+    pub exact_3p_len: usize,
+    /// The maximum number of mismatches in the annealing region. Adjacent mismatches end it.
+    pub max_mismatches: usize,
+    /// The minimum melting temperature of the annealing region, in °C.
+    pub min_tm: f32,
+}
+
+impl Default for BindingParams {
+    fn default() -> Self {
+        Self {
+            min_anneal_len: 15,
+            exact_3p_len: 5,
+            max_mismatches: 3,
+            min_tm: 40.,
+        }
+    }
+}
+
 #[derive(Default, Clone, Encode, Decode)]
 pub struct Primer {
     pub sequence: Seq,
@@ -51,10 +133,14 @@ pub struct Primer {
 }
 
 impl Primer {
-    /// Match this primer to a sequence. Check both directions.
-    /// Returns direction, and start and end indexes of the sequence. If direction is reversed,
-    /// the indexs matches to the reversed index.
-    pub fn match_to_seq(&self, seq: &[Nucleotide]) -> Vec<(PrimerDirection, Range<usize>)> {
+    /// Match this primer to a sequence. Check both directions. Returns direction, and the range matched,
+    /// on the top strand for both directions. For circular sequences, this includes matches that wrap
+    /// the origin.
+    pub fn match_to_seq(
+        &self,
+        seq: &[Nucleotide],
+        topology: SeqTopology,
+    ) -> Vec<(PrimerDirection, SeqRange)> {
         let mut result = Vec::new();
 
         // This check prevents spurious small-sequence matches, which may be numerous otherwise.
@@ -62,17 +148,31 @@ impl Primer {
             return result;
         }
 
-        // todo: Partial matches as well.
         let seq_len = seq.len();
         let primer_len = self.sequence.len();
         let complement = seq_complement(seq);
 
-        for seq_start in 0..seq_len {
+        // Linear sequences don't have matches past their end.
+        let starts = match topology {
+            SeqTopology::Circular => 0..seq_len,
+            SeqTopology::Linear => 0..(seq_len + 1).saturating_sub(primer_len),
+        };
+
+        // Degenerate nucleotides in the primer match any they represent.
+        let matches = |target: &[Nucleotide], start: usize| {
+            target
+                .iter()
+                .cycle()
+                .skip(start)
+                .zip(&self.sequence)
+                .all(|(t, p)| p.includes(*t))
+        };
+
+        for seq_start in starts.clone() {
             // Note: This approach handles sequence wraps, eg [circular] plasmids.
-            let seq_iter = seq.iter().cycle().skip(seq_start).take(primer_len);
-            if self.sequence.iter().eq(seq_iter) {
-                let seq_end = (seq_start + primer_len) % seq_len;
-                result.push((PrimerDirection::Forward, seq_start..seq_end));
+            if matches(seq, seq_start) {
+                let range = SeqRange::from_start_len(seq_start, primer_len, seq_len);
+                result.push((PrimerDirection::Forward, range));
             }
 
             // let end_i = (seq_start_i + self.sequence.len()) % seq_len;
@@ -81,17 +181,168 @@ impl Primer {
             // }
         }
 
-        for seq_start in 0..seq_len {
-            let seq_iter = complement.iter().cycle().skip(seq_start).take(primer_len);
-            if self.sequence.iter().eq(seq_iter) {
-                let seq_end = (seq_start + primer_len) % seq_len;
-                result.push((PrimerDirection::Reverse, seq_start..seq_end));
+        for seq_start in starts {
+            if matches(&complement, seq_start) {
+                // Convert from complement-strand indices to top-strand ones.
+                let range = SeqRange::from_start_len(seq_start, primer_len, seq_len);
+                result.push((PrimerDirection::Reverse, range.complement(seq_len)));
+            }
+        }
+
+        result
+    }
+
+    /// Find sites this primer binds, in both directions: Its 3' end anneals, with up to a few mismatches,
+    /// and its 5' end may be a tail that doesn't. Unlike `match_to_seq`, this finds mutagenic, tailed,
+    /// and degenerate primers. For circular sequences, this includes sites that wrap the origin.
+    pub fn find_binding_sites(
+        &self,
+        seq: &[Nucleotide],
+        topology: SeqTopology,
+        ion: &IonConcentrations,
+        params: &BindingParams,
+    ) -> Vec<PrimerMatch> {
+        let mut result = Vec::new();
+
+        if self.sequence.len() < MIN_PRIMER_LEN || seq.is_empty() {
+            return result;
+        }
+
+        let seq_len = seq.len();
+        let complement = seq_complement(seq);
+
+        // We compare the primer to the strand it has the same sense as; for reverse primers, this is
+        // the complement strand.
+        for (direction, target) in [
+            (PrimerDirection::Forward, seq),
+            (PrimerDirection::Reverse, &complement[..]),
+        ] {
+            for end in 0..seq_len {
+                let Some((anneal_len, mismatches, tm)) =
+                    self.bind_at(target, end, topology, ion, params)
+                else {
+                    continue;
+                };
+
+                let start = (end + seq_len + 1 - anneal_len) % seq_len;
+                let range = SeqRange::from_start_len(start, anneal_len, seq_len);
+
+                result.push(PrimerMatch {
+                    direction,
+                    range: match direction {
+                        PrimerDirection::Forward => range,
+                        // Convert from complement-strand indices to top-strand ones.
+                        PrimerDirection::Reverse => range.complement(seq_len),
+                    },
+                    tail_len: self.sequence.len() - anneal_len,
+                    mismatches,
+                    tm,
+                });
             }
         }
 
         result
     }
 
+    /// Check if the primer binds with its 3' end opposite index `end` of `target`, a strand of the same
+    /// sense as the primer. Returns the length of the annealing region, mismatches in it, and its Tm.
+    fn bind_at(
+        &self,
+        target: &[Nucleotide],
+        end: usize,
+        topology: SeqTopology,
+        ion: &IonConcentrations,
+        params: &BindingParams,
+    ) -> Option<(usize, Vec<usize>, Option<f32>)> {
+        let primer_len = self.sequence.len();
+        let seq_len = target.len();
+
+        // Linear sequences don't have sites past their end. The tail may overhang it.
+        let max_len = match topology {
+            SeqTopology::Circular => primer_len.min(seq_len),
+            SeqTopology::Linear => primer_len.min(end + 1),
+        };
+        if max_len < params.min_anneal_len.min(primer_len) {
+            return None;
+        }
+
+        // `k` is the distance from the primer's 3' end.
+        let primer_i = |k: usize| primer_len - 1 - k;
+        let target_nt = |k: usize| target[(end + seq_len - k) % seq_len];
+        let matches = |k: usize| self.sequence[primer_i(k)].includes(target_nt(k));
+
+        if !(0..params.exact_3p_len.min(max_len)).all(matches) {
+            return None;
+        }
+
+        let mut mismatches = Vec::new();
+        // Annealing region length, and its Tm.
+        let mut best: Option<(usize, Option<f32>)> = None;
+
+        for k in 0..max_len {
+            if !matches(k) {
+                if mismatches.last() == Some(&(primer_i(k) + 1))
+                    || mismatches.len() == params.max_mismatches
+                {
+                    break;
+                }
+                mismatches.push(primer_i(k));
+                continue;
+            }
+
+            let len = k + 1;
+            if len < params.min_anneal_len && len < primer_len {
+                continue;
+            }
+            // The annealing region ends at a mismatch, or the primer's 5' end; not within a run of
+            // matching nucleotides, even if a terminal A/T pair would lower its Tm.
+            if k + 1 < max_len && matches(k + 1) {
+                continue;
+            }
+
+            // For the Tm calculation, degenerate nucleotides in the primer take the value they match.
+            let anneal: Vec<Nucleotide> = (0..len)
+                .rev()
+                .map(|k| {
+                    if matches(k) {
+                        target_nt(k)
+                    } else {
+                        self.sequence[primer_i(k)]
+                    }
+                })
+                .collect();
+            let template: Vec<Nucleotide> =
+                (0..len).rev().map(|k| target_nt(k).complement()).collect();
+
+            let tm = calc_tm_mismatched(&anneal, &template, ion);
+            let better = match (best, tm) {
+                (None, _) => true,
+                (Some((_, Some(tm_best))), Some(tm)) => tm > tm_best,
+                (Some((_, None)), Some(_)) => true,
+                // Without a Tm, eg due to ambiguity codes in the sequence, prefer longer regions.
+                (Some((_, None)), None) => true,
+                (Some((_, Some(_))), None) => false,
+            };
+            if better {
+                best = Some((len, tm));
+            }
+        }
+
+        let (len, tm) = best?;
+        let mismatches: Vec<usize> = mismatches
+            .into_iter()
+            .filter(|i| *i >= primer_len - len)
+            .rev()
+            .collect();
+
+        match tm {
+            Some(tm) if tm < params.min_tm => None,
+            // Without a Tm, we only accept exact matches.
+            None if len < primer_len || !mismatches.is_empty() => None,
+            _ => Some((len, mismatches, tm)),
+        }
+    }
+
     /// Automatically select primer length based on quality score.
     pub fn tune(&mut self, ion: &IonConcentrations) {
         if self.volatile.tunable_3p != TuneSetting::Disabled
@@ -208,12 +459,7 @@ impl Primer {
         }
 
         if let TuneSetting::Enabled(i) = self.volatile.tunable_3p {
-            end = if i > full_len {
-                // Prevents an overrun.
-                0
-            } else {
-                full_len - i
-            };
+            end = full_len.saturating_sub(i);
         }
 
         if start > end || start + 1 > self.volatile.sequence_input.len() {
@@ -252,24 +498,21 @@ pub fn design_slic_fc_primers(
     let vector_reversed = seq_complement(seq_vector);
     let insert_reversed = seq_complement(seq_insert);
 
-    // todo: You should wrap the vector if the insert loc is near the edges. Clamping
-    // todo as we do will produce incorrect behavior.
-
     let insert_loc_reversed = seq_len_vector - insert_loc;
 
-    let (seq_vector_fwd, seq_vector_rev) = {
-        let mut end = insert_loc + UNTRIMMED_LEN_VECTOR;
-        end = end.clamp(0, seq_len_vector);
-
-        let mut end_reversed = insert_loc_reversed + UNTRIMMED_LEN_VECTOR;
-        end_reversed = end_reversed.clamp(0, seq_len_vector);
-
-        (
-            seq_vector[insert_loc..end].to_owned(),
-            vector_reversed[insert_loc_reversed..end_reversed].to_owned(),
-        )
+    // The vector is circular, so primers near its origin wrap it.
+    let vector_from = |seq: &[Nucleotide], start: usize| -> Seq {
+        seq.iter()
+            .cycle()
+            .skip(start)
+            .take(UNTRIMMED_LEN_VECTOR.min(seq_len_vector))
+            .copied()
+            .collect()
     };
 
+    let seq_vector_fwd = vector_from(seq_vector, insert_loc);
+    let seq_vector_rev = vector_from(&vector_reversed, insert_loc_reversed);
+
     let (seq_insert_fwd, seq_insert_rev) = {
         let mut insert_end = UNTRIMMED_LEN_INSERT;
         insert_end = insert_end.clamp(0, seq_len_insert);
@@ -327,7 +570,8 @@ pub fn design_slic_fc_primers(
     })
 }
 
-// todo: Use this A/R, called from the UI page.
+/// Primers at each end of a sequence, eg to amplify a whole insert. See `primer_design` for primers
+/// that amplify a target region, chosen from candidates on its flanks.
 pub fn design_amplification_primers(seq: &[Nucleotide]) -> Option<AmplificationPrimers> {
     // These lenghts should be long enough for reasonablely high-length primers, should that be
     // required for optimal characteristics.
@@ -380,8 +624,8 @@ pub struct PrimerData {
     // seq_removed_3p: Seq
     pub seq_removed_5p: String,
     pub seq_removed_3p: String,
-    /// todo: Which direction is the range, if the direction is reverse?
-    pub matches_seq: Vec<(PrimerDirection, Range<usize>)>,
+    /// Sites this primer binds to in the sequence.
+    pub matches_seq: Vec<PrimerMatch>,
     // pub matches_vector: Vec<(PrimerDirection, Range<usize>)>, // todo: Currently unused.
     // pub matches_insert: Vec<(PrimerDirection, Range<usize>)>, // todo: Currently unused.
     // pub matches_vector_with_insert: Vec<(PrimerDirection, Range<usize>)>,
@@ -389,20 +633,16 @@ pub struct PrimerData {
 
 impl PrimerData {
     pub fn new(seq: &[Nucleotide]) -> Self {
-        let mut result = Self::default();
-        result.sequence_input = seq_to_str(seq);
-        result
-    }
-}
-
-impl Default for TuneSetting {
-    fn default() -> Self {
-        Self::Disabled
+        Self {
+            sequence_input: seq_to_str(seq),
+            ..Default::default()
+        }
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Encode, Decode)]
+#[derive(Clone, Copy, PartialEq, Encode, Decode, Default)]
 pub enum TuneSetting {
+    #[default]
     Disabled,
     /// Inner: Offset index; this marks the distance from the respective ends that the sequence is attentuated to.
     Enabled(usize),
@@ -417,25 +657,29 @@ impl TuneSetting {
     }
 }
 
-/// Calculate GC portion, on a scale of 0 to 1.
+/// Calculate GC portion, on a scale of 0 to 1. Ambiguity codes count as the portion of the nucleotides
+/// they represent that are G or C; eg S counts fully, and N counts half.
 /// This is a standalone fn, as it's used outside of Primer methods.
 pub fn calc_gc(seq: &[Nucleotide]) -> f32 {
-    let mut num_gc = 0;
+    let mut num_gc = 0.;
     for nt in seq {
-        if *nt == C || *nt == G {
-            num_gc += 1;
-        }
+        let possible = nt.possible();
+        let gc = possible.iter().filter(|n| **n == C || **n == G).count();
+        num_gc += gc as f32 / possible.len() as f32;
     }
 
-    num_gc as f32 / seq.len() as f32
+    num_gc / seq.len() as f32
 }
 
-/// We run this to generate cloning primers when clicking the button
-pub fn make_cloning_primers(state: &mut State) {
-    let seq_vector = seq_from_str(&state.ui.seq_vector_input);
-    let seq_insert = seq_from_str(&state.ui.seq_insert_input);
-
-    if let Some(mut primers) = design_slic_fc_primers(&seq_vector, &seq_insert, state.insert_loc) {
+/// Design SLIC and FastCloning primers, and tune them. Returns insert forward, insert reverse, vector
+/// forward, and vector reverse primers.
+pub fn make_cloning_primers(
+    seq_vector: &Seq,
+    seq_insert: &Seq,
+    insert_loc: usize,
+    ion_concentrations: &IonConcentrations,
+) -> Option<[Primer; 4]> {
+    design_slic_fc_primers(seq_vector, seq_insert, insert_loc).map(|mut primers| {
         let sequence_input = seq_to_str(&primers.insert_fwd.sequence);
 
         let insert_fwd_data = PrimerData {
@@ -478,24 +722,26 @@ pub fn make_cloning_primers(state: &mut State) {
         primers.vector_fwd.volatile = vector_fwd_data;
         primers.vector_rev.volatile = vector_rev_data;
 
-        primers.insert_fwd.tune(&state.ion_concentrations);
-        primers.insert_rev.tune(&state.ion_concentrations);
-        primers.vector_fwd.tune(&state.ion_concentrations);
-        primers.vector_rev.tune(&state.ion_concentrations);
+        primers.insert_fwd.tune(ion_concentrations);
+        primers.insert_rev.tune(ion_concentrations);
+        primers.vector_fwd.tune(ion_concentrations);
+        primers.vector_rev.tune(ion_concentrations);
 
-        state.generic.primers.extend([
+        [
             primers.insert_fwd,
             primers.insert_rev,
             primers.vector_fwd,
             primers.vector_rev,
-        ]);
-
-        state.sync_primer_matches(None); // note: Not requried to run on all primers.
-    }
+        ]
+    })
 }
 
-pub fn make_amplification_primers(state: &mut State) {
-    if let Some(mut primers) = design_amplification_primers(&state.generic.seq) {
+/// Design primers that amplify the entire sequence, and tune them. Returns forward and reverse primers.
+pub fn make_amplification_primers(
+    seq: &[Nucleotide],
+    ion_concentrations: &IonConcentrations,
+) -> Option<[Primer; 2]> {
+    design_amplification_primers(seq).map(|mut primers| {
         let sequence_input = seq_to_str(&primers.fwd.sequence);
 
         let primer_fwd_data = PrimerData {
@@ -516,11 +762,110 @@ pub fn make_amplification_primers(state: &mut State) {
         primers.fwd.volatile = primer_fwd_data;
         primers.rev.volatile = primer_rev_data;
 
-        primers.fwd.tune(&state.ion_concentrations);
-        primers.rev.tune(&state.ion_concentrations);
+        primers.fwd.tune(ion_concentrations);
+        primers.rev.tune(ion_concentrations);
+
+        [primers.fwd, primers.rev]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEQ: &str = "ATGACCATGATTACGCCAAGCTTGCATGCCTGCAGGTCGACTCTAGAGGATCCCCGGGTACCGAGCTCGAATTC";
+
+    fn sites(primer: &str, topology: SeqTopology, params: &BindingParams) -> Vec<PrimerMatch> {
+        let primer = Primer {
+            sequence: seq_from_str(primer),
+            ..Default::default()
+        };
+        primer.find_binding_sites(
+            &seq_from_str(SEQ),
+            topology,
+            &IonConcentrations::default(),
+            params,
+        )
+    }
+
+    fn single_site(primer: &str, topology: SeqTopology) -> PrimerMatch {
+        let mut result = sites(primer, topology, &Default::default());
+        assert_eq!(result.len(), 1);
+        result.remove(0)
+    }
+
+    #[test]
+    fn exact() {
+        let site = single_site("TTACGCCAAGCTTGCATGCC", SeqTopology::Linear);
+        assert_eq!(site.direction, PrimerDirection::Forward);
+        assert_eq!(site.range, SeqRange::new(11, 30));
+        assert_eq!(site.tail_len, 0);
+        assert!(site.mismatches.is_empty());
+        assert!(site.tm.unwrap() > 50.);
+
+        // The reverse complement of 41 - 60.
+        let site = single_site("TACCCGGGGATCCTCTAGAG", SeqTopology::Linear);
+        assert_eq!(site.direction, PrimerDirection::Reverse);
+        assert_eq!(site.range, SeqRange::new(41, 60));
+        // The whole primer anneals, although a terminal A/T pair lowers Tm.
+        assert_eq!(site.tail_len, 0);
+    }
+
+    #[test]
+    fn tail() {
+        // A 5' tail that mismatches each nucleotide it's opposite.
+        let site = single_site("GGTACTTTACGCCAAGCTTGCATGCC", SeqTopology::Linear);
+        assert_eq!(site.range, SeqRange::new(11, 30));
+        assert_eq!(site.tail_len, 6);
+        assert!(site.mismatches.is_empty());
+    }
+
+    #[test]
+    fn mismatches() {
+        let exact = single_site("TTACGCCAAGCTTGCATGCC", SeqTopology::Linear);
+
+        let site = single_site("TTACGCCATGCTTGCATGCC", SeqTopology::Linear);
+        assert_eq!(site.range, SeqRange::new(11, 30));
+        assert_eq!(site.mismatches, vec![8]);
+        assert!(site.tm.unwrap() < exact.tm.unwrap());
+
+        // Mismatches near the 3' end prevent extension.
+        assert!(sites(
+            "TTACGCCAAGCTTGCATCCC",
+            SeqTopology::Linear,
+            &Default::default()
+        )
+        .is_empty());
+
+        // Without allowing mismatches, the annealing region ends at one.
+        let params = BindingParams {
+            max_mismatches: 0,
+            ..Default::default()
+        };
+        let result = sites("TTAGGCCAAGCTTGCATGCC", SeqTopology::Linear, &params);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].range, SeqRange::new(15, 30));
+        assert_eq!(result[0].tail_len, 4);
+        assert!(result[0].mismatches.is_empty());
+
+        // Or, if that's shorter than the minimum annealing length, there's no site.
+        let params = BindingParams {
+            max_mismatches: 0,
+            ..Default::default()
+        };
+        assert!(sites("TTACGCCATGCTTGCATGCC", SeqTopology::Linear, &params).is_empty());
+    }
+
+    #[test]
+    fn wraps_origin() {
+        let primer = "GCTCGAATTCATGACCATGATT";
 
-        state.generic.primers.extend([primers.fwd, primers.rev]);
+        let site = single_site(primer, SeqTopology::Circular);
+        assert_eq!(site.range, SeqRange::new(65, 12));
+        assert_eq!(site.tail_len, 0);
 
-        state.sync_primer_matches(None); // note: Not requried to run on all primers.
+        // On a linear sequence, only the part before the origin binds, as a tail.
+        let result = sites(primer, SeqTopology::Linear, &Default::default());
+        assert!(result.iter().all(|s| !s.range.wraps()));
     }
 }
@@ -0,0 +1,308 @@
+//! This module contains de novo primer pair design, similar to Primer3: Given a target region, we
+//! enumerate candidate primers on both flanks, filter them by length, Tm, and GC content, score them
+//! with `PrimerMetrics`, and rank pairs of them. Pair scores include the primers' Tm difference, the
+//! hetero-dimer they form, and secondary structure of the amplicon between them.
+
+use crate::{
+    dimer::{calc_dimers, DimerAnalysis},
+    melting_temp_calcs::calc_tm,
+    primer::{calc_gc, Primer, PrimerData},
+    secondary_structure::fold,
+    sequence::{seq_complement, Nucleotide, Seq, SeqRange, SeqTopology},
+    util::map_linear,
+    IonConcentrations,
+};
+
+/// After filtering, we keep this many of the highest-quality candidates on each flank, for pairing.
+const CANDIDATES_PER_SIDE: usize = 40;
+/// Amplicons longer than this are folded by their ends: Half this many nucleotides from each, joined.
+/// Folding time grows with the cube of length, and structure near the primer sites affects annealing
+/// most.
+const MAX_FOLD_LEN: usize = 120;
+/// We fold the amplicons of at most this many pairs per result, with the highest scores otherwise.
+const FOLDED_PER_RESULT: usize = 4;
+
+const WEIGHT_PRIMERS: f32 = 2.;
+const WEIGHT_TM_DIFF: f32 = 1.;
+const WEIGHT_DIMER: f32 = 1.;
+const WEIGHT_STRUCTURE: f32 = 1.;
+const WEIGHT_TOTAL: f32 = WEIGHT_PRIMERS + WEIGHT_TM_DIFF + WEIGHT_DIMER + WEIGHT_STRUCTURE;
+
+#[derive(Clone, Debug)]
+pub struct DesignParams {
+    /// The region the product must contain. Primers bind outside it.
+    pub target: SeqRange,
+    /// Minimum and maximum product length, in base pairs.
+    pub product_len: (usize, usize),
+    /// Minimum and maximum primer length.
+    pub primer_len: (usize, usize),
+    /// Minimum and maximum primer melting temperature, in °C.
+    pub tm: (f32, f32),
+    /// Minimum and maximum GC portion, from 0 to 1.
+    pub gc: (f32, f32),
+    /// The maximum difference between the primers' melting temperatures, in °C.
+    pub max_tm_diff: f32,
+    /// The number of pairs to return.
+    pub num_results: usize,
+}
+
+impl Default for DesignParams {
+    fn default() -> Self {
+        Self {
+            target: SeqRange::new(1, 1),
+            product_len: (100, 1_000),
+            primer_len: (18, 25),
+            tm: (55., 65.),
+            gc: (0.4, 0.6),
+            max_tm_diff: 3.,
+            num_results: 10,
+        }
+    }
+}
+
+/// A designed primer pair, and the product it amplifies.
+#[derive(Clone)]
+pub struct PrimerPair {
+    /// These have metrics set.
+    pub fwd: Primer,
+    pub rev: Primer,
+    /// Where each primer binds, on the top strand.
+    pub fwd_site: SeqRange,
+    pub rev_site: SeqRange,
+    /// The product, on the top strand, including both primers.
+    pub product: SeqRange,
+    pub product_len: usize,
+    /// °C
+    pub tm_diff: f32,
+    pub hetero_dimer: DimerAnalysis,
+    /// The ΔG, in kcal/mol, of the product's most stable secondary structure; for products longer than
+    /// `MAX_FOLD_LEN`, of their ends.
+    pub amplicon_dg: f32,
+    pub tm_diff_score: f32,
+    pub dimer_score: f32,
+    pub structure_score: f32,
+    /// A weighted overall score, from 0 to 1, including the primers' quality scores.
+    pub score: f32,
+}
+
+/// A primer on one flank, with its position relative to the target start, on the top strand.
+struct Candidate {
+    primer: Primer,
+    /// Inclusive.
+    start: isize,
+    end: isize,
+    quality: f32,
+}
+
+/// Design primer pairs that amplify a target region, ranked with the best first. Returns an empty
+/// list if the target is invalid, or no candidates meet the constraints. For circular sequences,
+/// primers and products may wrap the origin.
+pub fn design_primer_pairs(
+    seq: &[Nucleotide],
+    topology: SeqTopology,
+    params: &DesignParams,
+    ion: &IonConcentrations,
+) -> Vec<PrimerPair> {
+    let seq_len = seq.len();
+    if !params.target.is_valid(seq_len) || params.primer_len.0 > params.primer_len.1 {
+        return Vec::new();
+    }
+
+    let target_start = params.target.start as isize - 1;
+    let target_len = params.target.len(seq_len) as isize;
+    let product_max = match topology {
+        SeqTopology::Circular => params.product_len.1.min(seq_len),
+        SeqTopology::Linear => params.product_len.1,
+    } as isize;
+
+    // Index in the sequence of a position relative to the target start.
+    let index = |pos: isize| -> Option<usize> {
+        let i = target_start + pos;
+        match topology {
+            SeqTopology::Circular => Some(i.rem_euclid(seq_len as isize) as usize),
+            SeqTopology::Linear => (0..seq_len as isize).contains(&i).then_some(i as usize),
+        }
+    };
+    let top_strand = |start: isize, end: isize| -> Option<Seq> {
+        (start..=end).map(|p| index(p).map(|i| seq[i])).collect()
+    };
+
+    // The primer with the fewest nucleotides on the other flank sets the longest allowed flank.
+    let max_flank = product_max - target_len - params.primer_len.0 as isize;
+    if max_flank < 0 {
+        return Vec::new();
+    }
+
+    let candidate = |start: isize, end: isize, reverse: bool| -> Option<Candidate> {
+        let top = top_strand(start, end)?;
+        if top.iter().any(|nt| nt.is_ambiguous()) {
+            return None;
+        }
+
+        let sequence = if reverse { seq_complement(&top) } else { top };
+
+        let gc = calc_gc(&sequence);
+        if gc < params.gc.0 || gc > params.gc.1 {
+            return None;
+        }
+        // This is fast compared to the full metrics, so filter by it first.
+        let tm = calc_tm(&sequence, ion)?;
+        if tm < params.tm.0 || tm > params.tm.1 {
+            return None;
+        }
+
+        let mut primer = Primer {
+            volatile: PrimerData::new(&sequence),
+            sequence,
+            ..Default::default()
+        };
+        let metrics = primer.calc_metrics(ion)?;
+        let quality = metrics.quality_score;
+        primer.volatile.metrics = Some(metrics);
+
+        Some(Candidate {
+            primer,
+            start,
+            end,
+            quality,
+        })
+    };
+
+    let mut fwd = Vec::new();
+    let mut rev = Vec::new();
+    for dist in 0..=max_flank {
+        for len in params.primer_len.0..=params.primer_len.1 {
+            let len = len as isize;
+            // Forward primers end before the target, and reverse primers start after it.
+            fwd.extend(candidate(-dist - len, -dist - 1, false));
+            rev.extend(candidate(
+                target_len + dist,
+                target_len + dist + len - 1,
+                true,
+            ));
+        }
+    }
+
+    let best = |mut candidates: Vec<Candidate>| -> Vec<Candidate> {
+        candidates.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+
+        let mut result = Vec::new();
+        for c in candidates {
+            if result.len() == CANDIDATES_PER_SIDE {
+                break;
+            }
+
+            // Primers that bind more than one site may amplify other products.
+            if c.primer.match_to_seq(seq, topology).len() != 1 {
+                continue;
+            }
+
+            result.push(c);
+        }
+        result
+    };
+
+    let fwd = best(fwd);
+    let rev = best(rev);
+
+    // The most stable structure of the amplicon's top strand. The bottom one forms the complementary
+    // structures, with nearly the same stability.
+    let amplicon_dg = |start: isize, end: isize| -> f32 {
+        let mut top = top_strand(start, end).unwrap_or_default();
+        if top.len() > MAX_FOLD_LEN {
+            top.drain(MAX_FOLD_LEN / 2..top.len() - MAX_FOLD_LEN / 2);
+        }
+        fold(&top, ion).dg
+    };
+
+    // Scores assuming no amplicon structure; each pair's best possible. With the product's ends, relative
+    // to the target start.
+    let mut pairs = Vec::new();
+    for f in &fwd {
+        for r in &rev {
+            let product_len = (r.end - f.start + 1) as usize;
+            if product_len < params.product_len.0 || product_len > product_max as usize {
+                continue;
+            }
+
+            let (Some(tm_f), Some(tm_r)) = (&f.primer.volatile.metrics, &r.primer.volatile.metrics)
+            else {
+                continue;
+            };
+            let tm_diff = (tm_f.melting_temp - tm_r.melting_temp).abs();
+            if tm_diff > params.max_tm_diff {
+                continue;
+            }
+
+            let hetero_dimer = calc_dimers(&f.primer.sequence, &r.primer.sequence, ion);
+
+            let tm_diff_score = map_linear(tm_diff, (0., 5.), (1., 0.)).clamp(0., 1.);
+            let dimer_score = hetero_dimer.score();
+
+            let score = (WEIGHT_PRIMERS * (f.quality + r.quality) / 2.
+                + WEIGHT_TM_DIFF * tm_diff_score
+                + WEIGHT_DIMER * dimer_score
+                + WEIGHT_STRUCTURE)
+                / WEIGHT_TOTAL;
+
+            // Each end is a valid index, since we built the candidates from them.
+            let abs = |pos: isize| index(pos).unwrap();
+            let site = |start: isize, end: isize| {
+                SeqRange::from_start_len(abs(start), (end - start + 1) as usize, seq_len)
+            };
+
+            let pair = PrimerPair {
+                fwd: f.primer.clone(),
+                rev: r.primer.clone(),
+                fwd_site: site(f.start, f.end),
+                rev_site: site(r.start, r.end),
+                product: site(f.start, r.end),
+                product_len,
+                tm_diff,
+                hetero_dimer,
+                amplicon_dg: 0.,
+                tm_diff_score,
+                dimer_score,
+                structure_score: 1.,
+                score,
+            };
+            pairs.push((pair, f.start, r.end));
+        }
+    }
+
+    // Folding amplicons is slow compared to the other terms, so we fold pairs in order of their best
+    // possible score, and stop once no remaining pair can place, or we've folded enough of them.
+    pairs.sort_by(|a, b| b.0.score.total_cmp(&a.0.score));
+
+    let mut result: Vec<PrimerPair> = Vec::new();
+    for (mut pair, start, end) in pairs
+        .into_iter()
+        .take(params.num_results * FOLDED_PER_RESULT)
+    {
+        if result.len() == params.num_results
+            && result.last().is_some_and(|worst| worst.score >= pair.score)
+        {
+            break;
+        }
+
+        pair.amplicon_dg = amplicon_dg(start, end);
+        pair.structure_score = map_linear(pair.amplicon_dg, (-3., -10.), (1., 0.)).clamp(0., 1.);
+        pair.score -= WEIGHT_STRUCTURE * (1. - pair.structure_score) / WEIGHT_TOTAL;
+
+        result.push(pair);
+        result.sort_by(|a, b| b.score.total_cmp(&a.score));
+        result.truncate(params.num_results);
+    }
+
+    for (i, pair) in result.iter_mut().enumerate() {
+        pair.fwd.name = format!("Design {} fwd", i + 1);
+        pair.rev.name = format!("Design {} rev", i + 1);
+        pair.fwd.description = Some(format!(
+            "Amplifies {}, {} bp.",
+            pair.product, pair.product_len
+        ));
+        pair.rev.description.clone_from(&pair.fwd.description);
+    }
+
+    result
+}
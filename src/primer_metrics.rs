@@ -1,33 +1,54 @@
 //! This module handles assessing various primer metrics, such as GC concentration, and repeats.
 
-use bincode::{Decode, Encode};
+use bincode::{
+    de::Decoder,
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+    Decode, Encode,
+};
 
 use crate::{
+    dimer::{calc_dimers, DimerAnalysis},
     melting_temp_calcs,
-    primer::{calc_gc, Primer, MIN_PRIMER_LEN},
+    primer::{calc_gc, Primer, MIN_PRIMER_LEN, TM_TARGET},
+    secondary_structure::{fold, Fold},
     sequence::{
-        Nucleotide,
-        Nucleotide::{C, G},
+        seq_degeneracy, seq_expansion, Nucleotide,
+        Nucleotide::{A, C, G, T},
     },
     util::{map_linear, remove_duplicates},
-    IonConcentrations, TM_TARGET,
+    IonConcentrations,
 };
 
+/// For degenerate primers, we calculate the melting temperature of at most this many of the sequences
+/// they represent.
+const MAX_TM_EXPANSIONS: u64 = 256;
+
 /// Metrics related to primer quality.
-#[derive(Clone, Debug, Default, Encode, Decode)]
+#[derive(Clone, Debug, Default)]
 pub struct PrimerMetrics {
-    /// C
+    /// C. For degenerate primers, this is the mean of the sequences they represent.
     pub melting_temp: f32,
+    /// The lowest and highest melting temperature of the sequences a degenerate primer represents, in °C.
+    /// For other primers, both are `melting_temp`.
+    pub tm_range: (f32, f32),
+    /// The number of sequences a primer with ambiguity codes represents; eg 32 for one with an NNK
+    /// codon. 1 for other primers.
+    pub degeneracy: u64,
     /// 0. to 1.
     pub gc_portion: f32,
     /// How many G and C nts are in the last 5 (3' end) nts of the sequence.
     pub gc_3p_count: u8,
-    pub self_end_dimer: u8,
+    /// The most stable structures formed by two copies of this primer.
+    pub self_dimer: DimerAnalysis,
+    /// The most stable secondary structure of this primer, eg a hairpin.
+    pub hairpin: Fold,
     pub repeats: u8,
     pub tm_score: f32,
     pub gc_score: f32,
     pub gc_3p_score: f32,
     pub dimer_score: f32,
+    pub hairpin_score: f32,
     /// https://www.benchling.com/primer-design-for-pcr
     /// "Avoid runs of four or more of a single base (e.g., ACCCCC), or four or more dinucleotide
     /// repeats (e.g., ATATATATAT) as they will cause mispriming."
@@ -36,6 +57,55 @@ pub struct PrimerMetrics {
     pub quality_score: f32,
 }
 
+// Dimers, hairpins, and degeneracy aren't saved; they're recalculated when loading. In place of the dimers,
+// we write the byte that format version 1 used for the former self-end dimer count.
+impl Encode for PrimerMetrics {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.melting_temp.encode(encoder)?;
+        self.gc_portion.encode(encoder)?;
+        self.gc_3p_count.encode(encoder)?;
+        0_u8.encode(encoder)?;
+        self.repeats.encode(encoder)?;
+        self.tm_score.encode(encoder)?;
+        self.gc_score.encode(encoder)?;
+        self.gc_3p_score.encode(encoder)?;
+        self.dimer_score.encode(encoder)?;
+        self.repeats_score.encode(encoder)?;
+        self.quality_score.encode(encoder)?;
+
+        Ok(())
+    }
+}
+
+impl Decode for PrimerMetrics {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let melting_temp = f32::decode(decoder)?;
+        let gc_portion = f32::decode(decoder)?;
+        let gc_3p_count = u8::decode(decoder)?;
+        let _self_end_dimer = u8::decode(decoder)?;
+
+        Ok(Self {
+            melting_temp,
+            tm_range: (melting_temp, melting_temp),
+            degeneracy: 1,
+            gc_portion,
+            gc_3p_count,
+            self_dimer: Default::default(),
+            hairpin: Default::default(),
+            repeats: u8::decode(decoder)?,
+            tm_score: f32::decode(decoder)?,
+            gc_score: f32::decode(decoder)?,
+            gc_3p_score: f32::decode(decoder)?,
+            dimer_score: f32::decode(decoder)?,
+            hairpin_score: 0.,
+            repeats_score: f32::decode(decoder)?,
+            quality_score: f32::decode(decoder)?,
+        })
+    }
+}
+
+bincode::impl_borrow_decode!(PrimerMetrics);
+
 impl PrimerMetrics {
     /// Return a quality score, on a scale from 0 to 1.
     pub fn update_scores(&mut self) {
@@ -46,6 +116,7 @@ impl PrimerMetrics {
         const WEIGHT_STAB: f32 = 1.;
         // const WEIGHT_COMPLEXITY: f32 = 1.;
         const WEIGHT_DIMER: f32 = 1.;
+        const WEIGHT_HAIRPIN: f32 = 1.;
         const WEIGHT_REPEATS: f32 = 1.;
 
         // todo: Instead of closeness to 59, should it be >54??
@@ -67,6 +138,12 @@ impl PrimerMetrics {
             _ => unreachable!(),
         };
 
+        self.dimer_score = self.self_dimer.score();
+
+        // Hairpins weaker than about -3 kcal/mol are generally tolerated; a structure that's stable at
+        // the annealing temperature prevents binding.
+        self.hairpin_score = map_linear(self.hairpin.dg, (-3., -7.), (1., 0.)).clamp(0., 1.);
+
         self.repeats_score = match self.repeats {
             0 => 1.,
             1 => 0.8,
@@ -80,9 +157,10 @@ impl PrimerMetrics {
             + WEIGHT_GC * self.gc_score
             + WEIGHT_STAB * self.gc_3p_score
             // + WEIGHT_COMPLEXITY * self.complexity_score
-            + WEIGHT_DIMER * self.dimer_score)
+            + WEIGHT_DIMER * self.dimer_score
+            + WEIGHT_HAIRPIN * self.hairpin_score)
             + WEIGHT_REPEATS * self.repeats_score)
-            / 5.
+            / 6.
     }
 }
 
@@ -106,6 +184,8 @@ impl Primer {
     /// We use the "bases stacking method" as defined hte Amplifx guide above.
     ///
     /// See [The BioPython MeltingTemp module](https://github.com/biopython/biopython/blob/master/Bio/SeqUtils/MeltingTemp.py)
+    ///
+    /// For degenerate primers, this is the mean Tm of the sequences they represent.
     pub fn calc_tm(&self, ion_concentrations: &IonConcentrations) -> f32 {
         // const NAP_K_P: f32 = 0.05;
         //
@@ -119,7 +199,52 @@ impl Primer {
         // let N = self.sequence.len();
         //
         // dH / (dS + 0.368 * N * NAP.ln() + R * primer.ln() / 4.)
-        melting_temp_calcs::calc_tm(&self.sequence, ion_concentrations).unwrap_or(0.)
+        let tms = self.expansion_tms(ion_concentrations);
+        if tms.is_empty() {
+            return 0.;
+        }
+        tms.iter().sum::<f32>() / tms.len() as f32
+    }
+
+    /// Melting temperatures of the unambiguous sequences this primer represents; for primers without
+    /// ambiguity codes, this is its own. If there are more than `MAX_TM_EXPANSIONS`, we use evenly
+    /// spaced ones, and the most AT-rich and GC-rich, which are close to the lowest and highest Tm.
+    pub fn expansion_tms(&self, ion_concentrations: &IonConcentrations) -> Vec<f32> {
+        let degeneracy = seq_degeneracy(&self.sequence);
+
+        let mut expansions: Vec<Vec<Nucleotide>> = if degeneracy <= MAX_TM_EXPANSIONS {
+            (0..degeneracy)
+                .map(|i| seq_expansion(&self.sequence, i))
+                .collect()
+        } else {
+            let step = degeneracy / MAX_TM_EXPANSIONS;
+            (0..MAX_TM_EXPANSIONS)
+                .map(|i| seq_expansion(&self.sequence, i * step))
+                .collect()
+        };
+
+        if degeneracy > MAX_TM_EXPANSIONS {
+            // Prefer the given nucleotides at each position, if possible; eg for the most AT-rich.
+            let extreme = |preferred: [Nucleotide; 2]| {
+                self.sequence
+                    .iter()
+                    .map(|nt| {
+                        let possible = nt.possible();
+                        *possible
+                            .iter()
+                            .find(|p| preferred.contains(p))
+                            .unwrap_or(&possible[0])
+                    })
+                    .collect()
+            };
+            expansions.push(extreme([A, T]));
+            expansions.push(extreme([G, C]));
+        }
+
+        expansions
+            .iter()
+            .filter_map(|seq| melting_temp_calcs::calc_tm(seq, ion_concentrations))
+            .collect()
     }
 
     /// This is a metric known as 3' end stability. Return the number of Gs and Cs in the last 5 bases.
@@ -134,8 +259,9 @@ impl Primer {
             &self.sequence[..]
         };
 
+        // Ambiguity codes count if they only represent G and C, ie S.
         for nt in last_5 {
-            if *nt == C || *nt == G {
+            if nt.possible().iter().all(|n| *n == C || *n == G) {
                 result += 1
             }
         }
@@ -143,17 +269,15 @@ impl Primer {
         result
     }
 
-    /// Calculate self end dimer score. (Details on what this means here)
+    /// Find the most stable self-dimers: Structures formed by two copies of this primer binding each other.
     ///
     /// http://www.premierbiosoft.com/tech_notes/PCR_Primer_Design.html
     /// "A primer self-dimer is formed by intermolecular interactions between the two (same sense)
     /// primers, where the primer is homologous to itself. Generally a large amount of primers are
     /// used in PCR compared to the amount of target gene. When primers form intermolecular dimers
-    /// much more readily than hybridizing to target DNA, they reduce the product yield. Optimally a 3'
-    /// end self dimer with a ΔG of -5 kcal/mol and an internal self dimer with a ΔG of -6 kcal/mol is
-    /// tolerated generally."
-    pub fn calc_self_end_dimer(&self) -> u8 {
-        0
+    /// much more readily than hybridizing to target DNA, they reduce the product yield."
+    pub fn calc_self_dimer(&self, ion_concentrations: &IonConcentrations) -> DimerAnalysis {
+        calc_dimers(&self.sequence, &self.sequence, ion_concentrations)
     }
 
     /// Calculate how many single or double nucleotide sequences exist that are of len 4 or more of the
@@ -173,6 +297,11 @@ impl Primer {
         result
     }
 
+    /// Find the most stable secondary structure this primer forms on its own, eg a hairpin.
+    pub fn calc_hairpin(&self, ion_concentrations: &IonConcentrations) -> Fold {
+        fold(&self.sequence, ion_concentrations)
+    }
+
     /// Calculate all primer metrics.
     /// todo: methods on Metrics instead?
     pub fn calc_metrics(&self, ion_concentrations: &IonConcentrations) -> Option<PrimerMetrics> {
@@ -180,12 +309,28 @@ impl Primer {
             return None;
         }
 
+        let tms = self.expansion_tms(ion_concentrations);
+        let (melting_temp, tm_range) = if tms.is_empty() {
+            (0., (0., 0.))
+        } else {
+            (
+                tms.iter().sum::<f32>() / tms.len() as f32,
+                (
+                    tms.iter().copied().fold(f32::MAX, f32::min),
+                    tms.iter().copied().fold(f32::MIN, f32::max),
+                ),
+            )
+        };
+
         let mut result = PrimerMetrics {
-            melting_temp: self.calc_tm(ion_concentrations),
+            melting_temp,
+            tm_range,
+            degeneracy: seq_degeneracy(&self.sequence),
             gc_portion: calc_gc(&self.sequence),
             gc_3p_count: self.count_3p_g_c(),
             // complexity: self.calc_complexity(),
-            self_end_dimer: self.calc_self_end_dimer(),
+            self_dimer: self.calc_self_dimer(ion_concentrations),
+            hairpin: self.calc_hairpin(ion_concentrations),
             repeats: self.calc_repeats(),
             ..Default::default()
         };
@@ -203,7 +348,8 @@ fn single_nt_repeats(seq: &[Nucleotide]) -> u16 {
     let mut repeat_len = 1; // Counts the char.
 
     for nt in seq {
-        if *nt == prev_nt {
+        // Ambiguity codes, eg in NNK codons, don't represent a repeated nucleotide.
+        if *nt == prev_nt && !nt.is_ambiguous() {
             repeat_len += 1;
 
             if repeat_len >= 4 {
@@ -229,7 +375,7 @@ fn double_nt_repeats(seq: &[Nucleotide]) -> u16 {
     for i in 0..seq.len() / 2 - 1 {
         // todo: Incomplete: Need to do the same offset by one.
         let nts = (seq[i * 2], seq[(i * 2) + 1]);
-        if nts == prev_nt {
+        if nts == prev_nt && !nts.0.is_ambiguous() && !nts.1.is_ambiguous() {
             repeat_len += 1;
 
             if repeat_len >= 4 {
@@ -264,6 +410,9 @@ fn triplet_repeats(seq: &[Nucleotide]) -> u16 {
         }
 
         let triplet_this = (nt, seq[i + 1], seq[i + 2]);
+        if nt.is_ambiguous() || seq[i + 1].is_ambiguous() || seq[i + 2].is_ambiguous() {
+            continue;
+        }
 
         for triplet_other in &triplets {
             if triplet_this == (triplet_other.1, triplet_other.2, triplet_other.3)
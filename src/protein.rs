@@ -0,0 +1,526 @@
+//! This module contains code related to proteins: Amino acids, and translating DNA sequences into
+//! them using NCBI genetic codes.
+//!
+//! [NCBI: The Genetic Codes](https://www.ncbi.nlm.nih.gov/Taxonomy/Utils/wprintgc.cgi)
+
+use std::fmt::Display;
+
+use bincode::{Decode, Encode};
+
+use crate::sequence::{
+    seq_complement, Feature, FeatureDirection, Nucleotide,
+    Nucleotide::{A, C, G, T},
+    ReadingFrameMatch, SeqRange,
+};
+
+/// An amino acid, as translated from a codon. This also includes stop codons, and codons that
+/// can't be resolved to a single amino acid, eg due to ambiguity codes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AminoAcid {
+    Ala,
+    Arg,
+    Asn,
+    Asp,
+    Cys,
+    Gln,
+    Glu,
+    Gly,
+    His,
+    Ile,
+    Leu,
+    Lys,
+    Met,
+    Phe,
+    Pro,
+    Ser,
+    Thr,
+    Trp,
+    Tyr,
+    Val,
+    Stop,
+    /// Eg a codon containing an N, that may encode more than one amino acid.
+    Unknown,
+}
+
+impl AminoAcid {
+    /// Parse from the one-letter code used by NCBI translation tables.
+    pub fn from_letter(val: u8) -> Option<Self> {
+        Some(match val.to_ascii_uppercase() {
+            b'A' => Self::Ala,
+            b'R' => Self::Arg,
+            b'N' => Self::Asn,
+            b'D' => Self::Asp,
+            b'C' => Self::Cys,
+            b'Q' => Self::Gln,
+            b'E' => Self::Glu,
+            b'G' => Self::Gly,
+            b'H' => Self::His,
+            b'I' => Self::Ile,
+            b'L' => Self::Leu,
+            b'K' => Self::Lys,
+            b'M' => Self::Met,
+            b'F' => Self::Phe,
+            b'P' => Self::Pro,
+            b'S' => Self::Ser,
+            b'T' => Self::Thr,
+            b'W' => Self::Trp,
+            b'Y' => Self::Tyr,
+            b'V' => Self::Val,
+            b'*' => Self::Stop,
+            b'X' => Self::Unknown,
+            _ => return None,
+        })
+    }
+
+    pub fn to_letter(self) -> char {
+        match self {
+            Self::Ala => 'A',
+            Self::Arg => 'R',
+            Self::Asn => 'N',
+            Self::Asp => 'D',
+            Self::Cys => 'C',
+            Self::Gln => 'Q',
+            Self::Glu => 'E',
+            Self::Gly => 'G',
+            Self::His => 'H',
+            Self::Ile => 'I',
+            Self::Leu => 'L',
+            Self::Lys => 'K',
+            Self::Met => 'M',
+            Self::Phe => 'F',
+            Self::Pro => 'P',
+            Self::Ser => 'S',
+            Self::Thr => 'T',
+            Self::Trp => 'W',
+            Self::Tyr => 'Y',
+            Self::Val => 'V',
+            Self::Stop => '*',
+            Self::Unknown => 'X',
+        }
+    }
+
+    pub fn to_str_3_letter(self) -> &'static str {
+        match self {
+            Self::Ala => "Ala",
+            Self::Arg => "Arg",
+            Self::Asn => "Asn",
+            Self::Asp => "Asp",
+            Self::Cys => "Cys",
+            Self::Gln => "Gln",
+            Self::Glu => "Glu",
+            Self::Gly => "Gly",
+            Self::His => "His",
+            Self::Ile => "Ile",
+            Self::Leu => "Leu",
+            Self::Lys => "Lys",
+            Self::Met => "Met",
+            Self::Phe => "Phe",
+            Self::Pro => "Pro",
+            Self::Ser => "Ser",
+            Self::Thr => "Thr",
+            Self::Trp => "Trp",
+            Self::Tyr => "Tyr",
+            Self::Val => "Val",
+            Self::Stop => "Ter",
+            Self::Unknown => "Xaa",
+        }
+    }
+}
+
+/// How to display amino acids; eg in the sequence view.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum AaIdent {
+    #[default]
+    OneLetter,
+    ThreeLetters,
+}
+
+impl Display for AaIdent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::OneLetter => "1 letter",
+            Self::ThreeLetters => "3 letters",
+        }
+        .to_owned();
+        write!(f, "{}", str)
+    }
+}
+
+/// How to handle stop codons when translating.
+#[derive(Clone, Copy, PartialEq)]
+pub enum StopHandling {
+    /// Translate the whole sequence; stop codons are included as `AminoAcid::Stop`.
+    Include,
+    /// End the translation at the first stop codon, and don't include it.
+    Truncate,
+}
+
+/// NCBI translation tables. The explicit discriminants are the NCBI table IDs (`transl_table` in GenBank).
+#[derive(Clone, Copy, PartialEq, Debug, Default, Encode, Decode)]
+pub enum GeneticCode {
+    #[default]
+    Standard = 1,
+    VertebrateMitochondrial = 2,
+    YeastMitochondrial = 3,
+    /// Also used by Mycoplasma and Spiroplasma.
+    MoldMitochondrial = 4,
+    InvertebrateMitochondrial = 5,
+    /// Ciliate, Dasycladacean and Hexamita nuclear.
+    Ciliate = 6,
+    EchinodermMitochondrial = 9,
+    /// Bacterial, archaeal, and plant plastid.
+    Bacterial = 11,
+}
+
+impl Display for GeneticCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Standard => "Standard",
+            Self::VertebrateMitochondrial => "Vertebrate mito",
+            Self::YeastMitochondrial => "Yeast mito",
+            Self::MoldMitochondrial => "Mold/protozoan mito",
+            Self::InvertebrateMitochondrial => "Invertebrate mito",
+            Self::Ciliate => "Ciliate",
+            Self::EchinodermMitochondrial => "Echinoderm mito",
+            Self::Bacterial => "Bacterial/plastid",
+        }
+        .to_owned();
+        write!(f, "{} ({})", str, *self as u8)
+    }
+}
+
+impl GeneticCode {
+    pub const ALL: [Self; 8] = [
+        Self::Standard,
+        Self::VertebrateMitochondrial,
+        Self::YeastMitochondrial,
+        Self::MoldMitochondrial,
+        Self::InvertebrateMitochondrial,
+        Self::Ciliate,
+        Self::EchinodermMitochondrial,
+        Self::Bacterial,
+    ];
+
+    /// From the NCBI table ID, eg as found in GenBank `transl_table` qualifiers.
+    pub fn from_ncbi_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|code| *code as u8 == id)
+    }
+
+    /// Amino acids, and start codons, in NCBI's compact table format. Each char corresponds to a codon,
+    /// in the order TTT, TTC, TTA, TTG, TCT ... GGG. (Base order TCAG). In the start string, `M` marks
+    /// a codon that may be used as a start codon.
+    fn tables(self) -> (&'static [u8; 64], &'static [u8; 64]) {
+        match self {
+            Self::Standard => (
+                b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+                b"---M------**--*----M---------------M----------------------------",
+            ),
+            Self::VertebrateMitochondrial => (
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+                b"----------**--------------------MMMM----------**---M------------",
+            ),
+            Self::YeastMitochondrial => (
+                b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+                b"----------**----------------------MM---------------M------------",
+            ),
+            Self::MoldMitochondrial => (
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+                b"--MM------**-------M------------MMMM---------------M------------",
+            ),
+            Self::InvertebrateMitochondrial => (
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+                b"---M------**--------------------MMMM---------------M------------",
+            ),
+            Self::Ciliate => (
+                b"FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+                b"--------------*--------------------M----------------------------",
+            ),
+            Self::EchinodermMitochondrial => (
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+                b"----------**-----------------------M---------------M------------",
+            ),
+            Self::Bacterial => (
+                b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+                b"---M------**--*----M------------MMMM---------------M------------",
+            ),
+        }
+    }
+
+    /// Translate a single codon. Codons containing ambiguity codes translate to the amino acid all of
+    /// their possible codons encode if there is one (eg CTN is always Leu), and `Unknown` otherwise.
+    pub fn translate_codon(self, codon: &[Nucleotide]) -> AminoAcid {
+        let (aas, _) = self.tables();
+
+        let mut result = None;
+        for i in codon_indices(codon) {
+            let aa = AminoAcid::from_letter(aas[i]).unwrap_or(AminoAcid::Unknown);
+            match result {
+                None => result = Some(aa),
+                Some(r) if r != aa => return AminoAcid::Unknown,
+                _ => (),
+            }
+        }
+
+        result.unwrap_or(AminoAcid::Unknown)
+    }
+
+    /// Returns true if this codon may be used as a start codon in this genetic code. Eg ATG, and in
+    /// some codes, GTG and TTG.
+    pub fn is_start(self, codon: &[Nucleotide]) -> bool {
+        let (_, starts) = self.tables();
+        let indices = codon_indices(codon);
+
+        !indices.is_empty() && indices.iter().all(|i| starts[*i] == b'M')
+    }
+
+    /// Returns true if this codon is a stop codon in this genetic code.
+    pub fn is_stop(self, codon: &[Nucleotide]) -> bool {
+        self.translate_codon(codon) == AminoAcid::Stop
+    }
+
+    /// The codons that encode an amino acid in this genetic code, in NCBI's table order.
+    pub fn codons(self, aa: AminoAcid) -> Vec<[Nucleotide; 3]> {
+        let (aas, _) = self.tables();
+        let nts = [T, C, A, G];
+
+        (0..64)
+            .filter(|i| AminoAcid::from_letter(aas[*i]) == Some(aa))
+            .map(|i| [nts[i / 16], nts[i / 4 % 4], nts[i % 4]])
+            .collect()
+    }
+}
+
+/// Indices into NCBI's table format, of every unambiguous codon this codon may represent.
+fn codon_indices(codon: &[Nucleotide]) -> Vec<usize> {
+    fn nt_index(nt: Nucleotide) -> usize {
+        match nt {
+            T => 0,
+            C => 1,
+            A => 2,
+            G => 3,
+            _ => unreachable!(),
+        }
+    }
+
+    let mut result = Vec::new();
+    if codon.len() != 3 {
+        return result;
+    }
+
+    for nt0 in codon[0].possible() {
+        for nt1 in codon[1].possible() {
+            for nt2 in codon[2].possible() {
+                result.push(16 * nt_index(*nt0) + 4 * nt_index(*nt1) + nt_index(*nt2));
+            }
+        }
+    }
+
+    result
+}
+
+/// Translate a nucleotide sequence, from its first nucleotide. Incomplete codons at the end are ignored.
+/// If `from_start` is set, the first codon is translated as Met if it's an (alternative) start codon
+/// in this genetic code, as happens in vivo for eg GTG starts in bacteria.
+pub fn translate(
+    seq: &[Nucleotide],
+    code: GeneticCode,
+    from_start: bool,
+    stop_handling: StopHandling,
+) -> Vec<AminoAcid> {
+    let mut result = Vec::new();
+
+    for (i, codon) in seq.chunks_exact(3).enumerate() {
+        let aa = if i == 0 && from_start && code.is_start(codon) {
+            AminoAcid::Met
+        } else {
+            code.translate_codon(codon)
+        };
+
+        if aa == AminoAcid::Stop && stop_handling == StopHandling::Truncate {
+            break;
+        }
+        result.push(aa);
+    }
+
+    result
+}
+
+/// Translate a range of a sequence; eg a selection, a feature, or an open reading frame.
+/// If the direction is reverse, we translate the complement strand, starting from the range's end.
+/// Set `from_start` only if the range starts a coding region, eg an ORF; see `translate`.
+pub fn translate_range(
+    seq: &[Nucleotide],
+    range: SeqRange,
+    direction: FeatureDirection,
+    code: GeneticCode,
+    from_start: bool,
+    stop_handling: StopHandling,
+) -> Option<Vec<AminoAcid>> {
+    let seq_range = range.seq(seq)?;
+
+    let result = match direction {
+        FeatureDirection::Reverse => {
+            translate(&seq_complement(&seq_range), code, from_start, stop_handling)
+        }
+        _ => translate(&seq_range, code, from_start, stop_handling),
+    };
+
+    Some(result)
+}
+
+/// The genetic code a feature uses: From its `transl_table` note, as GenBank CDS features often have, or
+/// `default` if it doesn't have one.
+pub fn feature_genetic_code(feature: &Feature, default: GeneticCode) -> GeneticCode {
+    feature
+        .notes
+        .get("transl_table")
+        .and_then(|v| v.trim().parse().ok())
+        .and_then(GeneticCode::from_ncbi_id)
+        .unwrap_or(default)
+}
+
+/// Translate a coding region feature. For joined features, eg with multiple exons, we splice the segments
+/// before translating. If the feature has a `transl_table` note, as GenBank CDS features often do, we use
+/// that genetic code instead of the one passed.
+pub fn translate_feature(
+    feature: &Feature,
+    seq: &[Nucleotide],
+    code: GeneticCode,
+    stop_handling: StopHandling,
+) -> Option<Vec<AminoAcid>> {
+    let code = feature_genetic_code(feature, code);
+
+    let seq_feature = feature.spliced_seq(seq)?;
+
+    Some(translate(&seq_feature, code, true, stop_handling))
+}
+
+/// Translate an open reading frame, as found by our ORF search.
+pub fn translate_orf(
+    orf: &ReadingFrameMatch,
+    seq: &[Nucleotide],
+    code: GeneticCode,
+    stop_handling: StopHandling,
+) -> Option<Vec<AminoAcid>> {
+    let direction = if orf.frame.is_reverse() {
+        FeatureDirection::Reverse
+    } else {
+        FeatureDirection::Forward
+    };
+
+    translate_range(seq, orf.range, direction, code, true, stop_handling)
+}
+
+/// Convert a protein sequence to a string, eg for display, or for export.
+pub fn protein_to_str(protein: &[AminoAcid], ident: AaIdent) -> String {
+    let mut result = String::new();
+
+    for aa in protein {
+        match ident {
+            AaIdent::OneLetter => result.push(aa.to_letter()),
+            AaIdent::ThreeLetters => result.push_str(aa.to_str_3_letter()),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::{seq_from_str, ReadingFrame};
+
+    fn translated(seq: &str, code: GeneticCode, from_start: bool) -> String {
+        let protein = translate(&seq_from_str(seq), code, from_start, StopHandling::Include);
+        protein_to_str(&protein, AaIdent::OneLetter)
+    }
+
+    #[test]
+    fn genetic_codes() {
+        // ATA, AGA, TGA, CTT, AAA, TAA and TAG are the codons these codes differ on.
+        let seq = "ATAAGATGACTTAAATAATAG";
+
+        for (code, expected) in [
+            (GeneticCode::Standard, "IR*LK**"),
+            (GeneticCode::VertebrateMitochondrial, "M*WLK**"),
+            (GeneticCode::YeastMitochondrial, "MRWTK**"),
+            (GeneticCode::MoldMitochondrial, "IRWLK**"),
+            (GeneticCode::InvertebrateMitochondrial, "MSWLK**"),
+            (GeneticCode::Ciliate, "IR*LKQQ"),
+            (GeneticCode::EchinodermMitochondrial, "ISWLN**"),
+            (GeneticCode::Bacterial, "IR*LK**"),
+        ] {
+            assert_eq!(translated(seq, code, false), expected, "{code}");
+        }
+    }
+
+    #[test]
+    fn alternative_starts() {
+        for (code, gtg, att) in [
+            (GeneticCode::Standard, "VK*", "IK*"),
+            (GeneticCode::VertebrateMitochondrial, "MK*", "MK*"),
+            (GeneticCode::YeastMitochondrial, "MK*", "IK*"),
+            (GeneticCode::MoldMitochondrial, "MK*", "MK*"),
+            (GeneticCode::InvertebrateMitochondrial, "MK*", "MK*"),
+            (GeneticCode::Ciliate, "VKQ", "IKQ"),
+            (GeneticCode::EchinodermMitochondrial, "MN*", "IN*"),
+            (GeneticCode::Bacterial, "MK*", "MK*"),
+        ] {
+            assert_eq!(translated("GTGAAATAA", code, true), gtg, "{code}");
+            assert_eq!(translated("ATTAAATAA", code, true), att, "{code}");
+        }
+
+        // Only the first codon is a start.
+        assert_eq!(translated("GTGGTGTAA", GeneticCode::Bacterial, true), "MV*");
+    }
+
+    #[test]
+    fn stop_handling() {
+        let seq = seq_from_str("ATGAAATAAGGG");
+        let protein = translate(&seq, GeneticCode::Standard, true, StopHandling::Truncate);
+        assert_eq!(protein_to_str(&protein, AaIdent::OneLetter), "MK");
+        assert_eq!(protein_to_str(&protein, AaIdent::ThreeLetters), "MetLys");
+    }
+
+    #[test]
+    fn range_from_start() {
+        // GTGAAATAA on the forward strand at 3..11, and on the reverse strand at 14..22.
+        let seq = seq_from_str("CCGTGAAATAAGGTTATTTCACGG");
+        let code = GeneticCode::Bacterial;
+
+        for (range, direction, frame) in [
+            (
+                SeqRange::new(3, 11),
+                FeatureDirection::Forward,
+                ReadingFrame::Fwd2,
+            ),
+            (
+                SeqRange::new(14, 22),
+                FeatureDirection::Reverse,
+                ReadingFrame::Rev2,
+            ),
+        ] {
+            // A selection that happens to begin with GTG translates it as Val.
+            let selection =
+                translate_range(&seq, range, direction, code, false, StopHandling::Include)
+                    .unwrap();
+            assert_eq!(protein_to_str(&selection, AaIdent::OneLetter), "VK*");
+
+            let orf = ReadingFrameMatch { frame, range };
+            let protein = translate_orf(&orf, &seq, code, StopHandling::Include).unwrap();
+            assert_eq!(protein_to_str(&protein, AaIdent::OneLetter), "MK*");
+        }
+    }
+
+    #[test]
+    fn ambiguous_codons() {
+        let code = GeneticCode::Standard;
+        assert_eq!(code.translate_codon(&seq_from_str("CTN")), AminoAcid::Leu);
+        assert_eq!(
+            code.translate_codon(&seq_from_str("ATN")),
+            AminoAcid::Unknown
+        );
+        assert!(code.is_stop(&seq_from_str("TRA")));
+        assert!(!code.is_start(&seq_from_str("NTG")));
+    }
+}
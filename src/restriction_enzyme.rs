@@ -3,96 +3,454 @@
 //! [Wikipedia: List of RE sites](https://en.wikipedia.org/wiki/List_of_restriction_enzyme_cutting_sites:_A)
 //! [NEB guide](https://www.neb.com/en-us/tools-and-resources/selection-charts/frequencies-of-restriction-sites)
 //!
-//! Note: This module only currently includes a selection of popular REs, and only ones that match
-//! exact NTs.
+//! The library is loaded from REBASE (http://rebase.neb.com) data files; see `file_io::rebase`. A
+//! selection of common commercial enzymes is included, and users may load others, eg a current withrefm
+//! file. Buffer activity and methylation sensitivity aren't part of REBASE; these are from a
+//! separate table.
+//!
+//! Cut positions follow REBASE: Each is the number of nucleotides from the 5' end of the recognition
+//! site, on the top strand, to the cut. The bottom strand's cut uses the same coordinates. Cuts may be
+//! before the site (negative), or past its end, for Type IIS and IIB enzymes.
+
+use std::{fmt::Display, path::Path};
 
 use crate::{
-    primer::PrimerDirection,
-    sequence::{
-        Nucleotide::{A, C, G, T},
-        Seq,
+    file_io::{
+        error::{FileError, Imported},
+        rebase::{apply_enzyme_properties, import_rebase, parse_withrefm},
     },
+    sequence::{seq_complement, seq_from_str, seq_to_str, Nucleotide, Seq, SeqRange, SeqTopology},
 };
 
-/// Unlike `Nucleotide`, this includes wildcards
-#[derive(Clone, Copy)]
-pub enum NucleotideGeneral {
-    A,
-    T,
-    C,
-    G,
-    /// Any
-    N,
-    /// A or T
-    W,
-    /// C or G
-    S,
-    /// Pyrimidines: C or T
-    Y,
-    /// Purines: A or G
-    R,
-    /// A or C
-    M,
-    /// G or T
-    K,
+/// Common commercial enzymes, in REBASE withrefm format.
+const LIBRARY_BUILTIN: &str = include_str!("resources/rebase_withrefm.txt");
+/// Buffer activity and methylation sensitivity of the built-in enzymes; see `apply_enzyme_properties`.
+const PROPERTIES_BUILTIN: &str = include_str!("resources/enzyme_properties.tsv");
+
+/// REBASE codes for commercial suppliers, and their names.
+pub const SUPPLIERS: [(char, &str); 15] = [
+    ('B', "Thermo Fisher Scientific"),
+    ('C', "Minotech Biotechnology"),
+    ('E', "Agilent Technologies"),
+    ('I', "SibEnzyme"),
+    ('J', "Nippon Gene"),
+    ('K', "Takara Bio"),
+    ('M', "Roche Applied Science"),
+    ('N', "New England Biolabs"),
+    ('O', "Toyobo Biochemicals"),
+    ('Q', "Molecular Biology Resources - CHIMERx"),
+    ('R', "Promega"),
+    ('S', "Sigma-Aldrich"),
+    ('V', "Vivantis Technologies"),
+    ('X', "EURx"),
+    ('Y', "SinaClon BioScience"),
+];
+
+/// The name of a supplier, from its REBASE code.
+pub fn supplier_name(code: char) -> Option<&'static str> {
+    SUPPLIERS
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, name)| *name)
+}
+
+/// How methylation of a site affects cutting.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Sensitivity {
+    /// We don't have data for this enzyme.
+    #[default]
+    Unknown,
+    NotSensitive,
+    /// Cutting is slower, or blocked at some overlapping sites.
+    Impaired,
+    /// Cutting is blocked, at least at some overlapping sites.
+    Blocked,
+}
+
+impl Display for Sensitivity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Unknown => "?",
+            Self::NotSensitive => "Not sensitive",
+            Self::Impaired => "Impaired",
+            Self::Blocked => "Blocked",
+        }
+        .to_owned();
+        write!(f, "{}", str)
+    }
+}
+
+/// Sensitivity to methylation by the common E. coli methyltransferases, and of CpG, eg in DNA from
+/// mammalian cells.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct MethylationSensitivity {
+    /// Dam: N6-methyladenine, at GATC.
+    pub dam: Sensitivity,
+    /// Dcm: 5-methylcytosine, at CCWGG.
+    pub dcm: Sensitivity,
+    pub cpg: Sensitivity,
+}
+
+/// The ends a cut leaves.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EndType {
+    Blunt,
+    /// A single-stranded 5' overhang, of this length.
+    FivePrime(usize),
+    /// A single-stranded 3' overhang, of this length.
+    ThreePrime(usize),
+}
+
+impl Display for EndType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Blunt => write!(f, "Blunt"),
+            Self::FivePrime(len) => write!(f, "5' overhang ({len} nt)"),
+            Self::ThreePrime(len) => write!(f, "3' overhang ({len} nt)"),
+        }
+    }
+}
+
+/// A cut across both strands of a sequence.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CutSite {
+    /// The number of top strand nucleotides before the cut, ie the 0-based index of the nucleotide
+    /// after it. For circular sequences, this is less than the sequence length.
+    pub top: usize,
+    /// The bottom strand's cut, in the same coordinates.
+    pub bottom: usize,
 }
 
 pub struct ReMatch {
     pub lib_index: usize,
-    pub seq_index: usize,
-    // /// Direction helps align the cut site.
-    // pub direction: PrimerDirection,
+    /// The recognition site. This may wrap the origin of circular sequences.
+    pub range: SeqRange,
+    /// If true, the site is on the bottom strand: Its reverse complement is on the top. Only for
+    /// non-palindromic sites.
+    pub reverse: bool,
+    /// Where the enzyme cuts. Usually one; two for Type IIB enzymes, which excise their site. Cuts
+    /// past the end of linear sequences are omitted.
+    pub cuts: Vec<CutSite>,
 }
 
+#[derive(Clone, Debug, PartialEq, Default)]
 pub struct RestrictionEnzyme {
     pub name: String,
-    /// From the 5' end.
-    pub seq: Seq, // todo: You may eventually need Vec<NucleotideGeneral>.
-    /// Index to cut after, from the 5' end. For blunt ends, this will be
-    /// halfway through the seq (rounded down)
-    pub cut_after: u8,
+    /// The recognition site, 5' to 3' on the top strand. This may contain IUPAC ambiguity codes, eg N
+    /// in BglI, which match any nucleotide they represent.
+    pub site: Seq,
+    /// Where the enzyme cuts the top strand, from the 5' end of the site.
+    pub cut_top: isize,
+    /// Where it cuts the bottom strand, in the same coordinates. If this is greater than `cut_top`, the
+    /// cut leaves a 5' overhang; if less, a 3' one.
+    pub cut_bottom: isize,
+    /// For Type IIB enzymes, the cut on the site's other side; top strand, then bottom.
+    pub cut_2: Option<(isize, isize)>,
+    /// REBASE codes of commercial suppliers; see `SUPPLIERS`. Empty if it's not commercially available,
+    /// or the data didn't include suppliers.
+    pub suppliers: Vec<char>,
+    pub methylation: MethylationSensitivity,
+    /// Activity in each reaction buffer, in percent; eg `("rCutSmart", 100)`. 0 is under 10%.
+    pub buffer_activity: Vec<(String, u8)>,
 }
 
 impl RestrictionEnzyme {
-    pub fn new(name: &str, seq: Seq, cut_after: u8) -> Self {
+    pub fn new(name: &str, site: &str, cut_top: isize, cut_bottom: isize) -> Self {
         Self {
             name: name.to_owned(),
-            seq,
-            cut_after,
+            site: seq_from_str(site),
+            cut_top,
+            cut_bottom,
+            ..Default::default()
         }
     }
+
+    /// If the site reads the same on both strands, eg GAATTC. These only need to be searched for on one.
+    pub fn is_palindromic(&self) -> bool {
+        seq_complement(&self.site) == self.site
+    }
+
+    pub fn end_type(&self) -> EndType {
+        let overhang = self.cut_bottom - self.cut_top;
+        match overhang {
+            0 => EndType::Blunt,
+            1.. => EndType::FivePrime(overhang as usize),
+            _ => EndType::ThreePrime(overhang.unsigned_abs()),
+        }
+    }
+
+    /// Each cut, top strand then bottom, from the 5' end of the site.
+    fn cut_offsets(&self) -> Vec<(isize, isize)> {
+        let mut result = vec![(self.cut_top, self.cut_bottom)];
+        result.extend(self.cut_2);
+        result
+    }
+
+    /// The site in REBASE notation, eg `G^AATTC`, or `GGTCTC(1/5)`. Uppercase.
+    pub fn site_notation(&self) -> String {
+        let site = seq_to_str(&self.site).to_uppercase();
+        let len = self.site.len() as isize;
+
+        let cut = (self.cut_top, self.cut_bottom);
+        let mut result =
+            if self.cut_2.is_none() && (0..=len).contains(&cut.0) && (0..=len).contains(&cut.1) {
+                // Cuts within the site; the bottom strand's follows from symmetry for palindromes.
+                let i = cut.0 as usize;
+                format!("{}^{}", &site[..i], &site[i..])
+            } else if cut.0 > len || (self.cut_2.is_none() && cut.0 >= 0) {
+                format!("{site}({}/{})", cut.0 - len, cut.1 - len)
+            } else {
+                format!("({}/{}){site}", -cut.0, -cut.1)
+            };
+
+        if let Some((top, bottom)) = self.cut_2 {
+            result = format!("{result}({}/{})", top - len, bottom - len);
+        }
+
+        result
+    }
 }
 
 /// Load a set of common Restriction enzymes. Call this at program start, to load into a state field.
 pub fn load_re_library() -> Vec<RestrictionEnzyme> {
+    let Imported { data: mut lib, .. } = parse_withrefm(LIBRARY_BUILTIN);
+    apply_enzyme_properties(PROPERTIES_BUILTIN, &mut lib);
+    lib
+}
+
+/// Load a library from a REBASE file, eg a current withrefm. Enzymes in our built-in table get its buffer
+/// activity and methylation sensitivity.
+pub fn load_re_library_file(path: &Path) -> Result<Imported<Vec<RestrictionEnzyme>>, FileError> {
+    let mut result = import_rebase(path)?;
+    apply_enzyme_properties(PROPERTIES_BUILTIN, &mut result.data);
+    Ok(result)
+}
+
+/// Indices of other enzymes in the library that recognize the same site as the one at `i`, on either
+/// strand. The bool is true for neoschizomers, which cut it differently.
+pub fn isoschizomers(lib: &[RestrictionEnzyme], i: usize) -> Vec<(usize, bool)> {
+    let re = &lib[i];
+    let site_rc = seq_complement(&re.site);
+
+    lib.iter()
+        .enumerate()
+        .filter(|(j, other)| *j != i && (other.site == re.site || other.site == site_rc))
+        .map(|(j, other)| {
+            let neo = if other.site == re.site {
+                other.cut_offsets() != re.cut_offsets()
+            } else {
+                // Cuts on the other strand, mirrored.
+                let len = re.site.len() as isize;
+                let mirrored: Vec<_> = re
+                    .cut_offsets()
+                    .iter()
+                    .map(|(top, bottom)| (len - bottom, len - top))
+                    .collect();
+                other.cut_offsets() != mirrored
+            };
+            (j, neo)
+        })
+        .collect()
+}
+
+/// A named selection of enzymes from the library, eg for display, or for planning digests.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnzymeSet {
+    pub name: String,
+    /// Enzyme names. These may include enzymes not in the loaded library.
+    pub enzymes: Vec<String>,
+}
+
+impl EnzymeSet {
+    pub fn new(name: &str, enzymes: &[&str]) -> Self {
+        Self {
+            name: name.to_owned(),
+            enzymes: enzymes.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    /// Indices in the library of this set's enzymes. Names are case-insensitive.
+    pub fn indices(&self, lib: &[RestrictionEnzyme]) -> Vec<usize> {
+        lib.iter()
+            .enumerate()
+            .filter(|(_, re)| {
+                self.enzymes
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(&re.name))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// Sets available before the user defines their own.
+pub fn default_enzyme_sets() -> Vec<EnzymeSet> {
     vec![
-        RestrictionEnzyme::new("AatII", vec![G, A, C, G, T, C], 4),
-        RestrictionEnzyme::new("Acc65I", vec![G, G, T, A, C, C], 0),
-        RestrictionEnzyme::new("AscI", vec![G, G, C, G, C, G, C, C], 1),
-        RestrictionEnzyme::new("AsiSI", vec![G, C, G, A, T, C, G, C], 4),
-        RestrictionEnzyme::new("BamHI", vec![G, G, A, T, C, C], 0),
-        RestrictionEnzyme::new("BcII", vec![T, G, A, T, C, A], 0),
-        RestrictionEnzyme::new("BglII", vec![A, G, A, T, C, T], 0),
-        RestrictionEnzyme::new("BmtI", vec![G, C, T, A, G, C], 4),
-        RestrictionEnzyme::new("ClaI", vec![A, T, C, G, A, T], 1),
-        RestrictionEnzyme::new("EcoRI", vec![G, A, A, T, T, C], 0),
-        RestrictionEnzyme::new("EcoRV", vec![G, A, T, A, T, C], 2),
-        RestrictionEnzyme::new("HindIII", vec![A, A, G, C, T, T], 0),
-        RestrictionEnzyme::new("HpaI", vec![G, T, T, A, A, C], 2),
-        RestrictionEnzyme::new("KnpI", vec![G, G, T, A, C, C], 4),
-        RestrictionEnzyme::new("MscI", vec![T, G, G, C, C, A], 2),
-        RestrictionEnzyme::new("NdeI", vec![C, A, T, A, T, G], 1),
-        RestrictionEnzyme::new("NotI", vec![G, C, G, G, C, C, G, C], 1),
-        RestrictionEnzyme::new("PsiI", vec![T, T, A, T, A, A], 2),
-        RestrictionEnzyme::new("PstI", vec![C, T, G, C, A, G], 4),
-        RestrictionEnzyme::new("SacI", vec![G, A, G, C, T, C], 4),
-        RestrictionEnzyme::new("SalI", vec![G, T, C, G, A, C], 0),
-        RestrictionEnzyme::new("SmaI", vec![C, C, C, G, G, G], 2),
-        RestrictionEnzyme::new("SfbI", vec![C, C, T, G, C, A, G, G], 5),
-        RestrictionEnzyme::new("SpeI", vec![A, C, T, A, G, T], 0),
-        RestrictionEnzyme::new("XbaI", vec![T, C, T, A, G, A], 0),
-        RestrictionEnzyme::new("XhoI", vec![C, T, C, G, A, G], 0),
-        RestrictionEnzyme::new("ZraI", vec![G, A, C, G, T, C], 2),
-        // RestrictionEnzyme::new("HaeIII", vec![G, G, C, C], 1), // Too many matches
+        EnzymeSet::new(
+            "Common",
+            &[
+                "AatII", "Acc65I", "AscI", "AsiSI", "BamHI", "BclI", "BglII", "BmtI", "ClaI",
+                "EcoRI", "EcoRV", "HindIII", "HpaI", "KpnI", "MscI", "NdeI", "NotI", "PsiI",
+                "PstI", "SacI", "SalI", "SmaI", "SbfI", "SpeI", "XbaI", "XhoI", "ZraI",
+            ],
+        ),
+        EnzymeSet::new("Golden Gate", &["BsaI", "BsmBI", "BbsI", "SapI"]),
     ]
 }
+
+/// Identify restriction enzyme sites in a sequence, on both strands, sorted by their start index.
+/// Ambiguity codes in sites match any nucleotide they represent; ones in the sequence only match
+/// codes that include them.
+pub fn find_re_matches(
+    seq: &[Nucleotide],
+    topology: SeqTopology,
+    lib: &[RestrictionEnzyme],
+) -> Vec<ReMatch> {
+    let mut result = Vec::new();
+    let seq_len = seq.len();
+
+    for (lib_index, re) in lib.iter().enumerate() {
+        let site_len = re.site.len();
+        if site_len == 0 || seq_len < site_len {
+            continue;
+        }
+
+        let mut strands = vec![(re.site.clone(), false)];
+        if !re.is_palindromic() {
+            strands.push((seq_complement(&re.site), true));
+        }
+
+        // Sites on circular sequences may wrap the origin.
+        let starts = match topology {
+            SeqTopology::Circular => 0..seq_len,
+            SeqTopology::Linear => 0..seq_len - site_len + 1,
+        };
+
+        for i in starts {
+            for (site, reverse) in &strands {
+                let seq_iter = seq.iter().cycle().skip(i).take(site_len);
+                if !site.iter().zip(seq_iter).all(|(s, nt)| s.includes(*nt)) {
+                    continue;
+                }
+
+                // Cut positions on the top strand, relative to the site's start on it. On the bottom
+                // strand, the site's 5' end is at its top strand end, and the strands swap.
+                let cuts = re
+                    .cut_offsets()
+                    .into_iter()
+                    .map(|(top, bottom)| {
+                        if *reverse {
+                            (site_len as isize - bottom, site_len as isize - top)
+                        } else {
+                            (top, bottom)
+                        }
+                    })
+                    .filter_map(|(top, bottom)| {
+                        let top = i as isize + top;
+                        let bottom = i as isize + bottom;
+                        match topology {
+                            SeqTopology::Circular => Some(CutSite {
+                                top: top.rem_euclid(seq_len as isize) as usize,
+                                bottom: bottom.rem_euclid(seq_len as isize) as usize,
+                            }),
+                            // Cuts must be within the sequence, on at least one strand.
+                            SeqTopology::Linear => {
+                                let len = seq_len as isize;
+                                let inside = |v: isize| v > 0 && v < len;
+                                let bounded = |v: isize| (0..=len).contains(&v);
+                                (bounded(top) && bounded(bottom) && (inside(top) || inside(bottom)))
+                                    .then_some(CutSite {
+                                        top: top as usize,
+                                        bottom: bottom as usize,
+                                    })
+                            }
+                        }
+                    })
+                    .collect();
+
+                result.push(ReMatch {
+                    lib_index,
+                    range: SeqRange::from_start_len(i, site_len, seq_len),
+                    reverse: *reverse,
+                    cuts,
+                });
+            }
+        }
+    }
+
+    // This sorting aids in our up/down label alternation in the display.
+    result.sort_by_key(|a| a.range.start);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::enzymes;
+
+    const ENZYMES: [&str; 2] = ["EcoRI", "BsaI"];
+
+    fn cuts(m: &ReMatch) -> Vec<(usize, usize)> {
+        m.cuts.iter().map(|c| (c.top, c.bottom)).collect()
+    }
+
+    #[test]
+    fn both_strands() {
+        // EcoRI at 4, BsaI forward at 12, and BsaI reverse (GAGACC) at 30.
+        let seq = seq_from_str("AAAAGAATTCAAGGTCTCAAAAAAAAAAAAGAGACCAAAA");
+        let result = find_re_matches(&seq, SeqTopology::Linear, &enzymes(&ENZYMES));
+        assert_eq!(result.len(), 3);
+
+        // Palindromic sites match once.
+        let eco = &result[0];
+        assert_eq!((eco.lib_index, eco.reverse), (0, false));
+        assert_eq!(eco.range, SeqRange::new(5, 10));
+        assert_eq!(cuts(eco), [(5, 9)]);
+
+        let bsa = &result[1];
+        assert_eq!((bsa.lib_index, bsa.reverse), (1, false));
+        assert_eq!(bsa.range, SeqRange::new(13, 18));
+        assert_eq!(cuts(bsa), [(19, 23)]);
+
+        // On the bottom strand, BsaI cuts before the site on the top strand, and the top strand's cut
+        // is the further one.
+        let bsa_rev = &result[2];
+        assert_eq!((bsa_rev.lib_index, bsa_rev.reverse), (1, true));
+        assert_eq!(bsa_rev.range, SeqRange::new(31, 36));
+        assert_eq!(cuts(bsa_rev), [(25, 29)]);
+    }
+
+    #[test]
+    fn wraps_origin() {
+        // EcoRI across the origin.
+        let seq = seq_from_str("ATTCAAAAAAAAAAAAAAGA");
+        let result = find_re_matches(&seq, SeqTopology::Circular, &enzymes(&ENZYMES));
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].range, SeqRange::new(19, 4));
+        assert_eq!(cuts(&result[0]), [(19, 3)]);
+
+        assert!(find_re_matches(&seq, SeqTopology::Linear, &enzymes(&ENZYMES)).is_empty());
+
+        // A reverse BsaI site whose top strand cut is past the origin.
+        let seq = seq_from_str("AAGAGACCAAAAAAAAAAAA");
+        let result = find_re_matches(&seq, SeqTopology::Circular, &enzymes(&ENZYMES));
+        assert_eq!(cuts(&result[0]), [(17, 1)]);
+
+        // Linear sequences don't have that cut.
+        let result = find_re_matches(&seq, SeqTopology::Linear, &enzymes(&ENZYMES));
+        assert_eq!(result.len(), 1);
+        assert!(result[0].cuts.is_empty());
+    }
+
+    #[test]
+    fn ambiguity() {
+        let bgl = enzymes(&["BglI"]);
+        let result = find_re_matches(&seq_from_str("AAGCCATGCAGGCAA"), SeqTopology::Linear, &bgl);
+        assert_eq!(result.len(), 1);
+        assert_eq!(cuts(&result[0]), [(9, 6)]);
+
+        // Ambiguity codes in the sequence don't match specific nucleotides in the site.
+        let seq = seq_from_str("AAAAGANTTCAAAA");
+        assert!(find_re_matches(&seq, SeqTopology::Linear, &enzymes(&ENZYMES)).is_empty());
+    }
+}
@@ -1,2 +1,231 @@
 //! This module contains archived state structs used to open saves from previous versions
 //! of this program, and convert them to the latest version.
+//!
+//! Version 0: Files saved before our packet-based container. These are a single bincode blob of the
+//! state, and features have a single range.
+//!
+//! Version 1: Ion concentrations don't include Tris, or melting temperature settings.
+
+use std::collections::HashMap;
+
+use bincode::{config, error::DecodeError, Decode};
+
+use crate::{
+    file_io::{
+        error::{FileError, Imported},
+        save::{deser_seq_bin, StateToSave},
+        GenericData,
+    },
+    primer::Primer,
+    sequence::{Feature, FeatureDirection, FeatureType, ReadingFrame, SeqRange, SeqTopology},
+    Color, IonConcentrations, Metadata,
+};
+
+/// Ion concentrations, as saved in versions 0 and 1.
+#[derive(Decode)]
+pub struct IonConcentrationsV1 {
+    pub monovalent: f32,
+    pub divalent: f32,
+    pub dntp: f32,
+    pub primer: f32,
+}
+
+impl From<IonConcentrationsV1> for IonConcentrations {
+    fn from(v: IonConcentrationsV1) -> Self {
+        Self {
+            monovalent: v.monovalent,
+            divalent: v.divalent,
+            dntp: v.dntp,
+            primer: v.primer,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Decode)]
+struct FeatureV0 {
+    /// 1-based indexing, inclusive.
+    pub index_range: (usize, usize),
+    pub feature_type: FeatureType,
+    pub direction: FeatureDirection,
+    pub label: String,
+    pub color_override: Option<Color>,
+    pub notes: HashMap<String, String>,
+}
+
+impl From<FeatureV0> for Feature {
+    fn from(v: FeatureV0) -> Self {
+        Self {
+            index_range: SeqRange::new(v.index_range.0, v.index_range.1),
+            segments: Vec::new(),
+            feature_type: v.feature_type,
+            direction: v.direction,
+            label: v.label,
+            color_override: v.color_override,
+            notes: v.notes,
+        }
+    }
+}
+
+struct GenericDataV0 {
+    pub seq: Vec<u8>,
+    pub topology: SeqTopology,
+    pub features: Vec<FeatureV0>,
+    pub primers: Vec<Primer>,
+    pub metadata: Metadata,
+}
+
+impl Decode for GenericDataV0 {
+    fn decode<D: bincode::de::Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            seq: Vec::decode(decoder)?,
+            topology: SeqTopology::decode(decoder)?,
+            features: Vec::decode(decoder)?,
+            primers: Vec::decode(decoder)?,
+            metadata: Metadata::decode(decoder)?,
+        })
+    }
+}
+
+bincode::impl_borrow_decode!(GenericDataV0);
+
+#[derive(Decode)]
+struct StateToSaveV0 {
+    pub generic: GenericDataV0,
+    pub insert_loc: usize,
+    pub ion_concentrations: IonConcentrationsV1,
+    pub reading_frame: ReadingFrame,
+}
+
+impl StateToSaveV0 {
+    fn migrate(self) -> Result<StateToSave, FileError> {
+        Ok(StateToSave {
+            generic: GenericData {
+                seq: deser_seq_bin(&self.generic.seq)?,
+                topology: self.generic.topology,
+                features: self.generic.features.into_iter().map(Into::into).collect(),
+                primers: self.generic.primers,
+                metadata: self.generic.metadata,
+            },
+            insert_loc: self.insert_loc,
+            ion_concentrations: self.ion_concentrations.into(),
+            reading_frame: self.reading_frame,
+            history: None,
+        })
+    }
+}
+
+/// Load a file saved in format version 0, and convert it to the current version.
+pub fn load_v0(buf: &[u8]) -> Result<Imported<StateToSave>, FileError> {
+    let (state, len): (StateToSaveV0, usize) = bincode::decode_from_slice(buf, config::standard())
+        .map_err(|e| {
+            FileError::format(
+                None,
+                format!("Unable to read this file as a PlasCAD file: {e}"),
+            )
+        })?;
+
+    if len != buf.len() {
+        return Err(FileError::format(
+            None,
+            "Unable to read this file as a PlasCAD file: Unexpected data at its end.",
+        ));
+    }
+
+    Ok(Imported {
+        data: state.migrate()?,
+        warnings: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{file_io::save::serialize_seq_bin, sequence::seq_from_str};
+
+    #[test]
+    fn v0_file() {
+        let seq = seq_from_str("ATGGCCAAGTAGCTAGGTC");
+        let metadata = Metadata {
+            plasmid_name: "pV0".to_owned(),
+            ..Default::default()
+        };
+        let feature = (
+            (3_usize, 9_usize),
+            FeatureType::Ori,
+            FeatureDirection::Reverse,
+            "ori".to_owned(),
+            Some((10_u8, 20_u8, 30_u8)),
+            HashMap::<String, String>::new(),
+        );
+
+        // Version 0 files are the state's fields, encoded one after another; so is a tuple.
+        let buf = bincode::encode_to_vec(
+            (
+                (
+                    serialize_seq_bin(&seq),
+                    SeqTopology::Linear,
+                    vec![feature],
+                    Vec::<Primer>::new(),
+                    metadata,
+                ),
+                7_usize,
+                (100_f32, 2_f32, 0.4_f32, 50_f32),
+                ReadingFrame::Rev1,
+            ),
+            config::standard(),
+        )
+        .unwrap();
+
+        let Imported { data, warnings } = StateToSave::from_bytes(&buf).unwrap();
+        assert!(warnings.is_empty());
+
+        assert_eq!(data.generic.seq, seq);
+        assert!(data.generic.topology == SeqTopology::Linear);
+        assert_eq!(data.generic.metadata.plasmid_name, "pV0");
+        assert_eq!(data.insert_loc, 7);
+        assert_eq!(data.reading_frame, ReadingFrame::Rev1);
+        assert!(data.history.is_none());
+
+        assert_eq!(data.generic.features.len(), 1);
+        let feature = &data.generic.features[0];
+        assert_eq!(feature.index_range, SeqRange::new(3, 9));
+        assert!(feature.segments.is_empty());
+        assert!(feature.feature_type == FeatureType::Ori);
+        assert!(feature.direction == FeatureDirection::Reverse);
+        assert_eq!(feature.label, "ori");
+        assert_eq!(feature.color_override, Some((10, 20, 30)));
+
+        let ions = &data.ion_concentrations;
+        assert_eq!(ions.monovalent, 100.);
+        assert_eq!(ions.divalent, 2.);
+        assert_eq!(ions.dntp, 0.4);
+        assert_eq!(ions.primer, 50.);
+        // Added in version 2.
+        assert_eq!(ions.tris, 0.);
+    }
+
+    #[test]
+    fn v0_trailing_data() {
+        let mut buf = bincode::encode_to_vec(
+            (
+                (
+                    serialize_seq_bin(&seq_from_str("ATGC")),
+                    SeqTopology::Circular,
+                    Vec::<(usize, usize)>::new(),
+                    Vec::<Primer>::new(),
+                    Metadata::default(),
+                ),
+                0_usize,
+                (50_f32, 1.5_f32, 0.2_f32, 25_f32),
+                ReadingFrame::Fwd0,
+            ),
+            config::standard(),
+        )
+        .unwrap();
+        assert!(load_v0(&buf).is_ok());
+
+        buf.push(0);
+        assert!(load_v0(&buf).is_err());
+    }
+}
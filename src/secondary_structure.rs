@@ -0,0 +1,464 @@
+//! This module predicts the secondary structure of single-stranded DNA, eg hairpins in primers. We find
+//! the minimum free energy (MFE) structure using Zuker-style dynamic programming, with nearest-neighbor
+//! parameters for stacks and mismatches, and SantaLucia & Hicks (2004) loop parameters.
+//!
+//! This doesn't include pseudoknots, coaxial stacking, or dangling ends on the exterior loop. Loops are
+//! treated as purely entropic.
+
+use crate::{
+    melting_temp_calcs::{dH_dS_imm, dH_dS_neighbors, dH_dS_tmm, na_equivalent},
+    sequence::Nucleotide::{self, A, T},
+    IonConcentrations,
+};
+
+/// We calculate the MFE structure at 37°C, in K.
+const TEMP: f32 = 310.15;
+/// Universal gas constant, in cal/K·mol.
+const R: f32 = 1.987;
+
+/// Hairpin loops must have at least this many unpaired nucleotides.
+const MIN_HAIRPIN_LOOP: usize = 3;
+/// The maximum number of unpaired nucleotides in internal loops and bulges. This limits computation time.
+const MAX_INTERIOR_LOOP: usize = 30;
+
+/// Loop initiation ΔG at 37°C, in kcal/mol, by number of unpaired nucleotides. SantaLucia & Hicks (2004), Table 4.
+/// We interpolate between these, and extrapolate past the end.
+#[rustfmt::skip]
+const LOOP_HAIRPIN: [(usize, f32); 15] = [
+    (3, 3.5), (4, 3.5), (5, 3.3), (6, 4.0), (7, 4.2), (8, 4.3), (9, 4.5), (10, 4.6), (12, 5.0),
+    (14, 5.1), (16, 5.3), (18, 5.5), (20, 5.7), (25, 6.1), (30, 6.3),
+];
+#[rustfmt::skip]
+const LOOP_BULGE: [(usize, f32); 17] = [
+    (1, 4.0), (2, 2.9), (3, 3.1), (4, 3.2), (5, 3.3), (6, 3.5), (7, 3.7), (8, 3.9), (9, 4.1),
+    (10, 4.3), (12, 4.5), (14, 4.8), (16, 5.0), (18, 5.2), (20, 5.3), (25, 5.6), (30, 5.9),
+];
+#[rustfmt::skip]
+const LOOP_INTERNAL: [(usize, f32); 14] = [
+    (3, 3.2), (4, 3.6), (5, 4.0), (6, 4.4), (7, 4.6), (8, 4.8), (9, 4.9), (10, 4.9), (12, 5.2),
+    (14, 5.4), (16, 5.6), (18, 5.8), (20, 5.9), (25, 6.3),
+];
+/// ΔG per nucleotide of difference between the two sides of an internal loop.
+const INTERNAL_ASYMMETRY: f32 = 0.3;
+
+/// Multibranch loop ΔG: Initiation, and per branch. (As used for DNA by mfold)
+const MULTI_INIT: f32 = 3.4;
+const MULTI_BRANCH: f32 = 0.4;
+
+/// Applied to each helix end that's an A/T pair, unless a terminal mismatch parameter applies. (dH, dS)
+const TERMINAL_AT: (f32, f32) = (2.2, 6.9);
+
+/// Enthalpy in kcal/mol, and entropy in cal/K·mol.
+#[derive(Clone, Copy, Debug, Default)]
+struct Energy {
+    dh: f32,
+    ds: f32,
+}
+
+impl Energy {
+    fn new((dh, ds): (f32, f32)) -> Self {
+        Self { dh, ds }
+    }
+
+    /// A purely entropic term, from its ΔG at our temperature.
+    fn from_dg(dg: f32) -> Self {
+        Self {
+            dh: 0.,
+            ds: -dg * 1_000. / TEMP,
+        }
+    }
+
+    fn dg(self) -> f32 {
+        self.dh - TEMP * self.ds / 1_000.
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            dh: self.dh + other.dh,
+            ds: self.ds + other.ds,
+        }
+    }
+}
+
+/// The lower-energy of two options.
+fn min_energy<T: Copy>(a: Option<(Energy, T)>, b: Option<(Energy, T)>) -> Option<(Energy, T)> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.0.dg() < a.0.dg() { b } else { a }),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+/// Interpolate a loop initiation table, extrapolating logarithmically past its end.
+fn loop_init(table: &[(usize, f32)], n: usize) -> f32 {
+    let (n_max, dg_max) = table[table.len() - 1];
+    if n >= n_max {
+        return dg_max + 2.44 * R * TEMP * (n as f32 / n_max as f32).ln() / 1_000.;
+    }
+
+    for window in table.windows(2) {
+        let ((n0, dg0), (n1, dg1)) = (window[0], window[1]);
+        if n <= n0 {
+            return dg0;
+        }
+        if n < n1 {
+            return dg0 + (dg1 - dg0) * (n - n0) as f32 / (n1 - n0) as f32;
+        }
+    }
+    dg_max
+}
+
+/// The minimum free energy structure of a sequence.
+#[derive(Clone, Debug, Default)]
+pub struct Fold {
+    /// Base pairs, as 0-based indices into the sequence. The first index is the lower one.
+    pub pairs: Vec<(usize, usize)>,
+    /// Gibbs free energy at 37°C, in kcal/mol. This is 0 if there's no stable structure.
+    pub dg: f32,
+    /// The temperature, in °C, at which the structure is half-formed. `None` if there's no structure.
+    pub tm: Option<f32>,
+}
+
+impl Fold {
+    /// The structure in dot-bracket notation: Each paired nucleotide is a bracket, and each
+    /// unpaired one a dot.
+    pub fn dot_bracket(&self, seq_len: usize) -> String {
+        let mut result = vec!['.'; seq_len];
+        for (i, j) in &self.pairs {
+            result[*i] = '(';
+            result[*j] = ')';
+        }
+        result.into_iter().collect()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum PairedBy {
+    Hairpin,
+    /// A stack, bulge, or internal loop, closing the inner pair.
+    Interior(usize, usize),
+    /// A multibranch loop, split after this index.
+    Multi(usize),
+}
+
+#[derive(Clone, Copy)]
+enum MultiBy {
+    /// The first nucleotide is unpaired.
+    SkipStart,
+    /// The last nucleotide is unpaired.
+    SkipEnd,
+    /// The ends pair with each other, as a branch.
+    Branch,
+    /// Two regions, each with at least one branch, split after this index.
+    Split(usize),
+}
+
+struct Folder<'a> {
+    seq: &'a [Nucleotide],
+    /// Added to each nearest-neighbor stack's entropy.
+    salt_ds: f32,
+    /// Lowest energy with `i` and `j` paired, and how. Indexed by `i * len + j`.
+    paired: Vec<Option<(Energy, PairedBy)>>,
+    /// Lowest energy of a region within a multibranch loop, with at least one branch.
+    multi: Vec<Option<(Energy, MultiBy)>>,
+}
+
+impl<'a> Folder<'a> {
+    fn idx(&self, i: usize, j: usize) -> usize {
+        i * self.seq.len() + j
+    }
+
+    fn can_pair(&self, i: usize, j: usize) -> bool {
+        let (a, b) = (self.seq[i], self.seq[j]);
+        j > i + MIN_HAIRPIN_LOOP && !a.is_ambiguous() && a.complement() == b
+    }
+
+    /// Applied to helix ends facing a multibranch or exterior loop.
+    fn terminal_at(&self, i: usize) -> Energy {
+        if matches!(self.seq[i], A | T) {
+            Energy::new(TERMINAL_AT)
+        } else {
+            Energy::default()
+        }
+    }
+
+    /// A terminal mismatch on the loop side of pair `i`-`j`; `j` is on the 3' side of the loop
+    /// from `i`'s perspective. Falls back to the A/T penalty if there's no parameter.
+    fn terminal_mismatch(&self, i: usize, i_next: usize, j: usize, j_next: usize) -> Energy {
+        let key = [
+            Some(self.seq[i]),
+            Some(self.seq[i_next]),
+            Some(self.seq[j]),
+            Some(self.seq[j_next]),
+        ];
+        match dH_dS_tmm(key) {
+            Some(v) => Energy::new(v),
+            None => self.terminal_at(i),
+        }
+    }
+
+    fn stack(&self, i: usize) -> Option<Energy> {
+        let (dh, ds) = dH_dS_neighbors((self.seq[i], self.seq[i + 1]))?;
+        Some(Energy::new((dh, ds + self.salt_ds)))
+    }
+
+    fn hairpin(&self, i: usize, j: usize) -> Energy {
+        let n = j - i - 1;
+        let loop_ = Energy::from_dg(loop_init(&LOOP_HAIRPIN, n));
+
+        if n == MIN_HAIRPIN_LOOP {
+            loop_.add(self.terminal_at(i))
+        } else {
+            loop_.add(self.terminal_mismatch(i, i + 1, j, j - 1))
+        }
+    }
+
+    /// A stack, bulge, or internal loop between the outer pair `i`-`j`, and inner pair `k`-`l`.
+    fn interior(&self, i: usize, j: usize, k: usize, l: usize) -> Option<Energy> {
+        let n1 = k - i - 1;
+        let n2 = j - l - 1;
+
+        match (n1, n2) {
+            (0, 0) => self.stack(i),
+            (1, 1) => {
+                // A single mismatch, flanked by two pairs.
+                let key_outer = [i, i + 1, j, j - 1].map(|x| Some(self.seq[x]));
+                let key_inner = [i + 1, k, j - 1, l].map(|x| Some(self.seq[x]));
+                let (dh0, ds0) = dH_dS_imm(key_outer)?;
+                let (dh1, ds1) = dH_dS_imm(key_inner)?;
+                Some(Energy::new((dh0 + dh1, ds0 + ds1 + 2. * self.salt_ds)))
+            }
+            (0, n) | (n, 0) => {
+                let loop_ = Energy::from_dg(loop_init(&LOOP_BULGE, n));
+                if n == 1 {
+                    // Single bulges keep the stacking of their neighboring pairs.
+                    let (dh, ds) = dH_dS_neighbors((self.seq[i], self.seq[k]))?;
+                    Some(loop_.add(Energy::new((dh, ds + self.salt_ds))))
+                } else {
+                    Some(loop_.add(self.terminal_at(i)).add(self.terminal_at(k)))
+                }
+            }
+            _ => {
+                let asymmetry = INTERNAL_ASYMMETRY * n1.abs_diff(n2) as f32;
+                let loop_ = Energy::from_dg(loop_init(&LOOP_INTERNAL, n1 + n2) + asymmetry);
+                Some(
+                    loop_
+                        .add(self.terminal_mismatch(i, i + 1, j, j - 1))
+                        .add(self.terminal_mismatch(l, l + 1, k, k - 1)),
+                )
+            }
+        }
+    }
+
+    fn fill(&mut self) {
+        let len = self.seq.len();
+
+        // Fill shorter regions first, since longer ones depend on them.
+        for span in MIN_HAIRPIN_LOOP + 1..len {
+            for i in 0..len - span {
+                let j = i + span;
+
+                if self.can_pair(i, j) {
+                    let mut best = Some((self.hairpin(i, j), PairedBy::Hairpin));
+
+                    for k in i + 1..j {
+                        let n1 = k - i - 1;
+                        if n1 > MAX_INTERIOR_LOOP {
+                            break;
+                        }
+
+                        for l in (k + 1..j).rev() {
+                            if n1 + (j - l - 1) > MAX_INTERIOR_LOOP {
+                                break;
+                            }
+                            let Some((inner, _)) = self.paired[self.idx(k, l)] else {
+                                continue;
+                            };
+
+                            if let Some(e) = self.interior(i, j, k, l) {
+                                best = min_energy(
+                                    best,
+                                    Some((e.add(inner), PairedBy::Interior(k, l))),
+                                );
+                            }
+                        }
+                    }
+
+                    let closing = Energy::from_dg(MULTI_INIT + MULTI_BRANCH)
+                        .add(self.terminal_at(i))
+                        .add(self.terminal_at(j));
+                    for u in i + 1..j - 1 {
+                        if let (Some((a, _)), Some((b, _))) = (
+                            self.multi[self.idx(i + 1, u)],
+                            self.multi[self.idx(u + 1, j - 1)],
+                        ) {
+                            best =
+                                min_energy(best, Some((closing.add(a).add(b), PairedBy::Multi(u))));
+                        }
+                    }
+
+                    let idx = self.idx(i, j);
+                    self.paired[idx] = best;
+                }
+
+                let mut best = None;
+                if let Some((e, _)) = self.multi[self.idx(i + 1, j)] {
+                    best = Some((e, MultiBy::SkipStart));
+                }
+                if let Some((e, _)) = self.multi[self.idx(i, j - 1)] {
+                    best = min_energy(best, Some((e, MultiBy::SkipEnd)));
+                }
+                if let Some((e, _)) = self.paired[self.idx(i, j)] {
+                    let branch = e
+                        .add(Energy::from_dg(MULTI_BRANCH))
+                        .add(self.terminal_at(i))
+                        .add(self.terminal_at(j));
+                    best = min_energy(best, Some((branch, MultiBy::Branch)));
+                }
+                for u in i + 1..j {
+                    if let (Some((a, _)), Some((b, _))) =
+                        (self.multi[self.idx(i, u)], self.multi[self.idx(u + 1, j)])
+                    {
+                        best = min_energy(best, Some((a.add(b), MultiBy::Split(u))));
+                    }
+                }
+
+                let idx = self.idx(i, j);
+                self.multi[idx] = best;
+            }
+        }
+    }
+
+    fn traceback_paired(&self, i: usize, j: usize, pairs: &mut Vec<(usize, usize)>) {
+        pairs.push((i, j));
+
+        match self.paired[self.idx(i, j)] {
+            Some((_, PairedBy::Interior(k, l))) => self.traceback_paired(k, l, pairs),
+            Some((_, PairedBy::Multi(u))) => {
+                self.traceback_multi(i + 1, u, pairs);
+                self.traceback_multi(u + 1, j - 1, pairs);
+            }
+            _ => (),
+        }
+    }
+
+    fn traceback_multi(&self, i: usize, j: usize, pairs: &mut Vec<(usize, usize)>) {
+        match self.multi[self.idx(i, j)] {
+            Some((_, MultiBy::SkipStart)) => self.traceback_multi(i + 1, j, pairs),
+            Some((_, MultiBy::SkipEnd)) => self.traceback_multi(i, j - 1, pairs),
+            Some((_, MultiBy::Branch)) => self.traceback_paired(i, j, pairs),
+            Some((_, MultiBy::Split(u))) => {
+                self.traceback_multi(i, u, pairs);
+                self.traceback_multi(u + 1, j, pairs);
+            }
+            None => (),
+        }
+    }
+}
+
+/// Find the minimum free energy secondary structure of a single-stranded DNA sequence, eg hairpins.
+/// Computation time grows with the cube of sequence length; this is fast for primers, and for regions
+/// up to several hundred nucleotides.
+pub fn fold(seq: &[Nucleotide], ion: &IonConcentrations) -> Fold {
+    let len = seq.len();
+    if len < MIN_HAIRPIN_LOOP + 2 {
+        return Default::default();
+    }
+
+    let mut folder = Folder {
+        seq,
+        // Entropy salt correction per nearest-neighbor stack. SantaLucia (1998).
+        salt_ds: 0.368 * na_equivalent(ion).ln(),
+        paired: vec![None; len * len],
+        multi: vec![None; len * len],
+    };
+    folder.fill();
+
+    // The exterior loop: Lowest energy of the first `j` nucleotides, and the pair ending at
+    // `j - 1`, if any.
+    let mut exterior: Vec<(Energy, Option<usize>)> = vec![(Energy::default(), None); len + 1];
+    for j in 1..=len {
+        exterior[j] = (exterior[j - 1].0, None);
+
+        for i in 0..j {
+            if let Some((e, _)) = folder.paired[folder.idx(i, j - 1)] {
+                let total = exterior[i]
+                    .0
+                    .add(e)
+                    .add(folder.terminal_at(i))
+                    .add(folder.terminal_at(j - 1));
+                if total.dg() < exterior[j].0.dg() {
+                    exterior[j] = (total, Some(i));
+                }
+            }
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut j = len;
+    while j > 0 {
+        match exterior[j].1 {
+            Some(i) => {
+                folder.traceback_paired(i, j - 1, &mut pairs);
+                j = i;
+            }
+            None => j -= 1,
+        }
+    }
+    pairs.sort();
+
+    let energy = exterior[len].0;
+    if pairs.is_empty() {
+        return Default::default();
+    }
+
+    Fold {
+        pairs,
+        dg: energy.dg(),
+        // For a unimolecular structure, Tm doesn't depend on concentration.
+        tm: (energy.ds < 0.).then(|| 1_000. * energy.dh / energy.ds - 273.15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sequence::seq_from_str, test_fixtures::na_only};
+
+    #[test]
+    fn hairpin() {
+        let seq = seq_from_str("GCGCTTTTGCGC");
+        let result = fold(&seq, &na_only());
+
+        assert_eq!(result.pairs, vec![(0, 11), (1, 10), (2, 9), (3, 8)]);
+        assert_eq!(result.dot_bracket(seq.len()), "((((....))))");
+
+        // 3 stacks, a 4 nt hairpin loop, and the "CT/GT" terminal mismatch inside it.
+        assert!((result.dg - -2.961).abs() < 0.01, "{}", result.dg);
+        assert!((result.tm.unwrap() - 64.55).abs() < 0.1, "{:?}", result.tm);
+    }
+
+    #[test]
+    fn bulge() {
+        // A stem with a single unpaired A on one side.
+        let seq = seq_from_str("CGCGAGCGCTTTTGCGCCGCG");
+        let result = fold(&seq, &na_only());
+
+        assert_eq!(result.dot_bracket(seq.len()), "((((.((((....))))))))");
+        // Less stable than the same stem without the bulge.
+        assert!(result.dg < 0.);
+        assert!(result.dg > fold(&seq_from_str("CGCGGCGCTTTTGCGCCGCG"), &na_only()).dg);
+    }
+
+    #[test]
+    fn no_structure() {
+        let result = fold(&seq_from_str("AAAAAAAAAAAAAAAAAAAA"), &na_only());
+        assert!(result.pairs.is_empty());
+        assert_eq!(result.dg, 0.);
+        assert_eq!(result.tm, None);
+
+        // Too short to form a hairpin loop.
+        assert!(fold(&seq_from_str("GCAGC"), &na_only()).pairs.is_empty());
+        assert!(fold(&seq_from_str("GC"), &na_only()).pairs.is_empty());
+    }
+}
@@ -0,0 +1,778 @@
+//! This module contains operations that edit the sequence, while keeping features in sync. Features
+//! are updated according to these rules, applied to each of their segments:
+//!
+//! - Segments after an edit are shifted.
+//! - Sequence inserted inside a segment splits it: The feature is joined across the insert, so it still
+//!   describes the same nucleotides.
+//! - Deleting part of a segment truncates it; deleting all of a feature's segments deletes the feature.
+//! - Replacing part of a segment modifies it; a segment spanning the replaced region keeps its ends.
+//! - Reverse-complementing a region that contains a whole feature mirrors it, and flips its direction.
+//!   Features partially in the region keep their coordinates, and are reported as modified.
+//!
+//! Each operation returns a report of the features and primers it damaged. Positions use 1-based
+//! indexing, as features do.
+
+use std::fmt::Display;
+
+use crate::{
+    file_io::GenericData,
+    primer::PrimerMatch,
+    sequence::{seq_complement, Feature, FeatureDirection, Nucleotide, SeqRange, SeqTopology},
+};
+
+/// How an edit damaged a feature.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FeatureDamage {
+    /// Part of the feature was deleted, from an end or its interior.
+    Truncated,
+    /// Sequence was inserted inside the feature; it's now joined across the insert.
+    Split,
+    /// Part of the feature was replaced, or reverse-complemented.
+    Modified,
+    /// All of the feature was deleted.
+    Deleted,
+}
+
+impl Display for FeatureDamage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Truncated => "truncated",
+            Self::Split => "split",
+            Self::Modified => "modified",
+            Self::Deleted => "deleted",
+        }
+        .to_owned();
+        write!(f, "{}", str)
+    }
+}
+
+/// Annotations damaged by an edit.
+#[derive(Clone, Default)]
+pub struct EditReport {
+    /// Feature labels, and how they were damaged.
+    pub features: Vec<(String, FeatureDamage)>,
+    /// Names of primers that no longer bind at a site they bound before the edit.
+    pub primers: Vec<String>,
+}
+
+impl EditReport {
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty() && self.primers.is_empty()
+    }
+
+    fn add_feature(&mut self, label: &str, damage: FeatureDamage) {
+        // If a feature is damaged more than once, eg by a replacement, report the first.
+        if !self.features.iter().any(|(l, _)| l == label) {
+            self.features.push((label.to_owned(), damage));
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (label, damage) in other.features {
+            self.add_feature(&label, damage);
+        }
+        for primer in other.primers {
+            if !self.primers.contains(&primer) {
+                self.primers.push(primer);
+            }
+        }
+    }
+}
+
+impl Display for EditReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts: Vec<String> = self
+            .features
+            .iter()
+            .map(|(label, damage)| format!("{label} ({damage})"))
+            .collect();
+
+        for primer in &self.primers {
+            parts.push(format!("{primer} (primer unbound)"));
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// A segment piece that doesn't wrap the origin; 1-based, inclusive.
+type Piece = (usize, usize);
+
+/// Split a feature's segments into pieces that don't wrap the origin.
+fn feature_pieces(feature: &Feature, seq_len: usize) -> Vec<Piece> {
+    feature
+        .ranges()
+        .iter()
+        .flat_map(|r| r.to_linear(seq_len))
+        .map(|r| (r.start + 1, r.end))
+        .collect()
+}
+
+/// Set a feature's segments from pieces, merging pieces that are contiguous, or that abut across
+/// the origin of a circular sequence.
+fn set_feature_pieces(
+    feature: &mut Feature,
+    pieces: &[Piece],
+    seq_len: usize,
+    topology: SeqTopology,
+) {
+    let mut ranges: Vec<SeqRange> = Vec::new();
+
+    for (start, end) in pieces {
+        match ranges.last_mut() {
+            Some(prev) if prev.end + 1 == *start => prev.end = *end,
+            Some(prev)
+                if topology == SeqTopology::Circular && prev.end == seq_len && *start == 1 =>
+            {
+                prev.end = *end
+            }
+            _ => ranges.push(SeqRange::new(*start, *end)),
+        }
+    }
+
+    feature.set_ranges(ranges);
+}
+
+impl GenericData {
+    /// Apply an operation to each feature's pieces. The operation returns the new pieces, and damage
+    /// if applicable. Features with no pieces remaining are deleted.
+    fn edit_features(
+        &mut self,
+        seq_len_new: usize,
+        mut op: impl FnMut(Piece) -> (Vec<Piece>, Option<FeatureDamage>),
+    ) -> EditReport {
+        let mut report = EditReport::default();
+        let seq_len = self.seq.len();
+        let topology = self.topology;
+
+        self.features.retain_mut(|feature| {
+            let mut pieces = Vec::new();
+            let mut damage = None;
+
+            for piece in feature_pieces(feature, seq_len) {
+                let (new, dmg) = op(piece);
+                pieces.extend(new);
+                damage = damage.or(dmg);
+            }
+
+            if pieces.is_empty() {
+                report.add_feature(&feature.label, FeatureDamage::Deleted);
+                return false;
+            }
+
+            if let Some(d) = damage {
+                report.add_feature(&feature.label, d);
+            }
+
+            set_feature_pieces(feature, &pieces, seq_len_new, topology);
+            true
+        });
+
+        report
+    }
+
+    /// Re-match primers after an edit, and report ones that no longer bind at all their previous
+    /// exact sites. (Compared by count, since sites may have shifted) This only finds exact matches;
+    /// the application finds partial ones afterwards, as they depend on ion concentrations.
+    fn sync_primers(&mut self, report: &mut EditReport) {
+        for primer in &mut self.primers {
+            let matches = primer.match_to_seq(&self.seq, self.topology);
+            let exact_prev = primer
+                .volatile
+                .matches_seq
+                .iter()
+                .filter(|m| m.is_exact())
+                .count();
+            if matches.len() < exact_prev {
+                report.primers.push(primer.name.clone());
+            }
+            primer.volatile.matches_seq = matches
+                .into_iter()
+                .map(|(direction, range)| PrimerMatch::exact(direction, range))
+                .collect();
+        }
+    }
+
+    /// Insert nucleotides, so the first inserted is at position `pos`. `pos` may be one past the end of
+    /// the sequence, to append.
+    pub fn insert(&mut self, pos: usize, insert: &[Nucleotide]) -> EditReport {
+        let mut report = self.insert_inner(pos, insert, true);
+        self.sync_primers(&mut report);
+        report
+    }
+
+    /// Insert nucleotides as `insert` does, but extend features that span the insert over it, instead of
+    /// splitting them; eg for in-frame insertions in a coding region.
+    pub fn insert_extending(&mut self, pos: usize, insert: &[Nucleotide]) -> EditReport {
+        let mut report = self.insert_inner(pos, insert, false);
+        self.sync_primers(&mut report);
+        report
+    }
+
+    /// If `split` is false, segments spanning the insert are extended over it, instead of split.
+    fn insert_inner(&mut self, pos: usize, insert: &[Nucleotide], split: bool) -> EditReport {
+        let n = insert.len();
+        if n == 0 || pos < 1 || pos > self.seq.len() + 1 {
+            return Default::default();
+        }
+
+        let report = self.edit_features(self.seq.len() + n, |(start, end)| {
+            if pos <= start {
+                (vec![(start + n, end + n)], None)
+            } else if pos > end {
+                (vec![(start, end)], None)
+            } else if split {
+                (
+                    vec![(start, pos - 1), (pos + n, end + n)],
+                    Some(FeatureDamage::Split),
+                )
+            } else {
+                (vec![(start, end + n)], Some(FeatureDamage::Modified))
+            }
+        });
+
+        self.seq.splice(pos - 1..pos - 1, insert.iter().cloned());
+        report
+    }
+
+    /// Delete a range of nucleotides. The range may wrap the origin.
+    pub fn delete(&mut self, range: SeqRange) -> EditReport {
+        let mut report = self.delete_inner(range);
+        self.sync_primers(&mut report);
+        report
+    }
+
+    fn delete_inner(&mut self, range: SeqRange) -> EditReport {
+        if !range.is_valid(self.seq.len()) {
+            return Default::default();
+        }
+
+        if range.wraps() {
+            // Delete the part at the end first, so the part at the start keeps its indices.
+            let mut report = self.delete_inner(SeqRange::new(range.start, self.seq.len()));
+            report.merge(self.delete_inner(SeqRange::new(1, range.end)));
+            return report;
+        }
+
+        let (a, b) = (range.start, range.end);
+        let n = b - a + 1;
+
+        let report = self.edit_features(self.seq.len() - n, |(start, end)| {
+            if end < a {
+                (vec![(start, end)], None)
+            } else if start > b {
+                (vec![(start - n, end - n)], None)
+            } else if a <= start && end <= b {
+                // If this was the feature's only piece, it's reported as deleted instead.
+                (Vec::new(), Some(FeatureDamage::Truncated))
+            } else if start < a && end > b {
+                (vec![(start, end - n)], Some(FeatureDamage::Truncated))
+            } else if start < a {
+                (vec![(start, a - 1)], Some(FeatureDamage::Truncated))
+            } else {
+                (vec![(a, end - n)], Some(FeatureDamage::Truncated))
+            }
+        });
+
+        self.seq.drain(a - 1..b);
+        report
+    }
+
+    /// Replace a range of nucleotides with others. The range may wrap the origin.
+    pub fn replace(&mut self, range: SeqRange, insert: &[Nucleotide]) -> EditReport {
+        if !range.is_valid(self.seq.len()) {
+            return Default::default();
+        }
+
+        // If wrapping, the deletion removes everything after the range's start.
+        let pos = if range.wraps() {
+            self.seq.len() - range.len(self.seq.len()) + 1
+        } else {
+            range.start
+        };
+
+        let mut report = self.delete_inner(range);
+        // Features that span the deleted range were truncated; with the insert, they're modified.
+        for (_, damage) in &mut report.features {
+            if *damage == FeatureDamage::Truncated {
+                *damage = FeatureDamage::Modified;
+            }
+        }
+
+        report.merge(self.insert_inner(pos, insert, false));
+        self.sync_primers(&mut report);
+        report
+    }
+
+    /// Reverse-complement a range of nucleotides, eg to flip an insert. The range may wrap the origin.
+    pub fn reverse_complement(&mut self, range: SeqRange) -> EditReport {
+        let seq_len = self.seq.len();
+        if !range.is_valid(seq_len) {
+            return Default::default();
+        }
+
+        if range.wraps() {
+            // Rotate so the range doesn't wrap, then rotate back.
+            self.rotate(range.start);
+            let mut report = self.reverse_complement(SeqRange::new(1, range.len(seq_len)));
+            self.rotate((seq_len + 1 - range.start) % seq_len + 1);
+
+            // Rotating moves primer matches, but doesn't unbind them.
+            report.primers = Vec::new();
+            self.sync_primers(&mut report);
+            return report;
+        }
+
+        let (a, b) = (range.start, range.end);
+        let mut report = EditReport::default();
+
+        for feature in &mut self.features {
+            let pieces = feature_pieces(feature, seq_len);
+
+            if pieces.iter().all(|(start, end)| a <= *start && *end <= b) {
+                // Mirror the feature within the range, reversing its segment order along the top strand.
+                let mirrored: Vec<Piece> = pieces
+                    .iter()
+                    .rev()
+                    .map(|(start, end)| (a + b - end, a + b - start))
+                    .collect();
+                set_feature_pieces(feature, &mirrored, seq_len, self.topology);
+
+                feature.direction = match feature.direction {
+                    FeatureDirection::Forward => FeatureDirection::Reverse,
+                    FeatureDirection::Reverse => FeatureDirection::Forward,
+                    FeatureDirection::None => FeatureDirection::None,
+                };
+            } else if pieces.iter().any(|(start, end)| *start <= b && *end >= a) {
+                report.add_feature(&feature.label, FeatureDamage::Modified);
+            }
+        }
+
+        let compl = seq_complement(&self.seq[a - 1..b]);
+        self.seq.splice(a - 1..b, compl);
+
+        self.sync_primers(&mut report);
+        report
+    }
+
+    /// Change the origin, so that `origin` becomes position 1.
+    pub fn rotate(&mut self, origin: usize) {
+        let seq_len = self.seq.len();
+        if origin < 1 || origin > seq_len {
+            return;
+        }
+
+        self.seq.rotate_left(origin - 1);
+
+        for feature in &mut self.features {
+            feature.index_range = feature.index_range.rotate(origin, seq_len);
+            for segment in &mut feature.segments {
+                *segment = segment.rotate(origin, seq_len);
+            }
+        }
+
+        for primer in &mut self.primers {
+            for m in &mut primer.volatile.matches_seq {
+                m.range = m.range.rotate(origin, seq_len);
+            }
+        }
+    }
+
+    /// Copy a range of the sequence, along with features entirely inside it, eg to paste elsewhere.
+    /// The range may wrap the origin.
+    pub fn extract(&self, range: SeqRange) -> GenericData {
+        let seq_len = self.seq.len();
+        let Some(seq) = range.seq(&self.seq) else {
+            return Default::default();
+        };
+        let len = seq.len();
+
+        let mut features = Vec::new();
+        for feature in &self.features {
+            // Coordinates relative to the range's start.
+            let ranges: Vec<SeqRange> = feature
+                .ranges()
+                .iter()
+                .map(|r| r.rotate(range.start, seq_len))
+                .collect();
+
+            if ranges.iter().all(|r| !r.wraps() && r.end <= len) {
+                let mut f = feature.clone();
+                f.set_ranges(ranges);
+                features.push(f);
+            }
+        }
+
+        GenericData {
+            seq,
+            topology: SeqTopology::Linear,
+            features,
+            ..Default::default()
+        }
+    }
+
+    /// Insert a fragment, eg from `extract`, with its features, so it starts at position `pos`.
+    pub fn paste(&mut self, pos: usize, fragment: &GenericData) -> EditReport {
+        if fragment.seq.is_empty() || pos < 1 || pos > self.seq.len() + 1 {
+            return Default::default();
+        }
+
+        let mut report = self.insert_inner(pos, &fragment.seq, true);
+
+        for feature in &fragment.features {
+            let mut f = feature.clone();
+            let ranges = feature
+                .ranges()
+                .iter()
+                .map(|r| SeqRange::new(r.start + pos - 1, r.end + pos - 1))
+                .collect();
+            f.set_ranges(ranges);
+            self.features.push(f);
+        }
+
+        self.sync_primers(&mut report);
+        report
+    }
+
+    /// Replace the whole sequence with a new one, eg from a text editor, as the minimal edit
+    /// between them: The nucleotides they share at the start and end are kept.
+    pub fn edit_from_seq(&mut self, seq_new: &[Nucleotide]) -> EditReport {
+        let (len_old, len_new) = (self.seq.len(), seq_new.len());
+
+        let prefix = self
+            .seq
+            .iter()
+            .zip(seq_new)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let suffix = self
+            .seq
+            .iter()
+            .rev()
+            .zip(seq_new.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(len_old.min(len_new) - prefix);
+
+        let insert = &seq_new[prefix..len_new - suffix];
+
+        if prefix + suffix == len_old {
+            self.insert(prefix + 1, insert)
+        } else {
+            let range = SeqRange::new(prefix + 1, len_old - suffix);
+            if insert.is_empty() {
+                self.delete(range)
+            } else {
+                self.replace(range, insert)
+            }
+        }
+    }
+}
+
+/// Find the first occurrence of a sequence. Returns its 0-based start index. On circular sequences,
+/// this includes occurrences that wrap the origin.
+pub fn find_seq(seq: &[Nucleotide], topology: SeqTopology, query: &[Nucleotide]) -> Option<usize> {
+    if query.is_empty() || query.len() > seq.len() {
+        return None;
+    }
+
+    let starts = match topology {
+        SeqTopology::Circular => 0..seq.len(),
+        SeqTopology::Linear => 0..seq.len() - query.len() + 1,
+    };
+
+    starts.into_iter().find(|i| {
+        query
+            .iter()
+            .eq(seq.iter().cycle().skip(*i).take(query.len()))
+    })
+}
+
+impl GenericData {
+    /// Add a feature from another sequence, where its sequence is found in this one, on either
+    /// strand. `feature`'s ranges are relative to `feature_seq`. Returns false if it isn't found.
+    pub fn paste_feature(&mut self, feature: &Feature, feature_seq: &[Nucleotide]) -> bool {
+        let seq_len = self.seq.len();
+        let len = feature_seq.len();
+        let relative = feature.ranges();
+
+        let (start, relative, flip) = if let Some(i) =
+            find_seq(&self.seq, self.topology, feature_seq)
+        {
+            (i, relative, false)
+        } else if let Some(i) = find_seq(&self.seq, self.topology, &seq_complement(feature_seq)) {
+            // On the bottom strand: Mirror the segments, and reverse their order.
+            let mirrored = relative
+                .iter()
+                .rev()
+                .map(|r| SeqRange::new(len + 1 - r.end, len + 1 - r.start))
+                .collect();
+            (i, mirrored, true)
+        } else {
+            return false;
+        };
+
+        let mut result = feature.clone();
+        result.set_ranges(
+            relative
+                .iter()
+                .map(|r| {
+                    SeqRange::new(
+                        (r.start - 1 + start) % seq_len + 1,
+                        (r.end - 1 + start) % seq_len + 1,
+                    )
+                })
+                .collect(),
+        );
+
+        if flip {
+            result.direction = match feature.direction {
+                FeatureDirection::Forward => FeatureDirection::Reverse,
+                FeatureDirection::Reverse => FeatureDirection::Forward,
+                FeatureDirection::None => FeatureDirection::None,
+            };
+        }
+
+        self.features.push(result);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        primer::{Primer, PrimerData},
+        sequence::{seq_from_str, seq_to_str},
+        test_fixtures::{doc, feature},
+    };
+
+    fn ranges(data: &GenericData, i: usize) -> Vec<(usize, usize)> {
+        data.features[i]
+            .ranges()
+            .iter()
+            .map(|r| (r.start, r.end))
+            .collect()
+    }
+
+    #[test]
+    fn insert_shifts_and_splits() {
+        let mut data = doc(
+            "aaaaaaaaaaaaaaaaaaaa",
+            SeqTopology::Linear,
+            vec![
+                feature("before", &[(2, 4)]),
+                feature("spans", &[(5, 10)]),
+                feature("after", &[(12, 15)]),
+            ],
+        );
+
+        let report = data.insert(8, &seq_from_str("ccc"));
+        assert_eq!(data.seq.len(), 23);
+        assert_eq!(seq_to_str(&data.seq[7..10]), "ccc");
+
+        assert_eq!(ranges(&data, 0), vec![(2, 4)]);
+        assert_eq!(ranges(&data, 1), vec![(5, 7), (11, 13)]);
+        assert_eq!(ranges(&data, 2), vec![(15, 18)]);
+        assert_eq!(
+            report.features,
+            vec![("spans".to_owned(), FeatureDamage::Split)]
+        );
+
+        // Inserting at a feature's first position shifts it, instead of splitting it.
+        let report = data.insert(2, &seq_from_str("g"));
+        assert_eq!(ranges(&data, 0), vec![(3, 5)]);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn insert_extending() {
+        let mut data = doc(
+            "aaaaaaaaaa",
+            SeqTopology::Linear,
+            vec![feature("cds", &[(2, 7)])],
+        );
+
+        let report = data.insert_extending(4, &seq_from_str("ccc"));
+        assert_eq!(ranges(&data, 0), vec![(2, 10)]);
+        assert_eq!(
+            report.features,
+            vec![("cds".to_owned(), FeatureDamage::Modified)]
+        );
+    }
+
+    #[test]
+    fn delete_truncates_and_deletes() {
+        let mut data = doc(
+            "aaaaaaaaaaaaaaaaaaaa",
+            SeqTopology::Linear,
+            vec![
+                feature("left", &[(2, 6)]),
+                feature("inside", &[(7, 8)]),
+                feature("spans", &[(3, 15)]),
+                feature("after", &[(16, 18)]),
+            ],
+        );
+
+        let report = data.delete(SeqRange::new(5, 10));
+        assert_eq!(data.seq.len(), 14);
+
+        // "inside" was removed.
+        assert_eq!(data.features.len(), 3);
+        assert_eq!(ranges(&data, 0), vec![(2, 4)]);
+        assert_eq!(ranges(&data, 1), vec![(3, 9)]);
+        assert_eq!(ranges(&data, 2), vec![(10, 12)]);
+
+        assert_eq!(
+            report.features,
+            vec![
+                ("left".to_owned(), FeatureDamage::Truncated),
+                ("inside".to_owned(), FeatureDamage::Deleted),
+                ("spans".to_owned(), FeatureDamage::Truncated),
+            ]
+        );
+    }
+
+    #[test]
+    fn delete_wrapping_origin() {
+        let mut data = doc(
+            "acgtacgtacgtacgtacgt",
+            SeqTopology::Circular,
+            vec![
+                feature("end", &[(15, 18)]),
+                feature("start", &[(3, 6)]),
+                feature("wraps", &[(17, 20), (1, 3)]),
+            ],
+        );
+
+        // 18, 19, 20, 1, and 2.
+        let report = data.delete(SeqRange::new(18, 2));
+        assert_eq!(data.seq.len(), 15);
+        assert_eq!(seq_to_str(&data.seq), "gtacgtacgtacgta");
+
+        assert_eq!(ranges(&data, 0), vec![(13, 15)]);
+        assert_eq!(ranges(&data, 1), vec![(1, 4)]);
+        // Joined back into a single range across the origin.
+        assert_eq!(ranges(&data, 2), vec![(15, 1)]);
+        assert_eq!(
+            report.features,
+            vec![
+                ("end".to_owned(), FeatureDamage::Truncated),
+                ("wraps".to_owned(), FeatureDamage::Truncated),
+            ]
+        );
+    }
+
+    #[test]
+    fn replace_modifies() {
+        let mut data = doc(
+            "aaaaaaaaaaaaaaaaaaaa",
+            SeqTopology::Linear,
+            vec![feature("spans", &[(3, 12)]), feature("after", &[(15, 18)])],
+        );
+
+        let report = data.replace(SeqRange::new(5, 8), &seq_from_str("cc"));
+        assert_eq!(data.seq.len(), 18);
+        assert_eq!(seq_to_str(&data.seq[4..6]), "cc");
+
+        // Ends kept, and extended over the insert.
+        assert_eq!(ranges(&data, 0), vec![(3, 10)]);
+        assert_eq!(ranges(&data, 1), vec![(13, 16)]);
+        assert_eq!(
+            report.features,
+            vec![("spans".to_owned(), FeatureDamage::Modified)]
+        );
+    }
+
+    #[test]
+    fn reverse_complement_mirrors() {
+        let mut data = doc(
+            "aaaaccgggttttttttttt",
+            SeqTopology::Linear,
+            vec![feature("inside", &[(5, 6)]), feature("partial", &[(8, 12)])],
+        );
+
+        let report = data.reverse_complement(SeqRange::new(3, 10));
+        // aaccgggt -> acccggtt
+        assert_eq!(seq_to_str(&data.seq), "aaacccggtttttttttttt");
+
+        assert_eq!(ranges(&data, 0), vec![(7, 8)]);
+        assert!(data.features[0].direction == FeatureDirection::Reverse);
+        assert_eq!(ranges(&data, 1), vec![(8, 12)]);
+        assert!(data.features[1].direction == FeatureDirection::Forward);
+        assert_eq!(
+            report.features,
+            vec![("partial".to_owned(), FeatureDamage::Modified)]
+        );
+    }
+
+    #[test]
+    fn reverse_complement_wrapping_origin() {
+        let mut data = doc(
+            "ttgcaaaaac",
+            SeqTopology::Circular,
+            vec![feature("f", &[(9, 10)])],
+        );
+
+        // 9, 10, 1, and 2: acTT -> AAgt
+        let report = data.reverse_complement(SeqRange::new(9, 2));
+        assert_eq!(seq_to_str(&data.seq), "gtgcaaaaaa");
+        assert_eq!(ranges(&data, 0), vec![(1, 2)]);
+        assert!(data.features[0].direction == FeatureDirection::Reverse);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn primer_unbound_by_edit() {
+        let mut data = doc(
+            "aaaaagcatgcatcgatcgaggatttttt",
+            SeqTopology::Linear,
+            Vec::new(),
+        );
+        let sequence = seq_from_str("gcatgcatcgatcgagg");
+        let mut primer = Primer {
+            volatile: PrimerData::new(&sequence),
+            sequence,
+            name: "P1".to_owned(),
+            description: None,
+        };
+        primer.volatile.matches_seq = primer
+            .match_to_seq(&data.seq, data.topology)
+            .into_iter()
+            .map(|(direction, range)| PrimerMatch::exact(direction, range))
+            .collect();
+        assert_eq!(primer.volatile.matches_seq.len(), 1);
+        data.primers.push(primer);
+
+        // Outside the site: Still bound.
+        let report = data.delete(SeqRange::new(1, 2));
+        assert!(report.primers.is_empty());
+        assert_eq!(
+            data.primers[0].volatile.matches_seq[0].range,
+            SeqRange::new(4, 20)
+        );
+
+        let report = data.replace(SeqRange::new(10, 10), &seq_from_str("t"));
+        assert_eq!(report.primers, vec!["P1".to_owned()]);
+        assert!(data.primers[0].volatile.matches_seq.is_empty());
+    }
+
+    #[test]
+    fn edit_from_seq_is_minimal() {
+        let mut data = doc(
+            "aaaaccccgggg",
+            SeqTopology::Linear,
+            vec![feature("a", &[(1, 4)]), feature("g", &[(9, 12)])],
+        );
+
+        // Inserts tt after the c's.
+        data.edit_from_seq(&seq_from_str("aaaaccccttgggg"));
+        assert_eq!(ranges(&data, 0), vec![(1, 4)]);
+        assert_eq!(ranges(&data, 1), vec![(11, 14)]);
+
+        // Deletes the c's.
+        data.edit_from_seq(&seq_from_str("aaaattgggg"));
+        assert_eq!(ranges(&data, 1), vec![(7, 10)]);
+    }
+}
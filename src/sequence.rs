@@ -1,29 +1,23 @@
-use std::{collections::HashMap, fmt::Display, io};
+use std::{cmp::Reverse, collections::HashMap, fmt::Display, io, ops::Range};
 
 use bincode::{Decode, Encode};
 use num_enum::TryFromPrimitive;
 
 use crate::{
     primer::PrimerDirection,
-    sequence::Nucleotide::{A, C, G, T},
+    protein::GeneticCode,
+    sequence::Nucleotide::{A, B, C, D, G, H, K, M, N, R, S, T, V, W, Y},
     Color,
 };
 
 // Index 0: 5' end.
 pub type Seq = Vec<Nucleotide>;
 
-impl Nucleotide {
-    pub fn as_str(&self) -> &str {
-        match self {
-            A => "a",
-            T => "t",
-            C => "c",
-            G => "g",
-        }
-    }
-}
-
-/// A DNA nucleotide. The u8 repr is for use with a compact binary format.
+/// A DNA nucleotide. This includes the IUPAC ambiguity codes, eg for reference sequences that contain
+/// Ns, and degenerate primers or restriction sites. The u8 repr is for use with a compact binary format;
+/// A, T, G, and C fit in 2 bits, while the full set fits in 4.
+///
+/// [IUPAC codes](https://www.bioinformatics.org/sms/iupac.html)
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Encode, Decode, TryFromPrimitive)]
 #[repr(u8)]
 pub enum Nucleotide {
@@ -31,16 +25,69 @@ pub enum Nucleotide {
     T = 1,
     G = 2,
     C = 3,
+    /// Purines: A or G
+    R = 4,
+    /// Pyrimidines: C or T
+    Y = 5,
+    /// Strong: C or G
+    S = 6,
+    /// Weak: A or T
+    W = 7,
+    /// Keto: G or T
+    K = 8,
+    /// Amino: A or C
+    M = 9,
+    /// Not A
+    B = 10,
+    /// Not C
+    D = 11,
+    /// Not G
+    H = 12,
+    /// Not T
+    V = 13,
+    /// Any
+    N = 14,
 }
 
 impl Nucleotide {
-    /// For parsing from FASTA and SnapGene compatibility.
+    pub fn as_str(&self) -> &str {
+        match self {
+            A => "a",
+            T => "t",
+            C => "c",
+            G => "g",
+            R => "r",
+            Y => "y",
+            S => "s",
+            W => "w",
+            K => "k",
+            M => "m",
+            B => "b",
+            D => "d",
+            H => "h",
+            V => "v",
+            N => "n",
+        }
+    }
+
+    /// For parsing from FASTA, GenBank, and SnapGene compatibility.
     pub fn from_u8_letter(val_u8: u8) -> io::Result<Self> {
         match val_u8 {
             b'A' | b'a' => Ok(A),
             b'T' | b't' => Ok(T),
             b'G' | b'g' => Ok(G),
             b'C' | b'c' => Ok(C),
+            b'R' | b'r' => Ok(R),
+            b'Y' | b'y' => Ok(Y),
+            b'S' | b's' => Ok(S),
+            b'W' | b'w' => Ok(W),
+            b'K' | b'k' => Ok(K),
+            b'M' | b'm' => Ok(M),
+            b'B' | b'b' => Ok(B),
+            b'D' | b'd' => Ok(D),
+            b'H' | b'h' => Ok(H),
+            b'V' | b'v' => Ok(V),
+            b'N' | b'n' => Ok(N),
             _ => Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "Invalid nucleotide",
@@ -48,21 +95,74 @@ impl Nucleotide {
         }
     }
 
-    /// For FASTA and SnapGene compatibility.
-    pub fn to_u8_letter(&self) -> u8 {
+    /// For FASTA, GenBank, and SnapGene compatibility.
+    pub fn to_u8_letter(self) -> u8 {
+        self.as_str().as_bytes()[0].to_ascii_uppercase()
+    }
+
+    /// The nucleotide this pairs with, on the opposite strand. For ambiguity codes, this is the code
+    /// that represents the set of complements, eg R (A or G) pairs with Y (T or C).
+    pub fn complement(self) -> Self {
         match self {
-            A => b'A',
-            T => b'T',
-            G => b'G',
-            C => b'C',
+            A => T,
+            T => A,
+            C => G,
+            G => C,
+            R => Y,
+            Y => R,
+            S => S,
+            W => W,
+            K => M,
+            M => K,
+            B => V,
+            V => B,
+            D => H,
+            H => D,
+            N => N,
+        }
+    }
+
+    /// True for all IUPAC codes other than A, T, G, and C.
+    pub fn is_ambiguous(self) -> bool {
+        !matches!(self, A | T | G | C)
+    }
+
+    /// If this code represents every nucleotide `other` may; eg a degenerate primer nucleotide
+    /// matching a template. For A, T, G, and C, this is equality.
+    pub fn includes(self, other: Self) -> bool {
+        other
+            .possible()
+            .iter()
+            .all(|nt| self.possible().contains(nt))
+    }
+
+    /// The unambiguous nucleotides this code may represent. For A, T, G, and C, this is the nucleotide itself.
+    pub fn possible(self) -> &'static [Nucleotide] {
+        match self {
+            A => &[A],
+            T => &[T],
+            G => &[G],
+            C => &[C],
+            R => &[A, G],
+            Y => &[C, T],
+            S => &[G, C],
+            W => &[A, T],
+            K => &[G, T],
+            M => &[A, C],
+            B => &[C, G, T],
+            D => &[A, G, T],
+            H => &[A, C, T],
+            V => &[A, C, G],
+            N => &[A, C, G, T],
         }
     }
 }
 
 /// Of the 6 possible reading frames.
-#[derive(Clone, Copy, PartialEq, Debug, Encode, Decode)]
+#[derive(Clone, Copy, PartialEq, Debug, Encode, Decode, Default)]
 pub enum ReadingFrame {
     /// Forward, with 0 offset (This pattern applies for all variants)
+    #[default]
     Fwd0,
     Fwd1,
     Fwd2,
@@ -72,6 +172,15 @@ pub enum ReadingFrame {
 }
 
 impl ReadingFrame {
+    pub const ALL: [Self; 6] = [
+        Self::Fwd0,
+        Self::Fwd1,
+        Self::Fwd2,
+        Self::Rev0,
+        Self::Rev1,
+        Self::Rev2,
+    ];
+
     pub fn offset(&self) -> usize {
         match self {
             Self::Fwd0 | Self::Rev0 => 0,
@@ -79,11 +188,9 @@ impl ReadingFrame {
             Self::Fwd2 | Self::Rev2 => 2,
         }
     }
-}
 
-impl Default for ReadingFrame {
-    fn default() -> Self {
-        Self::Fwd0
+    pub fn is_reverse(&self) -> bool {
+        matches!(self, Self::Rev0 | Self::Rev1 | Self::Rev2)
     }
 }
 
@@ -106,13 +213,32 @@ impl Display for ReadingFrame {
 #[derive(Debug)]
 pub struct ReadingFrameMatch {
     pub frame: ReadingFrame,
-    /// 1-based indexing.
     /// Indices are respective to the non-complementary seq, for both forward and reverse reading frames.
-    pub range: (usize, usize),
+    pub range: SeqRange,
 }
 
-#[derive(Clone, Copy, PartialEq, Encode, Decode)]
+impl ReadingFrameMatch {
+    /// Create a coding region feature from this ORF.
+    pub fn to_feature(&self) -> Feature {
+        Feature {
+            index_range: self.range,
+            feature_type: FeatureType::CodingRegion,
+            direction: if self.frame.is_reverse() {
+                FeatureDirection::Reverse
+            } else {
+                FeatureDirection::Forward
+            },
+            label: format!("ORF {}", self.frame),
+            segments: Vec::new(),
+            color_override: None,
+            notes: Default::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Encode, Decode, Default)]
 pub enum FeatureType {
+    #[default]
     Generic,
     // Gene,
     Ori,
@@ -133,16 +259,10 @@ pub enum FeatureType {
     Transcript,
 }
 
-impl Default for FeatureType {
-    fn default() -> Self {
-        Self::Generic
-    }
-}
-
-impl FeatureType {
+impl Display for FeatureType {
     /// For displaying in the UI
-    pub fn to_string(&self) -> String {
-        match self {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
             Self::Generic => "Generic",
             // Self::Gene => "Gene",
             Self::Ori => "Origin of replication",
@@ -157,9 +277,12 @@ impl FeatureType {
             Self::Exon => "Exon",
             Self::Transcript => "Transcript",
         }
-        .to_owned()
+        .to_owned();
+        write!(f, "{}", str)
     }
+}
 
+impl FeatureType {
     pub fn color(&self) -> Color {
         match self {
             Self::Generic => (255, 0, 255),
@@ -199,7 +322,7 @@ impl FeatureType {
     }
 
     /// Create a string for use with SnapGene and GenBank formats.
-    pub fn to_external_str(&self) -> String {
+    pub fn to_external_str(self) -> String {
         // todo: Update as required with more
         match self {
             Self::Generic => "misc_feature",
@@ -219,8 +342,9 @@ impl FeatureType {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Encode, Decode)]
+#[derive(Clone, Copy, PartialEq, Encode, Decode, Default)]
 pub enum FeatureDirection {
+    #[default]
     None,
     Forward,
     Reverse,
@@ -235,27 +359,25 @@ impl From<PrimerDirection> for FeatureDirection {
     }
 }
 
-impl Default for FeatureDirection {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
-impl FeatureDirection {
-    pub fn to_string(&self) -> String {
-        match self {
+impl Display for FeatureDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
             Self::None => "None",
             Self::Forward => "Forward",
             Self::Reverse => "Reverse",
         }
-        .to_owned()
+        .to_owned();
+        write!(f, "{}", str)
     }
 }
 
 #[derive(Clone, Encode, Decode)]
 pub struct Feature {
-    /// 1-based indexing, inclusive. (Note: Could also use the builtin RangeInclusive.)
-    pub index_range: (usize, usize),
+    /// For joined features, this is the extent of all segments.
+    pub index_range: SeqRange,
+    /// For joined features, eg multi-exon coding regions: The ranges that make up the feature, in order
+    /// along the top strand. Empty for features with a single range.
+    pub segments: Vec<SeqRange>,
     pub feature_type: FeatureType,
     pub direction: FeatureDirection,
     pub label: String,
@@ -265,51 +387,220 @@ pub struct Feature {
     pub notes: HashMap<String, String>,
 }
 
-#[derive(Clone, Copy, PartialEq, Encode, Decode)]
-pub enum SeqTopology {
-    Linear,
-    Circular,
+impl Feature {
+    /// The ranges that make up this feature: Its segments if joined, or its index range otherwise.
+    pub fn ranges(&self) -> Vec<SeqRange> {
+        if self.segments.is_empty() {
+            vec![self.index_range]
+        } else {
+            self.segments.clone()
+        }
+    }
+
+    /// Set the ranges that make up this feature, in order along the top strand. Sets the index range to
+    /// their extent.
+    pub fn set_ranges(&mut self, ranges: Vec<SeqRange>) {
+        if ranges.is_empty() {
+            return;
+        }
+
+        self.index_range = SeqRange::new(ranges[0].start, ranges[ranges.len() - 1].end);
+
+        if ranges.len() == 1 {
+            self.segments = Vec::new();
+        } else {
+            self.segments = ranges;
+        }
+    }
+
+    /// If this feature contains a nucleotide; 1-based indexing. Handles joined features, and ones that
+    /// wrap the origin.
+    pub fn contains(&self, i: usize) -> bool {
+        self.ranges().iter().any(|r| r.contains(i))
+    }
+
+    /// The feature's sequence, with segments spliced together. For reverse features, this is the
+    /// complement strand.
+    pub fn spliced_seq(&self, seq: &[Nucleotide]) -> Option<Seq> {
+        let mut result = Vec::new();
+        for range in self.ranges() {
+            result.extend(range.seq(seq)?);
+        }
+
+        if self.direction == FeatureDirection::Reverse {
+            result = seq_complement(&result);
+        }
+
+        Some(result)
+    }
 }
 
-impl Default for SeqTopology {
-    fn default() -> Self {
-        Self::Circular
+/// A range of nucleotides, using 1-based, inclusive indexing, as GenBank and SnapGene do. If the end
+/// is less than the start, the range wraps the origin of a circular sequence. We use this for features,
+/// primer matches, reading frames, and selections.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Encode, Decode)]
+pub struct SeqRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Display for SeqRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
     }
 }
 
-/// Reverse direction, and swap C for G, A for T.
-pub fn seq_complement(seq: &[Nucleotide]) -> Seq {
-    let mut result = seq.to_vec();
-    result.reverse();
+impl SeqRange {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
 
-    for nt in &mut result {
-        *nt = match *nt {
-            A => T,
-            T => A,
-            C => G,
-            G => C,
-        };
+    /// Create from a 0-based start index, and a length, wrapping the origin if required.
+    pub fn from_start_len(start: usize, len: usize, seq_len: usize) -> Self {
+        Self {
+            start: start % seq_len + 1,
+            end: (start + len + seq_len - 1) % seq_len + 1,
+        }
     }
 
-    result
+    /// If this range wraps the origin.
+    pub fn wraps(self) -> bool {
+        self.end < self.start
+    }
+
+    /// If the indices are valid for a sequence of this length. (1-based indexing, so 0 is invalid)
+    pub fn is_valid(self, seq_len: usize) -> bool {
+        self.start >= 1 && self.end >= 1 && self.start <= seq_len && self.end <= seq_len
+    }
+
+    /// Length in nucleotides.
+    pub fn len(self, seq_len: usize) -> usize {
+        if self.wraps() {
+            seq_len - self.start + self.end + 1
+        } else {
+            self.end - self.start + 1
+        }
+    }
+
+    /// If this range contains a nucleotide; 1-based indexing.
+    pub fn contains(self, i: usize) -> bool {
+        if self.wraps() {
+            i >= self.start || i <= self.end
+        } else {
+            self.start <= i && i <= self.end
+        }
+    }
+
+    /// Split into 0-based, end-exclusive ranges that don't wrap the origin; eg for indexing and drawing.
+    /// This returns two ranges if this one wraps the origin, and one otherwise.
+    pub fn to_linear(self, seq_len: usize) -> Vec<Range<usize>> {
+        if !self.is_valid(seq_len) {
+            return Vec::new();
+        }
+
+        let mut result = Vec::new();
+        if self.wraps() {
+            result.push(self.start - 1..seq_len);
+            result.push(0..self.end);
+        } else {
+            result.push(self.start - 1..self.end);
+        }
+        result
+    }
+
+    /// The 0-based indices of the nucleotides in this range, in order.
+    pub fn indices(self, seq_len: usize) -> Vec<usize> {
+        self.to_linear(seq_len).into_iter().flatten().collect()
+    }
+
+    /// The part of a sequence in this range.
+    pub fn seq(self, seq: &[Nucleotide]) -> Option<Seq> {
+        if !self.is_valid(seq.len()) {
+            return None;
+        }
+
+        Some(
+            self.to_linear(seq.len())
+                .into_iter()
+                .flat_map(|r| seq[r].to_vec())
+                .collect(),
+        )
+    }
+
+    /// Convert between a range on the complement strand (as indexed from its own 5' end), and the
+    /// same nucleotides on the top strand.
+    pub fn complement(self, seq_len: usize) -> Self {
+        Self {
+            start: seq_len + 1 - self.end,
+            end: seq_len + 1 - self.start,
+        }
+    }
+
+    /// Update indices after changing the origin of a circular sequence; `origin` is the 1-based index
+    /// that becomes the new position 1.
+    pub fn rotate(self, origin: usize, seq_len: usize) -> Self {
+        let rotate_i = |i: usize| (i + seq_len - origin) % seq_len + 1;
+
+        Self {
+            start: rotate_i(self.start),
+            end: rotate_i(self.end),
+        }
+    }
 }
 
+#[derive(Clone, Copy, PartialEq, Encode, Decode, Default)]
+pub enum SeqTopology {
+    Linear,
+    #[default]
+    Circular,
+}
+
+/// Reverse direction, and swap C for G, A for T. Ambiguity codes are swapped for their complementary code.
+pub fn seq_complement(seq: &[Nucleotide]) -> Seq {
+    seq.iter().rev().map(|nt| nt.complement()).collect()
+}
+
+/// Parse a sequence from a string, eg from user input. This accepts IUPAC ambiguity codes, and
+/// ignores other characters, such as whitespace and numbers.
 pub fn seq_from_str(str: &str) -> Seq {
     let mut result = Vec::new();
 
-    for char in str.to_lowercase().chars() {
-        match char {
-            'a' => result.push(A),
-            't' => result.push(T),
-            'c' => result.push(C),
-            'g' => result.push(G),
-            _ => (),
-        };
+    for char in str.bytes() {
+        if let Ok(nt) = Nucleotide::from_u8_letter(char) {
+            result.push(nt);
+        }
     }
 
     result
 }
 
+/// The number of unambiguous sequences a sequence with ambiguity codes represents; eg 32 for NNK. This
+/// is 1 for sequences without them, and saturates at `u64::MAX`.
+pub fn seq_degeneracy(seq: &[Nucleotide]) -> u64 {
+    seq.iter().fold(1_u64, |acc, nt| {
+        acc.saturating_mul(nt.possible().len() as u64)
+    })
+}
+
+/// One of the unambiguous sequences a sequence with ambiguity codes represents, by index, from 0 up to its
+/// degeneracy. The last nucleotide varies fastest.
+pub fn seq_expansion(seq: &[Nucleotide], mut i: u64) -> Seq {
+    let mut result: Seq = seq
+        .iter()
+        .rev()
+        .map(|nt| {
+            let possible = nt.possible();
+            let n = possible.len() as u64;
+            let nt = possible[(i % n) as usize];
+            i /= n;
+            nt
+        })
+        .collect();
+
+    result.reverse();
+    result
+}
+
 /// Convert a nucleotide sequence to string.
 pub fn seq_to_str(seq: &[Nucleotide]) -> String {
     let mut result = String::new();
@@ -321,55 +612,384 @@ pub fn seq_to_str(seq: &[Nucleotide]) -> String {
     result
 }
 
-/// Find coding regions in a sequence, given a reading frame.
-pub fn find_orf_matches(seq: &[Nucleotide], orf: ReadingFrame) -> Vec<ReadingFrameMatch> {
-    const START_CODON: [Nucleotide; 3] = [A, T, G];
-    let stop_codons = [[T, A, A], [T, A, G], [T, G, A]];
+/// Which ORFs to report when several in-frame start codons share a stop codon.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum NestedOrfPolicy {
+    /// Only report the longest ORF ending at each stop codon.
+    #[default]
+    Outermost,
+    /// Report an ORF for every start codon.
+    All,
+}
 
+impl Display for NestedOrfPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Self::Outermost => "Longest",
+            Self::All => "All",
+        }
+        .to_owned();
+        write!(f, "{}", str)
+    }
+}
+
+/// Settings for finding open reading frames.
+#[derive(Clone, PartialEq)]
+pub struct OrfParams {
+    /// If false, only ATG is used as a start codon. If true, we also use alternative start codons of
+    /// the genetic code, eg GTG and TTG for bacteria.
+    pub alt_starts: bool,
+    /// ORFs with fewer amino acids than this (not counting the stop codon) are not reported.
+    pub min_len_aa: usize,
+    pub nested: NestedOrfPolicy,
+}
+
+impl Default for OrfParams {
+    fn default() -> Self {
+        Self {
+            alt_starts: false,
+            min_len_aa: 75,
+            nested: Default::default(),
+        }
+    }
+}
+
+/// Find open reading frames in the specified reading frames, using the genetic code to identify start
+/// and stop codons. For circular sequences, this includes ORFs that wrap around the origin; in these,
+/// the range's end is less than its start.
+pub fn find_orf_matches(
+    seq: &[Nucleotide],
+    topology: SeqTopology,
+    frames: &[ReadingFrame],
+    genetic_code: GeneticCode,
+    params: &OrfParams,
+) -> Vec<ReadingFrameMatch> {
     let mut result = Vec::new();
 
-    let mut offset = orf.offset();
+    let seq_len = seq.len();
+    if seq_len < 3 {
+        return result;
+    }
 
-    let seq_len_full = seq.len();
+    let seq_compl = seq_complement(seq);
 
-    let seq = &match orf {
-        ReadingFrame::Fwd0 | ReadingFrame::Fwd1 | ReadingFrame::Fwd2 => seq.to_vec(),
-        _ => seq_complement(seq),
-    }[offset..];
+    for frame in frames {
+        // Indices here are respective to the strand being read. For reverse frames, that's the complement.
+        let seq_strand = if frame.is_reverse() { &seq_compl } else { seq };
 
-    let len = seq.len();
+        // For circular sequences, continue reading past the origin, so ORFs starting near the end of the
+        // sequence can be closed. Starts are only accepted on the first pass.
+        let scan_end = match topology {
+            SeqTopology::Linear => seq_len,
+            SeqTopology::Circular => 2 * seq_len,
+        };
 
-    let mut frame_open = None; // Inner: Start index.
+        let mut starts_open = Vec::new();
+        // Start and end indices, 0-based and inclusive, on the strand being read. The end may be past
+        // the sequence's length, for ORFs that wrap the origin.
+        let mut orfs_strand = Vec::new();
+
+        let mut i = frame.offset();
+        while i + 3 <= scan_end {
+            let codon = [
+                seq_strand[i % seq_len],
+                seq_strand[(i + 1) % seq_len],
+                seq_strand[(i + 2) % seq_len],
+            ];
+
+            if genetic_code.is_stop(&codon) {
+                for start in &starts_open {
+                    // Wrapping ORFs can't be longer than the sequence.
+                    if i + 3 - start <= seq_len {
+                        orfs_strand.push((*start, i + 2));
+                    }
+                }
+                starts_open.clear();
+            } else if i < seq_len {
+                let is_start = if params.alt_starts {
+                    genetic_code.is_start(&codon)
+                } else {
+                    codon == [A, T, G]
+                };
+
+                if is_start && (params.nested == NestedOrfPolicy::All || starts_open.is_empty()) {
+                    starts_open.push(i);
+                }
+            } else if starts_open.is_empty() {
+                break; // Past the origin, with nothing left to close.
+            }
 
-    for i_ in 0..len / 3 {
-        let i = i_ * 3; // The actual sequence index.
+            i += 3;
+        }
 
-        let nts = &seq[i..i + 3];
+        if params.nested == NestedOrfPolicy::Outermost {
+            // An ORF wrapping the origin may contain one found on the first pass, with the same stop codon.
+            orfs_strand.sort_by_key(|(start, end)| (end % seq_len, Reverse(end - start)));
+            orfs_strand.dedup_by_key(|(_, end)| *end % seq_len);
+        }
 
-        if frame_open.is_none() && nts == START_CODON {
-            frame_open = Some(i);
+        for (start, end) in orfs_strand {
+            let len_aa = (end - start + 1) / 3 - 1;
+            if len_aa < params.min_len_aa {
+                continue;
+            }
 
-            // println!("Frame open: {:?}", i);
-        } else if frame_open.is_some() && stop_codons.contains(nts.try_into().unwrap()) {
-            // println!("Frame cl: {:?}", i);
+            let range_strand = SeqRange::from_start_len(start, end - start + 1, seq_len);
 
-            // + 1 for our 1-based seq name convention.
-            // This section's a bit hairy; worked by trial and error. Final indices are respective to
-            // the non-complementary seq, for both forward and reverse reading frames.
-            let range = match orf {
-                ReadingFrame::Fwd0 | ReadingFrame::Fwd1 | ReadingFrame::Fwd2 => {
-                    (frame_open.unwrap() + 1 + offset, i + 3 + offset)
-                }
-                _ => (
-                    seq_len_full - (i + 2 + offset),
-                    seq_len_full - (frame_open.unwrap() + offset),
-                ),
+            // Respective to the non-complementary seq.
+            let range = if frame.is_reverse() {
+                range_strand.complement(seq_len)
+            } else {
+                range_strand
             };
 
-            result.push(ReadingFrameMatch { frame: orf, range });
-            frame_open = None;
+            result.push(ReadingFrameMatch {
+                frame: *frame,
+                range,
+            });
         }
     }
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_from_start_len() {
+        assert_eq!(SeqRange::from_start_len(0, 4, 10), SeqRange::new(1, 4));
+        assert_eq!(SeqRange::from_start_len(6, 4, 10), SeqRange::new(7, 10));
+        // Wraps the origin.
+        assert_eq!(SeqRange::from_start_len(8, 4, 10), SeqRange::new(9, 2));
+        // Starts past the origin.
+        assert_eq!(SeqRange::from_start_len(12, 3, 10), SeqRange::new(3, 5));
+        // The whole sequence, from a position other than the origin.
+        assert_eq!(SeqRange::from_start_len(4, 10, 10), SeqRange::new(5, 4));
+    }
+
+    #[test]
+    fn range_wrap_len_contains() {
+        let r = SeqRange::new(9, 2);
+        assert!(r.wraps());
+        assert_eq!(r.len(10), 4);
+        assert!(r.contains(10) && r.contains(1) && r.contains(9) && r.contains(2));
+        assert!(!r.contains(3) && !r.contains(8));
+
+        let r = SeqRange::new(3, 5);
+        assert!(!r.wraps());
+        assert_eq!(r.len(10), 3);
+        assert!(r.contains(3) && r.contains(5) && !r.contains(6));
+
+        assert_eq!(SeqRange::new(5, 4).len(10), 10);
+        assert!(!SeqRange::new(0, 4).is_valid(10));
+        assert!(!SeqRange::new(2, 11).is_valid(10));
+    }
+
+    #[test]
+    fn range_to_linear_and_seq() {
+        let seq = seq_from_str("acgtacgtTT");
+
+        let r = SeqRange::new(9, 2);
+        assert_eq!(r.to_linear(10), vec![8..10, 0..2]);
+        assert_eq!(r.indices(10), vec![8, 9, 0, 1]);
+        assert_eq!(r.seq(&seq), Some(seq_from_str("ttac")));
+
+        assert_eq!(SeqRange::new(2, 4).seq(&seq), Some(seq_from_str("cgt")));
+        assert!(SeqRange::new(2, 11).to_linear(10).is_empty());
+        assert_eq!(SeqRange::new(2, 11).seq(&seq), None);
+    }
+
+    #[test]
+    fn range_complement() {
+        // Nucleotides 1-3 of the complement strand are the last 3 of the top strand.
+        assert_eq!(SeqRange::new(1, 3).complement(10), SeqRange::new(8, 10));
+        assert_eq!(SeqRange::new(8, 10).complement(10), SeqRange::new(1, 3));
+    }
+
+    #[test]
+    fn range_rotate() {
+        // Position 4 becomes 1.
+        assert_eq!(SeqRange::new(4, 6).rotate(4, 10), SeqRange::new(1, 3));
+        // A range before the new origin moves to the end.
+        assert_eq!(SeqRange::new(1, 2).rotate(4, 10), SeqRange::new(8, 9));
+        // A range spanning the new origin wraps.
+        assert_eq!(SeqRange::new(2, 5).rotate(4, 10), SeqRange::new(9, 2));
+        // A wrapping range spanning the new origin no longer wraps.
+        assert_eq!(SeqRange::new(9, 2).rotate(9, 10), SeqRange::new(1, 4));
+    }
+
+    /// ORF ranges in one reading frame, with the standard genetic code.
+    fn orfs(
+        seq: &str,
+        topology: SeqTopology,
+        frame: ReadingFrame,
+        params: &OrfParams,
+    ) -> Vec<SeqRange> {
+        find_orf_matches(
+            &seq_from_str(seq),
+            topology,
+            &[frame],
+            GeneticCode::Standard,
+            params,
+        )
+        .into_iter()
+        .map(|orf| orf.range)
+        .collect()
+    }
+
+    fn params(min_len_aa: usize, nested: NestedOrfPolicy) -> OrfParams {
+        OrfParams {
+            min_len_aa,
+            nested,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn orf_wraps_origin() {
+        // The ATG is at 13; the ORF reads through the origin to the TAA at 4..6.
+        let seq = "AAATAACCCCCCATGCCC";
+        let params = params(0, NestedOrfPolicy::Outermost);
+
+        assert!(orfs(seq, SeqTopology::Linear, ReadingFrame::Fwd0, &params).is_empty());
+        assert_eq!(
+            orfs(seq, SeqTopology::Circular, ReadingFrame::Fwd0, &params),
+            vec![SeqRange::new(13, 6)]
+        );
+
+        // The same ORF, on the reverse strand. Its range is respective to the forward strand.
+        let seq_rev = seq_complement(&seq_from_str(seq));
+        let found = find_orf_matches(
+            &seq_rev,
+            SeqTopology::Circular,
+            &[ReadingFrame::Rev0],
+            GeneticCode::Standard,
+            &params,
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].range, SeqRange::new(13, 6));
+        assert_eq!(
+            found[0].range.seq(&seq_rev).map(|s| seq_complement(&s)),
+            Some(seq_from_str("ATGCCCAAATAA"))
+        );
+    }
+
+    #[test]
+    fn orf_nested() {
+        // An in-frame ATG at 7 shares the outer ORF's stop codon.
+        let seq = "ATGAAAATGCCCTAA";
+
+        assert_eq!(
+            orfs(
+                seq,
+                SeqTopology::Linear,
+                ReadingFrame::Fwd0,
+                &params(0, NestedOrfPolicy::Outermost)
+            ),
+            vec![SeqRange::new(1, 15)]
+        );
+        assert_eq!(
+            orfs(
+                seq,
+                SeqTopology::Linear,
+                ReadingFrame::Fwd0,
+                &params(0, NestedOrfPolicy::All)
+            ),
+            vec![SeqRange::new(1, 15), SeqRange::new(7, 15)]
+        );
+
+        // The ORF at 4..12 is found before the origin, and again inside the ORF starting at 13, which wraps.
+        let seq = "CCCATGCCCTAAATGCCC";
+        assert_eq!(
+            orfs(
+                seq,
+                SeqTopology::Circular,
+                ReadingFrame::Fwd0,
+                &params(0, NestedOrfPolicy::Outermost)
+            ),
+            vec![SeqRange::new(13, 12)]
+        );
+        assert_eq!(
+            orfs(
+                seq,
+                SeqTopology::Circular,
+                ReadingFrame::Fwd0,
+                &params(0, NestedOrfPolicy::All)
+            ),
+            vec![SeqRange::new(4, 12), SeqRange::new(13, 12)]
+        );
+    }
+
+    #[test]
+    fn orf_no_stop() {
+        let params = params(0, NestedOrfPolicy::Outermost);
+
+        assert!(orfs(
+            "ATGCCCCCCCCCCCC",
+            SeqTopology::Linear,
+            ReadingFrame::Fwd0,
+            &params
+        )
+        .is_empty());
+        // Reading past the origin only once; an ORF can't be longer than the sequence.
+        assert!(orfs(
+            "ATGCCCCCCCCCCCC",
+            SeqTopology::Circular,
+            ReadingFrame::Fwd0,
+            &params
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn orf_min_len() {
+        // 3 amino acids, not counting the stop codon.
+        let seq = "ATGAAAAAATAA";
+
+        assert_eq!(
+            orfs(
+                seq,
+                SeqTopology::Linear,
+                ReadingFrame::Fwd0,
+                &params(3, NestedOrfPolicy::Outermost)
+            ),
+            vec![SeqRange::new(1, 12)]
+        );
+        assert!(orfs(
+            seq,
+            SeqTopology::Linear,
+            ReadingFrame::Fwd0,
+            &params(4, NestedOrfPolicy::Outermost)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn orf_alt_starts() {
+        let seq = seq_from_str("GTGAAATAA");
+        let alt = OrfParams {
+            alt_starts: true,
+            ..params(0, NestedOrfPolicy::Outermost)
+        };
+
+        let find = |code, params| {
+            find_orf_matches(
+                &seq,
+                SeqTopology::Linear,
+                &[ReadingFrame::Fwd0],
+                code,
+                params,
+            )
+        };
+
+        assert_eq!(find(GeneticCode::Bacterial, &alt).len(), 1);
+        assert!(find(GeneticCode::Standard, &alt).is_empty());
+        assert!(find(
+            GeneticCode::Bacterial,
+            &params(0, NestedOrfPolicy::Outermost)
+        )
+        .is_empty());
+    }
+}
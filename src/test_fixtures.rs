@@ -0,0 +1,50 @@
+//! Data shared by unit tests in several modules.
+
+use crate::{
+    file_io::GenericData,
+    restriction_enzyme::{load_re_library, RestrictionEnzyme},
+    sequence::{seq_from_str, Feature, FeatureDirection, FeatureType, SeqRange, SeqTopology},
+    IonConcentrations,
+};
+
+/// 50 mM Na+, with no Mg2+ or dNTPs, so salt corrections are simple to check by hand.
+pub fn na_only() -> IonConcentrations {
+    IonConcentrations {
+        divalent: 0.,
+        dntp: 0.,
+        ..Default::default()
+    }
+}
+
+/// Enzymes from the built-in library, in this order, so their library indices are known.
+pub fn enzymes(names: &[&str]) -> Vec<RestrictionEnzyme> {
+    let lib = load_re_library();
+    names
+        .iter()
+        .map(|name| lib.iter().find(|re| re.name == *name).unwrap().clone())
+        .collect()
+}
+
+/// A generic forward feature, made of these ranges; 1-based and inclusive.
+pub fn feature(label: &str, ranges: &[(usize, usize)]) -> Feature {
+    let mut result = Feature {
+        index_range: SeqRange::default(),
+        segments: Vec::new(),
+        feature_type: FeatureType::Generic,
+        direction: FeatureDirection::Forward,
+        label: label.to_owned(),
+        color_override: None,
+        notes: Default::default(),
+    };
+    result.set_ranges(ranges.iter().map(|r| SeqRange::new(r.0, r.1)).collect());
+    result
+}
+
+pub fn doc(seq: &str, topology: SeqTopology, features: Vec<Feature>) -> GenericData {
+    GenericData {
+        seq: seq_from_str(seq),
+        topology,
+        features,
+        ..Default::default()
+    }
+}
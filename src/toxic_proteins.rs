@@ -1,12 +1,10 @@
 //! Contains code related to identifying toxic proteins.
 
-// use bio::bio_types::sequence::Sequence;
+#![allow(dead_code)] // todo: WIP.
 
-use crate::sequence::Seq;
+// use bio::bio_types::sequence::Sequence;
 
-enum AminoAcid {
-    A,
-}
+use crate::protein::AminoAcid;
 
 #[derive(Clone, Copy)]
 enum Host {
@@ -14,6 +12,6 @@ enum Host {
     Aav,
 }
 
-fn get_toxic_seqs() -> Vec<Seq> {
+fn get_toxic_seqs() -> Vec<Vec<AminoAcid>> {
     vec![]
 }
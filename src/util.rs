@@ -1,11 +1,6 @@
 use std::{cmp::min, collections::HashSet, num::ParseIntError, ops::Range};
 
-use eframe::egui::{pos2, Pos2};
-
-use crate::{
-    gui::seq_view::{NT_WIDTH_PX, SEQ_ROW_SPACING_PX, TEXT_X_START, TEXT_Y_START},
-    Color, State,
-};
+use crate::{sequence::SeqRange, Color};
 /// Utility function to linearly map an input value to an output
 pub fn map_linear(val: f32, range_in: (f32, f32), range_out: (f32, f32)) -> f32 {
     // todo: You may be able to optimize calls to this by having the ranges pre-store
@@ -29,50 +24,6 @@ pub fn get_row_ranges(len: usize, chars_per_row: usize) -> Vec<Range<usize>> {
     result
 }
 
-// todo: We currently don't use this as a standalone fn; wrap back into `seq_i_to_pixel` a/r.
-/// Maps sequence index, as displayed on a manually-wrapped UI display, to row and column indices.
-fn seq_i_to_col_row(seq_i: usize, row_ranges: &[Range<usize>]) -> (usize, usize) {
-    let mut row = 0;
-    let mut row_range = 0..10;
-
-    for (row_, range) in row_ranges.iter().enumerate() {
-        if range.contains(&seq_i) {
-            row = row_;
-            row_range = range.clone();
-            break;
-        }
-    }
-
-    let col = seq_i - row_range.start;
-
-    (col, row)
-}
-
-/// Maps sequence index, as displayed on a manually-wrapped UI display, to the relative pixel.
-pub fn seq_i_to_pixel(seq_i: usize, row_ranges: &[Range<usize>]) -> Pos2 {
-    let (col, row) = seq_i_to_col_row(seq_i, row_ranges);
-
-    pos2(
-        TEXT_X_START + col as f32 * NT_WIDTH_PX,
-        TEXT_Y_START + row as f32 * SEQ_ROW_SPACING_PX,
-    )
-}
-
-pub fn pixel_to_seq_i(pixel: Pos2, row_ranges: &[Range<usize>]) -> Option<usize> {
-    // todo: ROunding?
-    let row = ((pixel.y - TEXT_Y_START) / SEQ_ROW_SPACING_PX) as usize;
-    let col = ((pixel.x - TEXT_X_START) / NT_WIDTH_PX) as usize;
-
-    // todo: Index vice loop?
-    for (row_, range) in row_ranges.iter().enumerate() {
-        if row_ == row {
-            return Some(range.start + col);
-        }
-    }
-
-    None
-}
-
 // todo; Move to Util A/R
 pub fn remove_duplicates<T: Eq + std::hash::Hash>(vec: Vec<T>) -> Vec<T> {
     let set: HashSet<_> = vec.into_iter().collect();
@@ -80,10 +31,23 @@ pub fn remove_duplicates<T: Eq + std::hash::Hash>(vec: Vec<T>) -> Vec<T> {
     vec
 }
 
-// todo: Abstract out/use in feature overlay
 /// Given an index range of a feature, return sequence ranges for each row the feature occupies, that
 /// contain the sequence. This, after converting to pixels, corresponds to how we draw features and primers.
+/// Ranges that wrap the origin are split into their parts at the end and start of the sequence.
 pub fn get_feature_ranges(
+    feature_rng: &SeqRange,
+    all_ranges: &[Range<usize>],
+    seq_len: usize,
+) -> Vec<Range<usize>> {
+    feature_rng
+        .to_linear(seq_len)
+        .iter()
+        .flat_map(|r| get_feature_ranges_linear(r, all_ranges))
+        .collect()
+}
+
+/// Row ranges for a feature range that doesn't wrap the origin. 0-based indexing, end-exclusive.
+fn get_feature_ranges_linear(
     feature_rng: &Range<usize>,
     all_ranges: &[Range<usize>],
 ) -> Vec<Range<usize>> {
@@ -135,26 +99,3 @@ pub fn color_from_hex(hex: &str) -> Result<Color, ParseIntError> {
 pub fn color_to_hex(color: Color) -> String {
     format!("#{:x}{:x}{:x}", color.0, color.1, color.2)
 }
-
-/// Change the origin. This involves updating the sequence, and all features.
-pub fn change_origin(state: &mut State) {
-    let origin = &state.ui.new_origin;
-    // Note the 1-based indexing logic we use.
-    if *origin < 1 || *origin > state.generic.seq.len() {
-        return;
-    }
-
-    state.generic.seq.rotate_left(origin - 1);
-
-    let seq_len = state.generic.seq.len();
-    for feature in &mut state.generic.features {
-        // Convert to isize to prevent an underflow on crash if we wrap.
-        feature.index_range.0 =
-            ((feature.index_range.0 as isize - *origin as isize) % seq_len as isize) as usize;
-        feature.index_range.1 =
-            ((feature.index_range.1 as isize - *origin as isize) % seq_len as isize) as usize;
-    }
-
-    // todo: What else to update?
-    state.sync_seq_related(None);
-}
@@ -0,0 +1,356 @@
+//! This module contains the workspace: Several open documents, eg plasmids, inserts, PCR products, and
+//! digest fragments. One is active at a time. Its data is stored in `State::generic` and
+//! `State::history` while active, so code that operates on the current sequence doesn't need to know
+//! about the workspace.
+
+use plascad::{
+    file_io::{
+        save::{ProjectToSave, StateToSave},
+        GenericData,
+    },
+    history::History,
+    primer::Primer,
+    sequence::{Feature, ReadingFrame, Seq},
+    IonConcentrations,
+};
+
+use crate::State;
+
+/// A document, and the settings saved with it.
+#[derive(Default)]
+pub struct Document {
+    pub generic: GenericData,
+    pub history: History,
+    pub insert_loc: usize,
+    pub ion_concentrations: IonConcentrations,
+    pub reading_frame: ReadingFrame,
+}
+
+impl Document {
+    pub fn from_save(s: StateToSave) -> Self {
+        Self {
+            generic: s.generic,
+            history: s.history.unwrap_or_default(),
+            insert_loc: s.insert_loc,
+            ion_concentrations: s.ion_concentrations,
+            reading_frame: s.reading_frame,
+        }
+    }
+
+    fn to_save(&self, save_history: bool) -> StateToSave {
+        StateToSave {
+            generic: self.generic.clone(),
+            insert_loc: self.insert_loc,
+            ion_concentrations: self.ion_concentrations.clone(),
+            reading_frame: self.reading_frame,
+            history: save_history.then(|| self.history.clone()),
+        }
+    }
+}
+
+pub struct Workspace {
+    /// The slot at `active` is empty; its data is in `State` while active.
+    pub documents: Vec<Document>,
+    pub active: usize,
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self {
+            documents: vec![Default::default()],
+            active: 0,
+        }
+    }
+}
+
+/// Features and primers copied from one document, for pasting into others.
+#[derive(Default)]
+pub struct Clipboard {
+    /// Each feature, with its sequence on the top strand, from the start of its first segment to the
+    /// end of its last. Feature ranges are relative to this sequence.
+    pub features: Vec<(Feature, Seq)>,
+    pub primers: Vec<Primer>,
+}
+
+impl State {
+    /// Move the active document's data from `State` to its slot in the workspace.
+    fn stash_document(&mut self) {
+        let doc = &mut self.workspace.documents[self.workspace.active];
+        doc.generic = std::mem::take(&mut self.generic);
+        doc.history = std::mem::take(&mut self.history);
+        doc.insert_loc = self.insert_loc;
+        doc.ion_concentrations = self.ion_concentrations.clone();
+        doc.reading_frame = self.reading_frame;
+    }
+
+    /// Move a document's data from its slot in the workspace to `State`, and make it active.
+    fn activate_document(&mut self, i: usize) {
+        self.workspace.active = i;
+
+        let doc = &mut self.workspace.documents[i];
+        self.generic = std::mem::take(&mut doc.generic);
+        self.history = std::mem::take(&mut doc.history);
+        self.insert_loc = doc.insert_loc;
+        self.ion_concentrations = doc.ion_concentrations.clone();
+        self.reading_frame = doc.reading_frame;
+
+        self.ui.edit_report = None;
+        self.ui.paste_not_found = Vec::new();
+        self.ui.primer_selected = None;
+        self.ui.feature_hover = None;
+        // These results are for the previously-active document.
+        self.ui.off_target.result = None;
+        self.ui.primer_design = Default::default();
+
+        self.sync_primer_metrics();
+        self.sync_seq_related(None);
+        // Syncing updates derived data that's stored with the document; this isn't a new action.
+        self.history.reset_current(&self.generic);
+    }
+
+    /// Add a document to the workspace, and make it active. It uses the active document's ion
+    /// concentrations and reading frame.
+    pub fn open_document(&mut self, generic: GenericData, history: History) {
+        self.open_saved(Document {
+            generic,
+            history,
+            insert_loc: 0,
+            ion_concentrations: self.ion_concentrations.clone(),
+            reading_frame: self.reading_frame,
+        });
+    }
+
+    /// Add a document, with its own settings, eg loaded from a file, and make it active.
+    pub fn open_saved(&mut self, doc: Document) {
+        self.stash_document();
+        self.workspace.documents.push(doc);
+        self.activate_document(self.workspace.documents.len() - 1);
+    }
+
+    pub fn switch_document(&mut self, i: usize) {
+        if i == self.workspace.active || i >= self.workspace.documents.len() {
+            return;
+        }
+
+        self.stash_document();
+        self.activate_document(i);
+    }
+
+    /// Close a document. If it's the only one, it's replaced with an empty one.
+    pub fn close_document(&mut self, i: usize) {
+        let ws = &mut self.workspace;
+        if i >= ws.documents.len() {
+            return;
+        }
+
+        if i < self.ui.off_target.documents.len() {
+            self.ui.off_target.documents.remove(i);
+        }
+        // Fragments are ranges of documents, by index.
+        for fragments in [
+            &mut self.ui.assembly.fragments,
+            &mut self.ui.golden_gate.parts,
+        ] {
+            fragments.retain(|f| f.document != i);
+            for fragment in fragments.iter_mut() {
+                if fragment.document > i {
+                    fragment.document -= 1;
+                }
+            }
+        }
+
+        if ws.documents.len() == 1 {
+            ws.documents[0] = Default::default();
+            self.activate_document(0);
+            self.history.reset(&self.generic);
+            return;
+        }
+
+        if i == ws.active {
+            ws.documents.remove(i);
+            let next = i.min(ws.documents.len() - 1);
+            self.activate_document(next);
+        } else {
+            ws.documents.remove(i);
+            if i < ws.active {
+                ws.active -= 1;
+            }
+        }
+    }
+
+    /// A document's data, whether or not it's active.
+    pub fn document(&self, i: usize) -> &GenericData {
+        if i == self.workspace.active {
+            &self.generic
+        } else {
+            &self.workspace.documents[i].generic
+        }
+    }
+
+    /// The name of each document in the workspace, for display.
+    pub fn document_names(&self) -> Vec<String> {
+        (0..self.workspace.documents.len())
+            .map(|i| {
+                let generic = self.document(i);
+                if generic.metadata.plasmid_name.is_empty() {
+                    "Untitled".to_owned()
+                } else {
+                    generic.metadata.plasmid_name.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Copy a feature from the active document to the clipboard.
+    pub fn copy_feature(&mut self, feature_i: usize) {
+        let Some(feature) = self.generic.features.get(feature_i) else {
+            return;
+        };
+
+        let seq_len = self.generic.seq.len();
+        let extent = feature.index_range;
+
+        if let Some(seq) = extent.seq(&self.generic.seq) {
+            let mut relative = feature.clone();
+            relative.set_ranges(
+                feature
+                    .ranges()
+                    .iter()
+                    .map(|r| r.rotate(extent.start, seq_len))
+                    .collect(),
+            );
+            self.clipboard.features.push((relative, seq));
+        }
+    }
+
+    /// Copy a primer from the active document to the clipboard.
+    pub fn copy_primer(&mut self, primer_i: usize) {
+        if let Some(primer) = self.generic.primers.get(primer_i) {
+            self.clipboard.primers.push(primer.clone());
+        }
+    }
+
+    /// Paste features from the clipboard into the active document, where their sequences are found.
+    /// Returns the labels of features whose sequence wasn't found.
+    pub fn paste_features(&mut self) -> Vec<String> {
+        let mut not_found = Vec::new();
+
+        for (feature, seq) in &self.clipboard.features {
+            if !self.generic.paste_feature(feature, seq) {
+                not_found.push(feature.label.clone());
+            }
+        }
+
+        self.commit("Paste features");
+        not_found
+    }
+
+    /// Paste primers from the clipboard into the active document.
+    pub fn paste_primers(&mut self) {
+        self.generic
+            .primers
+            .extend(self.clipboard.primers.iter().cloned());
+
+        self.sync_primer_matches(None);
+        self.sync_primer_metrics();
+        self.commit("Paste primers");
+    }
+
+    /// All open documents, and settings to save with them.
+    pub fn to_project(&self) -> ProjectToSave {
+        let documents = self
+            .workspace
+            .documents
+            .iter()
+            .enumerate()
+            .map(|(i, doc)| {
+                if i == self.workspace.active {
+                    self.to_save()
+                } else {
+                    doc.to_save(self.save_history)
+                }
+            })
+            .collect();
+
+        ProjectToSave {
+            documents,
+            active: self.workspace.active,
+        }
+    }
+
+    /// Used to load to state. Run `History::reset_current` on each document once it's active.
+    pub fn from_project(project: ProjectToSave) -> Self {
+        let active = project
+            .active
+            .min(project.documents.len().saturating_sub(1));
+        let mut result = Self::default();
+        let mut documents = Vec::new();
+
+        for (i, doc) in project.documents.into_iter().enumerate() {
+            if i == active {
+                result = Self::from_save(doc);
+                documents.push(Default::default());
+            } else {
+                documents.push(Document::from_save(doc));
+            }
+        }
+
+        if !documents.is_empty() {
+            result.workspace = Workspace { documents, active };
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plascad::{file_io::save::ProjectToSave, sequence::seq_from_str};
+
+    use super::*;
+
+    #[test]
+    fn project_round_trip() {
+        let mut state = State::default();
+        state.generic.seq = seq_from_str("ACGTACGTAC");
+        state.ion_concentrations.monovalent = 100.;
+        state.insert_loc = 4;
+
+        state.open_document(
+            GenericData {
+                seq: seq_from_str("GGGGCCCC"),
+                ..Default::default()
+            },
+            Default::default(),
+        );
+        // New documents start with the active one's settings.
+        assert_eq!(state.ion_concentrations.monovalent, 100.);
+        state.ion_concentrations.monovalent = 20.;
+        state.reading_frame = ReadingFrame::Rev1;
+
+        let bytes = state.to_project().to_bytes().unwrap();
+        let project = ProjectToSave::from_bytes(&bytes).unwrap().data;
+        let docs = &project.documents;
+        assert_eq!(docs.len(), 2);
+        assert_eq!(project.active, 1);
+
+        assert_eq!(docs[0].generic.seq, seq_from_str("ACGTACGTAC"));
+        assert_eq!(docs[0].ion_concentrations.monovalent, 100.);
+        assert_eq!(docs[0].insert_loc, 4);
+        assert_eq!(docs[0].reading_frame, ReadingFrame::Fwd0);
+
+        assert_eq!(docs[1].generic.seq, seq_from_str("GGGGCCCC"));
+        assert_eq!(docs[1].ion_concentrations.monovalent, 20.);
+        assert_eq!(docs[1].insert_loc, 0);
+        assert_eq!(docs[1].reading_frame, ReadingFrame::Rev1);
+
+        // Each document's settings are restored when it's active.
+        let mut state = State::from_project(project);
+        assert_eq!(state.ion_concentrations.monovalent, 20.);
+        state.switch_document(0);
+        assert_eq!(state.ion_concentrations.monovalent, 100.);
+        assert_eq!(state.insert_loc, 4);
+        state.switch_document(1);
+        assert_eq!(state.ion_concentrations.monovalent, 20.);
+        assert_eq!(state.reading_frame, ReadingFrame::Rev1);
+    }
+}